@@ -0,0 +1,130 @@
+use anyhow::{bail, Context, Result};
+use colored::{ColoredString, Colorize};
+use serde::{Deserialize, Serialize};
+
+use crate::benchmark::BenchmarkResult;
+use crate::support::ino_parse_millis;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct LatencyBands {
+    pub good: u64,
+    pub slow: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Band {
+    Good,
+    Slow,
+    Over,
+}
+
+impl TryFrom<String> for LatencyBands {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        ino_parse_bands(&value)
+    }
+}
+
+impl From<LatencyBands> for String {
+    fn from(bands: LatencyBands) -> Self {
+        format!("{}ms:{}ms", bands.good, bands.slow)
+    }
+}
+
+impl LatencyBands {
+
+    /**
+    *=================================================================
+    * ino_band()
+    *=================================================================
+    *
+    * Band of a response time: good up to the first bound, slow up
+    * to the second, over the goal beyond.
+    *
+    *=================================================================
+    * @param duration u64 ms
+    * @return Band
+    */
+    pub fn ino_band(&self, duration: u64) -> Band {
+        match duration {
+            d if d <= self.good => Band::Good,
+            d if d <= self.slow => Band::Slow,
+            _ => Band::Over,
+        }
+    }
+}
+
+/**
+ *=================================================================
+ * ino_parse_bands()
+ *=================================================================
+ *
+ * Parses the response time bands of the verbose output,
+ * "GOOD:SLOW" in milliseconds or human durations, ex. 200:500 or
+ * 200ms:1s.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<LatencyBands>
+ */
+pub fn ino_parse_bands(value: &str) -> Result<LatencyBands> {
+    let Some((good, slow)) = value.split_once(':') else {
+        bail!("Invalid latency bands {}, use GOOD:SLOW, ex. 200ms:1s", value);
+    };
+    let good = ino_parse_millis(good.trim()).with_context(|| format!("Invalid latency bands {}", value))?;
+    let slow = ino_parse_millis(slow.trim()).with_context(|| format!("Invalid latency bands {}", value))?;
+    if slow < good {
+        bail!("The slow bound of the latency bands {} is below the good one", value);
+    }
+    Ok(LatencyBands { good, slow })
+}
+
+/**
+ *=================================================================
+ * ino_verbose_line()
+ *=================================================================
+ *
+ * Line of the verbose output for a result. The response time is
+ * green, yellow or red by its band when bands are set, and a
+ * failed request, an error status or an unmet expectation, is
+ * flagged FAIL with its status in red so it stands out of the
+ * stream.
+ *
+ *=================================================================
+ * @param result &BenchmarkResult
+ * @param bands Option<LatencyBands>
+ * @return String
+ */
+pub fn ino_verbose_line(result: &BenchmarkResult, bands: Option<LatencyBands>) -> String {
+    let paint = |text: String| -> ColoredString {
+        match bands.map(|bands| bands.ino_band(result.duration)) {
+            None => text.cyan(),
+            Some(Band::Good) => text.green(),
+            Some(Band::Slow) => text.yellow(),
+            Some(Band::Over) => text.red().bold(),
+        }
+    };
+    let client = format!("[{} {} {} {}]", "Client".bold().green(), result.num_client.to_string().bold().green(), "Iteration".bold().green(), result.execution.to_string().bold().green());
+    match result.success {
+        true => format!("{} {} {}{}", client, result.status.bold().yellow(), paint(result.duration.to_string()), paint("ms".to_string())),
+        false => format!("{} {} {} {}{}", client, " FAIL ".white().bold().on_red(), result.status.red().bold(), paint(result.duration.to_string()), paint("ms".to_string())),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_band_the_response_times() -> Result<()> {
+        let bands = ino_parse_bands("200:1s")?;
+        assert_eq!(bands, LatencyBands { good: 200, slow: 1000 });
+        assert_eq!([150, 200, 201, 1000, 1001].map(|d| bands.ino_band(d)), [Band::Good, Band::Good, Band::Slow, Band::Slow, Band::Over]);
+        assert_eq!(String::from(bands), "200ms:1000ms");
+        assert!(ino_parse_bands("200").is_err() && ino_parse_bands("1s:200ms").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+const BODY_CHUNK_SIZE: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Bandwidth(pub u64);
+
+impl TryFrom<String> for Bandwidth {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        ino_parse_bandwidth(&value)
+    }
+}
+
+impl From<Bandwidth> for String {
+    fn from(bandwidth: Bandwidth) -> Self {
+        bandwidth.to_string()
+    }
+}
+
+impl Display for Bandwidth {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}Bps", self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct BandwidthLimiter {
+    rate: f64,
+    next: Mutex<Option<Instant>>,
+}
+
+impl BandwidthLimiter {
+
+    /**
+    *=================================================================
+    * new()
+    *=================================================================
+    *
+    * Creates a limiter letting `bandwidth` bytes per second through,
+    * shared by every transfer that uses it.
+    *
+    *=================================================================
+    * @param bandwidth Bandwidth
+    * @return Self
+    */
+    pub fn new(bandwidth: Bandwidth) -> Self {
+        BandwidthLimiter { rate: bandwidth.0.max(1) as f64, next: Mutex::new(None) }
+    }
+
+    /**
+    *=================================================================
+    * ino_reserve()
+    *=================================================================
+    *
+    * Books the transfer of `bytes` after the transfers already
+    * booked, an idle limiter does not save up any credit.
+    *
+    *=================================================================
+    * @param bytes usize
+    * @return Instant the end of the transfer at the rate
+    */
+    pub fn ino_reserve(&self, bytes: usize) -> Instant {
+        let now = Instant::now();
+        let mut next = self.next.lock().unwrap();
+        let end = next.map_or(now, |n| n.max(now)) + Duration::from_secs_f64(bytes as f64 / self.rate);
+        *next = Some(end);
+        end
+    }
+}
+
+/**
+ *=================================================================
+ * ino_parse_bandwidth()
+ *=================================================================
+ *
+ * Parses a bandwidth such as "10MBps", "512KiB/s" or "2Mbps":
+ * B, KB, MB and GB count bytes by thousands, KiB, MiB and GiB by
+ * 1024 and b, Kb, Mb and Gb count bits. A bare number is a count
+ * of bytes per second.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<Bandwidth>
+ */
+pub fn ino_parse_bandwidth(value: &str) -> Result<Bandwidth> {
+    let trimmed = value.trim();
+    let split = trimmed.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    let number: f64 = number.parse().with_context(|| format!("Invalid bandwidth {}", value))?;
+    let unit = unit.trim();
+    let unit = unit.strip_suffix("ps").or_else(|| unit.strip_suffix("/s")).unwrap_or(unit);
+    let factor = match unit {
+        "" | "B" => 1.0,
+        "KB" | "kB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "b" => 1.0 / 8.0,
+        "Kb" | "kb" => 1e3 / 8.0,
+        "Mb" => 1e6 / 8.0,
+        "Gb" => 1e9 / 8.0,
+        _ => bail!("Invalid bandwidth unit {} in {}, use B, KB, MB, GB, KiB, MiB, GiB or b, Kb, Mb, Gb per second", unit, value),
+    };
+    let bytes = (number * factor) as u64;
+    if bytes == 0 {
+        bail!("The bandwidth {} is below one byte per second", value);
+    }
+    Ok(Bandwidth(bytes))
+}
+
+/**
+ *=================================================================
+ * ino_throttle_transfer()
+ *=================================================================
+ *
+ * Waits until `bytes` could have gone through every limiter, the
+ * slowest one decides.
+ *
+ *=================================================================
+ * @param limiters &[Arc<BandwidthLimiter>]
+ * @param bytes usize
+ * @return void
+ */
+pub async fn ino_throttle_transfer(limiters: &[Arc<BandwidthLimiter>], bytes: usize) {
+    if let Some(end) = limiters.iter().map(|l| l.ino_reserve(bytes)).max() {
+        tokio::time::sleep_until(end).await;
+    }
+}
+
+/**
+ *=================================================================
+ * ino_throttle_stream()
+ *=================================================================
+ *
+ * Paces the chunks of a request body through the limiters.
+ *
+ *=================================================================
+ */
+pub fn ino_throttle_stream<S>(stream: S, limiters: Vec<Arc<BandwidthLimiter>>) -> impl Stream<Item = io::Result<Bytes>>
+where
+    S: Stream<Item = io::Result<Bytes>>,
+{
+    stream.then(move |chunk| {
+        let limiters = limiters.clone();
+        async move {
+            if let Ok(chunk) = &chunk {
+                ino_throttle_transfer(&limiters, chunk.len()).await;
+            }
+            chunk
+        }
+    })
+}
+
+/**
+ *=================================================================
+ * ino_throttle_body()
+ *=================================================================
+ *
+ * Paces a request body held in memory, cut in small chunks so a
+ * low bandwidth is not reached in bursts.
+ *
+ *=================================================================
+ * @param body Bytes
+ * @param limiters Vec<Arc<BandwidthLimiter>>
+ * @return reqwest::Body
+ */
+pub fn ino_throttle_body(body: Bytes, limiters: Vec<Arc<BandwidthLimiter>>) -> reqwest::Body {
+    let chunks: Vec<io::Result<Bytes>> = (0..body.len())
+        .step_by(BODY_CHUNK_SIZE)
+        .map(|at| Ok(body.slice(at..(at + BODY_CHUNK_SIZE).min(body.len()))))
+        .collect();
+    reqwest::Body::wrap_stream(ino_throttle_stream(futures::stream::iter(chunks), limiters))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_bandwidths() -> Result<()> {
+        assert_eq!(ino_parse_bandwidth("10MBps")?, Bandwidth(10_000_000));
+        assert_eq!(ino_parse_bandwidth("512KiB/s")?, Bandwidth(524_288));
+        assert_eq!(ino_parse_bandwidth("2Mbps")?, Bandwidth(250_000));
+        assert_eq!(ino_parse_bandwidth("1500")?, Bandwidth(1500));
+        assert!(ino_parse_bandwidth("10 furlongs").is_err());
+        assert!(ino_parse_bandwidth("1bps").is_err());
+        let bandwidth: Bandwidth = serde_yaml::from_str("1.5 MB/s")?;
+        assert_eq!(bandwidth, Bandwidth(1_500_000));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_pace_transfers_at_the_rate() {
+        let limiter = Arc::new(BandwidthLimiter::new(Bandwidth(100_000)));
+        let begin = Instant::now();
+        for _ in 0..4 {
+            ino_throttle_transfer(std::slice::from_ref(&limiter), 5_000).await;
+        }
+        let elapsed = begin.elapsed();
+        assert!(elapsed >= Duration::from_millis(195) && elapsed < Duration::from_millis(400), "{:?}", elapsed);
+    }
+}
@@ -1,100 +1,361 @@
+use anyhow::{Context, Result};
 use colored::Colorize;
 use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::time::Instant;
 
+use inoue::schema::{AccountingCounts, ConnectionCounts, EncodedHistogram, Latency, RequestCounts, RunSummary, Score, Verdict};
+
+use crate::bands::ino_verbose_line;
+use crate::stable::{StableMetric, StopOnStable};
+use crate::cache::{CacheHeaders, CacheStats, CacheSummary};
+use crate::checksum::ino_corrupted;
+use crate::exhaustion::LocalStats;
+use crate::conditional::{ConditionalStats, ConditionalSummary, StatusLatency};
+use crate::download::{DownloadStats, DownloadSummary};
+use crate::flow::{ino_has_checksum, PollOutcome, PollStats, PollSummary, StepStats, StepSummary};
+use crate::headers::{HeaderStats, HeaderTotals};
+use crate::heatmap::Heatmap;
+use crate::kafka::{KafkaStats, KafkaSummary};
+use crate::monitor::{Accounting, GeneratorSummary};
+use crate::pacing::{ScheduleStats, ScheduleSummary};
+use crate::range::{RangeStats, RangeSummary};
+use crate::resources::ResourceSample;
+use crate::sse::{SseStats, SseSummary, StreamOutcome};
+use crate::support::{LatencyBoundary, Mode, Settings};
+use crate::tags::Tags;
+use crate::threshold::StepScore;
+use crate::throttle::{ThrottleStats, ThrottleSummary};
+use crate::upload::{UploadStats, UploadSummary};
 
 
-pub trait Metrics {
-    fn ino_avg(&self) -> u64;
-    fn ino_max(&self) -> u64;
-    fn ino_min(&self) -> u64;
-}
 
 #[derive(Debug)]
 pub struct BenchmarkResult {
     pub status: String,
+    pub success: bool,
     pub duration: u64,
     pub execution: usize,
     pub num_client: usize,
+    pub size: Option<u64>,
+    pub reused: Option<bool>,
+    pub cache: Option<CacheHeaders>,
+    pub first_byte: Option<u64>,
+    pub ack: Option<u64>,
+    pub headers: Option<Vec<(String, f64)>>,
+    pub throttled: Option<u64>,
+    pub poll: Option<PollOutcome>,
+    pub stream: Option<StreamOutcome>,
+    pub step: Option<String>,
+    pub lag: Option<u64>,
+}
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub target: String,
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: Tags,
+    pub timestamp: u64,
+    pub clients: usize,
+    pub total_requests: u64,
+    #[serde(default)]
+    pub errors: u64,
+    pub elapsed: f64,
+    pub throughput: f64,
+    pub mean: f64,
+    pub max: u64,
+    pub min: u64,
+    pub p95: u64,
+    pub p999: u64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub percentiles: BTreeMap<String, u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_byte: Option<FirstByteSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_size: Option<SizeSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connections: Option<ConnectionSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator: Option<GeneratorSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accounting: Option<Accounting>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<Vec<ResourceSample>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conditional: Option<ConditionalSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ranges: Option<RangeSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download: Option<DownloadSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload: Option<UploadSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kafka: Option<KafkaSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corrupted: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_errors: Option<BTreeMap<String, u64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<ScheduleSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throttled: Option<ThrottleSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub polls: Option<BTreeMap<String, PollSummary>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sse: Option<SseSummary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steps: Option<BTreeMap<String, StepSummary>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<StepScore>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_headers: Option<BTreeMap<String, HeaderStats>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_timeline: Option<Vec<StatusSecond>>,
+}
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SizeSummary {
+    pub p50: u64,
+    pub p95: u64,
+    pub max: u64,
+}
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FirstByteSummary {
+    pub mean: f64,
+    pub p95: u64,
+    pub p999: u64,
+}
+
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionSummary {
+    pub new: u64,
+    pub reused: u64,
+}
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusSecond {
+    pub second: u64,
+    pub classes: BTreeMap<String, ClassLatency>,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClassLatency {
+    pub count: u64,
+    pub mean: f64,
+}
+
+
+#[derive(Debug, Default)]
+struct Aggregates {
+    connections: Vec<Option<ConnectionSummary>>,
+    cache: CacheStats,
+    conditional: ConditionalStats,
+    ranges: RangeStats,
+    download: DownloadStats,
+    upload: UploadStats,
+    kafka: KafkaStats,
+    corrupted: u64,
+    local: LocalStats,
+    schedule: ScheduleStats,
+    throttle: ThrottleStats,
+    polls: PollStats,
+    sse: SseStats,
+    steps: StepStats,
+    headers: HeaderTotals,
 }
 
 
 #[derive(Debug)]
 pub struct Report {
     clients: usize,
+    requested: Option<usize>,
     pub results: Vec<BenchmarkResult>,
+    aggregates: Aggregates,
     hist: Histogram<u64>,
+    sizes: Histogram<u64>,
+    first_bytes: Option<Histogram<u64>>,
+    boundary: Option<LatencyBoundary>,
+    percentiles: Vec<f64>,
+    verbose: bool,
+    conditional: bool,
+    range_size: Option<u64>,
+    download: bool,
+    upload: Option<u64>,
+    kafka: Option<usize>,
+    checksum: bool,
+    sweep: bool,
+    respect_retry_after: bool,
+    pub generator: Option<GeneratorSummary>,
+    pub accounting: Option<Accounting>,
+    pub resources: Option<Vec<ResourceSample>>,
+    pub stable: Option<(StopOnStable, Duration)>,
+    timeline: Vec<(u64, u64)>,
+    status_timeline: Vec<BTreeMap<&'static str, (u64, u64)>>,
+    heatmap: Heatmap,
+    sample_rate: Option<f64>,
+    errors: u64,
+    extremes: Option<(u64, u64)>,
     start: Instant,
 }
 
-impl Metrics for Vec<BenchmarkResult> {
+impl Summary {
 
     /**
     *=================================================================
-    * ino_avg()
+    * ino_run_summary()
     *=================================================================
     *
-    *
-    *
-    *
-    *
-    *
+    * Run of the summary in the stable schema of the JSON exit
+    * summary, with its failed thresholds, its latency histogram and,
+    * when computed, its step score and accounting audit.
     *
     *=================================================================
-    *
-    *
-    *
+    * @param failures &[String]
+    * @param histogram Option<EncodedHistogram>
+    * @return RunSummary
     */
-    fn ino_avg(&self) -> u64 {
-        let total: u64 = self.iter().map(|r| r.duration).sum();
-        let size: u64 = self.iter().len() as u64;
-        total / size
+    pub fn ino_run_summary(&self, failures: &[String], histogram: Option<EncodedHistogram>) -> RunSummary {
+        RunSummary {
+            target: self.target.clone(),
+            label: self.label.clone(),
+            tags: self.tags.clone(),
+            timestamp: self.timestamp,
+            passed: failures.is_empty(),
+            failures: failures.to_vec(),
+            clients: self.clients,
+            requests: RequestCounts {
+                total: self.total_requests,
+                errors: self.errors,
+                error_rate: self.errors as f64 / self.total_requests.max(1) as f64,
+            },
+            elapsed_seconds: self.elapsed,
+            throughput: self.throughput,
+            latency_ms: Latency { mean: self.mean, min: Some(self.min), max: Some(self.max), p95: self.p95, p999: self.p999 },
+            first_byte_ms: self.first_byte.as_ref().map(|first_byte| Latency {
+                mean: first_byte.mean,
+                min: None,
+                max: None,
+                p95: first_byte.p95,
+                p999: first_byte.p999,
+            }),
+            connections: self.connections.map(|c| ConnectionCounts { new: c.new, reused: c.reused }),
+            histogram,
+            score: self.score.as_ref().map(|score| Score {
+                score: score.score,
+                min_score: score.min_score,
+                steps: score
+                    .steps
+                    .iter()
+                    .map(|(step, v)| (step.clone(), Verdict { weight: v.weight, passed: v.passed, failures: v.failures.clone() }))
+                    .collect(),
+            }),
+            accounting: self.accounting.as_ref().map(|a| AccountingCounts {
+                dispatched: a.dispatched,
+                settled: a.settled,
+                cancelled: a.cancelled,
+                sent: a.sent,
+                dropped: a.dropped,
+                completed: a.completed,
+                failed: a.failed,
+                discrepancies: a.discrepancies.clone(),
+            }),
+        }
     }
+}
+
+impl Display for Summary {
 
     /**
     *=================================================================
-    * ino_max()
+    * Formatter()
     *=================================================================
     *
-    *
-    *
-    *
-    *
-    *
+    * Compact one line summary of a run.
     *
     *=================================================================
-    *
-    *
-    *
     */
-    fn ino_max(&self) -> u64 {
-        return self.iter().map(|r| r.duration).max().unwrap_or(0)
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} requests, {} errors, {:.1} rps, mean {:.1} ms, p95 {} ms, p99.9 {} ms, max {} ms",
+            self.total_requests, self.errors, self.throughput, self.mean, self.p95, self.p999, self.max
+        )
     }
+}
+
+
+
+impl BenchmarkResult {
 
     /**
     *=================================================================
-    * ino_min()
+    * new()
     *=================================================================
     *
-    *
-    *
-    *
-    *
-    *
+    * Result of an iteration without any of the optional
+    * measurements, the modes set the ones they take with a struct
+    * update.
     *
     *=================================================================
+    * @param status String
+    * @param success bool
+    * @param duration u64 milliseconds
+    * @param num_client usize
+    * @param execution usize
+    * @return BenchmarkResult
+    */
+    pub fn new(status: String, success: bool, duration: u64, num_client: usize, execution: usize) -> Self {
+        BenchmarkResult {
+            status,
+            success,
+            duration,
+            execution,
+            num_client,
+            size: None,
+            reused: None,
+            cache: None,
+            first_byte: None,
+            ack: None,
+            headers: None,
+            throttled: None,
+            poll: None,
+            stream: None,
+            step: None,
+            lag: None,
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_is_error()
+    *=================================================================
     *
+    * Whether the result counts as an error: a throttled response
+    * failed but is not an error of the target.
     *
-    *
+    *=================================================================
+    * @param void
+    * @return bool
     */
-    fn ino_min(&self) -> u64 {
-        return self.iter().map(|r| r.duration).min().unwrap_or(0)
+    pub fn ino_is_error(&self) -> bool {
+        !self.success && self.throttled.is_none()
     }
 }
 
-
-
 impl Display for BenchmarkResult {
 
     /**
@@ -114,13 +375,7 @@ impl Display for BenchmarkResult {
     *
     */
     fn fmt(&self, f: &mut Formatter<'_> ) -> std::fmt::Result {
-        let report = format!("[{} {} {} {}] {} {}{}", "Client".bold().green(), self.num_client.to_string().bold().green(), "Iteration".bold().green(),
-            self.execution.to_string().bold().green(),
-            self.status.to_string().bold().yellow(),
-            self.duration.to_string().cyan(),
-            "ms".cyan()
-        );
-        write!(f, "{}", report)
+        write!(f, "{}", ino_verbose_line(self, None))
     }
 }
 
@@ -133,24 +388,57 @@ impl Report {
     * new()
     *=================================================================
     *
-    *
-    *
-    *
-    *
-    *
+    * Creates an empty report. The latency histogram keeps the
+    * configured significant figures and, when a maximum is set,
+    * stops growing at that bound, as does the one of the time to
+    * first byte.
     *
     *=================================================================
-    *
-    *
-    *
+    * @param settings &Settings
+    * @return Result<Self>
     */
-    pub fn new(clients: usize) -> Self {
-        Report {
-            clients,
+    pub fn new(settings: &Settings) -> Result<Self> {
+        let hist = match settings.histogram_max {
+            None => Histogram::<u64>::new(settings.histogram_sigfig),
+            Some(max) => Histogram::<u64>::new_with_bounds(1, max.max(2), settings.histogram_sigfig),
+        }
+        .with_context(|| "Invalid histogram settings".to_string())?;
+        let sample_rate = settings.sample_rate.map(ino_check_sample_rate).transpose()?;
+        let first_bytes = (settings.latency_boundary == LatencyBoundary::Body).then(|| hist.clone());
+        Ok(Report {
+            clients: settings.clients,
+            requested: (settings.duration.is_none() && settings.steps.is_none()).then_some(settings.requests),
             results: vec![],
-            hist: Histogram::<u64>::new(5).unwrap(),
+            aggregates: Aggregates::default(),
+            hist,
+            sizes: Histogram::<u64>::new(3).with_context(|| "Invalid histogram settings".to_string())?,
+            first_bytes,
+            boundary: (settings.mode == Mode::Http).then(|| match settings.download || settings.range_size.is_some() {
+                true => LatencyBoundary::Body,
+                false => settings.latency_boundary,
+            }),
+            percentiles: settings.percentiles.iter().copied().map(ino_check_percentile).collect::<Result<_>>()?,
+            verbose: settings.verbose,
+            conditional: settings.conditional,
+            range_size: settings.range_size,
+            download: settings.download,
+            upload: settings.upload,
+            kafka: settings.kafka.as_ref().filter(|_| settings.mode == Mode::Kafka).map(|kafka| kafka.batch.max(1)),
+            checksum: settings.verify_sha256.is_some() || settings.steps.as_deref().is_some_and(ino_has_checksum),
+            sweep: settings.sweep.is_some(),
+            respect_retry_after: settings.respect_retry_after,
+            generator: None,
+            accounting: None,
+            resources: None,
+            stable: None,
+            timeline: vec![],
+            status_timeline: vec![],
+            heatmap: Heatmap::default(),
+            sample_rate,
+            errors: 0,
+            extremes: None,
             start: Instant::now()
-        }
+        })
     }
 
 
@@ -159,21 +447,132 @@ impl Report {
     * ino_add_result()
     *=================================================================
     *
+    * Stores a result and records its duration, durations beyond
+    * the maximum of the histogram are recorded as the maximum.
+    * The response size and, when the latency covers the body, the
+    * time to first byte go to their own histograms and the duration is also added to the second it completed in,
+    * overall, for its status class and to the heatmap.
+    * Every result goes to the aggregates of the report. With a
+    * sample rate, only that fraction of the results is kept in
+    * full, evenly spread, for the raw rows and the exports.
     *
+    *=================================================================
+    * @param result BenchmarkResult
+    * @return void
+    */
+    pub fn ino_add_result(&mut self, result: BenchmarkResult) -> Option<&BenchmarkResult> {
+        let duration = result.duration;
+        if let Some(size) = result.size {
+            ino_record(&mut self.sizes, size);
+        }
+        if let (Some(first_bytes), Some(first_byte)) = (&mut self.first_bytes, result.first_byte) {
+            ino_record(first_bytes, first_byte);
+        }
+        let class = ino_status_class(&result);
+        self.errors += u64::from(result.ino_is_error());
+        self.extremes = Some(match self.extremes {
+            None => (duration, duration),
+            Some((min, max)) => (min.min(duration), max.max(duration)),
+        });
+        let sampled = self.sample_rate.is_none_or(|rate| ino_sampled(self.hist.len(), rate));
+        ino_record(&mut self.hist, duration);
+        let second = self.start.elapsed().as_secs() as usize;
+        if self.timeline.len() <= second {
+            self.timeline.resize(second + 1, (0, 0));
+            self.status_timeline.resize(second + 1, BTreeMap::new());
+        }
+        let (count, total) = &mut self.timeline[second];
+        *count += 1;
+        *total += duration;
+        let (count, total) = self.status_timeline[second].entry(class).or_insert((0, 0));
+        *count += 1;
+        *total += duration;
+        self.heatmap.ino_add(second, duration);
+        self.ino_aggregate(&result);
+        if !sampled {
+            return None;
+        }
+        self.results.push(result);
+        self.results.last()
+    }
+
+
+    /**
+    *=================================================================
+    * ino_aggregate()
+    *=================================================================
     *
-    *
-    *
-    *
+    * Adds a result to the aggregates of the report, the ones of a
+    * mode only when the run uses it.
     *
     *=================================================================
+    */
+    fn ino_aggregate(&mut self, result: &BenchmarkResult) {
+        let aggregates = &mut self.aggregates;
+        if let Some(reused) = result.reused {
+            if aggregates.connections.len() <= result.num_client {
+                aggregates.connections.resize(result.num_client + 1, None);
+            }
+            let summary = aggregates.connections[result.num_client].get_or_insert_with(ConnectionSummary::default);
+            match reused {
+                true => summary.reused += 1,
+                false => summary.new += 1,
+            }
+        }
+        if self.conditional {
+            aggregates.conditional.ino_add(result);
+        }
+        if self.range_size.is_some() {
+            aggregates.ranges.ino_add(result);
+        }
+        if self.download {
+            aggregates.download.ino_add(result);
+        }
+        if self.upload.is_some() {
+            aggregates.upload.ino_add(result);
+        }
+        if self.kafka.is_some() {
+            aggregates.kafka.ino_add(result);
+        }
+        if self.checksum {
+            aggregates.corrupted += u64::from(ino_corrupted(result));
+        }
+        if self.respect_retry_after {
+            aggregates.throttle.ino_add(result);
+        }
+        aggregates.cache.ino_add(result);
+        aggregates.local.ino_add(result);
+        aggregates.schedule.ino_add(result);
+        aggregates.polls.ino_add(result);
+        aggregates.sse.ino_add(result);
+        aggregates.steps.ino_add(result);
+        aggregates.headers.ino_add(result);
+    }
+
+
+    fn ino_min(&self) -> u64 {
+        self.extremes.map_or(0, |(min, _)| min)
+    }
+
+
+    fn ino_max(&self) -> u64 {
+        self.extremes.map_or(0, |(_, max)| max)
+    }
+
+
+    /**
+    *=================================================================
+    * ino_heatmap()
+    *=================================================================
     *
+    * Latency distribution of the run second by second.
     *
-    *
+    *=================================================================
+    * @param void
+    * @return &Heatmap
     */
-    pub fn ino_add_result(&mut self, result: BenchmarkResult) {
-        let duration = result.duration;
-        self.results.push(result);
-        self.hist.record(duration).expect("");
+    pub fn ino_heatmap(&self) -> &Heatmap {
+        &self.heatmap
     }
 
 
@@ -201,11 +600,928 @@ impl Report {
 
         println!("{} {}", "Concurrency level".yellow().bold(), self.clients.to_string().purple());
         println!("{} {} {}", "Time taken".yellow().bold(), elapsed.as_secs().to_string().purple(), "seconds".purple());
+        if let Some((stop, at)) = &self.stable {
+            println!("{}", format!("Stopped after {} seconds, {}", at.as_secs(), stop).yellow());
+        }
         println!("{} {}", "Total requests ".yellow().bold(), self.hist.len().to_string().purple());
+        if let Some(requested) = self.requested.filter(|r| *r as u64 != self.hist.len()) {
+            println!("{}", format!("{} iterations were requested, the run was interrupted or changed", requested).yellow());
+        }
+        if let Some(rate) = self.sample_rate.filter(|r| *r < 1.0) {
+            println!("{}", format!("{} of {} results kept in full at a sample rate of {}", self.results.len(), self.hist.len(), rate).yellow());
+        }
+        match self.boundary {
+            Some(LatencyBoundary::Headers) => println!("{} {}", "Request time up to".yellow().bold(), "the response headers".purple()),
+            Some(LatencyBoundary::Body) => println!("{} {}", "Request time up to".yellow().bold(), "the complete body".purple()),
+            None => {}
+        }
         println!("{} {} {}", "Mean request time".yellow().bold(), self.hist.mean().to_string().purple(), "ms".purple());
-        println!("{} {} {}", "Max request time".yellow().bold(), self.results.ino_max().to_string().purple(), "ms".purple());
-        println!("{} {} {}", "Min request time".yellow().bold(), self.results.ino_min().to_string().purple(), "ms".purple());
+        println!("{} {} {}", "Max request time".yellow().bold(), self.ino_max().to_string().purple(), "ms".purple());
+        println!("{} {} {}", "Min request time".yellow().bold(), self.ino_min().to_string().purple(), "ms".purple());
         println!("{} {} {}", "95'th percentile:".yellow().bold(), self.hist.value_at_quantile(0.95).to_string().purple(), "ms".purple());
         println!("{} {} {}", "99.9'th percentile:".yellow().bold(), self.hist.value_at_quantile(0.999).to_string().purple(), "ms".purple());
+        for percentile in self.percentiles.iter().filter(|p| ![95.0, 99.9].contains(*p)) {
+            println!(
+                "{} {} {}",
+                format!("{}'th percentile:", percentile).yellow().bold(),
+                self.hist.value_at_quantile(percentile / 100.0).to_string().purple(),
+                "ms".purple()
+            );
+        }
+        if let Some(first_byte) = self.ino_first_byte_summary() {
+            println!(
+                "{} {} {} {} {} {} {}",
+                "Time to first byte mean".yellow().bold(),
+                format!("{:.1}", first_byte.mean).purple(),
+                "p95".yellow().bold(),
+                first_byte.p95.to_string().purple(),
+                "p99.9".yellow().bold(),
+                first_byte.p999.to_string().purple(),
+                "ms".purple()
+            );
+        }
+        if let Some(size) = self.ino_size_summary() {
+            println!(
+                "{} {} {} {} {} {}",
+                "Response size p50".yellow().bold(),
+                ino_format_bytes(size.p50).purple(),
+                "p95".yellow().bold(),
+                ino_format_bytes(size.p95).purple(),
+                "max".yellow().bold(),
+                ino_format_bytes(size.max).purple()
+            );
+        }
+        if let Some(connections) = self.ino_connections(None) {
+            println!(
+                "{} {} {} {} {}",
+                "Connections".yellow().bold(),
+                connections.new.to_string().purple(),
+                "new".purple(),
+                connections.reused.to_string().purple(),
+                "reused".purple()
+            );
+            if self.verbose {
+                for num_client in 0..self.clients {
+                    if let Some(c) = self.ino_connections(Some(num_client)) {
+                        println!("  {} {}: {} new, {} reused", "Client".green(), num_client, c.new, c.reused);
+                    }
+                }
+            }
+        }
+        if self.conditional {
+            let summary = self.aggregates.conditional.ino_summary();
+            let latency = |l: Option<StatusLatency>| match l {
+                None => "-".to_string(),
+                Some(l) => format!("mean {:.1} ms p95 {} ms", l.mean, l.p95),
+            };
+            println!(
+                "{} {}",
+                "Not modified".yellow().bold(),
+                format!(
+                    "{} of {} ({:.1}%), 304 {}, 200 {}",
+                    summary.not_modified,
+                    summary.not_modified + summary.modified,
+                    summary.not_modified_ratio * 100.0,
+                    latency(summary.not_modified_latency),
+                    latency(summary.modified_latency)
+                )
+                .purple()
+            );
+        }
+        if let Some(range_size) = self.range_size {
+            let ranges = self.aggregates.ranges.ino_summary(range_size, elapsed.as_secs_f64());
+            println!(
+                "{} {}",
+                "Ranges".yellow().bold(),
+                format!(
+                    "{} partial, {} full, {}/s, per range p50 {}/s p5 {}/s",
+                    ranges.partial,
+                    ranges.full,
+                    ino_format_bytes(ranges.throughput as u64),
+                    ino_format_bytes(ranges.p50_rate as u64),
+                    ino_format_bytes(ranges.p5_rate as u64)
+                )
+                .purple()
+            );
+        }
+        if self.download {
+            let download = self.aggregates.download.ino_summary(self.clients, elapsed.as_secs_f64());
+            let mut rates = download.client_rates.clone();
+            rates.sort_by(f64::total_cmp);
+            println!(
+                "{} {}",
+                "Download".yellow().bold(),
+                format!(
+                    "{} at {}/s, first byte mean {:.1} ms p95 {} ms, per client min {}/s max {}/s",
+                    ino_format_bytes(download.bytes),
+                    ino_format_bytes(download.goodput as u64),
+                    download.first_byte_mean,
+                    download.first_byte_p95,
+                    ino_format_bytes(rates.first().cloned().unwrap_or(0.0) as u64),
+                    ino_format_bytes(rates.last().cloned().unwrap_or(0.0) as u64)
+                )
+                .purple()
+            );
+            if self.verbose {
+                for (num_client, rate) in download.client_rates.iter().enumerate() {
+                    println!("  {} {}: {}/s", "Client".green(), num_client, ino_format_bytes(*rate as u64));
+                }
+            }
+        }
+        if let Some(size) = self.upload {
+            let upload = self.aggregates.upload.ino_summary(size, elapsed.as_secs_f64());
+            println!(
+                "{} {}",
+                "Upload".yellow().bold(),
+                format!(
+                    "{} at {}/s, ack mean {:.1} ms p95 {} ms",
+                    ino_format_bytes(upload.bytes),
+                    ino_format_bytes(upload.throughput as u64),
+                    upload.ack_mean,
+                    upload.ack_p95
+                )
+                .purple()
+            );
+        }
+        if let Some(batch) = self.kafka {
+            let kafka = self.aggregates.kafka.ino_summary(batch, elapsed.as_secs_f64());
+            println!(
+                "{} {}",
+                "Produced".yellow().bold(),
+                format!(
+                    "{} messages, {} at {:.1} msg/s, {}/s",
+                    kafka.messages,
+                    ino_format_bytes(kafka.bytes),
+                    kafka.messages_per_second,
+                    ino_format_bytes(kafka.bytes_per_second as u64)
+                )
+                .purple()
+            );
+        }
+        if self.checksum {
+            let corrupted = self.aggregates.corrupted;
+            println!(
+                "{} {}",
+                "Corrupted".yellow().bold(),
+                format!("{} responses ({:.2}%)", corrupted, corrupted as f64 * 100.0 / self.hist.len().max(1) as f64).purple()
+            );
+        }
+        if self.respect_retry_after {
+            let throttle = self.aggregates.throttle.ino_summary(elapsed.as_secs_f64());
+            println!(
+                "{} {}",
+                "Throttled".yellow().bold(),
+                format!(
+                    "{} requests, paused {:.1} s, goodput {:.1} successful requests/s",
+                    throttle.throttled,
+                    throttle.pause_ms as f64 / 1000.0,
+                    throttle.goodput
+                )
+                .purple()
+            );
+        }
+        if let Some(cache) = self.aggregates.cache.ino_summary() {
+            let statuses: Vec<String> = cache.statuses.iter().map(|(status, count)| format!("{} {}", status, count)).collect();
+            let age = match cache.age {
+                None => "-".to_string(),
+                Some(age) => format!("p50 {} s p95 {} s max {} s", age.p50, age.p95, age.max),
+            };
+            println!(
+                "{} {} {} {}",
+                "Cache".yellow().bold(),
+                match statuses.is_empty() {
+                    true => "-".to_string(),
+                    false => statuses.join(", "),
+                }
+                .purple(),
+                "Age".yellow().bold(),
+                age.purple()
+            );
+        }
+        let mut steps: Vec<(String, StepSummary)> = self.aggregates.steps.ino_summary().unwrap_or_default().into_iter().collect();
+        if self.sweep {
+            steps.sort_by_key(|(_, step)| Reverse(step.p95));
+        }
+        for (name, step) in steps {
+            println!(
+                "{} {} {}",
+                if self.sweep { "Sweep" } else { "Step" }.yellow().bold(),
+                name.yellow().bold(),
+                format!(
+                    "{} requests, {} errors, mean {:.1} ms p95 {} ms p99.9 {} ms max {} ms",
+                    step.requests, step.errors, step.mean, step.p95, step.p999, step.max
+                )
+                .purple()
+            );
+        }
+        for (name, poll) in self.aggregates.polls.ino_summary().unwrap_or_default() {
+            println!(
+                "{} {} {}",
+                "Poll".yellow().bold(),
+                name.yellow().bold(),
+                format!(
+                    "{} completed, {} timed out, mean {:.1} ms p95 {} ms max {} ms",
+                    poll.completed, poll.timed_out, poll.mean, poll.p95, poll.max
+                )
+                .purple()
+            );
+        }
+        if let Some(sse) = self.aggregates.sse.ino_summary() {
+            println!(
+                "{} {}",
+                "Streams".yellow().bold(),
+                format!(
+                    "{} completed, {} terminated ({:.1}%), {} failed ({:.1}%), {} events",
+                    sse.completed,
+                    sse.terminated,
+                    sse.termination_rate * 100.0,
+                    sse.failed,
+                    sse.error_rate * 100.0,
+                    sse.events
+                )
+                .purple()
+            );
+            println!(
+                "{} {}",
+                "First event".yellow().bold(),
+                format!(
+                    "mean {:.1} ms p95 {} ms, between events mean {:.1} ms p95 {} ms max {} ms",
+                    sse.first_event_mean, sse.first_event_p95, sse.gap_mean, sse.gap_p95, sse.gap_max
+                )
+                .purple()
+            );
+        }
+        for (name, header) in self.ino_header_summary().unwrap_or_default() {
+            println!(
+                "{} {} {}",
+                "Header".yellow().bold(),
+                name.yellow().bold(),
+                format!(
+                    "min {} mean {:.1} max {} last {} ({} responses)",
+                    header.min, header.mean, header.max, header.last, header.count
+                )
+                .purple()
+            );
+        }
+        if let Some(samples) = &self.resources {
+            let cpu: Vec<f64> = samples.iter().filter_map(|s| s.cpu_percent).collect();
+            let memory = samples.iter().filter_map(|s| s.memory_bytes).max();
+            if !cpu.is_empty() || memory.is_some() {
+                println!(
+                    "{} {}",
+                    "Target".yellow().bold(),
+                    format!(
+                        "CPU avg {:.0}% max {:.0}%, memory max {}",
+                        cpu.iter().sum::<f64>() / cpu.len().max(1) as f64,
+                        cpu.iter().cloned().fold(0.0, f64::max),
+                        memory.map(ino_format_bytes).unwrap_or_else(|| "-".to_string())
+                    )
+                    .purple()
+                );
+            }
+        }
+        if let Some(schedule) = self.aggregates.schedule.ino_summary() {
+            let ms = |us: u64| us as f64 / 1000.0;
+            println!(
+                "{} {}",
+                "Scheduling lag".yellow().bold(),
+                format!(
+                    "mean {:.2} ms p50 {:.2} ms p99 {:.2} ms p99.9 {:.2} ms max {:.2} ms ({} requests)",
+                    schedule.mean_us / 1000.0,
+                    ms(schedule.p50_us),
+                    ms(schedule.p99_us),
+                    ms(schedule.p999_us),
+                    ms(schedule.max_us),
+                    schedule.requests
+                )
+                .purple()
+            );
+            if !schedule.ino_accurate(self.hist.value_at_quantile(0.95)) {
+                println!(
+                    "{}",
+                    "requests left well after they were due, the tail latencies are likely under-reported".yellow()
+                );
+            }
+        }
+        if let Some(limits) = self.aggregates.local.ino_summary() {
+            let counts: Vec<String> = limits.iter().map(|(limit, count)| format!("{} {}", limit, count)).collect();
+            println!("{} {}", "Local errors".yellow().bold(), counts.join(", ").purple());
+            println!(
+                "{}",
+                "these requests failed on the machine running inoue, not on the target, see the warnings before the run".yellow()
+            );
+        }
+        if let Some(generator) = &self.generator {
+            println!(
+                "{} {}",
+                "Generator".yellow().bold(),
+                format!(
+                    "CPU {:.2} s ({:.0}%), peak memory {}, {} workers, {} blocked sends, {} dropped sends",
+                    generator.cpu_seconds,
+                    generator.cpu_percent,
+                    ino_format_bytes(generator.max_rss),
+                    generator.workers,
+                    generator.blocked_sends,
+                    generator.dropped_sends
+                )
+                .purple()
+            );
+            if generator.ino_saturated() {
+                println!(
+                    "{}",
+                    "inoue itself may be the bottleneck, try more --workers or fewer clients".yellow()
+                );
+            }
+        }
+        if let Some(accounting) = &self.accounting {
+            println!(
+                "{} {}",
+                "Accounting".yellow().bold(),
+                format!(
+                    "dispatched {}, completed {}, failed {}, cancelled {}, dropped {}",
+                    accounting.dispatched, accounting.completed, accounting.failed, accounting.cancelled, accounting.dropped
+                )
+                .purple()
+            );
+            for discrepancy in &accounting.discrepancies {
+                println!("{}", discrepancy.red());
+            }
+        }
     }
-}
\ No newline at end of file
+
+
+    /**
+    *=================================================================
+    * ino_summary()
+    *=================================================================
+    *
+    * Builds a serializable summary of the run, keyed by the target
+    * and the optional label from the settings.
+    *
+    *=================================================================
+    * @param settings &Settings
+    * @return Summary
+    */
+    pub fn ino_summary(&self, settings: &Settings) -> Summary {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let total_requests = self.hist.len();
+        let throughput = match elapsed > 0.0 {
+            true => total_requests as f64 / elapsed,
+            false => 0.0,
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let steps = self.aggregates.steps.ino_summary();
+        let score = settings.thresholds.as_ref().and_then(|thresholds| thresholds.ino_score(steps.as_ref()));
+
+        Summary {
+            target: settings.ino_name(),
+            label: settings.label.clone(),
+            tags: settings.tags.clone(),
+            timestamp,
+            clients: self.clients,
+            total_requests,
+            errors: self.errors,
+            elapsed,
+            throughput,
+            mean: self.hist.mean(),
+            max: self.ino_max(),
+            min: self.ino_min(),
+            p95: self.hist.value_at_quantile(0.95),
+            p999: self.hist.value_at_quantile(0.999),
+            percentiles: self
+                .percentiles
+                .iter()
+                .map(|percentile| (format!("p{}", percentile), self.hist.value_at_quantile(percentile / 100.0)))
+                .collect(),
+            first_byte: self.ino_first_byte_summary(),
+            response_size: self.ino_size_summary(),
+            connections: self.ino_connections(None),
+            generator: self.generator,
+            accounting: self.accounting.clone(),
+            resources: self.resources.clone(),
+            cache: self.aggregates.cache.ino_summary(),
+            conditional: self.conditional.then(|| self.aggregates.conditional.ino_summary()),
+            ranges: self.range_size.map(|size| self.aggregates.ranges.ino_summary(size, elapsed)),
+            download: self.download.then(|| self.aggregates.download.ino_summary(self.clients, elapsed)),
+            upload: self.upload.map(|size| self.aggregates.upload.ino_summary(size, elapsed)),
+            kafka: self.kafka.map(|batch| self.aggregates.kafka.ino_summary(batch, elapsed)),
+            corrupted: self.checksum.then_some(self.aggregates.corrupted),
+            local_errors: self.aggregates.local.ino_summary(),
+            schedule: self.aggregates.schedule.ino_summary(),
+            throttled: self.respect_retry_after.then(|| self.aggregates.throttle.ino_summary(elapsed)),
+            polls: self.aggregates.polls.ino_summary(),
+            sse: self.aggregates.sse.ino_summary(),
+            steps,
+            score,
+            response_headers: self.ino_header_summary(),
+            status_timeline: self.ino_status_timeline(),
+        }
+    }
+
+
+    /**
+    *=================================================================
+    * ino_encoded_histogram()
+    *=================================================================
+    *
+    * Latency histogram of the run, serialized to be merged with the
+    * runs of other inoue processes.
+    *
+    *=================================================================
+    * @param void
+    * @return Option<EncodedHistogram>
+    */
+    pub fn ino_encoded_histogram(&self) -> Option<EncodedHistogram> {
+        EncodedHistogram::encode(&self.hist).ok()
+    }
+
+
+    /**
+    *=================================================================
+    * ino_header_summary()
+    *=================================================================
+    *
+    * Statistics of the extracted and rate-limit response headers.
+    *
+    *=================================================================
+    * @param void
+    * @return Option<BTreeMap<String, HeaderStats>>
+    */
+    pub fn ino_header_summary(&self) -> Option<BTreeMap<String, HeaderStats>> {
+        self.aggregates.headers.ino_summary()
+    }
+
+
+    /**
+    *=================================================================
+    * ino_status_timeline()
+    *=================================================================
+    *
+    * Requests and mean latency of every status class, second by
+    * second, leaving out the seconds without any result. None
+    * before the first result.
+    *
+    *=================================================================
+    * @param void
+    * @return Option<Vec<StatusSecond>>
+    */
+    pub fn ino_status_timeline(&self) -> Option<Vec<StatusSecond>> {
+        let seconds: Vec<StatusSecond> = self
+            .status_timeline
+            .iter()
+            .enumerate()
+            .filter(|(_, classes)| !classes.is_empty())
+            .map(|(second, classes)| StatusSecond {
+                second: second as u64,
+                classes: classes
+                    .iter()
+                    .map(|(class, &(count, total))| {
+                        (class.to_string(), ClassLatency { count, mean: total as f64 / count as f64 })
+                    })
+                    .collect(),
+            })
+            .collect();
+        (!seconds.is_empty()).then_some(seconds)
+    }
+
+
+    /**
+    *=================================================================
+    * ino_elapsed()
+    *=================================================================
+    *
+    * Time since the report was created.
+    *
+    *=================================================================
+    * @param void
+    * @return Duration
+    */
+    pub fn ino_elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+
+    /**
+    *=================================================================
+    * ino_metric()
+    *=================================================================
+    *
+    * Value of a metric over the results received so far, the
+    * latencies in ms and the throughput in requests per second.
+    * None before the first result.
+    *
+    *=================================================================
+    * @param metric StableMetric
+    * @return Option<f64>
+    */
+    pub fn ino_metric(&self, metric: StableMetric) -> Option<f64> {
+        if self.hist.is_empty() {
+            return None;
+        }
+        Some(match metric {
+            StableMetric::Mean => self.hist.mean(),
+            StableMetric::P50 => self.hist.value_at_quantile(0.5) as f64,
+            StableMetric::P90 => self.hist.value_at_quantile(0.9) as f64,
+            StableMetric::P95 => self.hist.value_at_quantile(0.95) as f64,
+            StableMetric::P99 => self.hist.value_at_quantile(0.99) as f64,
+            StableMetric::Rps => self.hist.len() as f64 / self.ino_elapsed().as_secs_f64(),
+        })
+    }
+
+
+    /**
+    *=================================================================
+    * ino_set_resources()
+    *=================================================================
+    *
+    * Stores the resource samples of the target, each one completed
+    * with the requests and mean latency of the second before it.
+    *
+    *=================================================================
+    * @param samples Vec<ResourceSample>
+    * @return void
+    */
+    pub fn ino_set_resources(&mut self, mut samples: Vec<ResourceSample>) {
+        for sample in &mut samples {
+            let second = (sample.offset - 1.0).max(0.0) as usize;
+            if let Some(&(count, total)) = self.timeline.get(second) {
+                sample.requests = count;
+                sample.mean_latency = (count > 0).then(|| total as f64 / count as f64);
+            }
+        }
+        self.resources = Some(samples);
+    }
+
+
+    /**
+    *=================================================================
+    * ino_first_byte_summary()
+    *=================================================================
+    *
+    * Time to the response headers when the latency of the requests
+    * covers the whole body, None otherwise or before any response.
+    *
+    *=================================================================
+    * @param void
+    * @return Option<FirstByteSummary>
+    */
+    pub fn ino_first_byte_summary(&self) -> Option<FirstByteSummary> {
+        let first_bytes = self.first_bytes.as_ref().filter(|h| !h.is_empty())?;
+        Some(FirstByteSummary {
+            mean: first_bytes.mean(),
+            p95: first_bytes.value_at_quantile(0.95),
+            p999: first_bytes.value_at_quantile(0.999),
+        })
+    }
+
+
+    /**
+    *=================================================================
+    * ino_size_summary()
+    *=================================================================
+    *
+    * Percentiles of the response body sizes, None when no result
+    * carried a size.
+    *
+    *=================================================================
+    * @param void
+    * @return Option<SizeSummary>
+    */
+    pub fn ino_size_summary(&self) -> Option<SizeSummary> {
+        (!self.sizes.is_empty()).then(|| SizeSummary {
+            p50: self.sizes.value_at_quantile(0.5),
+            p95: self.sizes.value_at_quantile(0.95),
+            max: self.sizes.max(),
+        })
+    }
+
+
+    /**
+    *=================================================================
+    * ino_connections()
+    *=================================================================
+    *
+    * Counts the requests sent over a new connection and over a
+    * reused one, for a single client or for the whole run. None
+    * when no result tells about its connection.
+    *
+    *=================================================================
+    * @param num_client Option<usize>
+    * @return Option<ConnectionSummary>
+    */
+    pub fn ino_connections(&self, num_client: Option<usize>) -> Option<ConnectionSummary> {
+        let connections = &self.aggregates.connections;
+        match num_client {
+            Some(num_client) => connections.get(num_client).copied().flatten(),
+            None => connections.iter().flatten().copied().reduce(|total, client| ConnectionSummary {
+                new: total.new + client.new,
+                reused: total.reused + client.reused,
+            }),
+        }
+    }
+}
+
+
+/**
+ *=================================================================
+ * ino_status_class()
+ *=================================================================
+ *
+ * Class of the status of a result: 2xx to 5xx for an HTTP status,
+ * error when no response came back, ok for the successes of the
+ * other modes.
+ *
+ *=================================================================
+ * @param result &BenchmarkResult
+ * @return &'static str
+ */
+pub fn ino_status_class(result: &BenchmarkResult) -> &'static str {
+    let code = result.status.split_whitespace().next().and_then(|c| c.parse::<u16>().ok());
+    match (code, result.success) {
+        (Some(100..=199), _) => "1xx",
+        (Some(200..=299), _) => "2xx",
+        (Some(300..=399), _) => "3xx",
+        (Some(400..=499), _) => "4xx",
+        (Some(500..=599), _) => "5xx",
+        (_, true) => "ok",
+        (_, false) => "error",
+    }
+}
+
+
+/**
+ *=================================================================
+ * ino_check_sample_rate()
+ *=================================================================
+ *
+ * Checks a sample rate is a fraction of the results, above 0 and
+ * at most 1.
+ *
+ *=================================================================
+ * @param rate f64
+ * @return Result<f64>
+ */
+pub fn ino_check_sample_rate(rate: f64) -> Result<f64> {
+    if !(rate > 0.0 && rate <= 1.0) {
+        anyhow::bail!("Invalid sample rate {}, use a fraction above 0 and at most 1", rate);
+    }
+    Ok(rate)
+}
+
+
+/**
+ *=================================================================
+ * ino_check_percentile()
+ *=================================================================
+ *
+ * Checks a percentile is above 0 and at most 100.
+ *
+ *=================================================================
+ * @param percentile f64
+ * @return Result<f64>
+ */
+pub fn ino_check_percentile(percentile: f64) -> Result<f64> {
+    if !(percentile > 0.0 && percentile <= 100.0) {
+        anyhow::bail!("Invalid percentile {}, use a value above 0 and at most 100", percentile);
+    }
+    Ok(percentile)
+}
+
+
+/**
+ *=================================================================
+ * ino_sampled()
+ *=================================================================
+ *
+ * Whether the result following the given count is kept in full,
+ * one every 1 / rate results starting with the first one.
+ *
+ *=================================================================
+ * @param count u64 results received before this one
+ * @param rate f64
+ * @return bool
+ */
+fn ino_sampled(count: u64, rate: f64) -> bool {
+    ((count + 1) as f64 * rate).ceil() > (count as f64 * rate).ceil()
+}
+
+
+/**
+ *=================================================================
+ * ino_record()
+ *=================================================================
+ *
+ * Records a value, growing the histogram when it resizes itself
+ * and saturating at its maximum when it is bounded.
+ *
+ *=================================================================
+ */
+fn ino_record(hist: &mut Histogram<u64>, value: u64) {
+    if hist.record(value).is_err() {
+        hist.saturating_record(value);
+    }
+}
+
+
+/**
+ *=================================================================
+ * ino_quantile()
+ *=================================================================
+ *
+ * Value at the quantile of sorted values, nearest rank. The
+ * values must not be empty.
+ *
+ *=================================================================
+ * @param sorted &[u64]
+ * @param quantile f64
+ * @return u64
+ */
+pub fn ino_quantile(sorted: &[u64], quantile: f64) -> u64 {
+    let rank = (sorted.len() as f64 * quantile).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+
+/**
+ *=================================================================
+ * ino_format_bytes()
+ *=================================================================
+ *
+ * Human readable size, in B, KiB or MiB.
+ *
+ *=================================================================
+ * @param bytes u64
+ * @return String
+ */
+pub fn ino_format_bytes(bytes: u64) -> String {
+    match bytes {
+        b if b < 1024 => format!("{} B", b),
+        b if b < 1024 * 1024 => format!("{:.1} KiB", b as f64 / 1024.0),
+        b => format!("{:.1} MiB", b as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+
+/**
+ *=================================================================
+ * ino_result()
+ *=================================================================
+ *
+ * Successful result for the tests, which set the fields they look
+ * at with a struct update.
+ *
+ *=================================================================
+ */
+#[cfg(test)]
+pub fn ino_result() -> BenchmarkResult {
+    BenchmarkResult::new("200 OK".to_string(), true, 1, 0, 0)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_saturate_durations_over_histogram_max() -> Result<()> {
+        let settings: Settings = serde_yaml::from_str("clients: 1\nrequests: 2\nhistogram_max: 1000\nhistogram_sigfig: 3")?;
+        let mut report = Report::new(&settings)?;
+        for duration in [10, 1_000_000] {
+            report.ino_add_result(BenchmarkResult {
+                duration,
+                size: Some(duration),
+                ..ino_result()
+            });
+        }
+        assert_eq!(report.hist.len(), 2);
+        assert!(report.hist.max() <= 1000);
+        Ok(())
+    }
+
+    #[test]
+    fn should_keep_a_sample_of_the_results() -> Result<()> {
+        let settings: Settings = serde_yaml::from_str("clients: 1\nrequests: 100\nsample_rate: 0.1")?;
+        let mut report = Report::new(&settings)?;
+        for duration in 1..=100 {
+            report.ino_add_result(BenchmarkResult {
+                status: "500 Internal Server Error".to_string(),
+                success: false,
+                duration,
+                reused: Some(duration > 1),
+                step: Some("checkout".to_string()),
+                ..ino_result()
+            });
+        }
+        let summary = report.ino_summary(&settings);
+        assert_eq!(report.results.len(), 10);
+        assert_eq!(report.results[0].duration, 1);
+        assert_eq!((summary.total_requests, summary.errors, summary.min, summary.max), (100, 100, 1, 100));
+        assert_eq!(summary.connections, Some(ConnectionSummary { new: 1, reused: 99 }));
+        let steps = summary.steps.unwrap_or_default();
+        assert_eq!((steps["checkout"].requests, steps["checkout"].errors, steps["checkout"].p95), (100, 100, 95));
+        assert!(ino_check_sample_rate(0.0).is_err());
+        assert!(ino_check_sample_rate(1.5).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn should_split_the_time_to_first_byte() -> Result<()> {
+        for (yaml, expected) in [("", Some(FirstByteSummary { mean: 5.0, p95: 5, p999: 5 })), ("\nlatency_boundary: headers", None)] {
+            let settings: Settings = serde_yaml::from_str(&format!("clients: 1\nrequests: 1{}", yaml))?;
+            let mut report = Report::new(&settings)?;
+            report.ino_add_result(BenchmarkResult {
+                duration: 40,
+                size: Some(10),
+                first_byte: Some(5),
+                ..ino_result()
+            });
+            assert_eq!(report.ino_summary(&settings).first_byte, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_response_size_percentiles() -> Result<()> {
+        let settings: Settings = serde_yaml::from_str("clients: 1\nrequests: 100")?;
+        let mut report = Report::new(&settings)?;
+        assert_eq!(report.ino_size_summary(), None);
+        for size in (1..=99).chain([300_000]) {
+            report.ino_add_result(BenchmarkResult {
+                size: Some(size),
+                ..ino_result()
+            });
+        }
+        let size = report.ino_size_summary().unwrap();
+        assert_eq!((size.p50, size.p95, size.max / 1000), (50, 95, 300));
+        assert_eq!(ino_format_bytes(1536), "1.5 KiB");
+        Ok(())
+    }
+
+    #[test]
+    fn should_count_new_and_reused_connections_per_client() -> Result<()> {
+        let settings: Settings = serde_yaml::from_str("clients: 2\nrequests: 4")?;
+        let mut report = Report::new(&settings)?;
+        assert_eq!(report.ino_connections(None), None);
+        for (num_client, reused) in [(0, false), (0, true), (1, false), (1, false)] {
+            report.ino_add_result(BenchmarkResult {
+                num_client,
+                reused: Some(reused),
+                ..ino_result()
+            });
+        }
+        assert_eq!(report.ino_connections(None), Some(ConnectionSummary { new: 3, reused: 1 }));
+        assert_eq!(report.ino_connections(Some(1)), Some(ConnectionSummary { new: 2, reused: 0 }));
+        Ok(())
+    }
+
+    #[test]
+    fn should_bucket_latency_by_status_class() -> Result<()> {
+        let settings: Settings = serde_yaml::from_str("clients: 1\nrequests: 4")?;
+        let mut report = Report::new(&settings)?;
+        assert_eq!(report.ino_status_timeline(), None);
+        for (status, success, duration) in [("200 OK", true, 10), ("200 OK", true, 30), ("503 Service Unavailable", false, 5), ("Failed to connect", false, 1)] {
+            report.ino_add_result(BenchmarkResult {
+                status: status.to_string(),
+                success,
+                duration,
+                ..ino_result()
+            });
+        }
+        let timeline = report.ino_status_timeline().unwrap();
+        assert_eq!(timeline.len(), 1);
+        assert_eq!(timeline[0].classes["2xx"], ClassLatency { count: 2, mean: 20.0 });
+        assert_eq!(timeline[0].classes["5xx"].count, 1);
+        assert_eq!(timeline[0].classes["error"].count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn should_correlate_resources_with_latency() -> Result<()> {
+        let settings: Settings = serde_yaml::from_str("clients: 1\nrequests: 2")?;
+        let mut report = Report::new(&settings)?;
+        for duration in [10, 30] {
+            report.ino_add_result(BenchmarkResult {
+                duration,
+                ..ino_result()
+            });
+        }
+        let sample = |offset| ResourceSample { offset, cpu_percent: Some(50.0), memory_bytes: None, requests: 0, mean_latency: None };
+        report.ino_set_resources(vec![sample(1.0), sample(2.0)]);
+        let resources = report.resources.unwrap();
+        assert_eq!((resources[0].requests, resources[0].mean_latency), (2, Some(20.0)));
+        assert_eq!((resources[1].requests, resources[1].mean_latency), (0, None));
+        Ok(())
+    }
+
+    #[test]
+    fn should_not_count_throttled_results_as_errors() {
+        let failed = BenchmarkResult { success: false, ..ino_result() };
+        let throttled = BenchmarkResult { success: false, throttled: Some(1000), ..ino_result() };
+        assert!(!ino_result().ino_is_error());
+        assert!(failed.ino_is_error());
+        assert!(!throttled.ino_is_error());
+    }
+}
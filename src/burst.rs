@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::support::ino_parse_duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Burst {
+    pub size: usize,
+    pub interval: Duration,
+}
+
+impl TryFrom<String> for Burst {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        ino_parse_burst(&value)
+    }
+}
+
+impl From<Burst> for String {
+    fn from(burst: Burst) -> Self {
+        format!("{}:{}ms", burst.size, burst.interval.as_millis())
+    }
+}
+
+pub struct BurstScheduler {
+    burst: Burst,
+    start: Instant,
+    window: Mutex<(u32, usize)>,
+}
+
+impl BurstScheduler {
+
+    /**
+    *=================================================================
+    * new()
+    *=================================================================
+    *
+    * Creates the scheduler shared by every client of a run. The
+    * windows are aligned on the start of the run.
+    *
+    *=================================================================
+    * @param burst Burst
+    * @param start Instant
+    * @return Self
+    */
+    pub fn new(burst: Burst, start: Instant) -> Self {
+        BurstScheduler {
+            burst,
+            start,
+            window: Mutex::new((0, 0)),
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_acquire()
+    *=================================================================
+    *
+    * Waits until a request may be sent: the first `size` requests
+    * of every interval go at once, the next ones wait for the
+    * following interval. Returns the start of the burst of the
+    * request, the instant it was due.
+    *
+    *=================================================================
+    * @param void
+    * @return Instant
+    */
+    pub async fn ino_acquire(&self) -> Instant {
+        loop {
+            let next = {
+                let mut window = self.window.lock().unwrap();
+                let current = (self.start.elapsed().as_nanos() / self.burst.interval.as_nanos()) as u32;
+                if window.0 != current {
+                    *window = (current, 0);
+                }
+                if window.1 < self.burst.size {
+                    window.1 += 1;
+                    return self.start + self.burst.interval * current;
+                }
+                self.start + self.burst.interval * (current + 1)
+            };
+            tokio::time::sleep_until(next).await;
+        }
+    }
+}
+
+/**
+ *=================================================================
+ * ino_parse_burst()
+ *=================================================================
+ *
+ * Parses a burst such as "100:2s", a count of requests and the
+ * interval between the bursts.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<Burst>
+ */
+pub fn ino_parse_burst(value: &str) -> Result<Burst> {
+    let (size, interval) = value
+        .split_once(':')
+        .with_context(|| format!("Invalid burst {}, use size:interval, ex. 100:2s", value))?;
+    let size: usize = size
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid burst size in {}", value))?;
+    let interval = ino_parse_duration(interval)?;
+    if size == 0 || interval.is_zero() {
+        anyhow::bail!("Invalid burst {}, the size and the interval must be positive", value);
+    }
+    Ok(Burst { size, interval })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_burst() -> Result<()> {
+        let burst = ino_parse_burst("100:2s")?;
+        assert_eq!(burst, Burst { size: 100, interval: Duration::from_secs(2) });
+        assert_eq!(String::from(burst), "100:2000ms");
+        assert!(ino_parse_burst("100").is_err());
+        assert!(ino_parse_burst("0:1s").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_hold_requests_until_next_burst() {
+        let begin = Instant::now();
+        let scheduler = BurstScheduler::new(Burst { size: 2, interval: Duration::from_millis(100) }, begin);
+        for _ in 0..5 {
+            scheduler.ino_acquire().await;
+        }
+        let elapsed = begin.elapsed();
+        assert!(elapsed >= Duration::from_millis(200) && elapsed < Duration::from_millis(300));
+    }
+}
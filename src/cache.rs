@@ -0,0 +1,183 @@
+use reqwest::header::{HeaderMap, AGE};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::benchmark::{ino_quantile, BenchmarkResult};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheHeaders {
+    pub age: Option<u64>,
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheSummary {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age: Option<AgeSummary>,
+    #[serde(default)]
+    pub statuses: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AgeSummary {
+    pub count: u64,
+    pub p50: u64,
+    pub p95: u64,
+    pub max: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    ages: Vec<u64>,
+    statuses: BTreeMap<String, u64>,
+    found: bool,
+}
+
+/**
+ *=================================================================
+ * ino_cache_bust()
+ *=================================================================
+ *
+ * Appends a query parameter with a value unique to the request,
+ * so no cache in front of the target can answer it. The value is
+ * made of the current time, the client and the iteration.
+ *
+ *=================================================================
+ * @param url &mut Url
+ * @param param &str
+ * @param num_client usize
+ * @param execution usize
+ * @return void
+ */
+pub fn ino_cache_bust(url: &mut Url, param: &str, num_client: usize, execution: usize) {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    url.query_pairs_mut()
+        .append_pair(param, &format!("{:x}-{}-{}", nanos, num_client, execution));
+}
+
+/**
+ *=================================================================
+ * ino_cache_headers()
+ *=================================================================
+ *
+ * Reads the Age and X-Cache headers of a response. The X-Cache
+ * status is reduced to its first word, "HIT from edge-1" and
+ * "hit, miss" both count as HIT. None when neither is present.
+ *
+ *=================================================================
+ * @param headers &HeaderMap
+ * @return Option<CacheHeaders>
+ */
+pub fn ino_cache_headers(headers: &HeaderMap) -> Option<CacheHeaders> {
+    let age = headers
+        .get(AGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok());
+    let status = headers
+        .get("x-cache")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split([',', ' ']).find(|s| !s.is_empty()))
+        .map(|s| s.to_uppercase());
+    (age.is_some() || status.is_some()).then_some(CacheHeaders { age, status })
+}
+
+impl CacheStats {
+
+    /**
+    *=================================================================
+    * ino_add()
+    *=================================================================
+    *
+    * Records the Age and X-Cache status of a result, if any.
+    *
+    *=================================================================
+    * @param result &BenchmarkResult
+    * @return void
+    */
+    pub fn ino_add(&mut self, result: &BenchmarkResult) {
+        let Some(cache) = &result.cache else { return };
+        self.found = true;
+        if let Some(age) = cache.age {
+            self.ages.push(age);
+        }
+        if let Some(status) = &cache.status {
+            *self.statuses.entry(status.clone()).or_insert(0) += 1;
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_summary()
+    *=================================================================
+    *
+    * Distribution of the Age header in seconds and count of every
+    * X-Cache status over the results. None when no response carried
+    * any of them.
+    *
+    *=================================================================
+    * @param void
+    * @return Option<CacheSummary>
+    */
+    pub fn ino_summary(&self) -> Option<CacheSummary> {
+        let mut ages = self.ages.clone();
+        ages.sort_unstable();
+        let age = (!ages.is_empty()).then(|| AgeSummary {
+            count: ages.len() as u64,
+            p50: ino_quantile(&ages, 0.5),
+            p95: ino_quantile(&ages, 0.95),
+            max: ino_quantile(&ages, 1.0),
+        });
+        self.found.then(|| CacheSummary { age, statuses: self.statuses.clone() })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::ino_result;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn should_append_query_parameter() {
+        let mut url = Url::parse("http://host/a?b=2#top").unwrap();
+        ino_cache_bust(&mut url, "cb", 3, 7);
+        let (param, value) = url.query_pairs().nth(1).unwrap();
+        assert_eq!(param, "cb");
+        assert!(value.ends_with("-3-7"));
+        assert_eq!(url.fragment(), Some("top"));
+    }
+
+    #[test]
+    fn should_summarize_ages_and_statuses() {
+        let result = |age, status: &str| BenchmarkResult {
+            cache: Some(CacheHeaders { age, status: Some(status.to_string()) }),
+            ..ino_result()
+        };
+        let mut stats = CacheStats::default();
+        assert_eq!(stats.ino_summary(), None);
+        let results: Vec<BenchmarkResult> = (1..=20).map(|age| result(Some(age), "HIT")).chain([result(None, "MISS")]).collect();
+        results.iter().for_each(|result| stats.ino_add(result));
+        let summary = stats.ino_summary().unwrap();
+        assert_eq!(summary.age, Some(AgeSummary { count: 20, p50: 10, p95: 19, max: 20 }));
+        assert_eq!(summary.statuses.get("HIT"), Some(&20));
+        assert_eq!(summary.statuses.get("MISS"), Some(&1));
+    }
+
+    #[test]
+    fn should_read_cache_headers() {
+        assert_eq!(ino_cache_headers(&HeaderMap::new()), None);
+        let mut headers = HeaderMap::new();
+        headers.insert(AGE, HeaderValue::from_static("42"));
+        headers.insert("x-cache", HeaderValue::from_static("Hit from cloudfront"));
+        assert_eq!(
+            ino_cache_headers(&headers),
+            Some(CacheHeaders { age: Some(42), status: Some("HIT".to_string()) })
+        );
+    }
+}
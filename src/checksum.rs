@@ -0,0 +1,117 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::{Display, Formatter};
+
+use crate::benchmark::BenchmarkResult;
+use crate::publish::ino_hex;
+
+pub const CORRUPTED: &str = "corrupted body";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Checksum([u8; 32]);
+
+impl TryFrom<String> for Checksum {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        ino_parse_checksum(&value)
+    }
+}
+
+impl From<Checksum> for String {
+    fn from(checksum: Checksum) -> Self {
+        checksum.to_string()
+    }
+}
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", ino_hex(&self.0))
+    }
+}
+
+impl Checksum {
+
+    /**
+    *=================================================================
+    * ino_matches()
+    *=================================================================
+    *
+    * Whether the hash of a body streamed into the digest is the
+    * expected one.
+    *
+    *=================================================================
+    * @param digest Sha256
+    * @return bool
+    */
+    pub fn ino_matches(&self, digest: Sha256) -> bool {
+        digest.finalize().as_slice() == self.0
+    }
+}
+
+/**
+ *=================================================================
+ * ino_parse_checksum()
+ *=================================================================
+ *
+ * Parses a SHA-256 hash written as 64 hexadecimal digits, in any
+ * case and optionally prefixed with "sha256:".
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<Checksum>
+ */
+pub fn ino_parse_checksum(value: &str) -> Result<Checksum> {
+    let hex = value.trim();
+    let hex = hex.strip_prefix("sha256:").unwrap_or(hex);
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("Invalid SHA-256 {}, 64 hexadecimal digits expected", value);
+    }
+    let mut bytes = [0; 32];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16)?;
+    }
+    Ok(Checksum(bytes))
+}
+
+/**
+ *=================================================================
+ * ino_corrupted()
+ *=================================================================
+ *
+ * Whether a successful response had a body that did not match its
+ * checksum.
+ *
+ *=================================================================
+ * @param result &BenchmarkResult
+ * @return bool
+ */
+pub fn ino_corrupted(result: &BenchmarkResult) -> bool {
+    result
+        .status
+        .strip_suffix(')')
+        .and_then(|status| status.strip_suffix(CORRUPTED))
+        .is_some_and(|status| status.ends_with('('))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_verify_the_streamed_body() -> Result<()> {
+        let checksum = ino_parse_checksum("sha256:2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824")?;
+        assert_eq!(checksum.to_string(), "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824");
+        let mut digest = Sha256::new();
+        digest.update(b"hel");
+        digest.update(b"lo");
+        assert!(checksum.ino_matches(digest));
+        assert!(!checksum.ino_matches(Sha256::new_with_prefix(b"hell")));
+        assert!(ino_parse_checksum("2cf24dba").is_err());
+        assert!(ino_parse_checksum(&"g".repeat(64)).is_err());
+        Ok(())
+    }
+}
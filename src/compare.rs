@@ -0,0 +1,189 @@
+use anyhow::Result;
+use colored::Colorize;
+use indicatif::MultiProgress;
+use tokio::sync::watch;
+
+use crate::benchmark::Summary;
+use crate::run::{ino_benchmark, ino_progress_bar};
+use crate::support::{HttpVersion, Settings};
+use crate::watcher::ino_change;
+
+const VERSIONS: [HttpVersion; 2] = [HttpVersion::Http1, HttpVersion::Http2];
+
+type Metric = (&'static str, fn(&Summary) -> f64, &'static str);
+
+/**
+ *=================================================================
+ * ino_compare()
+ *=================================================================
+ *
+ * Runs the workload of the settings once per HTTP version, back
+ * to back, and prints a comparison table. A version the target
+ * does not speak is reported and left out of the table.
+ *
+ *=================================================================
+ * @param settings Settings
+ * @return Result<()>
+ */
+pub async fn ino_compare(settings: Settings) -> Result<()> {
+    settings.ino_print_banner();
+    let (tx_sigint, rx_sigint) = watch::channel(None);
+    ctrlc::set_handler(move || {
+        tx_sigint.send(Some(())).unwrap_or(());
+    })?;
+    let multi = MultiProgress::new();
+    let mut results = vec![];
+    for version in VERSIONS {
+        let mut settings = settings.clone();
+        settings.http_version = version;
+        let pb = ino_progress_bar(&multi, ino_version_name(version), &settings);
+        let report = ino_benchmark(&settings, rx_sigint.clone(), pb.clone(), None, None).await;
+        pb.finish();
+        match report {
+            Ok(report) => results.push((version, Some(report.ino_summary(&settings)))),
+            Err(e) => {
+                multi.suspend(|| println!("{} {} {:#}", "Skipped".red(), ino_version_name(version).purple(), e));
+                results.push((version, None));
+            }
+        }
+        if rx_sigint.borrow().is_some() {
+            break;
+        }
+    }
+    println!();
+    for row in ino_rows(&results) {
+        println!("{:<12} {:>14} {:>14} {:>10}", row[0], row[1], row[2], row[3]);
+    }
+    Ok(())
+}
+
+/**
+ *=================================================================
+ * ino_rows()
+ *=================================================================
+ *
+ * Cells of the comparison table: a header, then one row per
+ * metric with the value of each version and the change of the
+ * second version against the first.
+ *
+ *=================================================================
+ * @param results &[(HttpVersion, Option<Summary>)]
+ * @return Vec<[String; 4]>
+ */
+pub fn ino_rows(results: &[(HttpVersion, Option<Summary>)]) -> Vec<[String; 4]> {
+    let summary = |version: HttpVersion| {
+        results
+            .iter()
+            .find(|(v, _)| *v == version)
+            .and_then(|(_, s)| s.as_ref())
+    };
+    let (first, second) = (summary(VERSIONS[0]), summary(VERSIONS[1]));
+    let metrics: [Metric; 7] = [
+        ("Requests", |s| s.total_requests as f64, ""),
+        ("Errors", |s| s.errors as f64, ""),
+        ("Throughput", |s| s.throughput, " rps"),
+        ("Mean", |s| s.mean, " ms"),
+        ("p95", |s| s.p95 as f64, " ms"),
+        ("p99.9", |s| s.p999 as f64, " ms"),
+        ("Max", |s| s.max as f64, " ms"),
+    ];
+    let mut rows = vec![[
+        String::new(),
+        ino_version_name(VERSIONS[0]).to_string(),
+        ino_version_name(VERSIONS[1]).to_string(),
+        "Change".to_string(),
+    ]];
+    for (name, metric, unit) in metrics {
+        let cell = |s: Option<&Summary>| match s {
+            None => "-".to_string(),
+            Some(s) => format!("{:.1}{}", metric(s), unit),
+        };
+        let change = match (first, second) {
+            (Some(a), Some(b)) => format!("{:+.1}%", ino_change(metric(b), metric(a))),
+            _ => "-".to_string(),
+        };
+        rows.push([name.to_string(), cell(first), cell(second), change]);
+    }
+    rows
+}
+
+/**
+ *=================================================================
+ * ino_version_name()
+ *=================================================================
+ *
+ * Display name of an HTTP version.
+ *
+ *=================================================================
+ */
+fn ino_version_name(version: HttpVersion) -> &'static str {
+    match version {
+        HttpVersion::Http1 => "HTTP/1.1",
+        HttpVersion::Http2 => "HTTP/2",
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::Tags;
+
+    fn ino_summary(throughput: f64) -> Summary {
+        Summary {
+            target: "http://localhost".to_string(),
+            label: None,
+            tags: Tags::new(),
+            timestamp: 0,
+            clients: 1,
+            total_requests: 100,
+            errors: 0,
+            elapsed: 1.0,
+            throughput,
+            mean: 10.0,
+            max: 100,
+            min: 1,
+            p95: 20,
+            p999: 50,
+            percentiles: Default::default(),
+            first_byte: None,
+            response_size: None,
+            connections: None,
+            generator: None,
+            accounting: None,
+            resources: None,
+            cache: None,
+            conditional: None,
+            ranges: None,
+            download: None,
+            upload: None,
+            kafka: None,
+            corrupted: None,
+            local_errors: None,
+            schedule: None,
+            throttled: None,
+            polls: None,
+            sse: None,
+            steps: None,
+            score: None,
+            response_headers: None,
+            status_timeline: None,
+        }
+    }
+
+    #[test]
+    fn should_compare_second_version_against_first() {
+        let rows = ino_rows(&[
+            (HttpVersion::Http1, Some(ino_summary(100.0))),
+            (HttpVersion::Http2, Some(ino_summary(150.0))),
+        ]);
+        assert_eq!(rows[0][1], "HTTP/1.1");
+        assert_eq!(rows[3], ["Throughput", "100.0 rps", "150.0 rps", "+50.0%"]);
+    }
+
+    #[test]
+    fn should_leave_out_skipped_version() {
+        let rows = ino_rows(&[(HttpVersion::Http1, Some(ino_summary(100.0))), (HttpVersion::Http2, None)]);
+        assert_eq!(rows[3], ["Throughput", "100.0 rps", "-", "-"]);
+    }
+}
@@ -0,0 +1,176 @@
+use anyhow::{bail, Context, Result};
+use reqwest::header::{ETAG, LAST_MODIFIED};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::benchmark::{ino_quantile, BenchmarkResult};
+use crate::execution::{ino_build_request, ino_read_body};
+use crate::support::{Header, Operation, Settings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ConditionalSummary {
+    pub not_modified: u64,
+    pub modified: u64,
+    pub not_modified_ratio: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_modified_latency: Option<StatusLatency>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_latency: Option<StatusLatency>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StatusLatency {
+    pub mean: f64,
+    pub p95: u64,
+    pub max: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct ConditionalStats {
+    not_modified: Vec<u64>,
+    modified: Vec<u64>,
+}
+
+/**
+ *=================================================================
+ * ino_validators()
+ *=================================================================
+ *
+ * Fetches the target once and turns its ETag and Last-Modified
+ * into the If-None-Match and If-Modified-Since headers of the
+ * conditional requests. Fails when the target returns neither.
+ *
+ *=================================================================
+ * @param settings &Settings
+ * @param client &Client
+ * @return Result<Vec<Header>>
+ */
+pub async fn ino_validators(settings: &Settings, client: &Client) -> Result<Vec<Header>> {
+    let target = settings.ino_target();
+    let mut response = ino_build_request(client, settings, Operation::Get, target.clone())
+        .send()
+        .await
+        .with_context(|| format!("Can not fetch {} to capture its validators", target))?;
+    let validator = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let mut headers = vec![];
+    if let Some(etag) = validator(ETAG) {
+        headers.push(Header { key: "If-None-Match".to_string(), value: etag });
+    }
+    if let Some(last_modified) = validator(LAST_MODIFIED) {
+        headers.push(Header { key: "If-Modified-Since".to_string(), value: last_modified });
+    }
+    ino_read_body(&mut response, &mut vec![], 0, &[], None).await;
+    if headers.is_empty() {
+        bail!("{} returned neither an ETag nor a Last-Modified header, conditional requests are not possible", target);
+    }
+    Ok(headers)
+}
+
+impl ConditionalStats {
+
+    /**
+    *=================================================================
+    * ino_add()
+    *=================================================================
+    *
+    * Records the latency of a 304 Not Modified or 200 OK response,
+    * other statuses are left out.
+    *
+    *=================================================================
+    * @param result &BenchmarkResult
+    * @return void
+    */
+    pub fn ino_add(&mut self, result: &BenchmarkResult) {
+        if result.status.starts_with("304") {
+            self.not_modified.push(result.duration);
+        } else if result.status.starts_with("200") {
+            self.modified.push(result.duration);
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_summary()
+    *=================================================================
+    *
+    * Splits the results into 304 Not Modified and 200 OK responses,
+    * with their ratio and the latency of each, so a cache answering
+    * quickly is not hidden by the full responses.
+    *
+    *=================================================================
+    * @param void
+    * @return ConditionalSummary
+    */
+    pub fn ino_summary(&self) -> ConditionalSummary {
+        let sorted = |durations: &[u64]| {
+            let mut durations = durations.to_vec();
+            durations.sort_unstable();
+            durations
+        };
+        let (not_modified, modified) = (sorted(&self.not_modified), sorted(&self.modified));
+        let total = not_modified.len() + modified.len();
+        ConditionalSummary {
+            not_modified: not_modified.len() as u64,
+            modified: modified.len() as u64,
+            not_modified_ratio: match total {
+                0 => 0.0,
+                total => not_modified.len() as f64 / total as f64,
+            },
+            not_modified_latency: ino_status_latency(&not_modified),
+            modified_latency: ino_status_latency(&modified),
+        }
+    }
+}
+
+/**
+ *=================================================================
+ * ino_status_latency()
+ *=================================================================
+ *
+ * Mean, p95 and max of sorted durations, None when empty.
+ *
+ *=================================================================
+ */
+fn ino_status_latency(sorted: &[u64]) -> Option<StatusLatency> {
+    (!sorted.is_empty()).then(|| StatusLatency {
+        mean: sorted.iter().sum::<u64>() as f64 / sorted.len() as f64,
+        p95: ino_quantile(sorted, 0.95),
+        max: ino_quantile(sorted, 1.0),
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::ino_result;
+
+    #[test]
+    fn should_split_not_modified_from_full_responses() {
+        let result = |status: &str, duration| BenchmarkResult {
+            status: status.to_string(),
+            duration,
+            ..ino_result()
+        };
+        let results = [
+            result("304 Not Modified", 2),
+            result("304 Not Modified", 4),
+            result("304 Not Modified", 6),
+            result("200 OK", 40),
+            result("500 Internal Server Error", 1),
+        ];
+        let mut stats = ConditionalStats::default();
+        results.iter().for_each(|result| stats.ino_add(result));
+        let summary = stats.ino_summary();
+        assert_eq!((summary.not_modified, summary.modified), (3, 1));
+        assert_eq!(summary.not_modified_ratio, 0.75);
+        assert_eq!(summary.not_modified_latency, Some(StatusLatency { mean: 4.0, p95: 6, max: 6 }));
+        assert_eq!(summary.modified_latency.map(|l| l.max), Some(40));
+    }
+}
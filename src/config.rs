@@ -0,0 +1,189 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::bands::LatencyBands;
+use crate::prometheus::Prometheus;
+use crate::support::{human_duration, LatencyBoundary, OutputFormat};
+use crate::tags::{ino_check_tag_key, Tags};
+
+const CONFIG_DIR: &str = "inoue";
+const CONFIG_FILE: &str = "config.yaml";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Defaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<OutputFormat>,
+    #[serde(default, skip_serializing_if = "Tags::is_empty")]
+    pub tags: Tags,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_bands: Option<LatencyBands>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "human_duration::option")]
+    pub keep_alive: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub histogram_sigfig: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "human_duration::millis")]
+    pub histogram_max: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentiles: Option<Vec<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "human_duration::option")]
+    pub timeout: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_boundary: Option<LatencyBoundary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workers: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_max_idle_per_host: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_connections_per_host: Option<usize>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hosts: BTreeMap<String, IpAddr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publish: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prometheus: Option<Prometheus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub save_history: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_precheck: Option<bool>,
+}
+
+impl Defaults {
+
+    /**
+    *=================================================================
+    * ino_load()
+    *=================================================================
+    *
+    * Loads the defaults of the user from
+    * $XDG_CONFIG_HOME/inoue/config.yaml, ~/.config/inoue/config.yaml
+    * when the variable is not set. A missing file is no defaults, an
+    * invalid one is reported before the run starts.
+    *
+    *=================================================================
+    * @param void
+    * @return Result<Defaults>
+    */
+    pub fn ino_load() -> Result<Self> {
+        let Some(path) = ino_config_path() else {
+            return Ok(Defaults::default());
+        };
+        let content = match fs::read_to_string(&path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Defaults::default()),
+            content => content.with_context(|| format!("Failed to read file from {}", path.display()))?,
+        };
+        ino_parse(&content).with_context(|| format!("Invalid defaults in {}", path.display()))
+    }
+
+    /**
+    *=================================================================
+    * ino_merge_under()
+    *=================================================================
+    *
+    * Merges the defaults under a scenario, before it is read into
+    * settings: a key of the defaults is only taken when the scenario
+    * does not set it, and the maps, the tags or the host mapping,
+    * are merged key by key.
+    *
+    *=================================================================
+    * @param scenario &mut Value
+    * @return Result<()>
+    */
+    pub fn ino_merge_under(&self, scenario: &mut Value) -> Result<()> {
+        ino_merge(scenario, serde_yaml::to_value(self)?);
+        Ok(())
+    }
+}
+
+/**
+ *=================================================================
+ * ino_config_path()
+ *=================================================================
+ *
+ * Location of the defaults file, None when neither the
+ * configuration directory nor the home directory can be resolved.
+ *
+ *=================================================================
+ */
+fn ino_config_path() -> Option<PathBuf> {
+    let directory = match env::var_os("XDG_CONFIG_HOME").filter(|directory| !directory.is_empty()) {
+        Some(directory) => PathBuf::from(directory),
+        None => PathBuf::from(env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?).join(".config"),
+    };
+    Some(directory.join(CONFIG_DIR).join(CONFIG_FILE))
+}
+
+fn ino_parse(content: &str) -> Result<Defaults> {
+    if content.trim().is_empty() {
+        return Ok(Defaults::default());
+    }
+    let defaults: Defaults = serde_yaml::from_str(content)?;
+    for key in defaults.tags.keys() {
+        ino_check_tag_key(key)?;
+    }
+    Ok(defaults)
+}
+
+fn ino_merge(value: &mut Value, defaults: Value) {
+    match (value, defaults) {
+        (Value::Mapping(mapping), Value::Mapping(defaults)) => {
+            for (key, default) in defaults {
+                match mapping.get_mut(&key) {
+                    None => {
+                        mapping.insert(key, default);
+                    }
+                    Some(value) => ino_merge(value, default),
+                }
+            }
+        }
+        (value @ Value::Null, defaults) => *value = defaults,
+        _ => {}
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use crate::support::{Args, Settings};
+
+    #[test]
+    fn should_merge_the_defaults_under_the_scenario() -> Result<()> {
+        let defaults = ino_parse(
+            "format: json\nkeep_alive: 1m\nhistogram_max: 30s\ntimeout: 5s\npercentiles: [50, 99]\nproxy: http://proxy:3128\n\
+             tags:\n  team: checkout\n  env: staging\nsave_history: true",
+        )?;
+        let mut scenario: Value =
+            serde_yaml::from_str("target: http://host/\nrequests: 1\nclients: 1\nformat: text\ntimeout: 2s\ntags:\n  env: prod")?;
+        defaults.ino_merge_under(&mut scenario)?;
+        let mut settings: Settings = serde_yaml::from_value(scenario)?;
+        assert_eq!(settings.format, OutputFormat::Text);
+        assert_eq!(settings.tags, Tags::from([("env".to_string(), "prod".to_string()), ("team".to_string(), "checkout".to_string())]));
+        assert_eq!((settings.keep_alive, settings.histogram_max), (Some(Duration::from_secs(60)), Some(30000)));
+        assert!(settings.save_history);
+        assert_eq!(settings.timeout, Some(Duration::from_secs(2)));
+        assert_eq!((settings.percentiles.as_slice(), settings.proxy.as_deref()), ([50.0, 99.0].as_slice(), Some("http://proxy:3128")));
+        settings.ino_apply_args(&Args::parse_from(["inoue", "--scenario", "s.yaml", "--percentiles", "90,99.9", "--proxy", "http://other/"]));
+        assert_eq!((settings.percentiles.as_slice(), settings.proxy.as_deref()), ([90.0, 99.9].as_slice(), Some("http://other/")));
+        let mut args = Args::parse_from(["inoue", "-t", "http://host/", "--timeout", "1s"]);
+        args.ino_apply_defaults(&defaults);
+        let settings = Settings::ino_from_args(args)?;
+        assert_eq!((settings.timeout, settings.percentiles), (Some(Duration::from_secs(1)), vec![50.0, 99.0]));
+        assert!(ino_parse("retries: 3").is_err() && ino_parse("tags:\n  build-id: 1").is_err());
+        assert_eq!(ino_parse("")?, Defaults::default());
+        Ok(())
+    }
+}
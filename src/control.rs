@@ -0,0 +1,448 @@
+use anyhow::Result;
+use std::io::BufRead;
+use std::sync::Arc;
+use std::thread;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender, WeakSender};
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use crate::benchmark::BenchmarkResult;
+use crate::bandwidth::BandwidthLimiter;
+use crate::burst::BurstScheduler;
+use crate::execution::{ino_build_worker_client, ino_spawn_worker, Protocol};
+use crate::monitor::{Accounting, SendStats};
+use crate::rate::RateShare;
+use crate::replay::Replay;
+use crate::stages::Connections;
+use crate::support::Settings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ControlState {
+    pub paused: bool,
+    pub active_clients: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    AddClient,
+    RemoveClient,
+    TogglePause,
+    Summary,
+}
+
+pub struct RunControl {
+    start: Instant,
+    state: watch::Sender<ControlState>,
+    spawned: usize,
+    settings: Settings,
+    replay: Option<Arc<Replay>>,
+    protocol: Arc<Protocol>,
+    burst: Option<Arc<BurstScheduler>>,
+    rate: Option<RateShare>,
+    bandwidth: Option<Arc<BandwidthLimiter>>,
+    stats: Arc<SendStats>,
+    iterations: Option<Arc<IterationPool>>,
+    tx: WeakSender<BenchmarkResult>,
+    rx_sigint: watch::Receiver<Option<()>>,
+}
+
+pub struct IterationPool {
+    remaining: watch::Sender<usize>,
+}
+
+impl RunControl {
+
+    /**
+    *=================================================================
+    * new()
+    *=================================================================
+    *
+    * Creates the control of a run whose `spawned` workers are all
+    * active. Only a weak sender is kept so the results channel still
+    * closes once every worker is done. In iteration mode the
+    * iterations are taken from the pool shared by the workers.
+    *
+    *=================================================================
+    */
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        start: Instant,
+        spawned: usize,
+        settings: Settings,
+        replay: Option<Arc<Replay>>,
+        protocol: Arc<Protocol>,
+        burst: Option<Arc<BurstScheduler>>,
+        rate: Option<RateShare>,
+        bandwidth: Option<Arc<BandwidthLimiter>>,
+        stats: Arc<SendStats>,
+        iterations: Option<Arc<IterationPool>>,
+        tx: WeakSender<BenchmarkResult>,
+        rx_sigint: watch::Receiver<Option<()>>,
+    ) -> Self {
+        let (state, _) = watch::channel(ControlState {
+            paused: false,
+            active_clients: spawned,
+        });
+        RunControl {
+            start,
+            state,
+            spawned,
+            settings,
+            replay,
+            protocol,
+            burst,
+            rate,
+            bandwidth,
+            stats,
+            iterations,
+            tx,
+            rx_sigint,
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_subscribe()
+    *=================================================================
+    *
+    * Receiver given to every worker to follow the control state.
+    *
+    *=================================================================
+    */
+    pub fn ino_subscribe(&self) -> watch::Receiver<ControlState> {
+        self.state.subscribe()
+    }
+
+    /**
+    *=================================================================
+    * ino_state()
+    *=================================================================
+    */
+    pub fn ino_state(&self) -> ControlState {
+        *self.state.borrow()
+    }
+
+    /**
+    *=================================================================
+    * ino_send_stats()
+    *=================================================================
+    *
+    * Blocked and dropped result sends of the workers of the run.
+    *
+    *=================================================================
+    * @return (u64, u64)
+    */
+    pub fn ino_send_stats(&self) -> (u64, u64) {
+        self.stats.ino_counts()
+    }
+
+    /**
+    *=================================================================
+    * ino_accounting()
+    *=================================================================
+    *
+    * Accounting audit of the run, see SendStats::ino_accounting.
+    *
+    *=================================================================
+    * @param completed u64
+    * @param failed u64
+    * @return Accounting
+    */
+    pub fn ino_accounting(&self, completed: u64, failed: u64) -> Accounting {
+        self.stats.ino_accounting(completed, failed, self.settings.steps.is_none())
+    }
+
+    /**
+    *=================================================================
+    * ino_add_client()
+    *=================================================================
+    *
+    * Re-activates a parked worker or spawns a new one. Nothing is
+    * spawned once the run is over. In iteration mode a new worker
+    * adds its even share to the iterations of the run.
+    *
+    *=================================================================
+    * @return Result<()>
+    */
+    pub fn ino_add_client(&mut self) -> Result<()> {
+        let active = self.state.borrow().active_clients;
+        if active >= self.spawned {
+            let Some(tx) = self.tx.upgrade() else { return Ok(()) };
+            let client = ino_build_worker_client(&self.settings, self.spawned)?;
+            if let Some(iterations) = &self.iterations {
+                iterations.ino_add(self.settings.ino_requests_by_client(self.spawned));
+            }
+            ino_spawn_worker(
+                self.spawned,
+                &self.settings,
+                client,
+                Connections::default(),
+                self.replay.clone(),
+                self.protocol.clone(),
+                self.burst.clone(),
+                self.rate.clone(),
+                self.bandwidth.clone(),
+                self.ino_subscribe(),
+                self.start,
+                self.stats.clone(),
+                self.iterations.clone(),
+                tx,
+                self.rx_sigint.clone(),
+            );
+            self.spawned += 1;
+        }
+        self.state.send_modify(|s| s.active_clients += 1);
+        Ok(())
+    }
+
+    /**
+    *=================================================================
+    * ino_remove_client()
+    *=================================================================
+    *
+    * Parks the worker with the highest id, at least one stays active.
+    * In iteration mode the active workers take over the iterations
+    * the parked one would have sent.
+    *
+    *=================================================================
+    */
+    pub fn ino_remove_client(&self) {
+        self.state.send_modify(|s| s.active_clients = s.active_clients.saturating_sub(1).max(1));
+    }
+
+    /**
+    *=================================================================
+    * ino_toggle_pause()
+    *=================================================================
+    */
+    pub fn ino_toggle_pause(&self) {
+        self.state.send_modify(|s| s.paused = !s.paused);
+    }
+}
+
+impl IterationPool {
+
+    /**
+    *=================================================================
+    * new()
+    *=================================================================
+    *
+    * Iterations left to send in iteration mode. The workers take
+    * them one by one, so the share of a parked worker goes to the
+    * active ones.
+    *
+    *=================================================================
+    * @param iterations usize
+    * @return Self
+    */
+    pub fn new(iterations: usize) -> Self {
+        IterationPool { remaining: watch::channel(iterations).0 }
+    }
+
+    /**
+    *=================================================================
+    * ino_take()
+    *=================================================================
+    *
+    * Takes an iteration, false once none is left. Only taking the
+    * last one wakes the waiting workers.
+    *
+    *=================================================================
+    * @return bool
+    */
+    pub fn ino_take(&self) -> bool {
+        let mut taken = false;
+        self.remaining.send_if_modified(|remaining| {
+            taken = *remaining > 0;
+            *remaining = remaining.saturating_sub(1);
+            taken && *remaining == 0
+        });
+        taken
+    }
+
+    /**
+    *=================================================================
+    * ino_add()
+    *=================================================================
+    */
+    pub fn ino_add(&self, iterations: usize) {
+        self.remaining.send_modify(|remaining| *remaining += iterations);
+    }
+}
+
+/**
+ *=================================================================
+ * ino_wait_turn()
+ *=================================================================
+ *
+ * Waits until the run is not paused and the worker is active.
+ * Returns false when a stop signal is received while waiting, or
+ * in iteration mode once every iteration is taken, so a parked
+ * worker does not hold the run after the others are done.
+ *
+ *=================================================================
+ * @param control &mut watch::Receiver<ControlState>
+ * @param num_client usize
+ * @param iterations Option<&IterationPool>
+ * @param rx_sigint &mut watch::Receiver<Option<()>>
+ * @return bool
+ */
+pub async fn ino_wait_turn(
+    control: &mut watch::Receiver<ControlState>,
+    num_client: usize,
+    iterations: Option<&IterationPool>,
+    rx_sigint: &mut watch::Receiver<Option<()>>,
+) -> bool {
+    let mut remaining = iterations.map(|iterations| iterations.remaining.subscribe());
+    loop {
+        {
+            let state = control.borrow_and_update();
+            if !state.paused && num_client < state.active_clients {
+                return true;
+            }
+        }
+        if remaining.as_mut().is_some_and(|remaining| *remaining.borrow_and_update() == 0) {
+            return false;
+        }
+        let drained = async {
+            match remaining.as_mut() {
+                Some(remaining) => remaining.changed().await.unwrap_or_default(),
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            changed = control.changed() => if changed.is_err() { return true },
+            _ = drained => {}
+            _ = rx_sigint.changed() => return false,
+        }
+    }
+}
+
+/**
+ *=================================================================
+ * ino_commands()
+ *=================================================================
+ *
+ * Channel of the run control commands. SIGUSR1 pauses or resumes
+ * the dispatch on unix, and with the interactive mode the
+ * commands typed on stdin are read too.
+ *
+ *=================================================================
+ * @param interactive bool
+ * @return UnboundedReceiver<Command>
+ */
+pub fn ino_commands(interactive: bool) -> UnboundedReceiver<Command> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    if interactive {
+        ino_read_commands(tx.clone());
+    }
+    ino_listen_pause_signal(tx);
+    rx
+}
+
+/**
+ *=================================================================
+ * ino_read_commands()
+ *=================================================================
+ *
+ * Reads the interactive commands typed on stdin, one per line:
+ * '+' adds a client, '-' removes one, 'p' pauses or resumes the
+ * dispatch and 's' prints an interim summary. A plain thread is
+ * used so a pending read never blocks the runtime shutdown.
+ *
+ *=================================================================
+ */
+fn ino_read_commands(tx: UnboundedSender<Command>) {
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            for key in line.trim().chars() {
+                let command = match key {
+                    '+' => Command::AddClient,
+                    '-' => Command::RemoveClient,
+                    'p' | 'P' => Command::TogglePause,
+                    's' | 'S' => Command::Summary,
+                    _ => continue,
+                };
+                if tx.send(command).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+/**
+ *=================================================================
+ * ino_listen_pause_signal()
+ *=================================================================
+ *
+ * Turns every SIGUSR1 received into a pause toggle, until the run
+ * is over.
+ *
+ *=================================================================
+ */
+#[cfg(unix)]
+fn ino_listen_pause_signal(tx: UnboundedSender<Command>) {
+    use tokio::signal::unix::{signal, SignalKind};
+    let Ok(mut signal) = signal(SignalKind::user_defined1()) else { return };
+    tokio::spawn(async move {
+        while signal.recv().await.is_some() {
+            if tx.send(Command::TogglePause).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn ino_listen_pause_signal(_tx: UnboundedSender<Command>) {}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use crate::execution::ino_run;
+    use crate::support::Args;
+
+    #[tokio::test]
+    async fn should_end_an_iteration_run_with_a_parked_client() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let target = format!("http://{}/", listener.local_addr()?);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0; 1024];
+                    while stream.read(&mut request).await.is_ok_and(|read| read > 0) {
+                        if stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        let args = Args::parse_from(["inoue", "-t", &target, "-c", "3", "-i", "30", "--no-precheck"]);
+        let settings = Settings::ino_from_args(args)?;
+        let (tx, mut rx) = mpsc::channel(100);
+        let (_stop, rx_sigint) = watch::channel(None);
+        let control = ino_run(settings, tx, rx_sigint, None, None).await?;
+        control.ino_remove_client();
+        control.ino_remove_client();
+        let received = tokio::time::timeout(Duration::from_secs(10), async {
+            let mut received = vec![];
+            while let Some(result) = rx.recv().await {
+                received.push(result.num_client);
+            }
+            received
+        })
+        .await?;
+        assert_eq!(received.len(), 30);
+        assert!(received.iter().filter(|num_client| **num_client == 0).count() >= 20);
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::{bail, Context, Result};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::Identity;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::support::{ino_to_header_map, Header};
+
+#[derive(Eq, PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Credential {
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default)]
+    pub headers: Option<Vec<Header>>,
+    #[serde(default)]
+    pub cert: Option<String>,
+}
+
+impl Credential {
+
+    /**
+    *=================================================================
+    * ino_header_map()
+    *=================================================================
+    *
+    * Headers sent by the client owning the credential: its token as
+    * a bearer Authorization and its own headers, API keys usually.
+    *
+    *=================================================================
+    * @param void
+    * @return Result<HeaderMap>
+    */
+    pub fn ino_header_map(&self) -> Result<HeaderMap> {
+        let mut headers = ino_to_header_map(self.headers.iter().flatten())?;
+        if let Some(token) = &self.token {
+            let value = HeaderValue::from_str(&format!("Bearer {}", token.trim()))
+                .with_context(|| "Invalid token in the credentials".to_string())?;
+            headers.insert(AUTHORIZATION, value);
+        }
+        Ok(headers)
+    }
+
+    /**
+    *=================================================================
+    * ino_identity()
+    *=================================================================
+    *
+    * Client certificate of the credential, read from a PEM file
+    * holding the certificate chain and the private key.
+    *
+    *=================================================================
+    * @param void
+    * @return Result<Option<Identity>>
+    */
+    pub fn ino_identity(&self) -> Result<Option<Identity>> {
+        let Some(cert) = &self.cert else { return Ok(None) };
+        let pem = fs::read(cert).with_context(|| format!("Failed to read the client certificate {}", cert))?;
+        let identity = Identity::from_pem(&pem)
+            .with_context(|| format!("Invalid client certificate {}, a PEM file with the certificate and its key is expected", cert))?;
+        Ok(Some(identity))
+    }
+}
+
+/**
+ *=================================================================
+ * ino_read_tokens()
+ *=================================================================
+ *
+ * Reads a token file, one token per line, blank lines and lines
+ * starting with # are skipped.
+ *
+ *=================================================================
+ * @param file &str
+ * @return Result<Vec<Credential>>
+ */
+pub fn ino_read_tokens(file: &str) -> Result<Vec<Credential>> {
+    let content = fs::read_to_string(file).with_context(|| format!("Failed to read the tokens from {}", file))?;
+    let credentials: Vec<Credential> = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|token| Credential { token: Some(token.to_string()), ..Default::default() })
+        .collect();
+    if credentials.is_empty() {
+        bail!("No token in {}", file);
+    }
+    Ok(credentials)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_build_credential_headers() -> Result<()> {
+        let credential: Credential = serde_yaml::from_str("token: abc\nheaders:\n  - key: X-API-Key\n    value: k1")?;
+        let headers = credential.ino_header_map()?;
+        assert_eq!(headers.get(AUTHORIZATION).unwrap(), "Bearer abc");
+        assert_eq!(headers.get("x-api-key").unwrap(), "k1");
+        assert!(credential.ino_identity()?.is_none());
+        let invalid = Credential { token: Some("a\nb".to_string()), ..Default::default() };
+        assert!(invalid.ino_header_map().is_err());
+        Ok(())
+    }
+}
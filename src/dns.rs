@@ -0,0 +1,327 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{lookup_host, TcpStream, UdpSocket};
+use tokio::time::{timeout, Instant};
+
+use crate::benchmark::BenchmarkResult;
+
+const DNS_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub const RNG_STREAM: u64 = 1;
+
+#[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct DnsQuery {
+    pub name: String,
+    #[serde(default = "ino_default_record_type")]
+    pub record_type: String,
+    pub server: String,
+    #[serde(default)]
+    pub tcp: bool,
+}
+
+fn ino_default_record_type() -> String {
+    "A".to_string()
+}
+
+impl DnsQuery {
+
+    /**
+    *=================================================================
+    * ino_from_args()
+    *=================================================================
+    *
+    * Builds the query from the command line values
+    * "<name> [type] [@server]", the server defaults to the target.
+    *
+    *=================================================================
+    * @param values &[String]
+    * @param target Option<&str>
+    * @param tcp bool
+    * @return Result<DnsQuery>
+    */
+    pub fn ino_from_args(values: &[String], target: Option<&str>, tcp: bool) -> Result<Self> {
+        let mut name = None;
+        let mut record_type = ino_default_record_type();
+        let mut server = target.map(|t| t.trim_start_matches('@').to_string());
+        for value in values {
+            match value.strip_prefix('@') {
+                Some(s) => server = Some(s.to_string()),
+                None if name.is_none() => name = Some(value.clone()),
+                None => record_type = value.clone(),
+            }
+        }
+        Ok(DnsQuery {
+            name: name.with_context(|| "The DNS query needs a name".to_string())?,
+            record_type,
+            server: server.with_context(|| "The DNS query needs a @server".to_string())?,
+            tcp,
+        })
+    }
+}
+
+pub struct Dns {
+    server: SocketAddr,
+    tcp: bool,
+    question: Vec<u8>,
+    ids: Mutex<StdRng>,
+}
+
+impl Dns {
+
+    /**
+    *=================================================================
+    * ino_from_query()
+    *=================================================================
+    *
+    * Resolves the server once ("1.1.1.1", "1.1.1.1:5353",
+    * "tcp://..." or "udp://...") and encodes the question section.
+    *
+    *=================================================================
+    * @param query &DnsQuery
+    * @param rng StdRng draws the message ids
+    * @return Result<Dns>
+    */
+    pub async fn ino_from_query(query: &DnsQuery, rng: StdRng) -> Result<Self> {
+        let (tcp, server) = match query.server.split_once("://") {
+            None => (query.tcp, query.server.as_str()),
+            Some(("udp", server)) => (false, server),
+            Some(("tcp", server)) => (true, server),
+            Some((scheme, _)) => bail!("Unsupported scheme {} for dns, use udp:// or tcp://", scheme),
+        };
+        let server = server.trim_end_matches('/');
+        let server = match (server.parse::<SocketAddr>(), server.parse::<IpAddr>()) {
+            (Ok(addr), _) => addr,
+            (_, Ok(ip)) => SocketAddr::new(ip, 53),
+            _ => {
+                let with_port = match server.contains(':') {
+                    true => server.to_string(),
+                    false => format!("{}:53", server),
+                };
+                let resolved = lookup_host(&with_port)
+                    .await
+                    .with_context(|| format!("Can not resolve DNS server {}", server))?
+                    .next()
+                    .with_context(|| format!("No address found for {}", server))?;
+                resolved
+            }
+        };
+        let question = ino_encode_question(&query.name, ino_record_type(&query.record_type)?)?;
+        Ok(Dns { server, tcp, question, ids: Mutex::new(rng) })
+    }
+
+    /**
+    *=================================================================
+    * ino_exec()
+    *=================================================================
+    *
+    * Sends a single query and waits for the matching answer. The
+    * status is the response code, NOERROR and NXDOMAIN count as
+    * successful answers.
+    *
+    *=================================================================
+    * @param num_client usize
+    * @param execution usize
+    * @return BenchmarkResult
+    */
+    pub async fn ino_exec(&self, num_client: usize, execution: usize) -> BenchmarkResult {
+        let id: u16 = self.ids.lock().unwrap().gen();
+        let mut message = Vec::with_capacity(self.question.len() + 12);
+        message.extend_from_slice(&id.to_be_bytes());
+        message.extend_from_slice(&[0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        message.extend_from_slice(&self.question);
+
+        let begin = Instant::now();
+        let exchange = match self.tcp {
+            false => timeout(DNS_TIMEOUT, ino_exchange_udp(self.server, &message, id)).await,
+            true => timeout(DNS_TIMEOUT, ino_exchange_tcp(self.server, &message, id)).await,
+        };
+        let duration = begin.elapsed().as_millis() as u64;
+        let (status, success) = match exchange {
+            Err(_) => ("Timeout".to_string(), false),
+            Ok(Err(e)) => (format!("Failed: {}", e), false),
+            Ok(Ok(rcode)) => (ino_rcode_name(rcode), rcode == 0 || rcode == 3),
+        };
+        BenchmarkResult::new(status, success, duration, num_client, execution)
+    }
+}
+
+/**
+ *=================================================================
+ * ino_exchange_udp()
+ *=================================================================
+ *
+ * Sends the message over UDP and returns the response code of the
+ * first answer carrying the same id.
+ *
+ *=================================================================
+ */
+async fn ino_exchange_udp(server: SocketAddr, message: &[u8], id: u16) -> Result<u8> {
+    let bind = match server {
+        SocketAddr::V4(_) => "0.0.0.0:0",
+        SocketAddr::V6(_) => "[::]:0",
+    };
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(server).await?;
+    socket.send(message).await?;
+    let mut buffer = [0u8; 4096];
+    loop {
+        let size = socket.recv(&mut buffer).await?;
+        if let Some(rcode) = ino_parse_response(&buffer[..size], id) {
+            return Ok(rcode);
+        }
+    }
+}
+
+/**
+ *=================================================================
+ * ino_exchange_tcp()
+ *=================================================================
+ *
+ * Sends the length prefixed message over a new TCP connection and
+ * returns the response code of the answer.
+ *
+ *=================================================================
+ */
+async fn ino_exchange_tcp(server: SocketAddr, message: &[u8], id: u16) -> Result<u8> {
+    let mut stream = TcpStream::connect(server).await?;
+    let mut framed = Vec::with_capacity(message.len() + 2);
+    framed.extend_from_slice(&(message.len() as u16).to_be_bytes());
+    framed.extend_from_slice(message);
+    stream.write_all(&framed).await?;
+    let size = stream.read_u16().await? as usize;
+    let mut buffer = vec![0u8; size];
+    stream.read_exact(&mut buffer).await?;
+    ino_parse_response(&buffer, id).with_context(|| "Invalid DNS response".to_string())
+}
+
+/**
+ *=================================================================
+ * ino_parse_response()
+ *=================================================================
+ *
+ * Returns the response code of a DNS response with the given id,
+ * None when the message is not the expected response.
+ *
+ *=================================================================
+ */
+fn ino_parse_response(response: &[u8], id: u16) -> Option<u8> {
+    if response.len() < 12 || u16::from_be_bytes([response[0], response[1]]) != id {
+        return None;
+    }
+    let is_response = response[2] & 0x80 != 0;
+    is_response.then_some(response[3] & 0x0f)
+}
+
+/**
+ *=================================================================
+ * ino_encode_question()
+ *=================================================================
+ *
+ * Encodes the question section: the name as length prefixed labels,
+ * the record type and the IN class.
+ *
+ *=================================================================
+ */
+fn ino_encode_question(name: &str, record_type: u16) -> Result<Vec<u8>> {
+    let mut question = vec![];
+    for label in name.trim_end_matches('.').split('.').filter(|l| !l.is_empty()) {
+        if label.len() > 63 {
+            bail!("DNS label {} is longer than 63 characters", label);
+        }
+        question.push(label.len() as u8);
+        question.extend_from_slice(label.as_bytes());
+    }
+    question.push(0);
+    question.extend_from_slice(&record_type.to_be_bytes());
+    question.extend_from_slice(&1u16.to_be_bytes());
+    Ok(question)
+}
+
+/**
+ *=================================================================
+ * ino_record_type()
+ *=================================================================
+ *
+ * Maps a record type name (or its numeric value) to its code.
+ *
+ *=================================================================
+ */
+fn ino_record_type(record_type: &str) -> Result<u16> {
+    Ok(match record_type.to_uppercase().as_str() {
+        "A" => 1,
+        "NS" => 2,
+        "CNAME" => 5,
+        "SOA" => 6,
+        "PTR" => 12,
+        "MX" => 15,
+        "TXT" => 16,
+        "AAAA" => 28,
+        "SRV" => 33,
+        "HTTPS" => 65,
+        "ANY" => 255,
+        other => other
+            .trim_start_matches("TYPE")
+            .parse()
+            .with_context(|| format!("Unknown DNS record type {}", record_type))?,
+    })
+}
+
+/**
+ *=================================================================
+ * ino_rcode_name()
+ *=================================================================
+ *
+ * Name of a DNS response code.
+ *
+ *=================================================================
+ */
+fn ino_rcode_name(rcode: u8) -> String {
+    match rcode {
+        0 => "NOERROR".to_string(),
+        1 => "FORMERR".to_string(),
+        2 => "SERVFAIL".to_string(),
+        3 => "NXDOMAIN".to_string(),
+        4 => "NOTIMP".to_string(),
+        5 => "REFUSED".to_string(),
+        other => format!("RCODE{}", other),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_encode_question() -> Result<()> {
+        let question = ino_encode_question("example.com.", ino_record_type("aaaa")?)?;
+        assert_eq!(
+            question,
+            vec![7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0, 0, 28, 0, 1]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_query_arguments() -> Result<()> {
+        let values = vec!["example.com".to_string(), "MX".to_string(), "@1.1.1.1".to_string()];
+        let query = DnsQuery::ino_from_args(&values, None, false)?;
+        assert_eq!(query.name, "example.com");
+        assert_eq!(query.record_type, "MX");
+        assert_eq!(query.server, "1.1.1.1");
+        Ok(())
+    }
+
+    #[test]
+    fn should_ignore_response_with_other_id() {
+        let response = [0x12, 0x34, 0x81, 0x83, 0, 1, 0, 0, 0, 0, 0, 0];
+        assert_eq!(ino_parse_response(&response, 0x1234), Some(3));
+        assert_eq!(ino_parse_response(&response, 0x4321), None);
+    }
+}
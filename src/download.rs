@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+
+use crate::benchmark::{ino_quantile, BenchmarkResult};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DownloadSummary {
+    pub bytes: u64,
+    pub goodput: f64,
+    pub first_byte_mean: f64,
+    pub first_byte_p95: u64,
+    pub client_rates: Vec<f64>,
+}
+
+#[derive(Debug, Default)]
+pub struct DownloadStats {
+    bytes: u64,
+    per_client: Vec<(u64, u64)>,
+    first_bytes: Vec<u64>,
+}
+
+impl DownloadStats {
+
+    /**
+    *=================================================================
+    * ino_add()
+    *=================================================================
+    *
+    * Records the bytes, the time and the first byte of a successful
+    * download under its client.
+    *
+    *=================================================================
+    * @param result &BenchmarkResult
+    * @return void
+    */
+    pub fn ino_add(&mut self, result: &BenchmarkResult) {
+        if !result.success {
+            return;
+        }
+        let size = result.size.unwrap_or(0);
+        self.bytes += size;
+        if self.per_client.len() <= result.num_client {
+            self.per_client.resize(result.num_client + 1, (0, 0));
+        }
+        let (client_bytes, client_ms) = &mut self.per_client[result.num_client];
+        *client_bytes += size;
+        *client_ms += result.duration;
+        if let Some(first_byte) = result.first_byte {
+            self.first_bytes.push(first_byte);
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_summary()
+    *=================================================================
+    *
+    * Bytes of the successful downloads per second over the run, the
+    * time to first byte and the sustained rate of every client: its
+    * bytes over the time it spent downloading.
+    *
+    *=================================================================
+    * @param clients usize
+    * @param elapsed f64 seconds
+    * @return DownloadSummary
+    */
+    pub fn ino_summary(&self, clients: usize, elapsed: f64) -> DownloadSummary {
+        let mut per_client = self.per_client.clone();
+        if per_client.len() < clients {
+            per_client.resize(clients, (0, 0));
+        }
+        let mut first_bytes = self.first_bytes.clone();
+        first_bytes.sort_unstable();
+        DownloadSummary {
+            bytes: self.bytes,
+            goodput: match elapsed > 0.0 {
+                true => self.bytes as f64 / elapsed,
+                false => 0.0,
+            },
+            first_byte_mean: match first_bytes.len() {
+                0 => 0.0,
+                len => first_bytes.iter().sum::<u64>() as f64 / len as f64,
+            },
+            first_byte_p95: match first_bytes.is_empty() {
+                true => 0,
+                false => ino_quantile(&first_bytes, 0.95),
+            },
+            client_rates: per_client
+                .iter()
+                .map(|&(bytes, ms)| bytes as f64 * 1000.0 / ms.max(1) as f64)
+                .collect(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::ino_result;
+
+    #[test]
+    fn should_compute_goodput_and_client_rates() {
+        let result = |num_client, success, duration, first_byte| BenchmarkResult {
+            success,
+            duration,
+            num_client,
+            size: Some(1_000_000),
+            first_byte: Some(first_byte),
+            ..ino_result()
+        };
+        let results = [
+            result(0, true, 500, 10),
+            result(0, true, 500, 30),
+            result(1, true, 250, 20),
+            result(1, false, 1, 1),
+        ];
+        let mut stats = DownloadStats::default();
+        results.iter().for_each(|result| stats.ino_add(result));
+        let summary = stats.ino_summary(2, 2.0);
+        assert_eq!(summary.bytes, 3_000_000);
+        assert_eq!(summary.goodput, 1_500_000.0);
+        assert_eq!((summary.first_byte_mean, summary.first_byte_p95), (20.0, 30));
+        assert_eq!(summary.client_rates, vec![2_000_000.0, 4_000_000.0]);
+    }
+}
@@ -0,0 +1,103 @@
+use colored::Colorize;
+use reqwest::header::HeaderMap;
+use reqwest::{Request, StatusCode, Version};
+
+pub const ECHO_BODY_LIMIT: usize = 4096;
+
+/**
+ *=================================================================
+ * ino_echo_request()
+ *=================================================================
+ *
+ * Prints a request as it is sent: its method, URL, headers and
+ * body, a streamed body is only named.
+ *
+ *=================================================================
+ * @param request &Request
+ * @return void
+ */
+pub fn ino_echo_request(request: &Request) {
+    println!("{} {} {}", ">".cyan().bold(), request.method().to_string().bold(), request.url());
+    ino_echo_headers(">", request.headers());
+    match request.body().map(|b| b.as_bytes()) {
+        None => {}
+        Some(None) => println!("{}", "<streamed body>".dimmed()),
+        Some(Some(body)) => println!("{}", ino_format_body(body, body.len() as u64)),
+    }
+    println!();
+}
+
+/**
+ *=================================================================
+ * ino_echo_response()
+ *=================================================================
+ *
+ * Prints a response as it was received: its status line, headers
+ * and the beginning of its body.
+ *
+ *=================================================================
+ * @param status StatusCode
+ * @param version Version
+ * @param headers &HeaderMap
+ * @param body &[u8] the beginning of the body that was kept
+ * @param size Option<u64> the size of the whole body, None when unread
+ * @return void
+ */
+pub fn ino_echo_response(status: StatusCode, version: Version, headers: &HeaderMap, body: &[u8], size: Option<u64>) {
+    let line = format!("{:?} {}", version, status);
+    let line = match status.is_success() || status.is_redirection() {
+        true => line.green().bold(),
+        false => line.red().bold(),
+    };
+    println!("{} {}", "<".cyan().bold(), line);
+    ino_echo_headers("<", headers);
+    match size {
+        None => println!("{}", "<body not read>".dimmed()),
+        Some(0) => {}
+        Some(size) => println!("{}", ino_format_body(body, size)),
+    }
+    println!();
+}
+
+fn ino_echo_headers(prefix: &str, headers: &HeaderMap) {
+    for (name, value) in headers {
+        println!("{} {}: {}", prefix.cyan().bold(), name.as_str().yellow(), String::from_utf8_lossy(value.as_bytes()));
+    }
+}
+
+/**
+ *=================================================================
+ * ino_format_body()
+ *=================================================================
+ *
+ * Body as text, cut at the echo limit with the count of the bytes
+ * left out. Invalid UTF-8 is replaced.
+ *
+ *=================================================================
+ * @param body &[u8]
+ * @param size u64 the size of the whole body
+ * @return String
+ */
+fn ino_format_body(body: &[u8], size: u64) -> String {
+    let kept = &body[..body.len().min(ECHO_BODY_LIMIT)];
+    let text = String::from_utf8_lossy(kept);
+    match size.saturating_sub(kept.len() as u64) {
+        0 => text.into_owned(),
+        more => format!("{}\n{}", text, format!("... {} more bytes", more).dimmed()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_cut_long_bodies() {
+        assert_eq!(ino_format_body(b"{\"ok\":true}", 11), "{\"ok\":true}");
+        let body = vec![b'a'; ECHO_BODY_LIMIT + 10];
+        let text = ino_format_body(&body, 1_000_000);
+        assert!(text.starts_with("aaaa"));
+        assert!(text.contains(&format!("... {} more bytes", 1_000_000 - ECHO_BODY_LIMIT)));
+    }
+}
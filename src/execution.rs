@@ -1,15 +1,175 @@
-use std::str::FromStr;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use anyhow::{Context, Result};
-use reqwest::Client;
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+use hyper_util::client::legacy::connect::HttpInfo;
+use rand::rngs::StdRng;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use reqwest::{Client, ClientBuilder, Method, Proxy, RequestBuilder, Response, Url};
+use reqwest::header::{HeaderMap, RANGE};
 use tokio::sync::mpsc::Sender;
-use tokio::sync::watch::Receiver;
+use tokio::sync::Semaphore;
+use tokio::sync::watch::{self, Receiver};
 use tokio::time::Instant;
 
 use crate::benchmark::BenchmarkResult;
-use crate::support::{Operation, Settings};
-use crate::support::Operation::Head;
+use crate::bandwidth::{ino_throttle_body, ino_throttle_transfer, BandwidthLimiter};
+use crate::burst::BurstScheduler;
+use crate::cache::{ino_cache_bust, ino_cache_headers};
+use crate::checksum::CORRUPTED;
+use crate::conditional::ino_validators;
+use crate::control::{ino_wait_turn, ControlState, IterationPool, RunControl};
+use crate::credentials::Credential;
+use crate::dns::{self, Dns};
+use crate::echo::{ino_echo_request, ino_echo_response, ECHO_BODY_LIMIT};
+use crate::exhaustion::ino_connect_status;
+use crate::flow::ino_exec_flow;
+use crate::headers::{ino_check_headers, ino_extract_headers};
+use crate::latency::{self, LatencyInjector};
+use crate::limiter::HostLimiter;
+use crate::monitor::{Iteration, SendStats};
+use crate::kafka::Kafka;
+use crate::mqtt::Mqtt;
+use crate::pacing::{Pacer, Pacing};
+use crate::precheck::ino_precheck;
+use crate::range::RangeRequests;
+use crate::rate::{RateBudget, RateShare};
+use crate::redis::Redis;
+use crate::replay::{Replay, ReplayEntry};
+use crate::sql::Sql;
+use crate::sse::Sse;
+use crate::stages::{ConnectionPool, Connections};
+use crate::sweep;
+use crate::support::{HttpVersion, LatencyBoundary, Mode, Operation, Settings};
+use crate::proxy_protocol::{ProxyRelay, RelayLayer};
+use crate::tcp::TcpConnect;
+use crate::throttle::ino_retry_after;
+use crate::upload::ino_upload_body;
+use crate::xml::ino_check_xpath;
+
+const JITTER_RNG_STREAM: u64 = 2;
+const IDEMPOTENCY_RNG_STREAM: u64 = 4 << 32;
+
+struct Lane {
+    worker: Arc<Worker>,
+    control: watch::Receiver<ControlState>,
+    in_flight: Arc<Semaphore>,
+    pacer: Option<Pacer>,
+    end: Option<Duration>,
+    tx: Sender<BenchmarkResult>,
+}
+
+pub struct Worker {
+    pub num_client: usize,
+    pub settings: Settings,
+    pub client: Client,
+    pub request: PreparedRequest,
+    pub limiter: HostLimiter,
+    pub replay: Option<Arc<Replay>>,
+    pub protocol: Arc<Protocol>,
+    pub burst: Option<Arc<BurstScheduler>>,
+    pub rate: Option<RateShare>,
+    pub bandwidth: Vec<Arc<BandwidthLimiter>>,
+    pub latency: Option<LatencyInjector>,
+    pub start: Instant,
+    pub connections: Connections,
+    pub throttled_until: Mutex<Option<Instant>>,
+    pub idempotency: Mutex<StdRng>,
+    pub stats: Arc<SendStats>,
+    pub iterations: Option<Arc<IterationPool>>,
+}
+
+pub struct PreparedRequest {
+    pub method: Method,
+    pub url: Option<Url>,
+    pub headers: HeaderMap,
+    pub body: Option<Bytes>,
+}
+
+pub enum Protocol {
+    Http(Option<Box<RangeRequests>>),
+    TcpConnect(TcpConnect),
+    Dns(Box<Dns>),
+    Sse(Sse),
+    Redis(Box<Redis>),
+    Sql(Box<Sql>),
+    Mqtt(Box<Mqtt>),
+    Kafka(Box<Kafka>),
+}
+
+impl Protocol {
+
+    /**
+    *=================================================================
+    * ino_from_settings()
+    *=================================================================
+    *
+    * Prepares, once per run, what the protocol of the mode needs to
+    * execute its iterations.
+    *
+    *=================================================================
+    * @param settings &Settings
+    * @return Result<Protocol>
+    */
+    pub async fn ino_from_settings(settings: &Settings) -> Result<Self> {
+        Ok(match settings.mode {
+            Mode::Http => match settings.range_size {
+                None => Protocol::Http(None),
+                Some(size) => Protocol::Http(Some(Box::new(RangeRequests::ino_from_settings(settings, size).await?))),
+            },
+            Mode::TcpConnect => Protocol::TcpConnect(TcpConnect::ino_from_settings(settings).await?),
+            Mode::Dns => {
+                let query = settings
+                    .dns
+                    .as_ref()
+                    .with_context(|| "The dns mode needs a query".to_string())?;
+                Protocol::Dns(Box::new(Dns::ino_from_query(query, settings.ino_rng(dns::RNG_STREAM)).await?))
+            }
+            Mode::Sse => Protocol::Sse(Sse::ino_from_settings(settings)?),
+            Mode::Redis => Protocol::Redis(Box::new(Redis::ino_from_settings(settings).await?)),
+            Mode::Sql => Protocol::Sql(Box::new(Sql::ino_from_settings(settings).await?)),
+            Mode::Mqtt => Protocol::Mqtt(Box::new(Mqtt::ino_from_settings(settings).await?)),
+            Mode::Kafka => Protocol::Kafka(Box::new(Kafka::ino_from_settings(settings).await?)),
+        })
+    }
+}
+
+impl PreparedRequest {
+
+    /**
+    *=================================================================
+    * ino_from_settings()
+    *=================================================================
+    *
+    * Parses the method, URL, headers and body of the settings once
+    * per client, so its iterations only clone them. The URL is None
+    * without a valid target, when replaying a URL list only. The
+    * headers of the credential of the client are added.
+    *
+    *=================================================================
+    * @param settings &Settings
+    * @param num_client usize
+    * @return PreparedRequest
+    */
+    pub fn ino_from_settings(settings: &Settings, num_client: usize) -> Self {
+        let mut headers = settings.ino_header_map().unwrap_or_default();
+        if let Some(credential) = settings.ino_credential(num_client) {
+            headers.extend(credential.ino_header_map().unwrap_or_default());
+        }
+        PreparedRequest {
+            method: settings.ino_operation().ino_method(),
+            url: match settings.target.trim().is_empty() {
+                true => None,
+                false => Url::parse(&settings.ino_target()).ok(),
+            },
+            headers,
+            body: settings.body.clone().map(Bytes::from),
+        }
+    }
+}
 
 /**
  *=================================================================
@@ -18,30 +178,253 @@ use crate::support::Operation::Head;
  *
  * Asynchronously starts the benchmarking process by creating HTTP
  * clients and spawning tasks to execute requests. The function is
- * responsible for orchestrating the execution.
+ * responsible for orchestrating the execution and returns the
+ * control used to adjust the run while it is in progress. In the
+ * conditional mode the validators of the target are captured
+ * first and sent with every request. With a connection pool the
+ * workers reuse the clients, and their open connections, of the
+ * previous run. The requests take their slots from the rate share
+ * given, the share of a client group, or else from a budget of
+ * their own when the settings cap the rate.
  *
  *=================================================================
  */
-pub async fn ino_run(settings: Settings, tx: Sender<BenchmarkResult>, rx_sigint: Receiver<Option<()>>) -> Result<()> {
+pub async fn ino_run(
+    mut settings: Settings,
+    tx: Sender<BenchmarkResult>,
+    rx_sigint: Receiver<Option<()>>,
+    pool: Option<&ConnectionPool>,
+    rate: Option<RateShare>,
+) -> Result<RunControl> {
+    let rate = match (rate, settings.rate) {
+        (Some(share), _) => Some(share),
+        (None, None) => None,
+        (None, Some(rate)) => Some(RateBudget::new(rate, &[1.0])?.ino_share(0)),
+    };
+    let replay = Replay::ino_from_settings(&settings)?.map(Arc::new);
+    let protocol = Arc::new(Protocol::ino_from_settings(&settings).await?);
     let mut clients = Vec::with_capacity(settings.clients);
-    for _ in 0..settings.clients {
-        let client = Client::builder()
-            .danger_accept_invalid_certs(true)
-            .tcp_keepalive(settings.keep_alive)
-            .build()
-            .with_context(|| "Can not create http Client".to_string())?;
-        clients.push(client);
-    }
-    for (id, client) in clients.into_iter().enumerate() {
-        tokio::spawn(ino_exec_iterator(
+    for num_client in 0..settings.clients {
+        clients.push(match pool {
+            None => (ino_build_worker_client(&settings, num_client)?, Connections::default()),
+            Some(pool) => pool.ino_client(&settings, num_client)?,
+        });
+    }
+    if settings.steps.is_some()
+        && (settings.mode != Mode::Http || replay.is_some() || settings.range_size.is_some() || settings.conditional || settings.upload.is_some())
+    {
+        bail!("The steps need the http mode, without a URL list, ranges, conditional requests or uploads");
+    }
+    if settings.sweep.is_some() {
+        if settings.mode != Mode::Http || settings.steps.is_some() || replay.is_some() {
+            bail!("The sweep needs the http mode and a single target, without steps or a URL list");
+        }
+        let rng = settings.ino_rng(sweep::RNG_STREAM);
+        settings.sweep.as_mut().map(|sweep| sweep.ino_expand(rng)).transpose()?;
+    }
+    if settings.proxy_protocol.is_some() && settings.mode != Mode::Http {
+        bail!("The PROXY protocol header is only sent in the http mode");
+    }
+    if settings.proxy_protocol.is_some() && settings.proxy.is_some() {
+        bail!("The PROXY protocol header can not be sent through a proxy");
+    }
+    if settings.mode == Mode::Http && !settings.no_precheck && settings.steps.is_none() {
+        if let Some((client, _)) = clients.first() {
+            ino_precheck(&settings, client, replay.as_deref()).await?;
+        }
+    }
+    if settings.conditional {
+        if settings.mode != Mode::Http || replay.is_some() {
+            bail!("The conditional mode needs a single HTTP target");
+        }
+        if let Some((client, _)) = clients.first() {
+            let validators = ino_validators(&settings, client).await?;
+            settings.headers.get_or_insert_with(Vec::new).extend(validators);
+        }
+    }
+    let start = Instant::now();
+    let stats = Arc::new(SendStats::default());
+    let burst = settings.burst.map(|burst| Arc::new(BurstScheduler::new(burst, start)));
+    let bandwidth = settings.max_bandwidth.map(|bandwidth| Arc::new(BandwidthLimiter::new(bandwidth)));
+    let iterations = settings.duration.is_none().then(|| Arc::new(IterationPool::new(settings.requests)));
+    let control = RunControl::new(
+        start,
+        clients.len(),
+        settings.clone(),
+        replay.clone(),
+        protocol.clone(),
+        burst.clone(),
+        rate.clone(),
+        bandwidth.clone(),
+        stats.clone(),
+        iterations.clone(),
+        tx.downgrade(),
+        rx_sigint.clone(),
+    );
+    for (id, (client, connections)) in clients.into_iter().enumerate() {
+        ino_spawn_worker(
             id,
-            settings.clone(),
+            &settings,
             client,
+            connections,
+            replay.clone(),
+            protocol.clone(),
+            burst.clone(),
+            rate.clone(),
+            bandwidth.clone(),
+            control.ino_subscribe(),
+            start,
+            stats.clone(),
+            iterations.clone(),
             tx.clone(),
             rx_sigint.clone(),
-        ));
+        );
+    }
+    Ok(control)
+}
+
+/**
+ *=================================================================
+ * ino_build_client()
+ *=================================================================
+ *
+ * Creates an HTTP client speaking the configured HTTP version
+ * only, for the probes sent before the run.
+ *
+ *=================================================================
+ * @param settings &Settings
+ * @return Result<Client>
+ */
+pub fn ino_build_client(settings: &Settings) -> Result<Client> {
+    ino_client_builder(settings, 0)?
+        .build()
+        .with_context(|| "Can not create http Client".to_string())
+}
+
+/**
+ *=================================================================
+ * ino_build_worker_client()
+ *=================================================================
+ *
+ * Creates the HTTP client of a worker, presenting the client
+ * certificate of its credential if any.
+ *
+ *=================================================================
+ * @param settings &Settings
+ * @param num_client usize
+ * @return Result<Client>
+ */
+pub fn ino_build_worker_client(settings: &Settings, num_client: usize) -> Result<Client> {
+    let mut builder = ino_client_builder(settings, num_client)?;
+    let credential = settings.ino_credential(num_client);
+    if let Some(identity) = credential.map(Credential::ino_identity).transpose()?.flatten() {
+        builder = builder.identity(identity);
     }
-    Ok(())
+    builder
+        .build()
+        .with_context(|| "Can not create http Client".to_string())
+}
+
+/**
+ *=================================================================
+ * ino_client_builder()
+ *=================================================================
+ *
+ * Common settings of the HTTP clients. The hosts of the scenario
+ * resolve to their static address, the port of the URL is kept
+ * and TLS still presents the host name. With the PROXY protocol
+ * the hosts resolve to the relays of the client instead. A
+ * timeout bounds every request and a proxy carries them all.
+ *
+ *=================================================================
+ */
+fn ino_client_builder(settings: &Settings, num_client: usize) -> Result<ClientBuilder> {
+    let mut builder = Client::builder()
+        .danger_accept_invalid_certs(true)
+        .tcp_keepalive(settings.keep_alive);
+    if let Some(max_idle) = settings.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max_idle);
+    }
+    if let Some(timeout) = settings.timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(proxy) = &settings.proxy {
+        builder = builder.proxy(Proxy::all(proxy).with_context(|| format!("Invalid proxy {}", proxy))?);
+    }
+    for (host, ip) in &settings.hosts {
+        builder = builder.resolve(&host.to_ascii_lowercase(), SocketAddr::new(*ip, 0));
+    }
+    if let Some(proxy) = &settings.proxy_protocol {
+        let relay = ProxyRelay::ino_start(settings, proxy, num_client)?;
+        for (host, address) in &relay.resolves {
+            builder = builder.resolve(host, *address);
+        }
+        builder = builder.connector_layer(RelayLayer::new(relay));
+    }
+    Ok(match settings.http_version {
+        HttpVersion::Http1 => builder.http1_only(),
+        HttpVersion::Http2 => builder.http2_prior_knowledge(),
+    })
+}
+
+/**
+ *=================================================================
+ * ino_spawn_worker()
+ *=================================================================
+ *
+ * Spawns the task of a single virtual client. Timed runs end at
+ * the same time for every worker, whenever it was spawned. The
+ * transfers of the client go through the bandwidth limiter shared
+ * by the run and through its own one, its requests are delayed by
+ * its own latency injector. In iteration mode the iterations are
+ * taken from the pool of the run. The idempotency keys of the
+ * client are drawn from its own random stream.
+ *
+ *=================================================================
+ */
+#[allow(clippy::too_many_arguments)]
+pub fn ino_spawn_worker(
+    num_client: usize,
+    settings: &Settings,
+    client: Client,
+    connections: Connections,
+    replay: Option<Arc<Replay>>,
+    protocol: Arc<Protocol>,
+    burst: Option<Arc<BurstScheduler>>,
+    rate: Option<RateShare>,
+    bandwidth: Option<Arc<BandwidthLimiter>>,
+    control: watch::Receiver<ControlState>,
+    start: Instant,
+    stats: Arc<SendStats>,
+    iterations: Option<Arc<IterationPool>>,
+    tx: Sender<BenchmarkResult>,
+    rx_sigint: Receiver<Option<()>>,
+) {
+    let worker = Worker {
+        num_client,
+        settings: settings.clone(),
+        client,
+        request: PreparedRequest::ino_from_settings(settings, num_client),
+        limiter: HostLimiter::new(settings.max_connections_per_host),
+        replay,
+        protocol,
+        burst,
+        rate,
+        bandwidth: bandwidth
+            .into_iter()
+            .chain(settings.client_bandwidth.map(|b| Arc::new(BandwidthLimiter::new(b))))
+            .collect(),
+        latency: settings
+            .added_latency
+            .map(|latency| LatencyInjector::new(latency, settings.ino_rng(latency::RNG_STREAM + num_client as u64))),
+        start,
+        connections,
+        throttled_until: Mutex::new(None),
+        idempotency: Mutex::new(settings.ino_rng(IDEMPOTENCY_RNG_STREAM + num_client as u64)),
+        stats,
+        iterations,
+    };
+    tokio::spawn(ino_exec_iterator(worker, control, tx, rx_sigint));
 }
 
 /**
@@ -53,6 +436,8 @@ pub async fn ino_run(settings: Settings, tx: Sender<BenchmarkResult>, rx_sigint:
  * - Fixed iterations: Executes a specific number of requests.
  * - Timed execution: Runs for a given duration.
  *
+ * With a start jitter the client first waits a random offset, so
+ * the clients do not all fire their first request at once.
  *
  *
  *=================================================================
@@ -60,17 +445,50 @@ pub async fn ino_run(settings: Settings, tx: Sender<BenchmarkResult>, rx_sigint:
  *
  *
  */
-async fn ino_exec_iterator(num_client: usize, settings: Settings, client: Client, tx: Sender<BenchmarkResult>, mut rx_sigint: Receiver<Option<()>>) {
-    match settings.duration {
+async fn ino_exec_iterator(worker: Worker, control: watch::Receiver<ControlState>, tx: Sender<BenchmarkResult>, mut rx_sigint: Receiver<Option<()>>) {
+    if let Some(jitter) = worker.settings.start_jitter {
+        let mut rng = worker.settings.ino_rng(JITTER_RNG_STREAM + worker.num_client as u64);
+        let offset = Duration::from_millis(rng.gen_range(0..=jitter));
+        tokio::select! {
+            _ = tokio::time::sleep(offset) => {}
+            _ = rx_sigint.changed() => return,
+        }
+    }
+    let worker = Arc::new(worker);
+    let in_flight = Arc::new(Semaphore::new(worker.settings.in_flight.max(1)));
+    let pacer = worker.settings.pacing.map(|pacing| match (&worker.settings.profile, worker.settings.duration) {
+        (Some(profile), Some(duration)) => Pacer::new(pacing).ino_with_profile(profile.clone(), worker.start, Duration::from_secs(duration)),
+        _ => Pacer::new(pacing),
+    });
+    let end = worker.settings.ino_client_end(worker.num_client);
+    let mut lane = Lane { worker, control, in_flight, pacer, end, tx };
+    match lane.end {
         None => {
-            ino_by_iterations(num_client, &settings, &client, &tx, &mut rx_sigint).await;
+            ino_by_iterations(&mut lane, &mut rx_sigint).await;
         }
-        Some(duration) => {
-            ino_by_time(num_client, &settings, &client, tx, &mut rx_sigint, duration).await;
+        Some(_) => {
+            ino_by_time(&mut lane, &mut rx_sigint).await;
         }
     }
 }
 
+/**
+ *=================================================================
+ * ino_next_entry()
+ *=================================================================
+ *
+ * Picks the next URL list entry when replaying. Returns None when
+ * the client must stop because the replay is exhausted.
+ *
+ *=================================================================
+ */
+async fn ino_next_entry(replay: Option<&Replay>) -> Option<Option<ReplayEntry>> {
+    match replay {
+        None => Some(None),
+        Some(replay) => replay.ino_next().await.map(Some),
+    }
+}
+
 /**
  *=================================================================
  * ino_by_time()
@@ -80,27 +498,60 @@ async fn ino_exec_iterator(num_client: usize, settings: Settings, client: Client
  * It repeatedly sends requests until the time limit is reached or
  * a stop signal is received. Results are sent to the provided channel.
  *
+ * With a ramp-down the client stops at its own end, and with a
+ * cooldown the first client keeps probing the target once per
+ * second until the cooldown is over.
  *
  *=================================================================
  *
  *
  */
-async fn ino_by_time(num_client: usize, settings: &Settings, client: &Client, tx: Sender<BenchmarkResult>, rx_sigint: &mut Receiver<Option<()>>, duration: u64) {
-    let begin = Instant::now();
+async fn ino_by_time(lane: &mut Lane, rx_sigint: &mut Receiver<Option<()>>) {
     let mut execution_number = 0;
-    while begin.elapsed().as_secs() < duration {
-        let stop_signal = rx_sigint.changed();
-        let benchmark_result = ino_exec(num_client, execution_number, client, settings);
-        let ack_send_result = tx.send(benchmark_result.await);
-        execution_number += 1;
-        match tokio::select! {
-        _ = ack_send_result =>  None,
-        _ = stop_signal => Some(())
-        } {
-            None => {}
-            Some(_) => break,
+    if !ino_until_end(lane, rx_sigint, &mut execution_number).await {
+        return;
+    }
+    let settings = &lane.worker.settings;
+    let Some(cooldown) = settings.cooldown.filter(|_| lane.worker.num_client == 0) else { return };
+    lane.end = settings.duration.map(|duration| Duration::from_secs(duration + cooldown));
+    lane.pacer = Some(Pacer::new(Pacing { iterations: 1, period: Duration::from_secs(1) }));
+    ino_until_end(lane, rx_sigint, &mut execution_number).await;
+}
+
+/**
+ *=================================================================
+ * ino_until_end()
+ *=================================================================
+ *
+ * Dispatches the iterations of the client until its end. Returns
+ * false when the client must stop.
+ *
+ *=================================================================
+ */
+async fn ino_until_end(lane: &mut Lane, rx_sigint: &mut Receiver<Option<()>>, execution_number: &mut usize) -> bool {
+    while ino_before_end(lane) {
+        if !ino_dispatch(lane, *execution_number, rx_sigint).await {
+            return false;
         }
+        *execution_number += 1;
     }
+    true
+}
+
+/**
+ *=================================================================
+ * ino_before_end()
+ *=================================================================
+ *
+ * Whether the client may start another iteration before its end,
+ * the next paced iteration included.
+ *
+ *=================================================================
+ */
+fn ino_before_end(lane: &Lane) -> bool {
+    let Some(end) = lane.end.map(|end| lane.worker.start + end) else { return false };
+    let next = lane.pacer.as_ref().map_or_else(Instant::now, Pacer::ino_due);
+    Instant::now() < end && next < end
 }
 
 /**
@@ -116,26 +567,106 @@ async fn ino_by_time(num_client: usize, settings: &Settings, client: &Client, tx
  * signal (SIGINT) to
  * gracefully terminate execution if required.
  *
+ * The iterations are taken from the pool shared by the workers
+ * until it is empty, so the active clients send the share of the
+ * parked ones.
+ *
  *
  *=================================================================
  *
  *
  *
  */
-async fn ino_by_iterations(num_client: usize, settings: &Settings, client: &Client, tx: &Sender<BenchmarkResult>, rx_sigint: &mut Receiver<Option<()>>) {
-    for execution_number in 0..settings.ino_requests_by_client() {
-        let stop_signal = rx_sigint.changed();
-        let benchmark_result = ino_exec(num_client, execution_number, client, settings);
-        let ack_send_result = tx.send(benchmark_result.await);
+async fn ino_by_iterations(lane: &mut Lane, rx_sigint: &mut Receiver<Option<()>>) {
+    let mut execution_number = 0;
+    while ino_dispatch(lane, execution_number, rx_sigint).await {
+        execution_number += 1;
+    }
+}
 
-        match tokio::select! {
-        _ = ack_send_result =>  None,
-        _ = stop_signal => Some(())
-        } {
-            None => {}
-            Some(_) => break,
+/**
+ *=================================================================
+ * ino_dispatch()
+ *=================================================================
+ *
+ * Starts a single iteration of the client once one of its
+ * in-flight slots is free and it may run: in iteration mode an
+ * iteration is left, the Retry-After pause of a throttled
+ * response is over, in burst mode the current burst is not
+ * complete yet, with a pacing its next iteration is due and,
+ * under a rate cap, the budget gives it a slot. The iteration
+ * runs in its own task which sends the result, settles the
+ * iteration for the accounting and frees the slot, so up to
+ * in-flight iterations of the client overlap. With a burst, a
+ * pacing or a rate cap, a single request records how late it was
+ * sent after it was due. Returns false when the client must stop.
+ *
+ *=================================================================
+ */
+async fn ino_dispatch(lane: &mut Lane, execution: usize, rx_sigint: &mut Receiver<Option<()>>) -> bool {
+    let permit = tokio::select! {
+        permit = lane.in_flight.clone().acquire_owned() => permit.expect("in-flight semaphore is never closed"),
+        _ = rx_sigint.changed() => return false,
+    };
+    if !ino_wait_turn(&mut lane.control, lane.worker.num_client, lane.worker.iterations.as_deref(), rx_sigint).await {
+        return false;
+    }
+    if lane.worker.iterations.as_ref().is_some_and(|iterations| !iterations.ino_take()) {
+        return false;
+    }
+    let throttled_until = *lane.worker.throttled_until.lock().unwrap();
+    if let Some(until) = throttled_until.filter(|until| *until > Instant::now()) {
+        if lane.end.is_some_and(|end| until >= lane.worker.start + end) {
+            return false;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep_until(until) => {}
+            _ = rx_sigint.changed() => return false,
         }
     }
+    let mut due = None;
+    if let Some(burst) = &lane.worker.burst {
+        tokio::select! {
+            start = burst.ino_acquire() => due = Some(start),
+            _ = rx_sigint.changed() => return false,
+        }
+    }
+    if let Some(pacer) = &mut lane.pacer {
+        tokio::select! {
+            next = pacer.ino_wait() => due = due.max(Some(next)),
+            _ = rx_sigint.changed() => return false,
+        }
+        if lane.end.is_some_and(|end| Instant::now() >= lane.worker.start + end) {
+            return false;
+        }
+    }
+    if let Some(rate) = &lane.worker.rate {
+        tokio::select! {
+            slot = rate.ino_acquire() => due = due.max(Some(slot)),
+            _ = rx_sigint.changed() => return false,
+        }
+        if lane.end.is_some_and(|end| Instant::now() >= lane.worker.start + end) {
+            return false;
+        }
+    }
+    let Some(entry) = ino_next_entry(lane.worker.replay.as_deref()).await else { return false };
+    let worker = lane.worker.clone();
+    let tx = lane.tx.clone();
+    let iteration = Iteration::new(worker.stats.clone());
+    tokio::spawn(async move {
+        match &worker.settings.steps {
+            None => {
+                let lag = due.map(|due| Instant::now().saturating_duration_since(due).as_micros() as u64);
+                let mut result = ino_exec(&worker, execution, entry.as_ref()).await;
+                result.lag = lag;
+                worker.stats.ino_send(&tx, result).await;
+            }
+            Some(steps) => ino_exec_flow(&worker, steps, execution, &tx).await,
+        }
+        iteration.ino_settle();
+        drop(permit);
+    });
+    true
 }
 
 /**
@@ -143,67 +674,279 @@ async fn ino_by_iterations(num_client: usize, settings: &Settings, client: &Clie
  * ino_exec()
  *=================================================================
  *
+ * Executes a single benchmark iteration with the protocol of the
+ * run.
+ *
+ *=================================================================
+ */
+async fn ino_exec(worker: &Worker, execution: usize, entry: Option<&ReplayEntry>) -> BenchmarkResult {
+    match worker.protocol.as_ref() {
+        Protocol::Http(range) => {
+            let mut result = ino_exec_http(worker, execution, entry, range.as_deref()).await;
+            if let Some(sweep) = &worker.settings.sweep {
+                result.step = sweep.ino_pick(worker.num_client, execution, worker.settings.clients).map(|combination| combination.label.clone());
+            }
+            result
+        }
+        Protocol::TcpConnect(tcp) => tcp.ino_exec(worker.num_client, execution).await,
+        Protocol::Dns(dns) => dns.ino_exec(worker.num_client, execution).await,
+        Protocol::Sse(sse) => sse.ino_exec(worker, execution).await,
+        Protocol::Redis(redis) => redis.ino_exec(worker.num_client, execution).await,
+        Protocol::Sql(sql) => sql.ino_exec(worker.num_client, execution).await,
+        Protocol::Mqtt(mqtt) => mqtt.ino_exec(worker.num_client, execution).await,
+        Protocol::Kafka(kafka) => kafka.ino_exec(worker.num_client, execution).await,
+    }
+}
+
+/**
+ *=================================================================
+ * ino_exec_http()
+ *=================================================================
+ *
  * Executes a single HTTP request using the specified client and
- * benchmarking settings. Configures the HTTP method, headers, and
- * body as needed.
+ * the request prepared for it, only cloning the method, URL,
+ * headers and body. The method and URL come from the URL list entry
+ * when replaying. A unique query parameter is appended when cache
+ * busting. Waits for a free connection slot on the host
+ * when a per-host connection cap is configured. The time to the
+ * response headers is recorded as the time to first byte, the
+ * body is then read chunk by chunk without keeping it to measure
+ * its size, unless reading it is turned off. The duration ends
+ * with the body unless the latency boundary is the headers, the
+ * range and download modes always cover the whole body. A random
+ * range of the object is requested in the range mode. The upload
+ * mode streams a generated body and records the time the server
+ * took to answer once it was sent. The request and the response
+ * are printed when echoing. An added latency holds the request
+ * back within the measured time, like the round trip of a distant
+ * client.
  *
  *
  *=================================================================
  *
  *
  */
-async fn ino_exec(num_client: usize, execution: usize, client: &Client, settings: &Settings) -> BenchmarkResult {
-    let request_builder = match settings.ino_operation() {
-        Operation::Get => client.get(settings.ino_target()),
-        Operation::Post => client.post(settings.ino_target()),
-        Operation::Head => client.head(settings.ino_target()),
-        Operation::Patch => client.patch(settings.ino_target()),
-        Operation::Put => client.put(settings.ino_target()),
-        Operation::Delete => client.delete(settings.ino_target()),
-    };
-    let headers_map: HeaderMap = match &settings.headers {
-        None => HeaderMap::new(),
-        Some(headers) => {
-            let mut headers_map: HeaderMap = HeaderMap::new();
-            headers.iter().for_each(|h| {
-                let name = h.key.as_str();
-                let value = h.value.as_str();
-
-                let name = HeaderName::from_str(name).unwrap();
-                let value = HeaderValue::from_str(value).unwrap();
-                headers_map.insert(name, value);
-            });
-            headers_map
-        }
+async fn ino_exec_http(worker: &Worker, execution: usize, entry: Option<&ReplayEntry>, range: Option<&RangeRequests>) -> BenchmarkResult {
+    let Worker { num_client, settings, client, limiter, request: prepared, .. } = worker;
+    let num_client = *num_client;
+    let (method, url) = match entry {
+        None => (prepared.method.clone(), prepared.url.clone()),
+        Some(entry) => (entry.operation.ino_method(), Url::parse(&entry.url).ok()),
     };
-    let request_builder = match &settings.body {
-        None => request_builder,
-        Some(body) => request_builder.body(body.to_string()),
+    let Some(mut url) = url else { return ino_failure(num_client, execution, "Invalid URL".to_string(), 0) };
+    if let Some(combination) = settings.sweep.as_ref().and_then(|sweep| sweep.ino_pick(num_client, execution, settings.clients)) {
+        combination.ino_apply(&mut url);
+    }
+    if let Some(param) = &settings.cache_bust {
+        ino_cache_bust(&mut url, param, num_client, execution);
+    }
+    let signature = settings
+        .signing
+        .as_ref()
+        .map(|signing| signing.ino_headers(&method, &url, prepared.body.as_deref().unwrap_or_default()))
+        .transpose();
+    let signature = match signature {
+        Ok(signature) => signature,
+        Err(e) => return ino_failure(num_client, execution, e.to_string(), 0),
     };
-    let request = request_builder.headers(headers_map);
+    let _permit = limiter.ino_acquire(url.as_str()).await;
+    let mut request = client.request(method, url).headers(prepared.headers.clone());
+    if let Some(signature) = signature {
+        request = request.headers(signature);
+    }
+    if let Some(body) = &prepared.body {
+        request = match worker.bandwidth.is_empty() {
+            true => request.body(body.clone()),
+            false => request.body(ino_throttle_body(body.clone(), worker.bandwidth.clone())),
+        };
+    }
+    if let Some(range) = range {
+        request = request.header(RANGE, range.ino_next());
+    }
+    let sent = Arc::new(Mutex::new(None));
+    if let Some(size) = settings.upload {
+        request = request.body(ino_upload_body(size, settings.upload_rate, sent.clone(), worker.bandwidth.clone()));
+    }
+    if settings.echo {
+        if let Some(Ok(copy)) = request.try_clone().map(RequestBuilder::build) {
+            ino_echo_request(&copy);
+        }
+    }
     let begin = Instant::now();
+    if let Some(latency) = &worker.latency {
+        latency.ino_delay().await;
+    }
     let response = request.send().await;
-    let duration_ms = begin.elapsed().as_millis() as u64;
+    let mut duration_ms = begin.elapsed().as_millis() as u64;
+    let first_byte = duration_ms;
+    let ack = sent.lock().unwrap().map(|sent: Instant| sent.elapsed().as_millis() as u64);
     match response {
-        Ok(r) => BenchmarkResult {
-            status: r.status().to_string(),
-            duration: duration_ms,
-            num_client,
-            execution,
-        },
+        Ok(mut r) => {
+            let status = r.status();
+            let reused = ino_reused(worker, &r);
+            let cache = ino_cache_headers(r.headers());
+            let headers = ino_extract_headers(&settings.extract_header, r.headers());
+            let unmet_header = ino_check_headers(&settings.expect_header, r.headers()).map(|e| format!("header {}", e));
+            let throttled = match settings.respect_retry_after {
+                true => ino_retry_after(status, r.headers()).map(|delay| ino_throttle(worker, delay)),
+                false => None,
+            };
+            let keep = match (settings.expect_xpath.is_empty(), settings.echo) {
+                (false, _) => usize::MAX,
+                (true, true) => ECHO_BODY_LIMIT,
+                (true, false) => 0,
+            };
+            let mut kept = vec![];
+            let mut digest = settings.verify_sha256.map(|_| Sha256::new());
+            let size = match settings.no_read_body {
+                true => None,
+                false => Some(ino_read_body(&mut r, &mut kept, keep, &worker.bandwidth, digest.as_mut()).await),
+            };
+            if settings.echo {
+                ino_echo_response(status, r.version(), r.headers(), &kept, size);
+            }
+            let unmet = unmet_header.or_else(|| ino_check_xpath(&settings.expect_xpath, &kept).map(|e| format!("xpath {}", e)));
+            let corrupted = status.is_success() && settings.verify_sha256.zip(digest).is_some_and(|(checksum, digest)| !checksum.ino_matches(digest));
+            if size.is_some() && (range.is_some() || settings.download || settings.latency_boundary == LatencyBoundary::Body) {
+                duration_ms = begin.elapsed().as_millis() as u64;
+            }
+            let success = (status.is_success() || status.is_redirection()) && unmet.is_none() && !corrupted;
+            let status = match (unmet, corrupted) {
+                (Some(expectation), _) => format!("{} (expected {})", status, expectation),
+                (None, true) => format!("{} ({})", status, CORRUPTED),
+                (None, false) => status.to_string(),
+            };
+            BenchmarkResult {
+                size,
+                reused,
+                cache,
+                first_byte: Some(first_byte),
+                ack,
+                headers,
+                throttled,
+                ..BenchmarkResult::new(status, success, duration_ms, num_client, execution)
+            }
+        }
         Err(e) => {
             let status = match e.status() {
-                None => {
-                    "Failed to connect".to_string()
-                }
+                None => ino_connect_status(&e),
                 Some(s) => s.to_string(),
             };
-            BenchmarkResult {
-                status,
-                duration: duration_ms,
-                num_client,
-                execution,
-            }
+            ino_failure(num_client, execution, status, duration_ms)
+        }
+    }
+}
+
+/**
+ *=================================================================
+ * ino_read_body()
+ *=================================================================
+ *
+ * Reads a response body to its end and discards it chunk by chunk,
+ * a body left unread keeps the connection from being reused. The
+ * beginning of the body is kept, up to `keep` bytes, to be echoed
+ * or checked, and the whole body is hashed into the digest if any.
+ * The chunks are paced through the bandwidth limiters.
+ *
+ *=================================================================
+ * @param response &mut Response
+ * @param kept &mut Vec<u8>
+ * @param keep usize
+ * @param limiters &[Arc<BandwidthLimiter>]
+ * @param digest Option<&mut Sha256>
+ * @return u64 the size of the body read
+ */
+pub async fn ino_read_body(response: &mut Response, kept: &mut Vec<u8>, keep: usize, limiters: &[Arc<BandwidthLimiter>], mut digest: Option<&mut Sha256>) -> u64 {
+    let mut size = 0;
+    while let Ok(Some(chunk)) = response.chunk().await {
+        ino_throttle_transfer(limiters, chunk.len()).await;
+        if let Some(digest) = digest.as_deref_mut() {
+            digest.update(&chunk);
         }
+        let room = keep.saturating_sub(kept.len()).min(chunk.len());
+        kept.extend_from_slice(&chunk[..room]);
+        size += chunk.len() as u64;
     }
-}
\ No newline at end of file
+    size
+}
+
+/**
+ *=================================================================
+ * ino_throttle()
+ *=================================================================
+ *
+ * Pauses the client for the delay asked by a throttled response,
+ * the pauses of overlapping responses do not add up.
+ *
+ *=================================================================
+ * @param worker &Worker
+ * @param delay Duration
+ * @return u64 the pause in milliseconds
+ */
+fn ino_throttle(worker: &Worker, delay: Duration) -> u64 {
+    let until = Instant::now() + delay;
+    let mut throttled_until = worker.throttled_until.lock().unwrap();
+    *throttled_until = Some(throttled_until.map_or(until, |current| current.max(until)));
+    delay.as_millis() as u64
+}
+
+/**
+ *=================================================================
+ * ino_failure()
+ *=================================================================
+ *
+ * Result of a request that got no response.
+ *
+ *=================================================================
+ */
+pub fn ino_failure(num_client: usize, execution: usize, status: String, duration: u64) -> BenchmarkResult {
+    BenchmarkResult::new(status, false, duration, num_client, execution)
+}
+
+/**
+ *=================================================================
+ * ino_reused()
+ *=================================================================
+ *
+ * Tells whether the response came over a connection the client
+ * already used, recognized by its local address. None when the
+ * connection information is not available.
+ *
+ *=================================================================
+ */
+pub fn ino_reused(worker: &Worker, response: &Response) -> Option<bool> {
+    let local = response.extensions().get::<HttpInfo>()?.local_addr();
+    let mut connections = worker.connections.lock().unwrap();
+    Some(!connections.insert(local))
+}
+
+/**
+ *=================================================================
+ * ino_build_request()
+ *=================================================================
+ *
+ * Builds the HTTP request of the settings for the given method
+ * and URL, with the configured headers and body, signed when the
+ * scenario signs its requests.
+ *
+ *=================================================================
+ * @param client &Client
+ * @param settings &Settings
+ * @param operation Operation
+ * @param target String
+ * @return RequestBuilder
+ */
+pub fn ino_build_request(client: &Client, settings: &Settings, operation: Operation, target: String) -> RequestBuilder {
+    let signature = settings.signing.as_ref().zip(Url::parse(&target).ok()).and_then(|(signing, url)| {
+        let body = settings.body.as_deref().unwrap_or_default();
+        signing.ino_headers(&operation.ino_method(), &url, body.as_bytes()).ok()
+    });
+    let request_builder = client
+        .request(operation.ino_method(), target)
+        .headers(settings.ino_header_map().unwrap_or_default())
+        .headers(signature.unwrap_or_default());
+    match &settings.body {
+        None => request_builder,
+        Some(body) => request_builder.body(body.to_string()),
+    }
+}
@@ -0,0 +1,265 @@
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+
+use crate::benchmark::BenchmarkResult;
+use crate::support::Settings;
+
+const LOCAL_ERROR: &str = "Local error";
+
+const RESERVED_FILES: u64 = 64;
+
+const PORT_RANGE: &str = "/proc/sys/net/ipv4/ip_local_port_range";
+
+#[derive(Debug, Default)]
+pub struct LocalStats {
+    limits: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalLimit {
+    OpenFiles,
+    EphemeralPorts,
+    ConnectBacklog,
+}
+
+impl Display for LocalLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LocalLimit::OpenFiles => write!(f, "too many open files"),
+            LocalLimit::EphemeralPorts => write!(f, "ephemeral ports exhausted"),
+            LocalLimit::ConnectBacklog => write!(f, "connect backlog full"),
+        }
+    }
+}
+
+/**
+ *=================================================================
+ * ino_local_limit()
+ *=================================================================
+ *
+ * Finds a limit of the machine running inoue behind an error by
+ * walking its sources: no file descriptor left, no ephemeral port
+ * left to bind, or a connect the kernel could not queue. None when
+ * the failure is not local.
+ *
+ *=================================================================
+ * @param error &(dyn Error + 'static)
+ * @return Option<LocalLimit>
+ */
+pub fn ino_local_limit(error: &(dyn Error + 'static)) -> Option<LocalLimit> {
+    let mut source = Some(error);
+    while let Some(cause) = source {
+        if let Some(io_error) = cause.downcast_ref::<io::Error>() {
+            if io_error.kind() == io::ErrorKind::AddrNotAvailable {
+                return Some(LocalLimit::EphemeralPorts);
+            }
+            match io_error.raw_os_error() {
+                Some(libc::EMFILE | libc::ENFILE) => return Some(LocalLimit::OpenFiles),
+                Some(libc::EADDRNOTAVAIL) => return Some(LocalLimit::EphemeralPorts),
+                Some(libc::EAGAIN) => return Some(LocalLimit::ConnectBacklog),
+                _ => {}
+            }
+            // an io::Error wrapping another error skips it in its sources
+            if let Some(inner) = io_error.get_ref() {
+                source = Some(inner);
+                continue;
+            }
+        }
+        source = cause.source();
+    }
+    None
+}
+
+/**
+ *=================================================================
+ * ino_connect_status()
+ *=================================================================
+ *
+ * Status of a request that got no response, a local limit being
+ * reported apart from the failures of the target.
+ *
+ *=================================================================
+ * @param error &reqwest::Error
+ * @return String
+ */
+pub fn ino_connect_status(error: &reqwest::Error) -> String {
+    match ino_local_limit(error) {
+        Some(limit) => ino_local_status(limit),
+        None => "Failed to connect".to_string(),
+    }
+}
+
+/**
+ *=================================================================
+ * ino_local_status()
+ *=================================================================
+ *
+ * Status of a request that failed on a local limit.
+ *
+ *=================================================================
+ * @param limit LocalLimit
+ * @return String
+ */
+pub fn ino_local_status(limit: LocalLimit) -> String {
+    format!("{} ({})", LOCAL_ERROR, limit)
+}
+
+impl LocalStats {
+
+    /**
+    *=================================================================
+    * ino_add()
+    *=================================================================
+    *
+    * Counts a request that failed on a local limit under its limit.
+    *
+    *=================================================================
+    * @param result &BenchmarkResult
+    * @return void
+    */
+    pub fn ino_add(&mut self, result: &BenchmarkResult) {
+        let Some(limit) = result.status.strip_prefix(LOCAL_ERROR) else { return };
+        let limit = limit.trim().trim_start_matches('(').trim_end_matches(')');
+        *self.limits.entry(limit.to_string()).or_insert(0) += 1;
+    }
+
+    /**
+    *=================================================================
+    * ino_summary()
+    *=================================================================
+    *
+    * Requests that failed on a local limit, by limit, None when there
+    * is none.
+    *
+    *=================================================================
+    * @param void
+    * @return Option<BTreeMap<String, u64>>
+    */
+    pub fn ino_summary(&self) -> Option<BTreeMap<String, u64>> {
+        (!self.limits.is_empty()).then(|| self.limits.clone())
+    }
+}
+
+/**
+ *=================================================================
+ * ino_check_limits()
+ *=================================================================
+ *
+ * Checks before the run that the machine can hold a connection per
+ * concurrent request. The open files limit is raised up to its
+ * hard limit when needed, a warning tells what to raise when it is
+ * still too low or when the ephemeral port range is smaller than
+ * the connections.
+ *
+ *=================================================================
+ * @param settings &Settings
+ * @return void
+ */
+pub fn ino_check_limits(settings: &Settings) {
+    let clients = match (&settings.groups, &settings.stages) {
+        (Some(groups), _) => groups.iter().map(|group| group.clients).sum(),
+        (None, Some(stages)) => stages.iter().map(|stage| stage.clients.unwrap_or(settings.clients)).max().unwrap_or(0),
+        (None, None) => settings.clients,
+    };
+    let connections = (clients * settings.in_flight.max(1)) as u64;
+    if let Some(files) = ino_raise_open_files(connections + RESERVED_FILES) {
+        if files < connections + RESERVED_FILES {
+            ino_warn(format!(
+                "{} concurrent connections need more than the {} open files allowed, raise the limit with ulimit -n",
+                connections, files
+            ));
+        }
+    }
+    if let Some(ports) = ino_port_range() {
+        if connections > ports {
+            ino_warn(format!(
+                "{} concurrent connections need more than the {} ephemeral ports of {}, widen the range or use fewer clients",
+                connections, ports, PORT_RANGE
+            ));
+        }
+    }
+}
+
+fn ino_warn(message: String) {
+    eprintln!("{}", message.yellow());
+}
+
+/**
+ *=================================================================
+ * ino_raise_open_files()
+ *=================================================================
+ *
+ * Raises the soft open files limit towards the needed count, up to
+ * the hard limit, and returns the limit in place.
+ *
+ *=================================================================
+ */
+#[cfg(unix)]
+fn ino_raise_open_files(needed: u64) -> Option<u64> {
+    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    // SAFETY: getrlimit only writes into the provided struct.
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return None;
+    }
+    if limit.rlim_cur == libc::RLIM_INFINITY || limit.rlim_cur >= needed {
+        return Some(limit.rlim_cur);
+    }
+    let raised = libc::rlimit { rlim_cur: needed.min(limit.rlim_max), rlim_max: limit.rlim_max };
+    // SAFETY: setrlimit only reads the provided struct.
+    match unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raised) } {
+        0 => Some(raised.rlim_cur),
+        _ => Some(limit.rlim_cur),
+    }
+}
+
+#[cfg(not(unix))]
+fn ino_raise_open_files(_needed: u64) -> Option<u64> {
+    None
+}
+
+/**
+ *=================================================================
+ * ino_port_range()
+ *=================================================================
+ *
+ * Number of ephemeral ports the kernel hands out, where known.
+ *
+ *=================================================================
+ */
+fn ino_port_range() -> Option<u64> {
+    let range = fs::read_to_string(PORT_RANGE).ok()?;
+    let mut bounds = range.split_whitespace().map(|bound| bound.parse::<u64>());
+    match (bounds.next()?, bounds.next()?) {
+        (Ok(low), Ok(high)) if high >= low => Some(high - low + 1),
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::ino_failure;
+
+    #[test]
+    fn should_report_local_limits_apart() {
+        let wrapped = io::Error::other(io::Error::from_raw_os_error(libc::EMFILE));
+        assert_eq!(ino_local_limit(&wrapped), Some(LocalLimit::OpenFiles));
+        assert_eq!(ino_local_limit(&io::Error::from_raw_os_error(libc::EADDRNOTAVAIL)), Some(LocalLimit::EphemeralPorts));
+        assert_eq!(ino_local_limit(&io::Error::from(io::ErrorKind::ConnectionRefused)), None);
+        let results = [
+            ino_failure(0, 0, ino_local_status(LocalLimit::OpenFiles), 1),
+            ino_failure(0, 1, ino_local_status(LocalLimit::OpenFiles), 1),
+            ino_failure(0, 2, "Failed to connect".to_string(), 1),
+        ];
+        let mut stats = LocalStats::default();
+        stats.ino_add(&results[2]);
+        assert_eq!(stats.ino_summary(), None);
+        results.iter().for_each(|result| stats.ino_add(result));
+        assert_eq!(stats.ino_summary(), Some(BTreeMap::from([("too many open files".to_string(), 2)])));
+    }
+}
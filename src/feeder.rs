@@ -0,0 +1,122 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug)]
+pub struct DataFeeder {
+    rows: Vec<Vec<String>>,
+    cursor: AtomicUsize,
+}
+
+impl DataFeeder {
+
+    /**
+    *=================================================================
+    * ino_from_file()
+    *=================================================================
+    *
+    * Loads a CSV data file, its first line names the columns and
+    * is skipped, every other line is a row of values.
+    *
+    *=================================================================
+    * @param file &str
+    * @return Result<DataFeeder>
+    */
+    pub fn ino_from_file(file: &str) -> Result<Self> {
+        let content = fs::read_to_string(file).with_context(|| format!("Failed to read the data from {}", file))?;
+        let (_, rows) = ino_parse_csv(&content).with_context(|| format!("Invalid data file {}", file))?;
+        if rows.is_empty() {
+            bail!("The data file {} has no rows", file);
+        }
+        Ok(DataFeeder { rows, cursor: AtomicUsize::new(0) })
+    }
+
+    /**
+    *=================================================================
+    * ino_next()
+    *=================================================================
+    *
+    * Next row, shared by every client and starting over after the
+    * last one.
+    *
+    *=================================================================
+    * @param void
+    * @return &[String]
+    */
+    pub fn ino_next(&self) -> &[String] {
+        let index = self.cursor.fetch_add(1, Ordering::Relaxed) % self.rows.len();
+        &self.rows[index]
+    }
+}
+
+/**
+ *=================================================================
+ * ino_parse_csv()
+ *=================================================================
+ *
+ * Parses comma separated values with a header line. A field can
+ * be quoted to hold commas, a doubled quote stands for a quote.
+ * Blank lines are ignored and every row must have a value per
+ * column.
+ *
+ *=================================================================
+ * @param content &str
+ * @return Result<(Vec<String>, Vec<Vec<String>>)>
+ */
+fn ino_parse_csv(content: &str) -> Result<(Vec<String>, Vec<Vec<String>>)> {
+    let mut lines = content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let Some((_, header)) = lines.next() else { bail!("The header line is missing") };
+    let columns: Vec<String> = ino_split_csv_line(header)?.into_iter().map(|c| c.trim().to_string()).collect();
+    let mut rows = vec![];
+    for (number, line) in lines {
+        let row = ino_split_csv_line(line)?;
+        if row.len() != columns.len() {
+            bail!("Line {} has {} values for {} columns", number + 1, row.len(), columns.len());
+        }
+        rows.push(row);
+    }
+    Ok((columns, rows))
+}
+
+fn ino_split_csv_line(line: &str) -> Result<Vec<String>> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            (c, _) => field.push(c),
+        }
+    }
+    if quoted {
+        bail!("Unterminated quote in {}", line);
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_csv_and_cycle_rows() -> Result<()> {
+        let (columns, rows) = ino_parse_csv("id,name\n1,ada\n\n2,\"lovelace, \"\"the\"\" countess\"\r\n")?;
+        assert_eq!(columns, vec!["id", "name"]);
+        assert_eq!(rows, vec![vec!["1", "ada"], vec!["2", "lovelace, \"the\" countess"]]);
+        assert!(ino_parse_csv("id,name\n1\n").is_err());
+        assert!(ino_parse_csv("id\n\"1\n").is_err());
+        let feeder = DataFeeder { rows, cursor: AtomicUsize::new(0) };
+        let names: Vec<&str> = (0..3).map(|_| feeder.ino_next()[1].as_str()).collect();
+        assert_eq!(names, vec!["ada", "lovelace, \"the\" countess", "ada"]);
+        Ok(())
+    }
+}
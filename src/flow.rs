@@ -0,0 +1,867 @@
+use anyhow::{bail, Result};
+use bytes::Bytes;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use reqwest::{RequestBuilder, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use rand::Rng;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, PoisonError};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tokio::time::Instant;
+
+use crate::bandwidth::{ino_throttle_body, ino_throttle_transfer, Bandwidth, BandwidthLimiter};
+use crate::benchmark::{ino_quantile, BenchmarkResult};
+use crate::checksum::{Checksum, CORRUPTED};
+use crate::echo::{ino_echo_request, ino_echo_response};
+use crate::execution::{ino_failure, ino_reused, Worker};
+use crate::exhaustion::ino_connect_status;
+use crate::support::{human_duration, ino_default_content_type, ino_to_header_map, Header, LatencyBoundary, Operation};
+use crate::xml::{ino_xpath, XML_CONTENT_TYPE};
+
+pub type Variables = HashMap<String, String>;
+
+const IDEMPOTENCY_KEY: &str = "idempotency_key";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Step {
+    Loop(LoopStep),
+    If(IfStep),
+    Poll(PollStep),
+    Request(RequestStep),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequestStep {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub target: String,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub xml: Option<String>,
+    #[serde(default)]
+    pub headers: Option<Vec<Header>>,
+    #[serde(default)]
+    pub extract: BTreeMap<String, String>,
+    #[serde(default)]
+    pub bandwidth: Option<Bandwidth>,
+    #[serde(default)]
+    pub checksum: Option<Checksum>,
+    #[serde(default)]
+    pub duplicate: Option<Duplicate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Duplicate {
+    #[serde(default = "ino_default_idempotency_header")]
+    pub header: String,
+    #[serde(default)]
+    pub concurrent: bool,
+    #[serde(default)]
+    pub expect_status: Option<u16>,
+}
+
+fn ino_default_idempotency_header() -> String {
+    "Idempotency-Key".to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoopStep {
+    #[serde(rename = "loop")]
+    pub count: u32,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IfStep {
+    #[serde(rename = "if")]
+    pub condition: Condition,
+    #[serde(default)]
+    pub steps: Vec<Step>,
+    #[serde(default, rename = "break")]
+    pub stop: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PollStep {
+    pub poll: Poll,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Poll {
+    #[serde(flatten)]
+    pub request: RequestStep,
+    pub until: Condition,
+    #[serde(with = "human_duration")]
+    pub interval: Duration,
+    #[serde(with = "human_duration")]
+    pub timeout: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PollOutcome {
+    pub name: String,
+    pub elapsed: u64,
+    pub completed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PollSummary {
+    pub count: u64,
+    pub completed: u64,
+    pub timed_out: u64,
+    pub mean: f64,
+    pub p95: u64,
+    pub max: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StepSummary {
+    pub requests: u64,
+    pub errors: u64,
+    pub mean: f64,
+    pub p95: u64,
+    pub p999: u64,
+    pub max: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct PollStats {
+    polls: BTreeMap<String, (Vec<u64>, u64)>,
+}
+
+#[derive(Debug, Default)]
+pub struct StepStats {
+    steps: BTreeMap<String, (Vec<u64>, u64)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Condition {
+    pub variable: String,
+    pub equal: bool,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    Next,
+    Break,
+}
+
+impl TryFrom<String> for Condition {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        ino_parse_condition(&value)
+    }
+}
+
+impl From<Condition> for String {
+    fn from(condition: Condition) -> Self {
+        format!("{} {} {}", condition.variable, if condition.equal { "==" } else { "!=" }, condition.value)
+    }
+}
+
+impl RequestStep {
+
+    /**
+    *=================================================================
+    * ino_name()
+    *=================================================================
+    *
+    * Name the step is reported under, its URL when it has none.
+    *
+    *=================================================================
+    * @param void
+    * @return String
+    */
+    pub fn ino_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| ino_split_target(&self.target).1)
+    }
+}
+
+impl Condition {
+
+    /**
+    *=================================================================
+    * ino_holds()
+    *=================================================================
+    *
+    * Compares a variable with the value of the condition, a missing
+    * variable is never equal.
+    *
+    *=================================================================
+    * @param variables &HashMap<String, String>
+    * @return bool
+    */
+    fn ino_holds(&self, variables: &Variables) -> bool {
+        let equal = variables.get(&self.variable).is_some_and(|v| *v == self.value);
+        equal == self.equal
+    }
+}
+
+/**
+ *=================================================================
+ * ino_parse_condition()
+ *=================================================================
+ *
+ * Parses a condition such as "status == 200" or "state != done",
+ * quotes around the value are optional.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<Condition>
+ */
+pub fn ino_parse_condition(value: &str) -> Result<Condition> {
+    let (variable, equal, expected) = match (value.split_once("!="), value.split_once("==")) {
+        (Some((variable, expected)), _) => (variable, false, expected),
+        (None, Some((variable, expected))) => (variable, true, expected),
+        (None, None) => bail!("Invalid condition {}, use variable == value or variable != value", value),
+    };
+    let variable = variable.trim();
+    if variable.is_empty() {
+        bail!("Invalid condition {}, the variable is missing", value);
+    }
+    let expected = expected.trim();
+    let expected = expected
+        .strip_prefix('"')
+        .and_then(|e| e.strip_suffix('"'))
+        .unwrap_or(expected);
+    Ok(Condition {
+        variable: variable.to_string(),
+        equal,
+        value: expected.to_string(),
+    })
+}
+
+/**
+ *=================================================================
+ * ino_first_target()
+ *=================================================================
+ *
+ * URL of the first request of the steps, to name the run.
+ *
+ *=================================================================
+ * @param steps &[Step]
+ * @return Option<String>
+ */
+pub fn ino_first_target(steps: &[Step]) -> Option<String> {
+    steps.iter().find_map(|step| match step {
+        Step::Request(request) => Some(ino_split_target(&request.target).1),
+        Step::Poll(PollStep { poll }) => Some(ino_split_target(&poll.request.target).1),
+        Step::Loop(LoopStep { steps, .. }) | Step::If(IfStep { steps, .. }) => ino_first_target(steps),
+    })
+}
+
+/**
+ *=================================================================
+ * ino_targets()
+ *=================================================================
+ *
+ * URLs of every request of the steps, nested ones included.
+ *
+ *=================================================================
+ * @param steps &[Step]
+ * @return Vec<String>
+ */
+pub fn ino_targets(steps: &[Step]) -> Vec<String> {
+    steps
+        .iter()
+        .flat_map(|step| match step {
+            Step::Request(request) => vec![ino_split_target(&request.target).1],
+            Step::Poll(PollStep { poll }) => vec![ino_split_target(&poll.request.target).1],
+            Step::Loop(LoopStep { steps, .. }) | Step::If(IfStep { steps, .. }) => ino_targets(steps),
+        })
+        .collect()
+}
+
+/**
+ *=================================================================
+ * ino_has_checksum()
+ *=================================================================
+ *
+ * Whether a request of the steps, nested ones included, verifies
+ * the checksum of its response body.
+ *
+ *=================================================================
+ * @param steps &[Step]
+ * @return bool
+ */
+pub fn ino_has_checksum(steps: &[Step]) -> bool {
+    steps.iter().any(|step| match step {
+        Step::Request(request) => request.checksum.is_some(),
+        Step::Poll(PollStep { poll }) => poll.request.checksum.is_some(),
+        Step::Loop(LoopStep { steps, .. }) | Step::If(IfStep { steps, .. }) => ino_has_checksum(steps),
+    })
+}
+
+/**
+ *=================================================================
+ * ino_exec_flow()
+ *=================================================================
+ *
+ * Runs the steps of the scenario once, as one iteration of the
+ * client, sending the result of every request as soon as it
+ * completes. The variables extracted by a step are only seen by
+ * the next steps of the same iteration.
+ *
+ *=================================================================
+ * @param worker &Worker
+ * @param steps &[Step]
+ * @param execution usize
+ * @param tx &Sender<BenchmarkResult>
+ * @return void
+ */
+pub async fn ino_exec_flow(worker: &Worker, steps: &[Step], execution: usize, tx: &Sender<BenchmarkResult>) {
+    let mut variables = Variables::new();
+    ino_run_steps(worker, steps, execution, &mut variables, tx).await;
+}
+
+/**
+ *=================================================================
+ * ino_run_steps()
+ *=================================================================
+ *
+ * Runs a group of steps. A loop repeats its group until its count
+ * or a break, a break outside of any loop ends the iteration.
+ *
+ *=================================================================
+ */
+fn ino_run_steps<'a>(
+    worker: &'a Worker,
+    steps: &'a [Step],
+    execution: usize,
+    variables: &'a mut Variables,
+    tx: &'a Sender<BenchmarkResult>,
+) -> Pin<Box<dyn Future<Output = Flow> + Send + 'a>> {
+    Box::pin(async move {
+        for step in steps {
+            match step {
+                Step::Request(request @ RequestStep { duplicate: Some(duplicate), .. }) => {
+                    for result in ino_exec_duplicate(worker, request, duplicate, execution, variables).await {
+                        worker.stats.ino_send(tx, result).await;
+                    }
+                }
+                Step::Request(request) => {
+                    let (mut result, _) = ino_exec_step(worker, request, execution, variables).await;
+                    result.step = Some(request.ino_name());
+                    worker.stats.ino_send(tx, result).await;
+                }
+                Step::Poll(PollStep { poll }) => ino_exec_poll(worker, poll, execution, variables, tx).await,
+                Step::Loop(group) => {
+                    for _ in 0..group.count {
+                        if ino_run_steps(worker, &group.steps, execution, variables, tx).await == Flow::Break {
+                            break;
+                        }
+                    }
+                }
+                Step::If(branch) => {
+                    if !branch.condition.ino_holds(variables) {
+                        continue;
+                    }
+                    if ino_run_steps(worker, &branch.steps, execution, variables, tx).await == Flow::Break || branch.stop {
+                        return Flow::Break;
+                    }
+                }
+            }
+        }
+        Flow::Next
+    })
+}
+
+/**
+ *=================================================================
+ * ino_exec_poll()
+ *=================================================================
+ *
+ * Sends the request of a poll every interval until its condition
+ * holds or its timeout would be exceeded. The last request carries
+ * the outcome of the poll: its wall-clock time and whether it
+ * completed.
+ *
+ *=================================================================
+ */
+async fn ino_exec_poll(worker: &Worker, poll: &Poll, execution: usize, variables: &mut Variables, tx: &Sender<BenchmarkResult>) {
+    let begin = Instant::now();
+    loop {
+        let (mut result, _) = ino_exec_step(worker, &poll.request, execution, variables).await;
+        result.step = Some(poll.request.ino_name());
+        let completed = poll.until.ino_holds(variables);
+        if completed || begin.elapsed() + poll.interval >= poll.timeout {
+            result.poll = Some(PollOutcome {
+                name: poll.request.ino_name(),
+                elapsed: begin.elapsed().as_millis() as u64,
+                completed,
+            });
+            worker.stats.ino_send(tx, result).await;
+            return;
+        }
+        worker.stats.ino_send(tx, result).await;
+        tokio::time::sleep(poll.interval).await;
+    }
+}
+
+impl PollStats {
+
+    /**
+    *=================================================================
+    * ino_add()
+    *=================================================================
+    *
+    * Records the wall-clock time of a completed poll, or counts a
+    * poll that timed out, under its name.
+    *
+    *=================================================================
+    * @param result &BenchmarkResult
+    * @return void
+    */
+    pub fn ino_add(&mut self, result: &BenchmarkResult) {
+        let Some(poll) = &result.poll else { return };
+        let (elapsed, timed_out) = self.polls.entry(poll.name.clone()).or_default();
+        match poll.completed {
+            true => elapsed.push(poll.elapsed),
+            false => *timed_out += 1,
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_summary()
+    *=================================================================
+    *
+    * Wall-clock time of every poll over the results, by name: the
+    * completed ones and the ones that timed out, with the mean, 95th
+    * percentile and maximum of the completed ones in milliseconds.
+    * None without any poll.
+    *
+    *=================================================================
+    * @param void
+    * @return Option<BTreeMap<String, PollSummary>>
+    */
+    pub fn ino_summary(&self) -> Option<BTreeMap<String, PollSummary>> {
+        (!self.polls.is_empty()).then(|| {
+            self.polls
+                .iter()
+                .map(|(name, (elapsed, timed_out))| {
+                    let mut elapsed = elapsed.clone();
+                    elapsed.sort_unstable();
+                    let completed = elapsed.len() as u64;
+                    let summary = PollSummary {
+                        count: completed + timed_out,
+                        completed,
+                        timed_out: *timed_out,
+                        mean: elapsed.iter().sum::<u64>() as f64 / completed.max(1) as f64,
+                        p95: match elapsed.is_empty() {
+                            true => 0,
+                            false => ino_quantile(&elapsed, 0.95),
+                        },
+                        max: elapsed.last().cloned().unwrap_or(0),
+                    };
+                    (name.clone(), summary)
+                })
+                .collect()
+        })
+    }
+}
+
+impl StepStats {
+
+    /**
+    *=================================================================
+    * ino_add()
+    *=================================================================
+    *
+    * Records the latency of a step request, and whether it failed,
+    * under the name of its step.
+    *
+    *=================================================================
+    * @param result &BenchmarkResult
+    * @return void
+    */
+    pub fn ino_add(&mut self, result: &BenchmarkResult) {
+        let Some(step) = &result.step else { return };
+        let (durations, errors) = self.steps.entry(step.clone()).or_default();
+        durations.push(result.duration);
+        *errors += u64::from(!result.success);
+    }
+
+    /**
+    *=================================================================
+    * ino_summary()
+    *=================================================================
+    *
+    * Requests of every step over the results, by name: their count,
+    * errors and the mean, 95th and 99.9th percentiles and maximum of
+    * their latency in milliseconds. None without steps.
+    *
+    *=================================================================
+    * @param void
+    * @return Option<BTreeMap<String, StepSummary>>
+    */
+    pub fn ino_summary(&self) -> Option<BTreeMap<String, StepSummary>> {
+        (!self.steps.is_empty()).then(|| {
+            self.steps
+                .iter()
+                .map(|(name, (durations, errors))| {
+                    let mut durations = durations.clone();
+                    durations.sort_unstable();
+                    let summary = StepSummary {
+                        requests: durations.len() as u64,
+                        errors: *errors,
+                        mean: durations.iter().sum::<u64>() as f64 / durations.len() as f64,
+                        p95: ino_quantile(&durations, 0.95),
+                        p999: ino_quantile(&durations, 0.999),
+                        max: durations.last().cloned().unwrap_or(0),
+                    };
+                    (name.clone(), summary)
+                })
+                .collect()
+        })
+    }
+}
+
+/**
+ *=================================================================
+ * ino_exec_duplicate()
+ *=================================================================
+ *
+ * Sends the request of a step twice with the same fresh
+ * idempotency key, one after the other or both at once. The
+ * response that came back last is the duplicate, reported under
+ * the name of the step followed by "(duplicate)": it fails unless
+ * it has the expected status, or by default the same status and
+ * body as the other response. The key is the `idempotency_key`
+ * variable, drawn from the random stream of the client so a seeded
+ * run sends the same keys, the next steps see the variables
+ * extracted from the first request sent.
+ *
+ *=================================================================
+ */
+async fn ino_exec_duplicate(worker: &Worker, step: &RequestStep, duplicate: &Duplicate, execution: usize, variables: &mut Variables) -> [BenchmarkResult; 2] {
+    let key = worker.idempotency.lock().unwrap_or_else(PoisonError::into_inner).gen::<u128>();
+    variables.insert(IDEMPOTENCY_KEY.to_string(), format!("{:032x}", key));
+    let mut copy = variables.clone();
+    let timed = |variables| async move {
+        let exchange = ino_exec_step(worker, step, execution, variables).await;
+        (exchange, Instant::now())
+    };
+    let ((first, first_end), (second, second_end)) = match duplicate.concurrent {
+        true => tokio::join!(timed(variables), timed(&mut copy)),
+        false => (timed(variables).await, timed(&mut copy).await),
+    };
+    let ((mut original, original_body), (mut repeated, repeated_body)) = match second_end >= first_end {
+        true => (first, second),
+        false => (second, first),
+    };
+    let unmet = match duplicate.expect_status {
+        Some(expected) if !repeated.status.starts_with(&expected.to_string()) => Some(expected.to_string()),
+        Some(_) => None,
+        None if repeated.status != original.status || repeated_body != original_body => Some("the same response".to_string()),
+        None => None,
+    };
+    if let Some(expectation) = unmet {
+        repeated.success = false;
+        repeated.status = format!("{} (expected {})", repeated.status, expectation);
+    } else if duplicate.expect_status.is_some() {
+        repeated.success = true;
+    }
+    original.step = Some(step.ino_name());
+    repeated.step = Some(format!("{} (duplicate)", step.ino_name()));
+    [original, repeated]
+}
+
+/**
+ *=================================================================
+ * ino_exec_step()
+ *=================================================================
+ *
+ * Sends the request of a step with the variables substituted in
+ * its target, headers and body, then stores the status of the
+ * response as the `status` variable, 0 without a response, and
+ * the extracted values. A duplicated step also sends the
+ * `idempotency_key` variable in its idempotency header. Returns
+ * the result and the response body.
+ *
+ *=================================================================
+ */
+async fn ino_exec_step(worker: &Worker, step: &RequestStep, execution: usize, variables: &mut Variables) -> (BenchmarkResult, Vec<u8>) {
+    let num_client = worker.num_client;
+    let (operation, url) = ino_split_target(&ino_render(&step.target, variables));
+    let Ok(url) = Url::parse(&url) else {
+        variables.insert("status".to_string(), "0".to_string());
+        return (ino_failure(num_client, execution, "Invalid URL".to_string(), 0), vec![]);
+    };
+    let mut headers: Vec<Header> = step
+        .headers
+        .iter()
+        .flatten()
+        .map(|h| Header { key: h.key.clone(), value: ino_render(&h.value, variables) })
+        .collect();
+    if let (Some(duplicate), Some(key)) = (&step.duplicate, variables.get(IDEMPOTENCY_KEY)) {
+        headers.push(Header { key: duplicate.header.clone(), value: key.clone() });
+    }
+    if step.xml.is_some() && !worker.request.headers.contains_key(CONTENT_TYPE) {
+        ino_default_content_type(&mut headers, XML_CONTENT_TYPE);
+    }
+    let step_headers = match ino_to_header_map(&headers) {
+        Ok(headers) => headers,
+        Err(e) => {
+            variables.insert("status".to_string(), "0".to_string());
+            return (ino_failure(num_client, execution, e.to_string(), 0), vec![]);
+        }
+    };
+    let mut headers = worker.request.headers.clone();
+    headers.extend(step_headers);
+    let limiters: Vec<Arc<BandwidthLimiter>> = worker
+        .bandwidth
+        .iter()
+        .cloned()
+        .chain(step.bandwidth.map(|b| Arc::new(BandwidthLimiter::new(b))))
+        .collect();
+    let body = step.xml.as_ref().or(step.body.as_ref()).map(|body| Bytes::from(ino_render(body, variables)));
+    if let Some(signing) = &worker.settings.signing {
+        match signing.ino_headers(&operation.ino_method(), &url, body.as_deref().unwrap_or_default()) {
+            Ok(signature) => headers.extend(signature),
+            Err(e) => {
+                variables.insert("status".to_string(), "0".to_string());
+                return (ino_failure(num_client, execution, e.to_string(), 0), vec![]);
+            }
+        }
+    }
+    let mut request = worker.client.request(operation.ino_method(), url).headers(headers);
+    if let Some(body) = body {
+        request = match limiters.is_empty() {
+            true => request.body(body),
+            false => request.body(ino_throttle_body(body, limiters.clone())),
+        };
+    }
+    variables.insert("status".to_string(), "0".to_string());
+    if worker.settings.echo {
+        if let Some(Ok(copy)) = request.try_clone().map(RequestBuilder::build) {
+            ino_echo_request(&copy);
+        }
+    }
+    let begin = Instant::now();
+    if let Some(latency) = &worker.latency {
+        latency.ino_delay().await;
+    }
+    let mut response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            let status = e.status().map_or_else(|| ino_connect_status(&e), |s| s.to_string());
+            return (ino_failure(num_client, execution, status, begin.elapsed().as_millis() as u64), vec![]);
+        }
+    };
+    let first_byte = begin.elapsed().as_millis() as u64;
+    let status = response.status();
+    let version = response.version();
+    let reused = ino_reused(worker, &response);
+    let response_headers = response.headers().clone();
+    let mut body = vec![];
+    while let Ok(Some(chunk)) = response.chunk().await {
+        ino_throttle_transfer(&limiters, chunk.len()).await;
+        body.extend_from_slice(&chunk);
+    }
+    if worker.settings.echo {
+        ino_echo_response(status, version, &response_headers, &body, Some(body.len() as u64));
+    }
+    let duration = match worker.settings.latency_boundary {
+        LatencyBoundary::Headers => first_byte,
+        LatencyBoundary::Body => begin.elapsed().as_millis() as u64,
+    };
+    variables.insert("status".to_string(), status.as_u16().to_string());
+    for (name, source) in &step.extract {
+        match ino_extract(source, status, &response_headers, &body) {
+            Some(value) => variables.insert(name.clone(), value),
+            None => variables.remove(name),
+        };
+    }
+    let corrupted = status.is_success() && step.checksum.is_some_and(|checksum| !checksum.ino_matches(Sha256::new_with_prefix(&body)));
+    let success = (status.is_success() || status.is_redirection()) && !corrupted;
+    let status = match corrupted {
+        true => format!("{} ({})", status, CORRUPTED),
+        false => status.to_string(),
+    };
+    let result = BenchmarkResult {
+        size: Some(body.len() as u64),
+        reused,
+        first_byte: Some(first_byte),
+        ..BenchmarkResult::new(status, success, duration, num_client, execution)
+    };
+    (result, body)
+}
+
+/**
+ *=================================================================
+ * ino_extract()
+ *=================================================================
+ *
+ * Reads a value out of a response: "status", "header:<name>",
+ * "json:<path>" with a dotted path into the JSON body, array
+ * indexes included, "xpath:<path>" into the XML body or "body"
+ * for the whole body.
+ *
+ *=================================================================
+ * @param source &str
+ * @param status StatusCode
+ * @param headers &HeaderMap
+ * @param body &[u8]
+ * @return Option<String>
+ */
+fn ino_extract(source: &str, status: StatusCode, headers: &HeaderMap, body: &[u8]) -> Option<String> {
+    match source.trim().split_once(':') {
+        None if source.trim() == "status" => Some(status.as_u16().to_string()),
+        None if source.trim() == "body" => Some(String::from_utf8_lossy(body).into_owned()),
+        Some(("header", name)) => headers.get(name.trim())?.to_str().ok().map(str::to_string),
+        Some(("xpath", path)) => ino_xpath(path.trim(), body),
+        Some(("json", path)) => {
+            let json: Value = serde_json::from_slice(body).ok()?;
+            let value = path
+                .trim()
+                .split('.')
+                .filter(|key| !key.is_empty())
+                .try_fold(&json, |value, key| match key.parse::<usize>() {
+                    Ok(index) if value.is_array() => value.get(index),
+                    _ => value.get(key),
+                })?;
+            match value {
+                Value::String(s) => Some(s.clone()),
+                Value::Null => None,
+                other => Some(other.to_string()),
+            }
+        }
+        _ => None,
+    }
+}
+
+/**
+ *=================================================================
+ * ino_render()
+ *=================================================================
+ *
+ * Substitutes the {{name}} placeholders with the variables, the
+ * unknown ones are left as they are.
+ *
+ *=================================================================
+ * @param template &str
+ * @param variables &HashMap<String, String>
+ * @return String
+ */
+pub fn ino_render(template: &str, variables: &Variables) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else { break };
+        let name = rest[start + 2..start + end].trim();
+        rendered.push_str(&rest[..start]);
+        match variables.get(name) {
+            Some(value) => rendered.push_str(value),
+            None => rendered.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/**
+ *=================================================================
+ * ino_split_target()
+ *=================================================================
+ *
+ * Splits a step target such as "POST http://host/jobs" into its
+ * operation, GET when omitted, and its URL.
+ *
+ *=================================================================
+ */
+fn ino_split_target(target: &str) -> (Operation, String) {
+    let slices: Vec<&str> = target.split_whitespace().collect();
+    match slices.as_slice() {
+        [url] => (Operation::Get, url.to_string()),
+        [operation, url, ..] => (Operation::from_str(&operation.to_uppercase()).unwrap_or(Operation::Get), url.to_string()),
+        [] => (Operation::Get, String::new()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::ino_result;
+    use reqwest::header::{HeaderValue, LOCATION};
+
+    #[test]
+    fn should_parse_steps() -> Result<()> {
+        let steps: Vec<Step> = serde_yaml::from_str(
+            "- target: POST http://host/jobs\n  extract:\n    job: json:id\n\
+             - loop: 30\n  steps:\n    - target: http://host/jobs/{{job}}\n      extract:\n        state: json:state\n    - if: state == \"done\"\n      break: true",
+        )?;
+        assert_eq!(steps.len(), 2);
+        let Step::Loop(group) = &steps[1] else { panic!("loop expected") };
+        assert_eq!(group.count, 30);
+        let Step::If(branch) = &group.steps[1] else { panic!("if expected") };
+        assert_eq!(branch.condition, Condition { variable: "state".to_string(), equal: true, value: "done".to_string() });
+        assert!(branch.stop);
+        assert_eq!(ino_first_target(&steps), Some("http://host/jobs".to_string()));
+        assert!(ino_parse_condition("done").is_err());
+        let steps: Vec<Step> = serde_yaml::from_str("- target: POST http://host/payments\n  duplicate:\n    concurrent: true")?;
+        let Step::Request(request) = &steps[0] else { panic!("request expected") };
+        assert_eq!(
+            request.duplicate,
+            Some(Duplicate { header: "Idempotency-Key".to_string(), concurrent: true, expect_status: None })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_summarize_polls() -> Result<()> {
+        let steps: Vec<Step> = serde_yaml::from_str(
+            "- poll:\n    name: job\n    target: http://host/jobs/1\n    extract:\n      state: json:state\n    until: state == done\n    interval: 500ms\n    timeout: 30s",
+        )?;
+        let Step::Poll(PollStep { poll }) = &steps[0] else { panic!("poll expected") };
+        assert_eq!((poll.interval, poll.timeout), (Duration::from_millis(500), Duration::from_secs(30)));
+        assert_eq!(poll.request.extract.get("state").map(String::as_str), Some("json:state"));
+        let result = |elapsed, completed| BenchmarkResult {
+            poll: Some(PollOutcome { name: "job".to_string(), elapsed, completed }),
+            ..ino_result()
+        };
+        let mut stats = PollStats::default();
+        assert_eq!(stats.ino_summary(), None);
+        [result(1000, true), result(3000, true), result(30000, false)].iter().for_each(|result| stats.ino_add(result));
+        let summary = stats.ino_summary().unwrap();
+        assert_eq!(summary["job"], PollSummary { count: 3, completed: 2, timed_out: 1, mean: 2000.0, p95: 3000, max: 3000 });
+        Ok(())
+    }
+
+    #[test]
+    fn should_extract_and_render_variables() {
+        let mut headers = HeaderMap::new();
+        headers.insert(LOCATION, HeaderValue::from_static("/jobs/7"));
+        let body = br#"{"id": 7, "items": [{"state": "done"}], "none": null}"#;
+        let extract = |source| ino_extract(source, StatusCode::CREATED, &headers, body);
+        assert_eq!(extract("status"), Some("201".to_string()));
+        assert_eq!(extract("header:location"), Some("/jobs/7".to_string()));
+        assert_eq!(extract("json:id"), Some("7".to_string()));
+        assert_eq!(extract("json:items.0.state"), Some("done".to_string()));
+        assert_eq!(extract("json:none"), None);
+        assert_eq!(extract("json:missing"), None);
+        assert_eq!(extract("xpath:/order/id"), None);
+        let variables = Variables::from([("job".to_string(), "7".to_string())]);
+        assert_eq!(ino_render("http://host/jobs/{{ job }}?x={{other}}", &variables), "http://host/jobs/7?x={{other}}");
+        let not_done = Condition { variable: "state".to_string(), equal: false, value: "done".to_string() };
+        assert!(not_done.ino_holds(&variables));
+    }
+}
@@ -0,0 +1,189 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+use futures::future::join_all;
+use indicatif::MultiProgress;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::sync::watch;
+
+use crate::flow::Step;
+use crate::pacing::Pacing;
+use crate::rate::RateBudget;
+use crate::run::{ino_benchmark, ino_export, ino_print_exit_summary, ino_progress_bar, ino_verdict};
+use crate::support::{human_duration, Header, OutputFormat, Settings};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientGroup {
+    pub name: String,
+    pub clients: usize,
+    #[serde(default)]
+    pub requests: Option<usize>,
+    #[serde(default, deserialize_with = "human_duration::seconds")]
+    pub duration: Option<u64>,
+    #[serde(default)]
+    pub target: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub headers: Option<Vec<Header>>,
+    #[serde(default)]
+    pub steps: Option<Vec<Step>>,
+    #[serde(default)]
+    pub pacing: Option<Pacing>,
+    #[serde(default)]
+    pub weight: Option<f64>,
+}
+
+impl ClientGroup {
+
+    /**
+    *=================================================================
+    * ino_settings()
+    *=================================================================
+    *
+    * Settings of the group: the scenario with the values of the
+    * group on top. The group name is appended to the label, and to
+    * the raw results and heatmap files, so every group is reported
+    * and exported on its own. The interactive commands only drive
+    * single runs.
+    *
+    *=================================================================
+    * @param settings &Settings
+    * @return Settings
+    */
+    pub fn ino_settings(&self, settings: &Settings) -> Settings {
+        let mut group = settings.clone();
+        group.groups = None;
+        group.clients = self.clients;
+        group.requests = self.requests.unwrap_or(settings.requests);
+        group.duration = self.duration.or(settings.duration);
+        if let Some(target) = &self.target {
+            group.target = target.clone();
+            group.steps = None;
+        }
+        if self.body.is_some() {
+            group.body = self.body.clone();
+        }
+        if self.headers.is_some() {
+            group.headers = self.headers.clone();
+        }
+        if self.steps.is_some() {
+            group.steps = self.steps.clone();
+        }
+        if self.pacing.is_some() {
+            group.pacing = self.pacing;
+        }
+        group.label = Some(match &settings.label {
+            None => self.name.clone(),
+            Some(label) => format!("{}/{}", label, self.name),
+        });
+        group.raw_out = settings.raw_out.as_deref().map(|path| ino_group_path(path, &self.name));
+        group.heatmap = settings.heatmap.as_deref().map(|path| ino_group_path(path, &self.name));
+        group.interactive = false;
+        group
+    }
+}
+
+/**
+ *=================================================================
+ * ino_run_groups()
+ *=================================================================
+ *
+ * Runs all the client groups of the scenario at once, each with
+ * its own progress bar, then prints a report section per group
+ * and evaluates and exports every group on its own. A rate cap of
+ * the scenario is a single budget the groups share by weight.
+ *
+ *=================================================================
+ * @param settings &Settings
+ * @param groups &[ClientGroup]
+ * @param rx_sigint watch::Receiver<Option<()>>
+ * @return Result<bool> true when every group passed
+ */
+pub async fn ino_run_groups(settings: &Settings, groups: &[ClientGroup], rx_sigint: watch::Receiver<Option<()>>) -> Result<bool> {
+    if groups.is_empty() {
+        bail!("The scenario declares no client group");
+    }
+    let multi = MultiProgress::new();
+    let text = settings.format == OutputFormat::Text;
+    let runs: Vec<(&ClientGroup, Settings)> = groups.iter().map(|group| (group, group.ino_settings(settings))).collect();
+    if text {
+        for (_, group) in &runs {
+            group.ino_print_banner();
+        }
+    }
+    let budget = match settings.rate {
+        None => None,
+        Some(rate) => Some(RateBudget::new(rate, &groups.iter().map(|group| group.weight.unwrap_or(1.0)).collect::<Vec<_>>())?),
+    };
+    let reports = join_all(runs.iter().enumerate().map(|(index, (group, settings))| {
+        let pb = ino_progress_bar(&multi, &group.name, settings);
+        ino_benchmark(settings, rx_sigint.clone(), pb, None, budget.as_ref().map(|budget| budget.ino_share(index)))
+    }))
+    .await;
+    let mut passed = true;
+    let mut exit_runs = vec![];
+    for ((group, settings), report) in runs.iter().zip(reports) {
+        let report = report?;
+        if text {
+            println!();
+            println!("{} {}", "Group".yellow().bold(), group.name.purple().bold());
+            report.ino_show_result();
+        }
+        if let Some(path) = &settings.heatmap {
+            report.ino_heatmap().ino_write(path, &settings.ino_name())?;
+        }
+        let summary = report.ino_summary(settings);
+        let failures = ino_verdict(settings, &summary, text);
+        ino_export(settings, &summary, &failures).await?;
+        passed &= failures.is_empty();
+        exit_runs.push(summary.ino_run_summary(&failures, report.ino_encoded_histogram()));
+    }
+    if !text {
+        ino_print_exit_summary(exit_runs)?;
+    }
+    Ok(passed)
+}
+
+/**
+ *=================================================================
+ * ino_group_path()
+ *=================================================================
+ *
+ * Inserts the group name before the extension of a file path,
+ * "raw.jsonl" becomes "raw-admins.jsonl".
+ *
+ *=================================================================
+ */
+pub fn ino_group_path(path: &str, name: &str) -> String {
+    let file = Path::new(path);
+    let stem = file.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    let renamed = match file.extension() {
+        None => format!("{}-{}", stem, name),
+        Some(extension) => format!("{}-{}.{}", stem, name, extension.to_string_lossy()),
+    };
+    file.with_file_name(renamed).to_string_lossy().into_owned()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_put_the_group_on_top_of_the_scenario() -> Result<()> {
+        let settings: Settings = serde_yaml::from_str(
+            "target: http://host/\nrequests: 100\nlabel: shop\nraw_out: out/raw.jsonl\n\
+             groups:\n  - name: admins\n    clients: 10\n    pacing: \"6:1m\"\n    steps:\n      - target: http://host/admin",
+        )?;
+        let groups = settings.groups.clone().unwrap_or_default();
+        let admins = groups[0].ino_settings(&settings);
+        assert_eq!((admins.clients, admins.requests), (10, 100));
+        assert_eq!(admins.label.as_deref(), Some("shop/admins"));
+        assert_eq!(admins.raw_out.as_deref(), Some("out/raw-admins.jsonl"));
+        assert!(admins.pacing.is_some() && admins.steps.is_some() && admins.groups.is_none());
+        assert_eq!(ino_group_path("heatmap", "admins"), "heatmap-admins");
+        Ok(())
+    }
+}
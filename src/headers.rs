@@ -0,0 +1,229 @@
+use anyhow::{bail, Result};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use crate::benchmark::BenchmarkResult;
+
+pub const RATE_LIMIT_HEADERS: [&str; 6] = [
+    "x-ratelimit-limit",
+    "x-ratelimit-remaining",
+    "x-ratelimit-reset",
+    "ratelimit-limit",
+    "ratelimit-remaining",
+    "ratelimit-reset",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct HeaderExpectation {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl TryFrom<String> for HeaderExpectation {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        ino_parse_expectation(&value)
+    }
+}
+
+impl From<HeaderExpectation> for String {
+    fn from(expectation: HeaderExpectation) -> Self {
+        expectation.to_string()
+    }
+}
+
+impl Display for HeaderExpectation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            None => write!(f, "{}", self.name),
+            Some(value) => write!(f, "{}={}", self.name, value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HeaderStats {
+    pub count: u64,
+    pub min: f64,
+    pub mean: f64,
+    pub max: f64,
+    pub last: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct HeaderTotals {
+    headers: BTreeMap<String, (HeaderStats, f64)>,
+}
+
+/**
+ *=================================================================
+ * ino_parse_expectation()
+ *=================================================================
+ *
+ * Parses an expected response header, "name=value" when the value
+ * must contain the given text or "name" when the header must only
+ * be present. Header names are case insensitive.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<HeaderExpectation>
+ */
+pub fn ino_parse_expectation(value: &str) -> Result<HeaderExpectation> {
+    let (name, expected) = match value.split_once('=') {
+        None => (value, None),
+        Some((name, expected)) => (name, Some(expected.trim().to_string())),
+    };
+    let name = name.trim().to_lowercase();
+    if name.is_empty() {
+        bail!("Invalid expected header {}, use name=value or name", value);
+    }
+    Ok(HeaderExpectation { name, value: expected })
+}
+
+/**
+ *=================================================================
+ * ino_check_headers()
+ *=================================================================
+ *
+ * Checks the headers of a response against the expectations.
+ *
+ *=================================================================
+ * @param expectations &[HeaderExpectation]
+ * @param headers &HeaderMap
+ * @return Option<&HeaderExpectation> the first unmet expectation
+ */
+pub fn ino_check_headers<'a>(expectations: &'a [HeaderExpectation], headers: &HeaderMap) -> Option<&'a HeaderExpectation> {
+    expectations.iter().find(|expectation| {
+        let actual = headers.get(expectation.name.as_str()).and_then(|v| v.to_str().ok());
+        match (actual, &expectation.value) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(actual), Some(value)) => !actual.contains(value.as_str()),
+        }
+    })
+}
+
+/**
+ *=================================================================
+ * ino_extract_headers()
+ *=================================================================
+ *
+ * Reads the numeric value of the extracted headers and of the
+ * well-known rate-limit headers of a response. None when none of
+ * them is present.
+ *
+ *=================================================================
+ * @param names &[String]
+ * @param headers &HeaderMap
+ * @return Option<Vec<(String, f64)>>
+ */
+pub fn ino_extract_headers(names: &[String], headers: &HeaderMap) -> Option<Vec<(String, f64)>> {
+    let values: Vec<(String, f64)> = names
+        .iter()
+        .map(String::as_str)
+        .chain(RATE_LIMIT_HEADERS.into_iter().filter(|h| !names.iter().any(|n| n.eq_ignore_ascii_case(h))))
+        .filter_map(|name| {
+            let value = headers.get(name)?.to_str().ok()?.trim().parse().ok()?;
+            Some((name.to_lowercase(), value))
+        })
+        .collect();
+    (!values.is_empty()).then_some(values)
+}
+
+impl HeaderTotals {
+
+    /**
+    *=================================================================
+    * ino_add()
+    *=================================================================
+    *
+    * Records the extracted header values of a result, in the order
+    * the results are received.
+    *
+    *=================================================================
+    * @param result &BenchmarkResult
+    * @return void
+    */
+    pub fn ino_add(&mut self, result: &BenchmarkResult) {
+        for (name, value) in result.headers.iter().flatten() {
+            let (header, total) = self.headers.entry(name.clone()).or_insert((
+                HeaderStats { count: 0, min: f64::MAX, mean: 0.0, max: f64::MIN, last: 0.0 },
+                0.0,
+            ));
+            header.count += 1;
+            header.min = header.min.min(*value);
+            header.max = header.max.max(*value);
+            header.last = *value;
+            *total += value;
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_summary()
+    *=================================================================
+    *
+    * Statistics of every extracted header over the results, the last
+    * value being the one of the last response received. None when no
+    * response carried any of them.
+    *
+    *=================================================================
+    * @param void
+    * @return Option<BTreeMap<String, HeaderStats>>
+    */
+    pub fn ino_summary(&self) -> Option<BTreeMap<String, HeaderStats>> {
+        (!self.headers.is_empty()).then(|| {
+            self.headers
+                .iter()
+                .map(|(name, (header, total))| (name.clone(), HeaderStats { mean: total / header.count as f64, ..*header }))
+                .collect()
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::ino_result;
+    use reqwest::header::{HeaderValue, CONTENT_TYPE};
+
+    #[test]
+    fn should_check_expected_headers() -> Result<()> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json; charset=utf-8"));
+        let json = ino_parse_expectation("Content-Type=application/json")?;
+        let etag = ino_parse_expectation("etag")?;
+        assert_eq!(json.to_string(), "content-type=application/json");
+        assert_eq!(ino_check_headers(std::slice::from_ref(&json), &headers), None);
+        assert_eq!(ino_check_headers(&[json, etag.clone()], &headers), Some(&etag));
+        assert_eq!(ino_check_headers(&[ino_parse_expectation("content-type=text/html")?], &headers).map(|e| e.name.as_str()), Some("content-type"));
+        assert!(ino_parse_expectation("=json").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn should_summarize_rate_limit_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("99"));
+        headers.insert("x-request-cost", HeaderValue::from_static("2.5"));
+        headers.insert("x-region", HeaderValue::from_static("eu"));
+        assert_eq!(ino_extract_headers(&[], &HeaderMap::new()), None);
+        let extracted = ino_extract_headers(&["X-Request-Cost".to_string(), "x-region".to_string(), "X-RateLimit-Remaining".to_string()], &headers).unwrap();
+        assert_eq!(extracted, vec![("x-request-cost".to_string(), 2.5), ("x-ratelimit-remaining".to_string(), 99.0)]);
+        let mut totals = HeaderTotals::default();
+        assert_eq!(totals.ino_summary(), None);
+        for value in [99.0, 0.0, 3.0] {
+            totals.ino_add(&BenchmarkResult {
+                headers: Some(vec![("x-ratelimit-remaining".to_string(), value)]),
+                ..ino_result()
+            });
+        }
+        let summary = totals.ino_summary().unwrap();
+        assert_eq!(summary["x-ratelimit-remaining"], HeaderStats { count: 3, min: 0.0, mean: 34.0, max: 99.0, last: 3.0 });
+    }
+}
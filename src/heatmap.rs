@@ -0,0 +1,173 @@
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::fs;
+
+pub const HEATMAP_BUCKETS: usize = 24;
+
+#[derive(Debug, Default)]
+pub struct Heatmap {
+    rows: Vec<[u64; HEATMAP_BUCKETS]>,
+}
+
+impl Heatmap {
+
+    /**
+    *=================================================================
+    * ino_add()
+    *=================================================================
+    *
+    * Counts a request of the given latency in the second it
+    * completed in.
+    *
+    *=================================================================
+    * @param second usize
+    * @param duration u64 milliseconds
+    * @return void
+    */
+    pub fn ino_add(&mut self, second: usize, duration: u64) {
+        if self.rows.len() <= second {
+            self.rows.resize(second + 1, [0; HEATMAP_BUCKETS]);
+        }
+        self.rows[second][ino_bucket(duration)] += 1;
+    }
+
+    /**
+    *=================================================================
+    * ino_to_html()
+    *=================================================================
+    *
+    * Self-contained HTML page drawing the heatmap on a canvas: one
+    * column per second, one row per latency bucket, the darker the
+    * more requests. Hovering a cell shows its count.
+    *
+    *=================================================================
+    * @param title &str
+    * @return String
+    */
+    pub fn ino_to_html(&self, title: &str) -> String {
+        let labels: Vec<String> = (0..HEATMAP_BUCKETS).map(ino_bucket_label).collect();
+        let data = json!({ "title": title, "labels": labels, "rows": self.rows }).to_string().replace("</", "<\\/");
+        HEATMAP_TEMPLATE.replace("{{data}}", &data)
+    }
+
+    /**
+    *=================================================================
+    * ino_write()
+    *=================================================================
+    *
+    * Writes the HTML heatmap to a file.
+    *
+    *=================================================================
+    * @param path &str
+    * @param title &str
+    * @return Result<()>
+    */
+    pub fn ino_write(&self, path: &str, title: &str) -> Result<()> {
+        fs::write(path, self.ino_to_html(title)).with_context(|| format!("Can not write the heatmap to {}", path))
+    }
+}
+
+/**
+ *=================================================================
+ * ino_bucket()
+ *=================================================================
+ *
+ * Latency bucket of a duration, bucket 0 holds 0 ms and bucket k
+ * the durations from 2^(k-1) to 2^k - 1 ms, the last one every
+ * slower request.
+ *
+ *=================================================================
+ * @param duration u64 milliseconds
+ * @return usize
+ */
+pub fn ino_bucket(duration: u64) -> usize {
+    ((u64::BITS - duration.leading_zeros()) as usize).min(HEATMAP_BUCKETS - 1)
+}
+
+fn ino_bucket_label(bucket: usize) -> String {
+    match bucket {
+        0 => "0 ms".to_string(),
+        1 => "1 ms".to_string(),
+        b if b == HEATMAP_BUCKETS - 1 => format!(">= {} ms", 1u64 << (b - 1)),
+        b => format!("{}-{} ms", 1u64 << (b - 1), (1u64 << b) - 1),
+    }
+}
+
+const HEATMAP_TEMPLATE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>inoue latency heatmap</title>
+<style>
+  body { font-family: sans-serif; margin: 20px; }
+  #tip { height: 1.2em; color: #555; }
+</style>
+</head>
+<body>
+<h3 id="title"></h3>
+<canvas id="heatmap"></canvas>
+<div id="tip"></div>
+<script>
+const data = {{data}};
+const canvas = document.getElementById("heatmap");
+const context = canvas.getContext("2d");
+const left = 90, bottom = 30, cell = Math.max(2, Math.min(20, Math.floor(1200 / Math.max(1, data.rows.length))));
+const used = data.labels.length - [...data.labels.keys()].reverse().findIndex(b => data.rows.some(r => r[b] > 0));
+const buckets = Math.max(1, Math.min(data.labels.length, used));
+const height = 16;
+canvas.width = left + data.rows.length * cell + 10;
+canvas.height = buckets * height + bottom + 10;
+document.getElementById("title").textContent = "Latency heatmap of " + data.title;
+const max = Math.max(1, ...data.rows.flat());
+context.font = "11px sans-serif";
+for (let b = 0; b < buckets; b++) {
+  const y = (buckets - 1 - b) * height;
+  context.fillStyle = "#333";
+  context.fillText(data.labels[b], 0, y + 12);
+  data.rows.forEach((row, second) => {
+    if (row[b] === 0) return;
+    const t = Math.log(1 + row[b]) / Math.log(1 + max);
+    context.fillStyle = "hsl(" + (220 - 220 * t) + ", 80%, " + (85 - 45 * t) + "%)";
+    context.fillRect(left + second * cell, y, cell, height);
+  });
+}
+context.fillStyle = "#333";
+const step = Math.max(1, Math.ceil(40 / cell));
+for (let second = 0; second < data.rows.length; second += step) {
+  context.fillText(second + "s", left + second * cell, buckets * height + 15);
+}
+canvas.addEventListener("mousemove", event => {
+  const second = Math.floor((event.offsetX - left) / cell);
+  const b = buckets - 1 - Math.floor(event.offsetY / height);
+  const row = data.rows[second];
+  document.getElementById("tip").textContent = row && b >= 0 && b < buckets
+    ? second + "s, " + data.labels[b] + ": " + row[b] + " requests" : "";
+});
+</script>
+</body>
+</html>
+"##;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_bucket_latencies_by_power_of_two() {
+        assert_eq!(ino_bucket(0), 0);
+        assert_eq!(ino_bucket(1), 1);
+        assert_eq!((ino_bucket(2), ino_bucket(3)), (2, 2));
+        assert_eq!(ino_bucket(1000), 10);
+        assert_eq!(ino_bucket(u64::MAX), HEATMAP_BUCKETS - 1);
+        assert_eq!(ino_bucket_label(10), "512-1023 ms");
+        let mut heatmap = Heatmap::default();
+        heatmap.ino_add(2, 5);
+        heatmap.ino_add(2, 6);
+        assert_eq!(heatmap.rows.len(), 3);
+        assert_eq!(heatmap.rows[2][3], 2);
+        let html = heatmap.ino_to_html("http://host/</script>");
+        assert!(html.contains("\"rows\":[[0,"));
+        assert!(!html.contains("host/</script>"));
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::benchmark::Summary;
+
+const HISTORY_DIR: &str = ".inoue";
+const HISTORY_FILE: &str = "history.jsonl";
+
+/**
+ *=================================================================
+ * ino_history_path()
+ *=================================================================
+ *
+ * Resolves the location of the history file inside the user's
+ * home directory: ~/.inoue/history.jsonl
+ *
+ *=================================================================
+ * @return Result<PathBuf>
+ */
+fn ino_history_path() -> Result<PathBuf> {
+    let home = env::var("HOME")
+        .or_else(|_| env::var("USERPROFILE"))
+        .with_context(|| "Can not resolve the home directory".to_string())?;
+    Ok(PathBuf::from(home).join(HISTORY_DIR).join(HISTORY_FILE))
+}
+
+/**
+ *=================================================================
+ * ino_save()
+ *=================================================================
+ *
+ * Appends the summary of a run as a single JSON line to the
+ * history file, creating the directory when needed.
+ *
+ *=================================================================
+ * @param summary &Summary
+ * @return Result<()>
+ */
+pub fn ino_save(summary: &Summary) -> Result<()> {
+    let path = ino_history_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open file {}", path.display()))?;
+    let line = serde_json::to_string(summary)?;
+    writeln!(file, "{}", line).with_context(|| format!("Failed to write file {}", path.display()))?;
+    Ok(())
+}
+
+/**
+ *=================================================================
+ * ino_load()
+ *=================================================================
+ *
+ * Reads every saved run for the given target (and label, when
+ * provided) in the order they were recorded. Malformed lines are
+ * skipped.
+ *
+ *=================================================================
+ * @param target &str
+ * @param label Option<&str>
+ * @return Result<Vec<Summary>>
+ */
+pub fn ino_load(target: &str, label: Option<&str>) -> Result<Vec<Summary>> {
+    let path = ino_history_path()?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read file from {}", path.display()))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Summary>(line).ok())
+        .filter(|s| s.target == target)
+        .filter(|s| label.is_none() || s.label.as_deref() == label)
+        .collect())
+}
+
+/**
+ *=================================================================
+ * ino_show()
+ *=================================================================
+ *
+ * Prints the p95 and throughput trend of the saved runs for a
+ * target, comparing every run against the previous one.
+ *
+ *=================================================================
+ * @param target &str
+ * @param label Option<&str>
+ * @return Result<()>
+ */
+pub fn ino_show(target: &str, label: Option<&str>) -> Result<()> {
+    let runs = ino_load(target, label)?;
+    if runs.is_empty() {
+        println!("No history found for {}", target);
+        return Ok(());
+    }
+    println!("{} {}", "History of".yellow().bold(), target.purple());
+    println!(
+        "{:<20} {:<16} {:>10} {:>14}   {:>10}",
+        "Date", "Label", "Requests", "Throughput", "p95"
+    );
+    let mut previous: Option<&Summary> = None;
+    for run in &runs {
+        let throughput = format!("{:.1} rps", run.throughput);
+        let p95 = format!("{} ms", run.p95);
+        let (throughput_trend, p95_trend) = match previous {
+            None => (" ".normal(), " ".normal()),
+            Some(p) => (
+                ino_trend(run.throughput, p.throughput, true),
+                ino_trend(run.p95 as f64, p.p95 as f64, false),
+            ),
+        };
+        println!(
+            "{:<20} {:<16} {:>10} {:>14} {} {:>10} {}",
+            ino_format_timestamp(run.timestamp),
+            run.label.as_deref().unwrap_or("-"),
+            run.total_requests,
+            throughput,
+            throughput_trend,
+            p95,
+            p95_trend
+        );
+        previous = Some(run);
+    }
+    Ok(())
+}
+
+/**
+ *=================================================================
+ * ino_trend()
+ *=================================================================
+ *
+ * Returns a colored arrow describing how a value moved against
+ * the previous run, green when it moved in the good direction.
+ *
+ *=================================================================
+ * @param current f64
+ * @param previous f64
+ * @param higher_is_better bool
+ * @return ColoredString
+ */
+fn ino_trend(current: f64, previous: f64, higher_is_better: bool) -> colored::ColoredString {
+    if current == previous {
+        return "=".normal();
+    }
+    let up = current > previous;
+    let arrow = if up { "▲" } else { "▼" };
+    match up == higher_is_better {
+        true => arrow.green(),
+        false => arrow.red(),
+    }
+}
+
+/**
+ *=================================================================
+ * ino_format_timestamp()
+ *=================================================================
+ *
+ * Formats unix seconds as a UTC "YYYY-MM-DD HH:MM:SS" string.
+ *
+ *=================================================================
+ * @param timestamp u64
+ * @return String
+ */
+pub fn ino_format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let secs = timestamp % 86_400;
+    // civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        (secs % 3_600) / 60,
+        secs % 60
+    )
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_format_unix_timestamp() {
+        assert_eq!("1970-01-01 00:00:00", ino_format_timestamp(0));
+        assert_eq!("2024-02-29 12:30:15", ino_format_timestamp(1_709_209_815));
+    }
+}
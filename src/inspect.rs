@@ -0,0 +1,331 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use hdrhistogram::Histogram;
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "status <prefix>  keeps the statuses starting with the prefix, 5xx for the server errors
+step <name>      keeps the requests of the steps whose name contains the text
+client <n>       keeps the requests of a client
+window <from> <to>  keeps the requests completed between two offsets, in seconds
+errors           keeps the failed requests
+clear            drops every filter
+by <status|step|client>  breaks the selection down
+quit             leaves the explorer";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RawEntry {
+    pub offset_ms: f64,
+    pub client: usize,
+    pub status: String,
+    pub success: bool,
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub step: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Filter {
+    status: Option<String>,
+    step: Option<String>,
+    client: Option<usize>,
+    window: Option<(f64, f64)>,
+    errors: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Selection {
+    pub requests: u64,
+    pub errors: u64,
+    pub mean: f64,
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+    pub p999: u64,
+    pub max: u64,
+}
+
+pub struct Explorer {
+    entries: Vec<RawEntry>,
+    filter: Filter,
+}
+
+impl Filter {
+
+    /**
+    *=================================================================
+    * ino_matches()
+    *=================================================================
+    *
+    * Tells whether a raw result passes every filter. A status
+    * prefix ending with "xx" is a class of statuses, 5xx keeps every
+    * status starting with 5. The steps without a name are named by
+    * their target, so a step is kept when its name contains the
+    * text.
+    *
+    *=================================================================
+    * @param entry &RawEntry
+    * @return bool
+    */
+    pub fn ino_matches(&self, entry: &RawEntry) -> bool {
+        let status = self.status.as_deref().map(|status| status.strip_suffix("xx").unwrap_or(status));
+        status.is_none_or(|prefix| entry.status.starts_with(prefix))
+            && self.step.as_ref().is_none_or(|step| entry.step.as_ref().is_some_and(|name| name.contains(step.as_str())))
+            && self.client.is_none_or(|client| entry.client == client)
+            && self.window.is_none_or(|(from, to)| entry.offset_ms >= from * 1000.0 && entry.offset_ms < to * 1000.0)
+            && (!self.errors || !entry.success)
+    }
+}
+
+impl Explorer {
+
+    /**
+    *=================================================================
+    * ino_load()
+    *=================================================================
+    *
+    * Loads a raw results file written by --raw-out, one JSON object
+    * per line. The fields the explorer does not use are ignored.
+    *
+    *=================================================================
+    * @param file &str
+    * @return Result<Explorer>
+    */
+    pub fn ino_load(file: &str) -> Result<Self> {
+        let content = fs::read_to_string(file).with_context(|| format!("Failed to read file from {}", file))?;
+        let mut entries = vec![];
+        for (number, line) in content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let entry = serde_json::from_str(line).with_context(|| format!("Invalid raw result on line {} of {}", number + 1, file))?;
+            entries.push(entry);
+        }
+        if entries.is_empty() {
+            bail!("{} holds no raw result", file);
+        }
+        Ok(Explorer { entries, filter: Filter::default() })
+    }
+
+    /**
+    *=================================================================
+    * ino_execute()
+    *=================================================================
+    *
+    * Executes a command of the explorer and prints its outcome. A
+    * filter command narrows the selection, or widens it when it
+    * replaces a filter, and prints the recomputed figures.
+    *
+    *=================================================================
+    * @param line &str
+    * @return Result<bool> false once the explorer must be left
+    */
+    pub fn ino_execute(&mut self, line: &str) -> Result<bool> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => return Ok(true),
+            ["quit" | "q" | "exit"] => return Ok(false),
+            ["help" | "?"] => {
+                println!("{}", HELP);
+                return Ok(true);
+            }
+            ["by", key] => return self.ino_breakdown(key).map(|_| true),
+            ["status", status] => self.filter.status = Some(status.to_string()),
+            ["step", name @ ..] if !name.is_empty() => self.filter.step = Some(name.join(" ")),
+            ["client", client] => self.filter.client = Some(client.parse().with_context(|| format!("Invalid client {}", client))?),
+            ["window", from, to] => {
+                let from: f64 = from.parse().with_context(|| format!("Invalid offset {}", from))?;
+                let to: f64 = to.parse().with_context(|| format!("Invalid offset {}", to))?;
+                if to <= from {
+                    bail!("The window must end after it starts");
+                }
+                self.filter.window = Some((from, to));
+            }
+            ["errors"] => self.filter.errors = true,
+            ["clear"] => self.filter = Filter::default(),
+            _ => bail!("Unknown command {}, type help for the commands", line.trim()),
+        }
+        self.ino_show_selection();
+        Ok(true)
+    }
+
+    /**
+    *=================================================================
+    * ino_selection()
+    *=================================================================
+    *
+    * Raw results passing the current filters.
+    *
+    *=================================================================
+    * @param void
+    * @return Vec<&RawEntry>
+    */
+    pub fn ino_selection(&self) -> Vec<&RawEntry> {
+        self.entries.iter().filter(|entry| self.filter.ino_matches(entry)).collect()
+    }
+
+    fn ino_show_selection(&self) {
+        let selection = self.ino_selection();
+        let share = selection.len() as f64 * 100.0 / self.entries.len() as f64;
+        match ino_selection_summary(&selection) {
+            None => println!("{} no request", "Selection".yellow().bold()),
+            Some(s) => println!(
+                "{} {} requests ({:.1}%), {} errors, mean {:.1} p50 {} p95 {} p99 {} p99.9 {} max {} ms",
+                "Selection".yellow().bold(),
+                s.requests,
+                share,
+                s.errors,
+                s.mean,
+                s.p50,
+                s.p95,
+                s.p99,
+                s.p999,
+                s.max
+            ),
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_breakdown()
+    *=================================================================
+    *
+    * Prints the figures of the selection by status, step or client,
+    * the largest groups first.
+    *
+    *=================================================================
+    */
+    fn ino_breakdown(&self, key: &str) -> Result<()> {
+        if !["status", "step", "client"].contains(&key) {
+            bail!("Unknown breakdown {}, use status, step or client", key);
+        }
+        let mut groups: BTreeMap<String, Vec<&RawEntry>> = BTreeMap::new();
+        for entry in self.ino_selection() {
+            let group = match key {
+                "status" => entry.status.clone(),
+                "step" => entry.step.clone().unwrap_or_else(|| "-".to_string()),
+                _ => entry.client.to_string(),
+            };
+            groups.entry(group).or_default().push(entry);
+        }
+        let mut rows: Vec<(String, Selection)> = groups
+            .into_iter()
+            .filter_map(|(group, entries)| ino_selection_summary(&entries).map(|s| (group, s)))
+            .collect();
+        rows.sort_by_key(|(_, s)| Reverse(s.requests));
+        let width = rows.iter().map(|(group, _)| group.len()).max().unwrap_or(0).max(key.len());
+        println!(
+            "{:<width$} {:>10} {:>8} {:>10} {:>8} {:>8} {:>8} {:>8}",
+            key.yellow().bold(), "Requests", "Errors", "Mean", "p50", "p95", "p99", "Max"
+        );
+        for (group, s) in rows {
+            println!(
+                "{:<width$} {:>10} {:>8} {:>10.1} {:>8} {:>8} {:>8} {:>8}",
+                group, s.requests, s.errors, s.mean, s.p50, s.p95, s.p99, s.max
+            );
+        }
+        Ok(())
+    }
+}
+
+/**
+ *=================================================================
+ * ino_selection_summary()
+ *=================================================================
+ *
+ * Request count, errors and latency percentiles of a selection,
+ * computed again from its raw durations. None when the selection
+ * is empty.
+ *
+ *=================================================================
+ * @param entries &[&RawEntry]
+ * @return Option<Selection>
+ */
+pub fn ino_selection_summary(entries: &[&RawEntry]) -> Option<Selection> {
+    let mut histogram = Histogram::<u64>::new(3).ok()?;
+    for entry in entries {
+        histogram.saturating_record(entry.duration_ms);
+    }
+    (!histogram.is_empty()).then(|| Selection {
+        requests: histogram.len(),
+        errors: entries.iter().filter(|entry| !entry.success).count() as u64,
+        mean: histogram.mean(),
+        p50: histogram.value_at_quantile(0.5),
+        p95: histogram.value_at_quantile(0.95),
+        p99: histogram.value_at_quantile(0.99),
+        p999: histogram.value_at_quantile(0.999),
+        max: histogram.max(),
+    })
+}
+
+/**
+ *=================================================================
+ * ino_inspect()
+ *=================================================================
+ *
+ * Explores a raw results file: prints the figures of the whole
+ * run, then reads the commands typed on stdin, one per line, until
+ * quit or the end of the input, so the explorer can be scripted
+ * with a pipe as well.
+ *
+ *=================================================================
+ * @param file &str
+ * @return Result<()>
+ */
+pub fn ino_inspect(file: &str) -> Result<()> {
+    let mut explorer = Explorer::ino_load(file)?;
+    println!("{} {}, type help for the commands", "Raw results of".yellow().bold(), file.purple());
+    explorer.ino_show_selection();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("{} ", ">".purple().bold());
+        io::stdout().flush()?;
+        let Some(line) = lines.next() else { break };
+        match explorer.ino_execute(&line?) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => eprintln!("{}", format!("{:#}", e).red()),
+        }
+    }
+    println!();
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_recompute_percentiles_over_the_selection() -> Result<()> {
+        let entry = |client, status: &str, duration_ms, step: Option<&str>| RawEntry {
+            offset_ms: duration_ms as f64 * 100.0,
+            client,
+            status: status.to_string(),
+            success: status.starts_with('2'),
+            duration_ms,
+            step: step.map(str::to_string),
+        };
+        let mut explorer = Explorer {
+            entries: vec![
+                entry(0, "200 OK", 10, Some("login")),
+                entry(0, "200 OK", 20, Some("cart")),
+                entry(1, "503 Service Unavailable", 30, Some("cart")),
+                entry(1, "500 Internal Server Error", 40, None),
+            ],
+            filter: Filter::default(),
+        };
+        assert!(explorer.ino_execute("status 5xx")?);
+        let selection = ino_selection_summary(&explorer.ino_selection());
+        assert_eq!(selection.map(|s| (s.requests, s.errors, s.max)), Some((2, 2, 40)));
+        assert!(explorer.ino_execute("step car")?);
+        assert_eq!(explorer.ino_selection().len(), 1);
+        assert!(explorer.ino_execute("clear")? && explorer.ino_execute("window 1.5 3.5")?);
+        let selection = ino_selection_summary(&explorer.ino_selection());
+        assert_eq!(selection.map(|s| (s.requests, s.mean)), Some((2, 25.0)));
+        assert!(explorer.ino_execute("client x").is_err() && explorer.ino_execute("by host").is_err());
+        assert!(!explorer.ino_execute("quit")?);
+        Ok(())
+    }
+}
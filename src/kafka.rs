@@ -0,0 +1,245 @@
+use anyhow::{Context, Result};
+use rskafka::chrono::{DateTime, Utc};
+use rskafka::client::partition::{Compression, PartitionClient, UnknownTopicHandling};
+use rskafka::client::ClientBuilder;
+use rskafka::record::Record;
+use rskafka::BackoffConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+
+use crate::benchmark::BenchmarkResult;
+use crate::support::Settings;
+
+const KAFKA_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct KafkaSettings {
+    pub topic: String,
+    #[serde(default = "ino_default_message_size")]
+    pub message_size: usize,
+    #[serde(default = "ino_default_batch")]
+    pub batch: usize,
+}
+
+fn ino_default_message_size() -> usize {
+    1024
+}
+
+fn ino_default_batch() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KafkaSummary {
+    pub messages: u64,
+    pub bytes: u64,
+    pub messages_per_second: f64,
+    pub bytes_per_second: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct KafkaStats {
+    acknowledged: u64,
+    bytes: u64,
+}
+
+pub struct Kafka {
+    partitions: Vec<PartitionClient>,
+    cursor: AtomicUsize,
+    payload: Vec<u8>,
+    batch: usize,
+}
+
+impl KafkaSettings {
+
+    /**
+    *=================================================================
+    * ino_from_args()
+    *=================================================================
+    *
+    * Builds the producer settings from the command line values,
+    * the defaults filling the missing ones.
+    *
+    *=================================================================
+    * @param topic String
+    * @param message_size Option<usize>
+    * @param batch Option<usize>
+    * @return KafkaSettings
+    */
+    pub fn ino_from_args(topic: String, message_size: Option<usize>, batch: Option<usize>) -> Self {
+        KafkaSettings {
+            topic,
+            message_size: message_size.unwrap_or_else(ino_default_message_size),
+            batch: batch.unwrap_or_else(ino_default_batch).max(1),
+        }
+    }
+}
+
+impl Kafka {
+
+    /**
+    *=================================================================
+    * ino_from_settings()
+    *=================================================================
+    *
+    * Connects to the bootstrap brokers of the target,
+    * "host:port[,host:port]" with an optional "kafka://", and
+    * prepares a producer per partition of the topic, which must
+    * exist. A request to a broker is retried for 10 seconds at most.
+    * The body of the settings is the value of the messages when
+    * there is one, otherwise a value of the configured size.
+    *
+    *=================================================================
+    * @param settings &Settings
+    * @return Result<Kafka>
+    */
+    pub async fn ino_from_settings(settings: &Settings) -> Result<Self> {
+        let kafka = settings.kafka.as_ref().with_context(|| "The kafka mode needs a topic".to_string())?;
+        let target = settings.ino_target();
+        let brokers: Vec<String> = target
+            .trim_start_matches("kafka://")
+            .split(',')
+            .map(|broker| broker.trim().trim_end_matches('/').to_string())
+            .filter(|broker| !broker.is_empty())
+            .collect();
+        let backoff = BackoffConfig { deadline: Some(KAFKA_TIMEOUT), ..BackoffConfig::default() };
+        let client = ClientBuilder::new(brokers)
+            .client_id("inoue")
+            .backoff_config(backoff)
+            .build()
+            .await
+            .with_context(|| format!("Can not connect to the Kafka brokers {}", target))?;
+        let topic = client
+            .list_topics()
+            .await
+            .with_context(|| format!("Can not list the topics of {}", target))?
+            .into_iter()
+            .find(|topic| topic.name == kafka.topic)
+            .with_context(|| format!("The topic {} does not exist on {}", kafka.topic, target))?;
+        let mut partitions = vec![];
+        for partition in topic.partitions {
+            let producer = client
+                .partition_client(kafka.topic.clone(), partition, UnknownTopicHandling::Error)
+                .await
+                .with_context(|| format!("Can not reach the leader of partition {} of {}", partition, kafka.topic))?;
+            partitions.push(producer);
+        }
+        Ok(Kafka {
+            partitions,
+            cursor: AtomicUsize::new(0),
+            payload: match &settings.body {
+                Some(body) => body.clone().into_bytes(),
+                None => vec![b'x'; kafka.message_size],
+            },
+            batch: kafka.batch.max(1),
+        })
+    }
+
+    /**
+    *=================================================================
+    * ino_exec()
+    *=================================================================
+    *
+    * Produces a batch of messages to the next partition, in turn,
+    * and waits until every in-sync replica acknowledged it. The
+    * size is the bytes of the message values.
+    *
+    *=================================================================
+    * @param num_client usize
+    * @param execution usize
+    * @return BenchmarkResult
+    */
+    pub async fn ino_exec(&self, num_client: usize, execution: usize) -> BenchmarkResult {
+        let partition = &self.partitions[self.cursor.fetch_add(1, Ordering::Relaxed) % self.partitions.len()];
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as i64).unwrap_or(0);
+        let timestamp = DateTime::<Utc>::from_timestamp_millis(millis).unwrap_or_default();
+        let records = (0..self.batch)
+            .map(|_| Record {
+                key: None,
+                value: Some(self.payload.clone()),
+                headers: BTreeMap::new(),
+                timestamp,
+            })
+            .collect();
+        let begin = Instant::now();
+        let produced = partition.produce(records, Compression::NoCompression).await;
+        let duration = begin.elapsed().as_millis() as u64;
+        let status = match &produced {
+            Ok(_) => "Acknowledged".to_string(),
+            Err(e) => format!("Failed: {}", e),
+        };
+        BenchmarkResult {
+            size: Some((self.payload.len() * self.batch) as u64),
+            ..BenchmarkResult::new(status, produced.is_ok(), duration, num_client, execution)
+        }
+    }
+}
+
+impl KafkaStats {
+
+    /**
+    *=================================================================
+    * ino_add()
+    *=================================================================
+    *
+    * Counts the batch of a result when the brokers acknowledged it.
+    *
+    *=================================================================
+    * @param result &BenchmarkResult
+    * @return void
+    */
+    pub fn ino_add(&mut self, result: &BenchmarkResult) {
+        if result.success {
+            self.acknowledged += 1;
+            self.bytes += result.size.unwrap_or(0);
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_summary()
+    *=================================================================
+    *
+    * Messages and bytes the brokers acknowledged, in total and per
+    * second over the run.
+    *
+    *=================================================================
+    * @param batch usize messages per iteration
+    * @param elapsed f64 seconds
+    * @return KafkaSummary
+    */
+    pub fn ino_summary(&self, batch: usize, elapsed: f64) -> KafkaSummary {
+        let messages = self.acknowledged * batch as u64;
+        let rate = |count: u64| match elapsed > 0.0 {
+            true => count as f64 / elapsed,
+            false => 0.0,
+        };
+        KafkaSummary { messages, bytes: self.bytes, messages_per_second: rate(messages), bytes_per_second: rate(self.bytes) }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::ino_result;
+
+    #[test]
+    fn should_count_acknowledged_messages() {
+        let result = |success| BenchmarkResult {
+            status: "Acknowledged".to_string(),
+            success,
+            duration: 3,
+            size: Some(5000),
+            ..ino_result()
+        };
+        let mut stats = KafkaStats::default();
+        assert_eq!(stats.ino_summary(5, 0.0).messages_per_second, 0.0);
+        [result(true), result(true), result(false)].iter().for_each(|result| stats.ino_add(result));
+        let summary = stats.ino_summary(5, 2.0);
+        assert_eq!(summary, KafkaSummary { messages: 10, bytes: 10_000, messages_per_second: 5.0, bytes_per_second: 5000.0 });
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::{bail, Result};
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::support::ino_parse_duration;
+
+pub const RNG_STREAM: u64 = 2 << 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AddedLatency {
+    pub delay: Duration,
+    pub jitter: Duration,
+}
+
+impl TryFrom<String> for AddedLatency {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        ino_parse_latency(&value)
+    }
+}
+
+impl From<AddedLatency> for String {
+    fn from(latency: AddedLatency) -> Self {
+        format!("{}ms±{}ms", latency.delay.as_millis(), latency.jitter.as_millis())
+    }
+}
+
+pub struct LatencyInjector {
+    latency: AddedLatency,
+    rng: Mutex<StdRng>,
+}
+
+impl LatencyInjector {
+
+    /**
+    *=================================================================
+    * new()
+    *=================================================================
+    *
+    * Creates the latency injector of a client, drawing its jitter
+    * from its own random generator.
+    *
+    *=================================================================
+    * @param latency AddedLatency
+    * @param rng StdRng
+    * @return Self
+    */
+    pub fn new(latency: AddedLatency, rng: StdRng) -> Self {
+        LatencyInjector { latency, rng: Mutex::new(rng) }
+    }
+
+    /**
+    *=================================================================
+    * ino_next()
+    *=================================================================
+    *
+    * Delay of the next request, uniform within the jitter around
+    * the added latency and never negative.
+    *
+    *=================================================================
+    * @param void
+    * @return Duration
+    */
+    pub fn ino_next(&self) -> Duration {
+        let AddedLatency { delay, jitter } = self.latency;
+        if jitter.is_zero() {
+            return delay;
+        }
+        let offset = self.rng.lock().unwrap().gen_range(-1.0..=1.0) * jitter.as_secs_f64();
+        Duration::from_secs_f64((delay.as_secs_f64() + offset).max(0.0))
+    }
+
+    /**
+    *=================================================================
+    * ino_delay()
+    *=================================================================
+    *
+    * Holds the request back for the next delay.
+    *
+    *=================================================================
+    * @param void
+    * @return void
+    */
+    pub async fn ino_delay(&self) {
+        tokio::time::sleep(self.ino_next()).await;
+    }
+}
+
+/**
+ *=================================================================
+ * ino_parse_latency()
+ *=================================================================
+ *
+ * Parses an added latency, "50ms" or "50ms±10ms" with a jitter,
+ * "+-" standing for "±".
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<AddedLatency>
+ */
+pub fn ino_parse_latency(value: &str) -> Result<AddedLatency> {
+    let (delay, jitter) = match value.split_once('±').or_else(|| value.split_once("+-")) {
+        None => (value, None),
+        Some((delay, jitter)) => (delay, Some(jitter)),
+    };
+    let delay = ino_parse_duration(delay)?;
+    let jitter = jitter.map(ino_parse_duration).transpose()?.unwrap_or_default();
+    if delay.is_zero() && jitter.is_zero() {
+        bail!("Invalid added latency {}, use a delay such as 50ms or 50ms±10ms", value);
+    }
+    Ok(AddedLatency { delay, jitter })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn should_draw_delays_within_the_jitter() -> Result<()> {
+        let latency = ino_parse_latency("50ms±10ms")?;
+        assert_eq!(latency, AddedLatency { delay: Duration::from_millis(50), jitter: Duration::from_millis(10) });
+        assert_eq!(ino_parse_latency("50ms+-10ms")?, latency);
+        assert_eq!(String::from(latency), "50ms±10ms");
+        assert!(ino_parse_latency("0ms").is_err());
+        assert!(ino_parse_latency("fast").is_err());
+        let injector = LatencyInjector::new(latency, StdRng::seed_from_u64(7));
+        let delays: Vec<Duration> = (0..100).map(|_| injector.ino_next()).collect();
+        assert!(delays.iter().all(|d| *d >= Duration::from_millis(40) && *d <= Duration::from_millis(60)));
+        assert!(delays.iter().any(|d| *d != delays[0]));
+        let fixed = LatencyInjector::new(ino_parse_latency("20ms")?, StdRng::seed_from_u64(7));
+        assert_eq!(fixed.ino_next(), Duration::from_millis(20));
+        Ok(())
+    }
+}
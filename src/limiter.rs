@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+#[derive(Debug, Default)]
+pub struct HostLimiter {
+    max_per_host: Option<usize>,
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl HostLimiter {
+
+    /**
+    *=================================================================
+    * new()
+    *=================================================================
+    *
+    * Creates a limiter allowing at most `max_per_host` concurrent
+    * connections to every host, None means unlimited.
+    *
+    *=================================================================
+    * @param max_per_host Option<usize>
+    * @return Self
+    */
+    pub fn new(max_per_host: Option<usize>) -> Self {
+        HostLimiter {
+            max_per_host,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_acquire()
+    *=================================================================
+    *
+    * Waits for a free connection slot on the host of the URL. The
+    * slot is released when the returned permit is dropped.
+    *
+    *=================================================================
+    * @param url &str
+    * @return Option<OwnedSemaphorePermit>
+    */
+    pub async fn ino_acquire(&self, url: &str) -> Option<OwnedSemaphorePermit> {
+        let max = self.max_per_host?;
+        let host = ino_host(url);
+        let semaphore = self
+            .hosts
+            .lock()
+            .unwrap()
+            .entry(host.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(max)))
+            .clone();
+        semaphore.acquire_owned().await.ok()
+    }
+}
+
+/**
+ *=================================================================
+ * ino_host()
+ *=================================================================
+ *
+ * Extracts the "host:port" part of a URL.
+ *
+ *=================================================================
+ * @param url &str
+ * @return &str
+ */
+fn ino_host(url: &str) -> &str {
+    let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    without_scheme
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or(without_scheme)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_extract_host_from_url() {
+        assert_eq!("localhost:3000", ino_host("http://localhost:3000/foo?bar=1"));
+        assert_eq!("example.com", ino_host("https://example.com"));
+    }
+
+    #[tokio::test]
+    async fn should_not_limit_without_cap() {
+        let limiter = HostLimiter::new(None);
+        assert!(limiter.ino_acquire("http://localhost:3000").await.is_none());
+    }
+}
@@ -1,36 +1,136 @@
+mod bands;
+mod bandwidth;
 mod benchmark;
+mod burst;
+mod cache;
+mod checksum;
+mod compare;
+mod config;
+mod conditional;
+mod control;
+mod credentials;
+mod dns;
+mod download;
+mod feeder;
+mod echo;
 mod execution;
+mod exhaustion;
+mod flow;
+mod groups;
+mod headers;
+mod heatmap;
+mod history;
+mod inspect;
+mod kafka;
+mod latency;
+mod limiter;
+mod merge;
+mod monitor;
+mod mqtt;
+mod notify;
+mod pacing;
+mod precheck;
+mod profile;
+mod prometheus;
+mod proxy_protocol;
+mod publish;
+mod range;
+mod rate;
+mod redis;
+mod raw;
+mod replay;
+mod resources;
+mod run;
+mod smoke;
+mod sql;
+mod signing;
+mod sse;
+mod stable;
+mod stages;
 mod support;
+mod sweep;
+mod tags;
+mod tcp;
+mod throttle;
+mod threshold;
+mod tls;
+mod upload;
+mod watcher;
+mod xml;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::Parser;
 
-use crate::benchmark::Report;
-use crate::execution::ino_run;
-use crate::support::{Args, Settings};
-use indicatif::ProgressBar;
-use tokio::sync::{mpsc, watch};
+use crate::run::{ino_benchmark, ino_export, ino_print_exit_summary, ino_progress_bar, ino_runtime, ino_verdict};
+use crate::support::{Args, Command, OutputFormat, Settings};
+use indicatif::MultiProgress;
+use tokio::sync::watch;
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let settings: Settings = Args::parse().ino_to_string()?;
-    let mut report = Report::new(settings.clients);
-    settings.ino_print_banner();
-    let pb = ProgressBar::new(settings.requests as u64);
+fn main() -> Result<()> {
+    let mut args = Args::parse();
+    if let Some(command) = args.command.take() {
+        return match command {
+            Command::History { target, label } => history::ino_show(&target, label.as_deref()),
+            Command::Merge { files } => merge::ino_merge(&files),
+            Command::Inspect { file } => inspect::ino_inspect(&file),
+            Command::CompareProtocols(workload) => {
+                let settings = workload.ino_to_settings()?;
+                ino_runtime(settings.workers)?.block_on(compare::ino_compare(settings))
+            }
+            Command::Smoke(smoke) => {
+                let settings = smoke.ino_to_settings()?;
+                if !ino_runtime(settings.workers)?.block_on(smoke::ino_smoke(settings))? {
+                    std::process::exit(1);
+                }
+                Ok(())
+            }
+        };
+    }
+    let settings: Settings = args.ino_to_string()?;
+    let passed = ino_runtime(settings.workers)?.block_on(ino_main(&settings))?;
+    if !passed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn ino_main(settings: &Settings) -> Result<bool> {
+    let text = settings.format == OutputFormat::Text;
+    if !text && (settings.verbose || settings.interactive || settings.watch.is_some()) {
+        bail!("The JSON format does not combine with --verbose, --interactive or --watch");
+    }
+    if text && settings.groups.is_none() && settings.stages.is_none() {
+        settings.ino_print_banner();
+    }
+    exhaustion::ino_check_limits(settings);
     let (tx_sigint, rx_sigint) = watch::channel(None);
-    let (benchmark_tx, mut benchmark_rx) = mpsc::channel(settings.requests);
 
     ctrlc::set_handler(move || {
         tx_sigint.send(Some(())).unwrap_or(());
     })?;
-    ino_run(settings.clone(), benchmark_tx, rx_sigint).await?;
-    while let Some(value) = benchmark_rx.recv().await {
-        match settings.verbose {
-            true => println!("{}", value),
-            false => pb.inc(1),
+    Ok(match (settings.watch, &settings.groups, &settings.stages) {
+        (_, Some(_), Some(_)) => bail!("Client groups and stages can not be combined"),
+        (Some(_), Some(_), None) => bail!("The watch mode does not run client groups"),
+        (Some(_), None, Some(_)) => bail!("The watch mode does not run stages"),
+        (Some(interval), None, None) => watcher::ino_watch(settings, interval, rx_sigint).await?,
+        (None, Some(groups), None) => groups::ino_run_groups(settings, groups, rx_sigint).await?,
+        (None, None, Some(stages)) => stages::ino_run_stages(settings, stages, rx_sigint).await?,
+        (None, None, None) => {
+            let pb = ino_progress_bar(&MultiProgress::new(), settings.label.as_deref().unwrap_or("run"), settings);
+            let report = ino_benchmark(settings, rx_sigint, pb, None, None).await?;
+            if text {
+                report.ino_show_result();
+            }
+            if let Some(path) = &settings.heatmap {
+                report.ino_heatmap().ino_write(path, &settings.ino_name())?;
+            }
+            let summary = report.ino_summary(settings);
+            let failures = ino_verdict(settings, &summary, text);
+            ino_export(settings, &summary, &failures).await?;
+            if !text {
+                ino_print_exit_summary(vec![summary.ino_run_summary(&failures, report.ino_encoded_histogram())])?;
+            }
+            failures.is_empty()
         }
-        report.ino_add_result(value);
-    }
-    report.ino_show_result();
-    Ok(())
-}
\ No newline at end of file
+    })
+}
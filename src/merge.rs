@@ -0,0 +1,91 @@
+use anyhow::{bail, Context, Result};
+use inoue::schema::{ExitSummary, RunSummary, SCHEMA_VERSION};
+use std::fs;
+
+/**
+ *=================================================================
+ * ino_merge()
+ *=================================================================
+ *
+ * Merges the JSON exit summaries written by several inoue
+ * processes, usually on different machines, into one: the runs of
+ * the same target are merged together with their latency
+ * histograms, and the result is printed on stdout.
+ *
+ *=================================================================
+ * @param files &[String]
+ * @return Result<()>
+ */
+pub fn ino_merge(files: &[String]) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&ino_merge_files(files)?)?);
+    Ok(())
+}
+
+/**
+ *=================================================================
+ * ino_merge_files()
+ *=================================================================
+ *
+ * Reads the exit summaries and merges their runs by target.
+ *
+ *=================================================================
+ * @param files &[String]
+ * @return Result<ExitSummary>
+ */
+fn ino_merge_files(files: &[String]) -> Result<ExitSummary> {
+    let mut runs: Vec<RunSummary> = vec![];
+    for file in files {
+        let content = fs::read_to_string(file).with_context(|| format!("Failed to read file from {}", file))?;
+        let summary: ExitSummary = serde_json::from_str(&content).with_context(|| format!("Invalid exit summary {}", file))?;
+        if summary.schema_version != SCHEMA_VERSION {
+            bail!("The exit summary {} has schema version {}, {} expected", file, summary.schema_version, SCHEMA_VERSION);
+        }
+        for run in summary.runs {
+            match runs.iter_mut().find(|merged| merged.target == run.target) {
+                None => runs.push(run),
+                Some(merged) => merged.merge(run).with_context(|| format!("Can not merge the runs of {}", file))?,
+            }
+        }
+    }
+    Ok(ExitSummary::new(runs))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ino_write(name: &str, version: u32, target: &str) -> Result<String> {
+        let path = std::env::temp_dir().join(format!("inoue-merge-{}-{}.json", std::process::id(), name));
+        let json = format!(
+            r#"{{"schema_version":{},"passed":true,"runs":[{{"target":"{}","timestamp":1,"passed":true,"failures":[],
+            "clients":1,"requests":{{"total":1,"errors":0,"error_rate":0.0}},"elapsed_seconds":1.0,"throughput":1.0,
+            "latency_ms":{{"mean":1.0,"p95":1,"p999":1}}}}]}}"#,
+            version, target
+        );
+        fs::write(&path, json)?;
+        Ok(path.to_string_lossy().to_string())
+    }
+
+    #[test]
+    fn should_reject_another_schema_version() -> Result<()> {
+        let files = [ino_write("current", SCHEMA_VERSION, "http://a/")?, ino_write("future", SCHEMA_VERSION + 1, "http://a/")?];
+        let error = ino_merge_files(&files).err().unwrap();
+        files.iter().try_for_each(fs::remove_file)?;
+        assert_eq!(
+            error.to_string(),
+            format!("The exit summary {} has schema version {}, {} expected", files[1], SCHEMA_VERSION + 1, SCHEMA_VERSION)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_keep_the_runs_of_different_targets_apart() -> Result<()> {
+        let files = [ino_write("a", SCHEMA_VERSION, "http://a/")?, ino_write("b", SCHEMA_VERSION, "http://b/")?];
+        let merged = ino_merge_files(&files);
+        files.iter().try_for_each(fs::remove_file)?;
+        let targets: Vec<_> = merged?.runs.into_iter().map(|run| run.target).collect();
+        assert_eq!(targets, ["http://a/", "http://b/"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,328 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::Sender;
+
+use crate::benchmark::BenchmarkResult;
+
+#[derive(Debug, Default)]
+pub struct SendStats {
+    blocked: AtomicU64,
+    dropped: AtomicU64,
+    sent: AtomicU64,
+    dispatched: AtomicU64,
+    settled: AtomicU64,
+    cancelled: AtomicU64,
+}
+
+pub struct Iteration {
+    stats: Arc<SendStats>,
+    settled: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Accounting {
+    pub dispatched: u64,
+    pub settled: u64,
+    pub cancelled: u64,
+    pub sent: u64,
+    pub dropped: u64,
+    pub completed: u64,
+    pub failed: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub discrepancies: Vec<String>,
+}
+
+impl SendStats {
+
+    /**
+    *=================================================================
+    * ino_send()
+    *=================================================================
+    *
+    * Sends a result to the report, counting the sends that had to
+    * wait because the channel was full and the results dropped
+    * because the report was already gone.
+    *
+    *=================================================================
+    * @param tx &Sender<BenchmarkResult>
+    * @param result BenchmarkResult
+    * @return void
+    */
+    pub async fn ino_send(&self, tx: &Sender<BenchmarkResult>, result: BenchmarkResult) {
+        self.sent.fetch_add(1, Ordering::Relaxed);
+        let result = match tx.try_send(result) {
+            Ok(()) => return,
+            Err(TrySendError::Closed(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            Err(TrySendError::Full(result)) => result,
+        };
+        self.blocked.fetch_add(1, Ordering::Relaxed);
+        if tx.send(result).await.is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_counts()
+    *=================================================================
+    *
+    * Blocked and dropped send counts.
+    *
+    *=================================================================
+    * @param void
+    * @return (u64, u64)
+    */
+    pub fn ino_counts(&self) -> (u64, u64) {
+        (self.blocked.load(Ordering::Relaxed), self.dropped.load(Ordering::Relaxed))
+    }
+
+    /**
+    *=================================================================
+    * ino_accounting()
+    *=================================================================
+    *
+    * Audits the run once every iteration is over, against the
+    * results the report received.
+    *
+    *=================================================================
+    * @param completed u64 results received that are not errors
+    * @param failed u64 errors received, as counted by the report
+    * @param single bool every iteration sends a single result
+    * @return Accounting
+    */
+    pub fn ino_accounting(&self, completed: u64, failed: u64, single: bool) -> Accounting {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        Accounting::new(
+            Accounting {
+                dispatched: load(&self.dispatched),
+                settled: load(&self.settled),
+                cancelled: load(&self.cancelled),
+                sent: load(&self.sent),
+                dropped: load(&self.dropped),
+                completed,
+                failed,
+                discrepancies: vec![],
+            },
+            single,
+        )
+    }
+}
+
+impl Iteration {
+
+    /**
+    *=================================================================
+    * new()
+    *=================================================================
+    *
+    * Counts an iteration as dispatched. Dropped before it is
+    * settled, its task ended without sending all its results and it
+    * is counted as cancelled.
+    *
+    *=================================================================
+    * @param stats Arc<SendStats>
+    * @return Self
+    */
+    pub fn new(stats: Arc<SendStats>) -> Self {
+        stats.dispatched.fetch_add(1, Ordering::Relaxed);
+        Iteration { stats, settled: false }
+    }
+
+    /**
+    *=================================================================
+    * ino_settle()
+    *=================================================================
+    *
+    * Marks the iteration as over once its results were sent.
+    *
+    *=================================================================
+    * @param void
+    * @return void
+    */
+    pub fn ino_settle(mut self) {
+        self.settled = true;
+        self.stats.settled.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Iteration {
+    fn drop(&mut self) {
+        if !self.settled {
+            self.stats.cancelled.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Accounting {
+
+    /**
+    *=================================================================
+    * new()
+    *=================================================================
+    *
+    * Checks the counts of a run: every dispatched iteration settled
+    * or was cancelled, every result sent reached the report or was
+    * counted as dropped and, when an iteration sends a single
+    * result, dispatched == completed + failed + cancelled.
+    *
+    *=================================================================
+    * @param accounting Accounting
+    * @param single bool
+    * @return Self
+    */
+    pub fn new(mut accounting: Accounting, single: bool) -> Self {
+        let Accounting { dispatched, settled, cancelled, sent, dropped, completed, failed, .. } = accounting;
+        let mut discrepancies = vec![];
+        if dispatched != settled + cancelled {
+            discrepancies.push(format!("{} iterations dispatched but {} settled and {} cancelled", dispatched, settled, cancelled));
+        }
+        if single && dispatched != completed + failed + cancelled {
+            discrepancies.push(format!(
+                "{} iterations dispatched but {} completed, {} failed and {} cancelled",
+                dispatched, completed, failed, cancelled
+            ));
+        }
+        if sent != completed + failed + dropped {
+            discrepancies.push(format!("{} results sent but {} received and {} dropped", sent, completed + failed, dropped));
+        }
+        if cancelled > 0 {
+            discrepancies.push(format!("{} iterations were cancelled before sending their results", cancelled));
+        }
+        if dropped > 0 {
+            discrepancies.push(format!("{} results were dropped because the report was already gone", dropped));
+        }
+        accounting.discrepancies = discrepancies;
+        accounting
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeneratorSummary {
+    pub workers: usize,
+    pub cpu_seconds: f64,
+    pub cpu_percent: f64,
+    pub max_rss: u64,
+    pub blocked_sends: u64,
+    pub dropped_sends: u64,
+}
+
+impl GeneratorSummary {
+
+    /**
+    *=================================================================
+    * ino_saturated()
+    *=================================================================
+    *
+    * Tells whether inoue itself was likely the bottleneck: its CPU
+    * usage came close to what the runtime workers can use, or the
+    * clients had to wait for the report to keep up.
+    *
+    *=================================================================
+    * @param void
+    * @return bool
+    */
+    pub fn ino_saturated(&self) -> bool {
+        self.cpu_percent >= self.workers as f64 * 100.0 * 0.8 || self.blocked_sends > 0
+    }
+}
+
+/**
+ *=================================================================
+ * ino_cpu_time()
+ *=================================================================
+ *
+ * User plus system CPU time consumed by the process so far, None
+ * where it is not available.
+ *
+ *=================================================================
+ * @param void
+ * @return Option<Duration>
+ */
+pub fn ino_cpu_time() -> Option<Duration> {
+    ino_rusage().map(|(cpu, _)| cpu)
+}
+
+/**
+ *=================================================================
+ * ino_max_rss()
+ *=================================================================
+ *
+ * Peak resident memory of the process in bytes, None where it is
+ * not available.
+ *
+ *=================================================================
+ * @param void
+ * @return Option<u64>
+ */
+pub fn ino_max_rss() -> Option<u64> {
+    ino_rusage().map(|(_, rss)| rss)
+}
+
+#[cfg(unix)]
+fn ino_rusage() -> Option<(Duration, u64)> {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    // SAFETY: getrusage only writes into the provided struct.
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+    let time = |t: libc::timeval| Duration::new(t.tv_sec as u64, t.tv_usec as u32 * 1_000);
+    let cpu = time(usage.ru_utime) + time(usage.ru_stime);
+    // ru_maxrss is in bytes on macOS and in kilobytes elsewhere
+    let rss = match cfg!(target_os = "macos") {
+        true => usage.ru_maxrss as u64,
+        false => usage.ru_maxrss as u64 * 1024,
+    };
+    Some((cpu, rss))
+}
+
+#[cfg(not(unix))]
+fn ino_rusage() -> Option<(Duration, u64)> {
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::ino_result;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn should_count_blocked_and_dropped_sends() {
+        let stats = SendStats::default();
+        let (tx, mut rx) = mpsc::channel(1);
+        stats.ino_send(&tx, ino_result()).await;
+        let receiver = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            rx.recv().await;
+            rx
+        });
+        stats.ino_send(&tx, ino_result()).await;
+        drop(receiver.await.unwrap());
+        stats.ino_send(&tx, ino_result()).await;
+        assert_eq!(stats.ino_counts(), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn should_account_for_every_iteration() {
+        let stats = Arc::new(SendStats::default());
+        let (tx, mut rx) = mpsc::channel(4);
+        for _ in 0..2 {
+            let iteration = Iteration::new(stats.clone());
+            stats.ino_send(&tx, ino_result()).await;
+            iteration.ino_settle();
+        }
+        drop(Iteration::new(stats.clone()));
+        let accounting = stats.ino_accounting(1, 1, true);
+        assert_eq!((accounting.dispatched, accounting.settled, accounting.cancelled, accounting.sent), (3, 2, 1, 2));
+        assert_eq!(accounting.discrepancies, ["1 iterations were cancelled before sending their results"]);
+        assert_eq!(stats.ino_accounting(1, 0, true).discrepancies.len(), 3);
+        rx.close();
+    }
+}
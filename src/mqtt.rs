@@ -0,0 +1,458 @@
+use anyhow::{bail, Context, Result};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{lookup_host, TcpStream};
+use tokio::sync::oneshot;
+use tokio::time::{timeout, Instant};
+
+use crate::benchmark::BenchmarkResult;
+use crate::support::Settings;
+
+const MQTT_TIMEOUT: Duration = Duration::from_secs(10);
+
+const MQTT_PORT: u16 = 1883;
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const PUBACK: u8 = 0x40;
+const SUBSCRIBE: u8 = 0x82;
+const SUBACK: u8 = 0x90;
+
+type SessionSlot = Arc<tokio::sync::Mutex<Option<Arc<Session>>>>;
+
+#[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct MqttSettings {
+    #[serde(default = "ino_default_topic")]
+    pub topic: String,
+    #[serde(default)]
+    pub qos: u8,
+    #[serde(default)]
+    pub subscribe: bool,
+    #[serde(default = "ino_default_payload_size")]
+    pub payload_size: usize,
+}
+
+fn ino_default_topic() -> String {
+    "inoue/{client}".to_string()
+}
+
+fn ino_default_payload_size() -> usize {
+    64
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        MqttSettings { topic: ino_default_topic(), qos: 0, subscribe: false, payload_size: ino_default_payload_size() }
+    }
+}
+
+pub struct Mqtt {
+    addr: SocketAddr,
+    username: Option<String>,
+    password: Option<String>,
+    settings: MqttSettings,
+    payload: Option<Vec<u8>>,
+    sessions: Mutex<HashMap<usize, SessionSlot>>,
+}
+
+struct Session {
+    writer: tokio::sync::Mutex<OwnedWriteHalf>,
+    topic: String,
+    packet_ids: AtomicU16,
+    acks: Mutex<HashMap<u16, oneshot::Sender<()>>>,
+    deliveries: Mutex<HashMap<u64, oneshot::Sender<Instant>>>,
+    closed: AtomicBool,
+}
+
+impl Mqtt {
+
+    /**
+    *=================================================================
+    * ino_from_settings()
+    *=================================================================
+    *
+    * Parses the target "host:port" or
+    * "mqtt://[user[:password]@]host[:port]" and resolves it once.
+    * The body of the settings is the payload when there is one,
+    * otherwise a payload of the configured size is generated.
+    *
+    *=================================================================
+    * @param settings &Settings
+    * @return Result<Mqtt>
+    */
+    pub async fn ino_from_settings(settings: &Settings) -> Result<Self> {
+        let target = settings.ino_target();
+        let url = match target.contains("://") {
+            true => Url::parse(&target).with_context(|| format!("Invalid mqtt target {}", target))?,
+            false => Url::parse(&format!("mqtt://{}", target)).with_context(|| format!("Invalid mqtt target {}", target))?,
+        };
+        if url.scheme() != "mqtt" {
+            bail!("Unsupported scheme {} for mqtt, use mqtt://", url.scheme());
+        }
+        let host = url.host_str().with_context(|| format!("The mqtt target {} needs a host", target))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let addr = lookup_host((host, url.port().unwrap_or(MQTT_PORT)))
+            .await
+            .with_context(|| format!("Can not resolve {}", host))?
+            .next()
+            .with_context(|| format!("No address found for {}", host))?;
+        let mqtt = settings.mqtt.clone().unwrap_or_default();
+        if mqtt.qos > 1 {
+            bail!("The mqtt mode publishes with QoS 0 or 1, not {}", mqtt.qos);
+        }
+        if mqtt.topic.is_empty() || mqtt.topic.contains(['#', '+']) {
+            bail!("Invalid mqtt topic {}, a topic to publish to has no wildcard", mqtt.topic);
+        }
+        Ok(Mqtt {
+            addr,
+            username: Some(url.username().to_string()).filter(|u| !u.is_empty()),
+            password: url.password().map(str::to_string),
+            settings: mqtt,
+            payload: settings.body.clone().map(String::into_bytes),
+            sessions: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /**
+    *=================================================================
+    * ino_exec()
+    *=================================================================
+    *
+    * Publishes a single message on the session of the client, opened
+    * on its first iteration or after a failure and measured with it.
+    * The duration ends when the message was written with QoS 0, when
+    * the broker acknowledged it with QoS 1 and, when subscribing,
+    * when the broker delivered it back: the end-to-end latency. The
+    * message is recognized by the id written over the first 8
+    * bytes of its payload.
+    *
+    *=================================================================
+    * @param num_client usize
+    * @param execution usize
+    * @return BenchmarkResult
+    */
+    pub async fn ino_exec(&self, num_client: usize, execution: usize) -> BenchmarkResult {
+        let begin = Instant::now();
+        let mut reused = true;
+        let exchange = timeout(MQTT_TIMEOUT, async {
+            let slot = self.sessions.lock().unwrap().entry(num_client).or_default().clone();
+            let session = {
+                let mut slot = slot.lock().await;
+                match slot.as_ref().filter(|s| !s.closed.load(Ordering::Relaxed)) {
+                    Some(session) => session.clone(),
+                    None => {
+                        reused = false;
+                        slot.insert(self.ino_connect(num_client).await?).clone()
+                    }
+                }
+            };
+            self.ino_publish(&session, num_client, execution).await
+        })
+        .await;
+        let (status, success) = match exchange {
+            Err(_) => ("Timeout".to_string(), false),
+            Ok(Err(e)) => (format!("Failed: {}", e), false),
+            Ok(Ok(status)) => (status.to_string(), true),
+        };
+        BenchmarkResult {
+            reused: Some(reused),
+            ..BenchmarkResult::new(status, success, begin.elapsed().as_millis() as u64, num_client, execution)
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_publish()
+    *=================================================================
+    *
+    * Sends the message and waits for its acknowledgement and its
+    * delivery when required.
+    *
+    *=================================================================
+    */
+    async fn ino_publish(&self, session: &Session, num_client: usize, execution: usize) -> Result<&'static str> {
+        let id = (num_client as u64) << 32 | execution as u64;
+        let mut payload = self.payload.clone().unwrap_or_else(|| vec![b'x'; self.settings.payload_size]);
+        if self.settings.subscribe {
+            if payload.len() < 8 {
+                payload.resize(8, b'x');
+            }
+            payload[..8].copy_from_slice(&id.to_be_bytes());
+        }
+        let delivered = self.settings.subscribe.then(|| {
+            let (tx, rx) = oneshot::channel();
+            session.deliveries.lock().unwrap().insert(id, tx);
+            rx
+        });
+        let packet_id = (self.settings.qos == 1).then(|| session.ino_next_packet_id());
+        let acked = packet_id.map(|packet_id| {
+            let (tx, rx) = oneshot::channel();
+            session.acks.lock().unwrap().insert(packet_id, tx);
+            rx
+        });
+        let packet = ino_encode_publish(&session.topic, packet_id, &payload);
+        session.writer.lock().await.write_all(&packet).await?;
+        let mut status = "Published";
+        if let Some(acked) = acked {
+            acked.await.with_context(|| "Connection closed".to_string())?;
+            status = "Acknowledged";
+        }
+        if let Some(delivered) = delivered {
+            delivered.await.with_context(|| "Connection closed".to_string())?;
+            status = "Delivered";
+        }
+        Ok(status)
+    }
+
+    /**
+    *=================================================================
+    * ino_connect()
+    *=================================================================
+    *
+    * Opens the session of a client with a clean session and no keep
+    * alive, subscribes it to its own topic when required and spawns
+    * the task reading what the broker sends.
+    *
+    *=================================================================
+    */
+    async fn ino_connect(&self, num_client: usize) -> Result<Arc<Session>> {
+        let stream = TcpStream::connect(self.addr).await?;
+        stream.set_nodelay(true)?;
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        let client_id = format!("inoue-{}-{}", std::process::id(), num_client);
+        writer.write_all(&ino_encode_connect(&client_id, self.username.as_deref(), self.password.as_deref())).await?;
+        match ino_read_packet(&mut reader).await? {
+            (CONNACK, body) if body.get(1) == Some(&0) => {}
+            (CONNACK, body) => bail!("Connection refused with code {}", body.get(1).cloned().unwrap_or(0)),
+            (kind, _) => bail!("Unexpected packet {:#x} instead of CONNACK", kind),
+        }
+        let topic = self.settings.topic.replace("{client}", &num_client.to_string());
+        if self.settings.subscribe {
+            writer.write_all(&ino_encode_subscribe(1, &topic)).await?;
+            match ino_read_packet(&mut reader).await? {
+                (SUBACK, body) if body.get(2).is_some_and(|code| *code < 0x80) => {}
+                (SUBACK, _) => bail!("Subscription to {} refused", topic),
+                (kind, _) => bail!("Unexpected packet {:#x} instead of SUBACK", kind),
+            }
+        }
+        let session = Arc::new(Session {
+            writer: tokio::sync::Mutex::new(writer),
+            topic,
+            packet_ids: AtomicU16::new(1),
+            acks: Mutex::new(HashMap::new()),
+            deliveries: Mutex::new(HashMap::new()),
+            closed: AtomicBool::new(false),
+        });
+        tokio::spawn(ino_listen(session.clone(), reader));
+        Ok(session)
+    }
+}
+
+impl Session {
+
+    /**
+    *=================================================================
+    * ino_next_packet_id()
+    *=================================================================
+    *
+    * Next packet id of the session, 0 is not a valid one.
+    *
+    *=================================================================
+    */
+    fn ino_next_packet_id(&self) -> u16 {
+        loop {
+            let id = self.packet_ids.fetch_add(1, Ordering::Relaxed);
+            if id != 0 {
+                return id;
+            }
+        }
+    }
+}
+
+/**
+ *=================================================================
+ * ino_encode_remaining_length()
+ *=================================================================
+ *
+ * Appends the remaining length of a packet, seven bits per byte.
+ *
+ *=================================================================
+ */
+fn ino_encode_remaining_length(mut length: usize, packet: &mut Vec<u8>) {
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            return;
+        }
+    }
+}
+
+fn ino_encode_string(value: &str, body: &mut Vec<u8>) {
+    body.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    body.extend_from_slice(value.as_bytes());
+}
+
+fn ino_encode_packet(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![kind];
+    ino_encode_remaining_length(body.len(), &mut packet);
+    packet.extend_from_slice(body);
+    packet
+}
+
+/**
+ *=================================================================
+ * ino_encode_connect()
+ *=================================================================
+ *
+ * CONNECT packet of MQTT 3.1.1 with a clean session, no keep
+ * alive and the optional credentials.
+ *
+ *=================================================================
+ */
+fn ino_encode_connect(client_id: &str, username: Option<&str>, password: Option<&str>) -> Vec<u8> {
+    let mut body = vec![];
+    ino_encode_string("MQTT", &mut body);
+    let mut flags = 0x02;
+    if username.is_some() {
+        flags |= 0x80;
+    }
+    if password.is_some() {
+        flags |= 0x40;
+    }
+    body.extend_from_slice(&[4, flags, 0, 0]);
+    ino_encode_string(client_id, &mut body);
+    for value in [username, password].into_iter().flatten() {
+        ino_encode_string(value, &mut body);
+    }
+    ino_encode_packet(CONNECT, &body)
+}
+
+fn ino_encode_subscribe(packet_id: u16, topic: &str) -> Vec<u8> {
+    let mut body = packet_id.to_be_bytes().to_vec();
+    ino_encode_string(topic, &mut body);
+    body.push(0);
+    ino_encode_packet(SUBSCRIBE, &body)
+}
+
+fn ino_encode_publish(topic: &str, packet_id: Option<u16>, payload: &[u8]) -> Vec<u8> {
+    let mut body = vec![];
+    ino_encode_string(topic, &mut body);
+    if let Some(packet_id) = packet_id {
+        body.extend_from_slice(&packet_id.to_be_bytes());
+    }
+    body.extend_from_slice(payload);
+    let qos = match packet_id {
+        None => 0,
+        Some(_) => 1 << 1,
+    };
+    ino_encode_packet(PUBLISH | qos, &body)
+}
+
+/**
+ *=================================================================
+ * ino_read_packet()
+ *=================================================================
+ *
+ * Reads a packet, its type and flags byte and its body.
+ *
+ *=================================================================
+ */
+async fn ino_read_packet<R: AsyncRead + Unpin>(reader: &mut R) -> Result<(u8, Vec<u8>)> {
+    let kind = reader.read_u8().await?;
+    let mut length = 0usize;
+    for shift in 0..4 {
+        let byte = reader.read_u8().await?;
+        length |= ((byte & 0x7f) as usize) << (7 * shift);
+        if byte & 0x80 == 0 {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await?;
+            return Ok((kind, body));
+        }
+    }
+    bail!("Invalid remaining length")
+}
+
+/**
+ *=================================================================
+ * ino_listen()
+ *=================================================================
+ *
+ * Reads what the broker sends on a session until it closes:
+ * completes the acknowledgements by packet id and the deliveries
+ * by message id. A closed session fails the pending messages and
+ * is replaced on the next iteration of its client.
+ *
+ *=================================================================
+ */
+async fn ino_listen<R: AsyncRead + Unpin>(session: Arc<Session>, mut reader: R) {
+    while let Ok((kind, body)) = ino_read_packet(&mut reader).await {
+        match kind & 0xf0 {
+            PUBACK if body.len() >= 2 => {
+                let packet_id = u16::from_be_bytes([body[0], body[1]]);
+                if let Some(tx) = session.acks.lock().unwrap().remove(&packet_id) {
+                    let _ = tx.send(());
+                }
+            }
+            PUBLISH => {
+                let at = Instant::now();
+                let topic_length = body.get(..2).map_or(0, |l| u16::from_be_bytes([l[0], l[1]]) as usize);
+                let offset = 2 + topic_length + if kind & 0x06 != 0 { 2 } else { 0 };
+                if let Some(id) = body.get(offset..offset + 8) {
+                    let id = u64::from_be_bytes(id.try_into().expect("8 bytes"));
+                    if let Some(tx) = session.deliveries.lock().unwrap().remove(&id) {
+                        let _ = tx.send(at);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    session.closed.store(true, Ordering::Relaxed);
+    session.acks.lock().unwrap().clear();
+    session.deliveries.lock().unwrap().clear();
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_encode_packets() {
+        let mut length = vec![];
+        ino_encode_remaining_length(321, &mut length);
+        assert_eq!(length, vec![0xc1, 0x02]);
+        assert_eq!(
+            ino_encode_connect("c", Some("u"), None),
+            vec![0x10, 0x10, 0, 4, b'M', b'Q', b'T', b'T', 4, 0x82, 0, 0, 0, 1, b'c', 0, 1, b'u']
+        );
+        assert_eq!(ino_encode_publish("t", Some(7), b"hi"), vec![0x32, 7, 0, 1, b't', 0, 7, b'h', b'i']);
+        assert_eq!(ino_encode_subscribe(1, "t"), vec![0x82, 6, 0, 1, 0, 1, b't', 0]);
+    }
+
+    #[tokio::test]
+    async fn should_read_packets() -> Result<()> {
+        let mut packet = ino_encode_publish("t", None, &[b'x'; 200]);
+        packet.extend_from_slice(&[0x40, 2, 0, 7]);
+        let mut reader = packet.as_slice();
+        let (kind, body) = ino_read_packet(&mut reader).await?;
+        assert_eq!((kind, body.len()), (PUBLISH, 203));
+        assert_eq!(ino_read_packet(&mut reader).await?, (PUBACK, vec![0, 7]));
+        assert!(ino_read_packet(&mut reader).await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::benchmark::Summary;
+use crate::tags::ino_format_tags;
+
+#[derive(Eq, PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyFormat {
+    #[default]
+    Webhook,
+    Slack,
+    Teams,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyOn {
+    #[default]
+    Always,
+    Failure,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub url: String,
+    #[serde(default)]
+    pub format: NotifyFormat,
+    #[serde(default)]
+    pub on: NotifyOn,
+}
+
+/**
+ *=================================================================
+ * ino_notify()
+ *=================================================================
+ *
+ * Fires the configured notifications with the summary figures
+ * of the run. Notifications declared with `on: failure` are only
+ * fired when a threshold failed. A failing notification is
+ * reported as a warning and never fails the run itself.
+ *
+ *=================================================================
+ * @param notifications &[Notification]
+ * @param summary &Summary
+ * @param failures &[String] failed thresholds
+ * @return void
+ */
+pub async fn ino_notify(notifications: &[Notification], summary: &Summary, failures: &[String]) {
+    let client = Client::new();
+    for notification in notifications {
+        if notification.on == NotifyOn::Failure && failures.is_empty() {
+            continue;
+        }
+        if let Err(e) = ino_send(&client, notification, summary, failures).await {
+            eprintln!("{} {:#}", "Notification failed:".red().bold(), e);
+        }
+    }
+}
+
+/**
+ *=================================================================
+ * ino_send()
+ *=================================================================
+ *
+ * Posts a single notification using the payload template of its
+ * format.
+ *
+ *=================================================================
+ * @param client &Client
+ * @param notification &Notification
+ * @param summary &Summary
+ * @param failures &[String]
+ * @return Result<()>
+ */
+async fn ino_send(client: &Client, notification: &Notification, summary: &Summary, failures: &[String]) -> Result<()> {
+    let payload = ino_payload(&notification.format, summary, failures);
+    let response = client
+        .post(&notification.url)
+        .json(&payload)
+        .send()
+        .await
+        .with_context(|| format!("Can not reach {}", notification.url))?;
+    if !response.status().is_success() {
+        bail!("{} answered {}", notification.url, response.status());
+    }
+    Ok(())
+}
+
+/**
+ *=================================================================
+ * ino_payload()
+ *=================================================================
+ *
+ * Builds the JSON payload for a notification format:
+ * - webhook: the raw summary
+ * - slack:   an incoming webhook message
+ * - teams:   a connector MessageCard
+ *
+ *=================================================================
+ * @param format &NotifyFormat
+ * @param summary &Summary
+ * @param failures &[String]
+ * @return Value
+ */
+pub fn ino_payload(format: &NotifyFormat, summary: &Summary, failures: &[String]) -> Value {
+    let (event, title) = match failures.is_empty() {
+        true => ("finished", format!("inoue run against {} finished", summary.target)),
+        false => ("threshold_failed", format!("inoue run against {} failed its thresholds", summary.target)),
+    };
+    let mut text = format!(
+        "{} requests with {} clients in {:.1}s, {:.1} rps, mean {:.1} ms, p95 {} ms, p99.9 {} ms, max {} ms",
+        summary.total_requests,
+        summary.clients,
+        summary.elapsed,
+        summary.throughput,
+        summary.mean,
+        summary.p95,
+        summary.p999,
+        summary.max
+    );
+    if !summary.tags.is_empty() {
+        text.push_str(&format!("\n{}", ino_format_tags(&summary.tags)));
+    }
+    failures.iter().for_each(|f| text.push_str(&format!("\n✗ {}", f)));
+    match format {
+        NotifyFormat::Webhook => json!({ "event": event, "summary": summary, "failures": failures }),
+        NotifyFormat::Slack => json!({ "text": format!("*{}*\n{}", title, text) }),
+        NotifyFormat::Teams => json!({
+            "@type": "MessageCard",
+            "@context": "http://schema.org/extensions",
+            "summary": title,
+            "title": title,
+            "text": text,
+        }),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use crate::benchmark::Report;
+    use crate::support::Settings;
+
+    fn ino_summary() -> Result<Summary> {
+        let settings: Settings = serde_yaml::from_str("target: http://host/\nclients: 2\nrequests: 10\ntags:\n  env: ci")?;
+        Ok(Report::new(&settings)?.ino_summary(&settings))
+    }
+
+    #[test]
+    fn should_build_the_payload_of_each_format() -> Result<()> {
+        let summary = ino_summary()?;
+        let webhook = ino_payload(&NotifyFormat::Webhook, &summary, &[]);
+        assert_eq!(webhook["event"], "finished");
+        assert_eq!(webhook["summary"]["target"], "http://host/");
+        assert_eq!(webhook["failures"], json!([]));
+        let slack = ino_payload(&NotifyFormat::Slack, &summary, &[]);
+        let text = slack["text"].as_str().unwrap();
+        assert!(text.starts_with("*inoue run against http://host/ finished*\n0 requests with 2 clients"));
+        assert!(text.ends_with("\nenv=ci"));
+        let teams = ino_payload(&NotifyFormat::Teams, &summary, &[]);
+        assert_eq!(teams["@type"], "MessageCard");
+        assert_eq!(teams["title"], "inoue run against http://host/ finished");
+        assert_eq!(teams["summary"], teams["title"]);
+        Ok(())
+    }
+
+    #[test]
+    fn should_report_the_failed_thresholds() -> Result<()> {
+        let summary = ino_summary()?;
+        let failures = ["p95 < 100ms".to_string()];
+        let webhook = ino_payload(&NotifyFormat::Webhook, &summary, &failures);
+        assert_eq!(webhook["event"], "threshold_failed");
+        assert_eq!(webhook["failures"], json!(["p95 < 100ms"]));
+        let teams = ino_payload(&NotifyFormat::Teams, &summary, &failures);
+        assert_eq!(teams["title"], "inoue run against http://host/ failed its thresholds");
+        assert!(teams["text"].as_str().unwrap().ends_with("\n✗ p95 < 100ms"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_skip_failure_notifications_without_failures() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base = format!("http://{}", listener.local_addr()?);
+        let paths = Arc::new(Mutex::new(vec![]));
+        let received = paths.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0; 4096];
+                let read = stream.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]).to_string();
+                if let Some(path) = request.split_whitespace().nth(1) {
+                    received.lock().unwrap().push(path.to_string());
+                }
+                stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n").await.unwrap_or(());
+            }
+        });
+        let notifications = [
+            Notification { url: format!("{}/always", base), format: NotifyFormat::Webhook, on: NotifyOn::Always },
+            Notification { url: format!("{}/failure", base), format: NotifyFormat::Webhook, on: NotifyOn::Failure },
+        ];
+        let summary = ino_summary()?;
+        ino_notify(&notifications, &summary, &[]).await;
+        assert_eq!(*paths.lock().unwrap(), ["/always"]);
+        ino_notify(&notifications, &summary, &["errors < 1%".to_string()]).await;
+        assert_eq!(*paths.lock().unwrap(), ["/always", "/always", "/failure"]);
+        Ok(())
+    }
+}
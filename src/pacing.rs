@@ -0,0 +1,308 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::benchmark::{ino_quantile, BenchmarkResult};
+use crate::profile::LoadProfile;
+use crate::support::ino_parse_duration;
+
+const PROFILE_CHECK: Duration = Duration::from_millis(100);
+
+const LAG_TOLERANCE: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Pacing {
+    pub iterations: u32,
+    pub period: Duration,
+}
+
+impl TryFrom<String> for Pacing {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        ino_parse_pacing(&value)
+    }
+}
+
+impl From<Pacing> for String {
+    fn from(pacing: Pacing) -> Self {
+        format!("{}:{}ms", pacing.iterations, pacing.period.as_millis())
+    }
+}
+
+pub struct Pacer {
+    interval: Duration,
+    next: Instant,
+    shaping: Option<Shaping>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleSummary {
+    pub requests: u64,
+    pub mean_us: f64,
+    pub p50_us: u64,
+    pub p99_us: u64,
+    pub p999_us: u64,
+    pub max_us: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct ScheduleStats {
+    lags: Vec<u64>,
+}
+
+struct Shaping {
+    profile: LoadProfile,
+    start: Instant,
+    duration: Duration,
+    credit: f64,
+    checked: Instant,
+}
+
+impl Pacer {
+
+    /**
+    *=================================================================
+    * new()
+    *=================================================================
+    *
+    * Creates the pacer of a single client, its iterations are spread
+    * evenly over the period.
+    *
+    *=================================================================
+    * @param pacing Pacing
+    * @return Self
+    */
+    pub fn new(pacing: Pacing) -> Self {
+        Pacer {
+            interval: pacing.period / pacing.iterations,
+            next: Instant::now(),
+            shaping: None,
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_with_profile()
+    *=================================================================
+    *
+    * Shapes the rate of the client with a load profile spread over
+    * the run, the pacing being the rate at a multiplier of 1.
+    *
+    *=================================================================
+    * @param profile LoadProfile
+    * @param start Instant of the run
+    * @param duration Duration of the run
+    * @return Self
+    */
+    pub fn ino_with_profile(mut self, profile: LoadProfile, start: Instant, duration: Duration) -> Self {
+        self.shaping = Some(Shaping { profile, start, duration, credit: 1.0, checked: Instant::now() });
+        self
+    }
+
+    /**
+    *=================================================================
+    * ino_due()
+    *=================================================================
+    *
+    * Instant the next iteration of the client may start.
+    *
+    *=================================================================
+    * @param void
+    * @return Instant
+    */
+    pub fn ino_due(&self) -> Instant {
+        self.next
+    }
+
+    /**
+    *=================================================================
+    * ino_wait()
+    *=================================================================
+    *
+    * Waits until the next iteration of the client may start. An
+    * iteration started late, behind slow responses, does not make
+    * the following ones catch up, so the client never goes above
+    * its rate. With a load profile the rate follows the multiplier
+    * of the moment, checked at least every 100 ms, so a client held
+    * back by a multiplier close to 0 starts again as soon as the
+    * rate picks up. Returns the instant the iteration was due.
+    *
+    *=================================================================
+    * @param void
+    * @return Instant
+    */
+    pub async fn ino_wait(&mut self) -> Instant {
+        let Some(shaping) = &mut self.shaping else {
+            let due = self.next;
+            tokio::time::sleep_until(due).await;
+            self.next = due.max(Instant::now()) + self.interval;
+            return due;
+        };
+        loop {
+            let due = self.next;
+            tokio::time::sleep_until(due).await;
+            let now = Instant::now();
+            let progress = (now - shaping.start).as_secs_f64() / shaping.duration.as_secs_f64().max(f64::EPSILON);
+            let rate = shaping.profile.ino_multiplier(progress) / self.interval.as_secs_f64();
+            shaping.credit = (shaping.credit + (now - shaping.checked).as_secs_f64() * rate).min(1.0);
+            shaping.checked = now;
+            let ready = shaping.credit >= 1.0;
+            if ready {
+                shaping.credit = 0.0;
+            }
+            let wait = match rate > 0.0 {
+                true => Duration::from_secs_f64((1.0 - shaping.credit) / rate).min(PROFILE_CHECK),
+                false => PROFILE_CHECK,
+            };
+            self.next = now + wait;
+            if ready {
+                return due;
+            }
+        }
+    }
+}
+
+impl ScheduleSummary {
+
+    /**
+    *=================================================================
+    * ino_accurate()
+    *=================================================================
+    *
+    * Whether the requests left close enough to their due time for
+    * the response times to be trusted: the p99 lag stays under a
+    * tenth of the p95 response time, or under 1 ms.
+    *
+    *=================================================================
+    * @param p95 u64 response time in ms
+    * @return bool
+    */
+    pub fn ino_accurate(&self, p95: u64) -> bool {
+        self.p99_us as f64 <= (p95 as f64 * 1000.0 * LAG_TOLERANCE).max(1000.0)
+    }
+}
+
+impl ScheduleStats {
+
+    /**
+    *=================================================================
+    * ino_add()
+    *=================================================================
+    *
+    * Records the scheduling lag of a paced or burst request.
+    *
+    *=================================================================
+    * @param result &BenchmarkResult
+    * @return void
+    */
+    pub fn ino_add(&mut self, result: &BenchmarkResult) {
+        if let Some(lag) = result.lag {
+            self.lags.push(lag);
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_summary()
+    *=================================================================
+    *
+    * Distribution of the lag between the instant a paced or burst
+    * request was due and the instant it was sent, None when no
+    * request was scheduled.
+    *
+    *=================================================================
+    * @param void
+    * @return Option<ScheduleSummary>
+    */
+    pub fn ino_summary(&self) -> Option<ScheduleSummary> {
+        if self.lags.is_empty() {
+            return None;
+        }
+        let mut lags = self.lags.clone();
+        lags.sort_unstable();
+        Some(ScheduleSummary {
+            requests: lags.len() as u64,
+            mean_us: lags.iter().sum::<u64>() as f64 / lags.len() as f64,
+            p50_us: ino_quantile(&lags, 0.5),
+            p99_us: ino_quantile(&lags, 0.99),
+            p999_us: ino_quantile(&lags, 0.999),
+            max_us: lags[lags.len() - 1],
+        })
+    }
+}
+
+/**
+ *=================================================================
+ * ino_parse_pacing()
+ *=================================================================
+ *
+ * Parses a pacing such as "6:1m", the iterations a client runs at
+ * most during the period.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<Pacing>
+ */
+pub fn ino_parse_pacing(value: &str) -> Result<Pacing> {
+    let (iterations, period) = value
+        .split_once(':')
+        .with_context(|| format!("Invalid pacing {}, use iterations:period, ex. 6:1m", value))?;
+    let iterations: u32 = iterations
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid pacing iterations in {}", value))?;
+    let period = ino_parse_duration(period)?;
+    if iterations == 0 || period.is_zero() {
+        bail!("Invalid pacing {}, the iterations and the period must be positive", value);
+    }
+    Ok(Pacing { iterations, period })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::ino_result;
+
+    #[test]
+    fn should_parse_pacing() -> Result<()> {
+        let pacing = ino_parse_pacing("6:1m")?;
+        assert_eq!(pacing, Pacing { iterations: 6, period: Duration::from_secs(60) });
+        assert_eq!(String::from(pacing), "6:60000ms");
+        assert!(ino_parse_pacing("6").is_err());
+        assert!(ino_parse_pacing("0:1s").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_spread_iterations_over_the_period() {
+        let begin = Instant::now();
+        let mut pacer = Pacer::new(Pacing { iterations: 10, period: Duration::from_secs(1) });
+        for _ in 0..4 {
+            pacer.ino_wait().await;
+        }
+        let elapsed = begin.elapsed();
+        assert!(elapsed >= Duration::from_millis(300) && elapsed < Duration::from_millis(400));
+    }
+
+    #[test]
+    fn should_summarize_the_scheduling_lag() {
+        let result = |lag| BenchmarkResult {
+            duration: 20,
+            lag,
+            ..ino_result()
+        };
+        let results: Vec<BenchmarkResult> = (1..=100).map(|lag| result(Some(lag * 100))).chain([result(None)]).collect();
+        let mut stats = ScheduleStats::default();
+        stats.ino_add(&result(None));
+        assert_eq!(stats.ino_summary(), None);
+        results.iter().for_each(|result| stats.ino_add(result));
+        let summary = stats.ino_summary().unwrap();
+        assert_eq!((summary.requests, summary.p50_us, summary.p99_us, summary.max_us), (100, 5000, 9900, 10000));
+        assert_eq!(summary.mean_us, 5050.0);
+        assert!(!summary.ino_accurate(20) && summary.ino_accurate(100));
+    }
+}
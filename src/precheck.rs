@@ -0,0 +1,208 @@
+use anyhow::{bail, Result};
+use reqwest::Client;
+use std::error::Error;
+use std::io;
+use std::time::Duration;
+
+use crate::execution::{ino_build_request, ino_read_body};
+use crate::replay::Replay;
+use crate::support::Settings;
+
+const PRECHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/**
+ *=================================================================
+ * ino_precheck()
+ *=================================================================
+ *
+ * Sends a single probe request before spawning the clients and
+ * fails fast with a clear message when the target can not be
+ * reached, does not answer in time or answers with a 5xx status.
+ * It waits as long as a request of the run, or 10s without
+ * --timeout. Its body is read so that the client keeps the
+ * connection.
+ *
+ *=================================================================
+ * @param settings &Settings
+ * @param client &Client
+ * @param replay Option<&Replay>
+ * @return Result<()>
+ */
+pub async fn ino_precheck(settings: &Settings, client: &Client, replay: Option<&Replay>) -> Result<()> {
+    let (operation, target) = match replay.and_then(|r| r.ino_first()) {
+        None => (settings.ino_operation(), settings.ino_target()),
+        Some(entry) => (entry.operation, entry.url.clone()),
+    };
+    let response = ino_build_request(client, settings, operation, target.clone())
+        .timeout(settings.timeout.unwrap_or(PRECHECK_TIMEOUT))
+        .send()
+        .await;
+    match response {
+        Err(e) => bail!(
+            "Pre-flight check of {} failed: {} (use --no-precheck to skip it)",
+            target,
+            ino_classify(&e)
+        ),
+        Ok(r) if r.status().is_server_error() => bail!(
+            "Pre-flight check of {} failed: the target answered {} (use --no-precheck to skip it)",
+            target,
+            r.status()
+        ),
+        Ok(mut r) => {
+            if !settings.no_read_body {
+                ino_read_body(&mut r, &mut vec![], 0, &[], None).await;
+            }
+            Ok(())
+        }
+    }
+}
+
+/**
+ *=================================================================
+ * ino_classify()
+ *=================================================================
+ *
+ * Turns a request error into a readable cause by walking its
+ * sources: DNS failure, connection refused, TLS error, timeout.
+ *
+ *=================================================================
+ * @param error &reqwest::Error
+ * @return String
+ */
+fn ino_classify(error: &reqwest::Error) -> String {
+    if error.is_builder() {
+        return format!("invalid request ({})", error);
+    }
+    if error.is_timeout() {
+        return "the request timed out".to_string();
+    }
+    let mut source: Option<&(dyn Error + 'static)> = error.source();
+    let mut causes = vec![];
+    while let Some(cause) = source {
+        if let Some(io_error) = cause.downcast_ref::<io::Error>() {
+            match io_error.kind() {
+                io::ErrorKind::ConnectionRefused => return "connection refused".to_string(),
+                io::ErrorKind::TimedOut => return "connection timed out".to_string(),
+                _ => {}
+            }
+            if let Some(tls_error) = ino_find_tls_error(io_error) {
+                return format!("TLS error ({})", tls_error);
+            }
+        }
+        if cause.downcast_ref::<rustls::Error>().is_some() {
+            return format!("TLS error ({})", cause);
+        }
+        causes.push(cause.to_string());
+        source = cause.source();
+    }
+    let message = causes.join(": ");
+    let lower = message.to_lowercase();
+    if lower.contains("dns error") || lower.contains("failed to lookup") {
+        return format!("DNS resolution failed ({})", causes.last().unwrap_or(&message));
+    }
+    if lower.contains("tls") || lower.contains("certificate") || lower.contains("handshake") {
+        return format!("TLS error ({})", message);
+    }
+    match message.is_empty() {
+        true => error.to_string(),
+        false => message,
+    }
+}
+
+/**
+ *=================================================================
+ * ino_find_tls_error()
+ *=================================================================
+ *
+ * Looks for a rustls error wrapped, possibly several times, in an
+ * io error: io::Error::source() skips the wrapped error itself.
+ *
+ *=================================================================
+ * @param error &io::Error
+ * @return Option<&rustls::Error>
+ */
+fn ino_find_tls_error(error: &io::Error) -> Option<&rustls::Error> {
+    let inner = error.get_ref()?;
+    match inner.downcast_ref::<io::Error>() {
+        Some(io_error) => ino_find_tls_error(io_error),
+        None => inner.downcast_ref::<rustls::Error>(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::{mpsc, watch};
+
+    use crate::execution::{ino_build_client, ino_run};
+    use crate::support::Args;
+
+    async fn ino_server(response: &'static [u8]) -> Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let target = format!("http://{}/", listener.local_addr()?);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0; 1024];
+                    while stream.read(&mut request).await.is_ok_and(|read| read > 0) {
+                        if stream.write_all(response).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        Ok(target)
+    }
+
+    fn ino_settings(args: &[&str]) -> Result<Settings> {
+        Settings::ino_from_args(Args::parse_from(["inoue"].iter().chain(args)))
+    }
+
+    #[tokio::test]
+    async fn should_report_a_refused_connection() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let target = format!("http://{}/", listener.local_addr()?);
+        drop(listener);
+        let settings = ino_settings(&["-t", &target])?;
+        let error = ino_precheck(&settings, &ino_build_client(&settings)?, None).await.err().unwrap();
+        assert!(error.to_string().ends_with("failed: connection refused (use --no-precheck to skip it)"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_fail_on_a_server_error() -> Result<()> {
+        let target = ino_server(b"HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\n\r\n").await?;
+        let settings = ino_settings(&["-t", &target])?;
+        let error = ino_precheck(&settings, &ino_build_client(&settings)?, None).await.err().unwrap();
+        assert!(error.to_string().contains("the target answered 500 Internal Server Error"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_wait_as_long_as_the_request_timeout() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let target = format!("http://{}/", listener.local_addr()?);
+        let settings = ino_settings(&["-t", &target, "--timeout", "100ms"])?;
+        let error = ino_precheck(&settings, &ino_build_client(&settings)?, None).await.err().unwrap();
+        assert!(error.to_string().contains("failed: the request timed out"));
+        drop(listener);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_skip_the_probe_with_no_precheck() -> Result<()> {
+        let target = ino_server(b"HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\n\r\n").await?;
+        let (tx, _rx) = mpsc::channel(10);
+        let (_stop, rx_sigint) = watch::channel(None);
+        let settings = ino_settings(&["-t", &target, "-i", "1"])?;
+        assert!(ino_run(settings, tx.clone(), rx_sigint.clone(), None, None).await.is_err());
+        let settings = ino_settings(&["-t", &target, "-i", "1", "--no-precheck"])?;
+        assert!(ino_run(settings, tx, rx_sigint, None, None).await.is_ok());
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct LoadProfile {
+    file: String,
+    points: Vec<(f64, f64)>,
+}
+
+impl TryFrom<String> for LoadProfile {
+    type Error = anyhow::Error;
+
+    fn try_from(file: String) -> Result<Self> {
+        LoadProfile::ino_from_file(file)
+    }
+}
+
+impl From<LoadProfile> for String {
+    fn from(profile: LoadProfile) -> Self {
+        profile.file
+    }
+}
+
+impl LoadProfile {
+
+    /**
+    *=================================================================
+    * ino_from_file()
+    *=================================================================
+    *
+    * Loads a load profile, one "timestamp,multiplier" point per
+    * line. The timestamp is a time of day, "HH:MM" or "HH:MM:SS",
+    * or a number of seconds, and grows from line to line. Blank
+    * lines, comments starting with # and a header line are skipped.
+    *
+    *=================================================================
+    * @param file String
+    * @return Result<LoadProfile>
+    */
+    pub fn ino_from_file(file: String) -> Result<Self> {
+        let content = fs::read_to_string(&file).with_context(|| format!("Failed to read the load profile from {}", file))?;
+        let points = ino_parse_profile(&content).with_context(|| format!("Invalid load profile {}", file))?;
+        Ok(LoadProfile { file, points })
+    }
+
+    /**
+    *=================================================================
+    * ino_multiplier()
+    *=================================================================
+    *
+    * Rate multiplier at a fraction of the run, the whole profile
+    * being compressed or stretched over the run. Linear between two
+    * points of the profile.
+    *
+    *=================================================================
+    * @param progress f64 fraction of the run, from 0 to 1
+    * @return f64
+    */
+    pub fn ino_multiplier(&self, progress: f64) -> f64 {
+        let (first, last) = (self.points[0].0, self.points[self.points.len() - 1].0);
+        let at = first + (last - first) * progress.clamp(0.0, 1.0);
+        let next = self.points.iter().position(|&(timestamp, _)| timestamp >= at).unwrap_or(self.points.len() - 1);
+        if next == 0 || self.points[next].0 <= at {
+            return self.points[next].1;
+        }
+        let ((t0, m0), (t1, m1)) = (self.points[next - 1], self.points[next]);
+        m0 + (m1 - m0) * (at - t0) / (t1 - t0)
+    }
+}
+
+/**
+ *=================================================================
+ * ino_parse_profile()
+ *=================================================================
+ *
+ * Parses the points of a load profile into seconds and
+ * multipliers, at least two of them.
+ *
+ *=================================================================
+ */
+fn ino_parse_profile(content: &str) -> Result<Vec<(f64, f64)>> {
+    let mut points: Vec<(f64, f64)> = vec![];
+    let mut first = true;
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let header = std::mem::replace(&mut first, false);
+        let (timestamp, multiplier) = line
+            .split_once(',')
+            .with_context(|| format!("Line {} is not timestamp,multiplier", number + 1))?;
+        let Ok(multiplier) = multiplier.trim().parse::<f64>() else {
+            if header {
+                continue;
+            }
+            bail!("Invalid multiplier {} on line {}", multiplier.trim(), number + 1);
+        };
+        let timestamp = ino_parse_timestamp(timestamp.trim()).with_context(|| format!("Invalid timestamp on line {}", number + 1))?;
+        if multiplier < 0.0 || !multiplier.is_finite() {
+            bail!("The multiplier on line {} must be positive", number + 1);
+        }
+        if points.last().is_some_and(|&(last, _)| timestamp <= last) {
+            bail!("The timestamp on line {} does not grow", number + 1);
+        }
+        points.push((timestamp, multiplier));
+    }
+    if points.len() < 2 {
+        bail!("A load profile needs two points at least");
+    }
+    Ok(points)
+}
+
+fn ino_parse_timestamp(value: &str) -> Result<f64> {
+    if !value.contains(':') {
+        return Ok(value.parse()?);
+    }
+    let parts = value.split(':').map(|part| part.parse::<u32>()).collect::<Result<Vec<u32>, _>>()?;
+    match parts.as_slice() {
+        [hours, minutes] => Ok((hours * 3600 + minutes * 60) as f64),
+        [hours, minutes, seconds] => Ok((hours * 3600 + minutes * 60 + seconds) as f64),
+        _ => bail!("Invalid time of day {}", value),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_compress_the_profile_over_the_run() -> Result<()> {
+        let points = ino_parse_profile("time,multiplier\n# night\n00:00,0.2\n\n06:00,1\n12:00:00,3\n24:00,0.2\n")?;
+        assert_eq!(points, vec![(0.0, 0.2), (21600.0, 1.0), (43200.0, 3.0), (86400.0, 0.2)]);
+        let profile = LoadProfile { file: String::new(), points };
+        assert_eq!(profile.ino_multiplier(0.0), 0.2);
+        assert_eq!(profile.ino_multiplier(0.375), 2.0);
+        assert_eq!(profile.ino_multiplier(0.5), 3.0);
+        assert_eq!(profile.ino_multiplier(2.0), 0.2);
+        assert!(ino_parse_profile("0,1\n0,2\n").is_err());
+        assert!(ino_parse_profile("0,1\n").is_err());
+        assert!(ino_parse_profile("0,1\n10,x\n").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,382 @@
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use colored::Colorize;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::benchmark::{BenchmarkResult, Summary};
+use crate::tags::Tags;
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Prometheus {
+    pub pushgateway: String,
+    #[serde(default = "ino_default_job")]
+    pub job: String,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+fn ino_default_job() -> String {
+    "inoue".to_string()
+}
+
+impl Prometheus {
+    pub fn new(pushgateway: String) -> Self {
+        Prometheus {
+            pushgateway,
+            job: ino_default_job(),
+            labels: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PrometheusExporter {
+    client: Client,
+    url: String,
+    labels: String,
+    window_start: Instant,
+    window_requests: u64,
+    window_errors: u64,
+    window_duration: u64,
+    pusher: Option<(mpsc::UnboundedSender<String>, JoinHandle<()>)>,
+}
+
+impl PrometheusExporter {
+
+    /**
+    *=================================================================
+    * new()
+    *=================================================================
+    *
+    * Creates an exporter pushing to the pushgateway grouping key
+    * /metrics/job/<job>/<label>/<value>..., values are base64
+    * encoded so they may contain any character. The tags of the run
+    * label every sample next to the target.
+    *
+    *=================================================================
+    * @param config &Prometheus
+    * @param target String
+    * @param tags &Tags
+    * @return Self
+    */
+    pub fn new(config: &Prometheus, target: String, tags: &Tags) -> Self {
+        let mut url = format!(
+            "{}/metrics/job@base64/{}",
+            config.pushgateway.trim_end_matches('/'),
+            URL_SAFE_NO_PAD.encode(&config.job)
+        );
+        for (name, value) in &config.labels {
+            let value = match value.is_empty() {
+                true => "=".to_string(),
+                false => URL_SAFE_NO_PAD.encode(value),
+            };
+            url.push_str(&format!("/{}@base64/{}", name, value));
+        }
+        PrometheusExporter {
+            client: Client::new(),
+            url,
+            labels: tags.iter().fold(format!("target=\"{}\"", ino_escape(&target)), |labels, (key, value)| {
+                format!("{},{}=\"{}\"", labels, key, ino_escape(value))
+            }),
+            window_start: Instant::now(),
+            window_requests: 0,
+            window_errors: 0,
+            window_duration: 0,
+            pusher: None,
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_add_result()
+    *=================================================================
+    *
+    * Accumulates a result in the current one second window and,
+    * once the window is over, queues the push of its aggregates.
+    *
+    *=================================================================
+    * @param result &BenchmarkResult
+    * @return void
+    */
+    pub fn ino_add_result(&mut self, result: &BenchmarkResult) {
+        self.window_requests += 1;
+        self.window_duration += result.duration;
+        if !result.success {
+            self.window_errors += 1;
+        }
+        if self.window_start.elapsed() >= Duration::from_secs(1) {
+            self.ino_push_window();
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_push_window()
+    *=================================================================
+    *
+    * Queues the aggregates of the current window and starts a new
+    * one. A single background task sends the queued windows one
+    * after the other, so the pushgateway receives them in order.
+    *
+    *=================================================================
+    * @return void
+    */
+    fn ino_push_window(&mut self) {
+        let body = self.ino_window_body(self.window_start.elapsed());
+        self.window_start = Instant::now();
+        self.window_requests = 0;
+        self.window_errors = 0;
+        self.window_duration = 0;
+        let (client, url) = (self.client.clone(), self.url.clone());
+        let (tx, _) = self.pusher.get_or_insert_with(|| {
+            let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+            let handle = tokio::spawn(async move {
+                while let Some(body) = rx.recv().await {
+                    if let Err(e) = client.post(&url).body(body).send().await.and_then(|r| r.error_for_status()) {
+                        eprintln!("{} {}", "Pushgateway push failed:".red().bold(), e);
+                    }
+                }
+            });
+            (tx, handle)
+        });
+        tx.send(body).unwrap_or(());
+    }
+
+    /**
+    *=================================================================
+    * ino_window_body()
+    *=================================================================
+    *
+    * Exposition of the aggregates of the current window.
+    *
+    *=================================================================
+    * @param elapsed Duration length of the window
+    * @return String
+    */
+    fn ino_window_body(&self, elapsed: Duration) -> String {
+        let mut body = String::new();
+        let rate = self.window_requests as f64 / elapsed.as_secs_f64();
+        let mean = self.window_duration as f64 / self.window_requests as f64;
+        ino_gauge(&mut body, "inoue_window_requests_per_second", &self.labels, rate);
+        ino_gauge(&mut body, "inoue_window_errors", &self.labels, self.window_errors as f64);
+        ino_gauge(&mut body, "inoue_window_latency_mean_ms", &self.labels, mean);
+        body
+    }
+
+    /**
+    *=================================================================
+    * ino_flush()
+    *=================================================================
+    *
+    * Pushes the last, partial, window when it has results and waits
+    * until every queued window is sent.
+    *
+    *=================================================================
+    * @return void
+    */
+    pub async fn ino_flush(&mut self) {
+        if self.window_requests > 0 {
+            self.ino_push_window();
+        }
+        if let Some((tx, handle)) = self.pusher.take() {
+            drop(tx);
+            handle.await.unwrap_or(());
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_push_summary()
+    *=================================================================
+    *
+    * Pushes the final figures of the run, after the windows still
+    * pending.
+    *
+    *=================================================================
+    * @param summary &Summary
+    * @return Result<()>
+    */
+    pub async fn ino_push_summary(&mut self, summary: &Summary) -> Result<()> {
+        self.ino_flush().await;
+        let body = self.ino_summary_body(summary);
+        let response = self
+            .client
+            .post(&self.url)
+            .body(body)
+            .send()
+            .await
+            .with_context(|| format!("Can not reach pushgateway {}", self.url))?;
+        if !response.status().is_success() {
+            bail!("Pushgateway {} answered {}", self.url, response.status());
+        }
+        Ok(())
+    }
+
+    /**
+    *=================================================================
+    * ino_summary_body()
+    *=================================================================
+    *
+    * Exposition of the final figures of the run.
+    *
+    *=================================================================
+    * @param summary &Summary
+    * @return String
+    */
+    fn ino_summary_body(&self, summary: &Summary) -> String {
+        let mut body = String::new();
+        let labels = &self.labels;
+        ino_gauge(&mut body, "inoue_requests_total", labels, summary.total_requests as f64);
+        ino_gauge(&mut body, "inoue_errors_total", labels, summary.errors as f64);
+        ino_gauge(&mut body, "inoue_clients", labels, summary.clients as f64);
+        ino_gauge(&mut body, "inoue_duration_seconds", labels, summary.elapsed);
+        ino_gauge(&mut body, "inoue_throughput_rps", labels, summary.throughput);
+        ino_gauge(&mut body, "inoue_latency_mean_ms", labels, summary.mean);
+        ino_gauge(&mut body, "inoue_latency_min_ms", labels, summary.min as f64);
+        ino_gauge(&mut body, "inoue_latency_max_ms", labels, summary.max as f64);
+        let _ = writeln!(body, "# TYPE inoue_latency_ms gauge");
+        let _ = writeln!(body, "inoue_latency_ms{{{},quantile=\"0.95\"}} {}", labels, summary.p95);
+        let _ = writeln!(body, "inoue_latency_ms{{{},quantile=\"0.999\"}} {}", labels, summary.p999);
+        body
+    }
+}
+
+/**
+ *=================================================================
+ * ino_gauge()
+ *=================================================================
+ *
+ * Appends a gauge sample in the Prometheus text exposition format.
+ *
+ *=================================================================
+ * @param body &mut String
+ * @param name &str
+ * @param labels &str
+ * @param value f64
+ * @return void
+ */
+fn ino_gauge(body: &mut String, name: &str, labels: &str, value: f64) {
+    let _ = writeln!(body, "# TYPE {} gauge", name);
+    let _ = writeln!(body, "{}{{{}}} {}", name, labels, value);
+}
+
+/**
+ *=================================================================
+ * ino_escape()
+ *=================================================================
+ *
+ * Escapes a label value for the text exposition format.
+ *
+ *=================================================================
+ * @param value &str
+ * @return String
+ */
+fn ino_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use crate::benchmark::Report;
+    use crate::support::Settings;
+
+    #[tokio::test]
+    async fn should_build_grouping_key_url() {
+        let config = Prometheus {
+            pushgateway: "http://localhost:9091/".to_string(),
+            job: "inoue".to_string(),
+            labels: BTreeMap::from([("env".to_string(), "staging/eu".to_string())]),
+        };
+        let tags = Tags::from([("branch".to_string(), "main".to_string())]);
+        let exporter = PrometheusExporter::new(&config, "http://localhost:3000".to_string(), &tags);
+        assert_eq!(
+            "http://localhost:9091/metrics/job@base64/aW5vdWU/env@base64/c3RhZ2luZy9ldQ",
+            exporter.url
+        );
+        assert_eq!(exporter.labels, "target=\"http://localhost:3000\",branch=\"main\"");
+    }
+
+    #[tokio::test]
+    async fn should_expose_the_window_and_summary_gauges() -> Result<()> {
+        let tags = Tags::from([("note".to_string(), "a \"b\" c\\d\ne".to_string())]);
+        let mut exporter = PrometheusExporter::new(&Prometheus::new("http://localhost:9091".to_string()), "http://host/".to_string(), &tags);
+        let labels = "target=\"http://host/\",note=\"a \\\"b\\\" c\\\\d\\ne\"";
+        exporter.window_requests = 10;
+        exporter.window_errors = 2;
+        exporter.window_duration = 50;
+        assert_eq!(
+            exporter.ino_window_body(Duration::from_secs(2)),
+            format!(
+                "# TYPE inoue_window_requests_per_second gauge\ninoue_window_requests_per_second{{{0}}} 5\n\
+                 # TYPE inoue_window_errors gauge\ninoue_window_errors{{{0}}} 2\n\
+                 # TYPE inoue_window_latency_mean_ms gauge\ninoue_window_latency_mean_ms{{{0}}} 5\n",
+                labels
+            )
+        );
+        let settings: Settings = serde_yaml::from_str("clients: 4\nrequests: 10")?;
+        let summary = Report::new(&settings)?.ino_summary(&settings);
+        let body = exporter.ino_summary_body(&summary);
+        assert!(body.starts_with(&format!("# TYPE inoue_requests_total gauge\ninoue_requests_total{{{}}} 0\n", labels)));
+        assert!(body.contains(&format!("\ninoue_clients{{{}}} 4\n", labels)));
+        assert!(body.ends_with(&format!(
+            "# TYPE inoue_latency_ms gauge\ninoue_latency_ms{{{0},quantile=\"0.95\"}} 0\ninoue_latency_ms{{{0},quantile=\"0.999\"}} 0\n",
+            labels
+        )));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_push_the_windows_in_order_and_flush_the_last_one() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let pushgateway = format!("http://{}", listener.local_addr()?);
+        let bodies = Arc::new(Mutex::new(vec![]));
+        let received = bodies.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![];
+                let mut chunk = [0; 4096];
+                while !String::from_utf8_lossy(&request).contains("gauge\ninoue_window_latency_mean_ms") {
+                    let read = stream.read(&mut chunk).await.unwrap_or(0);
+                    if read == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&chunk[..read]);
+                }
+                let request = String::from_utf8_lossy(&request).to_string();
+                received.lock().unwrap().push(request.split("\r\n\r\n").nth(1).unwrap_or_default().to_string());
+                stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n").await.unwrap_or(());
+            }
+        });
+        let mut exporter = PrometheusExporter::new(&Prometheus::new(pushgateway), "t".to_string(), &Tags::new());
+        for errors in 0..3 {
+            exporter.window_requests = 1;
+            exporter.window_errors = errors;
+            exporter.ino_push_window();
+        }
+        exporter.window_requests = 1;
+        exporter.window_errors = 3;
+        exporter.ino_flush().await;
+        let errors: Vec<String> = bodies
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|body| body.lines().find(|line| line.starts_with("inoue_window_errors")).unwrap_or_default().to_string())
+            .collect();
+        assert_eq!(errors, (0..4).map(|n| format!("inoue_window_errors{{target=\"t\"}} {}", n)).collect::<Vec<_>>());
+        Ok(())
+    }
+}
@@ -0,0 +1,430 @@
+use anyhow::{bail, Context, Result};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use tokio::io::{copy_bidirectional, AsyncWriteExt};
+use tokio::net::{lookup_host, TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::support::Settings;
+
+const RELAY_NETWORK: Ipv4Addr = Ipv4Addr::new(127, 128, 0, 0);
+
+const RELAY_ADDRESSES: u32 = (1 << 23) - 2;
+
+const RELAY_ATTEMPTS: usize = 16;
+
+static NEXT_RELAY: AtomicU32 = AtomicU32::new(0);
+
+const V2_SIGNATURE: [u8; 12] = [0x0D, 0x0A, 0x0D, 0x0A, 0x00, 0x0D, 0x0A, 0x51, 0x55, 0x49, 0x54, 0x0A];
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProxyVersion {
+    V1,
+    #[default]
+    V2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SourceRange {
+    pub network: IpAddr,
+    pub prefix: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProxyProtocol {
+    #[serde(default)]
+    pub version: ProxyVersion,
+    #[serde(default)]
+    pub sources: Vec<SourceRange>,
+}
+
+/**
+ *=================================================================
+ * ProxyRelay
+ *=================================================================
+ *
+ * Relays of a client: one loopback listener per target host, the
+ * client connecting to them in place of the host. Every connection
+ * accepted is opened again to the host and starts with the PROXY
+ * header of the client. The listeners stop with the client.
+ *
+ *=================================================================
+ */
+pub struct ProxyRelay {
+    pub resolves: Vec<(String, SocketAddr)>,
+    listeners: Vec<JoinHandle<()>>,
+}
+
+#[derive(Clone)]
+pub struct RelayLayer {
+    relay: Arc<ProxyRelay>,
+}
+
+#[derive(Clone)]
+pub struct RelayService<S> {
+    inner: S,
+    _relay: Arc<ProxyRelay>,
+}
+
+impl TryFrom<String> for SourceRange {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        ino_parse_source(&value)
+    }
+}
+
+impl From<SourceRange> for String {
+    fn from(range: SourceRange) -> Self {
+        format!("{}/{}", range.network, range.prefix)
+    }
+}
+
+impl SourceRange {
+
+    /**
+    *=================================================================
+    * ino_addresses()
+    *=================================================================
+    *
+    * Number of addresses the clients take from the range, the
+    * network and broadcast addresses of an IPv4 network left out.
+    *
+    *=================================================================
+    */
+    fn ino_addresses(&self) -> u128 {
+        let bits = self.ino_bits() - u32::from(self.prefix);
+        let size = 1u128.checked_shl(bits).unwrap_or(u128::MAX);
+        match (self.network, bits) {
+            (IpAddr::V4(_), 2..) => size - 2,
+            _ => size,
+        }
+    }
+
+    fn ino_address(&self, index: u128) -> IpAddr {
+        let offset = match (self.network, self.ino_bits() - u32::from(self.prefix)) {
+            (IpAddr::V4(_), 2..) => index + 1,
+            _ => index,
+        };
+        match self.network {
+            IpAddr::V4(ip) => IpAddr::V4(Ipv4Addr::from(u32::from(ip).wrapping_add(offset as u32))),
+            IpAddr::V6(ip) => IpAddr::V6(Ipv6Addr::from(u128::from(ip).wrapping_add(offset))),
+        }
+    }
+
+    fn ino_bits(&self) -> u32 {
+        match self.network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+}
+
+impl ProxyProtocol {
+
+    /**
+    *=================================================================
+    * ino_source()
+    *=================================================================
+    *
+    * Spoofed address of a client: the clients take the addresses of
+    * the ranges one after the other, starting over once they are
+    * all taken, so a client keeps its address on every connection.
+    * None without ranges, the real address of the connection is
+    * sent then.
+    *
+    *=================================================================
+    * @param num_client usize
+    * @return Option<IpAddr>
+    */
+    pub fn ino_source(&self, num_client: usize) -> Option<IpAddr> {
+        let total = self.sources.iter().fold(0u128, |total, range| total.saturating_add(range.ino_addresses()));
+        if total == 0 {
+            return None;
+        }
+        let mut index = num_client as u128 % total;
+        for range in &self.sources {
+            if index < range.ino_addresses() {
+                return Some(range.ino_address(index));
+            }
+            index -= range.ino_addresses();
+        }
+        None
+    }
+
+    /**
+    *=================================================================
+    * ino_header()
+    *=================================================================
+    *
+    * PROXY header of a TCP connection, the text line of version 1 or
+    * the binary block of version 2. An IPv4 address paired with an
+    * IPv6 one is sent as an IPv4-mapped IPv6 address, both ends of
+    * the header belonging to the same family.
+    *
+    *=================================================================
+    * @param source SocketAddr
+    * @param destination SocketAddr
+    * @return Vec<u8>
+    */
+    pub fn ino_header(&self, source: SocketAddr, destination: SocketAddr) -> Vec<u8> {
+        let (source_ip, destination_ip) = match (source.ip(), destination.ip()) {
+            (IpAddr::V4(s), IpAddr::V6(d)) => (IpAddr::V6(s.to_ipv6_mapped()), IpAddr::V6(d)),
+            (IpAddr::V6(s), IpAddr::V4(d)) => (IpAddr::V6(s), IpAddr::V6(d.to_ipv6_mapped())),
+            (s, d) => (s, d),
+        };
+        match self.version {
+            ProxyVersion::V1 => {
+                let family = if source_ip.is_ipv4() { "TCP4" } else { "TCP6" };
+                format!("PROXY {} {} {} {} {}\r\n", family, source_ip, destination_ip, source.port(), destination.port()).into_bytes()
+            }
+            ProxyVersion::V2 => {
+                let mut header = V2_SIGNATURE.to_vec();
+                let addresses: Vec<u8> = match (source_ip, destination_ip) {
+                    (IpAddr::V4(s), IpAddr::V4(d)) => {
+                        header.extend([0x21, 0x11]);
+                        [s.octets(), d.octets()].concat()
+                    }
+                    (s, d) => {
+                        header.extend([0x21, 0x21]);
+                        [ino_v6(s).octets(), ino_v6(d).octets()].concat()
+                    }
+                };
+                header.extend(((addresses.len() + 4) as u16).to_be_bytes());
+                header.extend(addresses);
+                header.extend(source.port().to_be_bytes());
+                header.extend(destination.port().to_be_bytes());
+                header
+            }
+        }
+    }
+}
+
+impl ProxyRelay {
+
+    /**
+    *=================================================================
+    * ino_start()
+    *=================================================================
+    *
+    * Starts the relays of a client for the hosts of the target and
+    * of the steps. Every host gets an address of its own in
+    * 127.0.0.0/8 for the client to resolve it to, with a listener
+    * on each port of its URLs since the client keeps the port of
+    * the URL. The host is reached through its static address when
+    * the scenario maps it.
+    *
+    *=================================================================
+    * @param settings &Settings
+    * @param proxy &ProxyProtocol
+    * @param num_client usize
+    * @return Result<Arc<ProxyRelay>>
+    */
+    pub fn ino_start(settings: &Settings, proxy: &ProxyProtocol, num_client: usize) -> Result<Arc<Self>> {
+        let source = proxy.ino_source(num_client);
+        let mut hosts: BTreeMap<String, BTreeSet<u16>> = BTreeMap::new();
+        for target in settings.ino_targets() {
+            let Ok(url) = Url::parse(&target) else { continue };
+            let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else { continue };
+            hosts.entry(host.to_ascii_lowercase()).or_default().insert(port);
+        }
+        if hosts.is_empty() {
+            bail!("The PROXY protocol needs a target URL with a host");
+        }
+        let mut relay = ProxyRelay { resolves: vec![], listeners: vec![] };
+        for (host, ports) in hosts {
+            let (address, bound) = ino_bind(&ports)?;
+            for (port, listener) in ports.into_iter().zip(bound) {
+                let upstream = match settings.hosts.get(&host) {
+                    Some(ip) => SocketAddr::new(*ip, port).to_string(),
+                    None => format!("{}:{}", host, port),
+                };
+                relay.listeners.push(tokio::spawn(ino_accept(listener, upstream, proxy.clone(), source)));
+            }
+            relay.resolves.push((host, SocketAddr::new(address, 0)));
+        }
+        Ok(Arc::new(relay))
+    }
+}
+
+impl Drop for ProxyRelay {
+    fn drop(&mut self) {
+        for listener in &self.listeners {
+            listener.abort();
+        }
+    }
+}
+
+impl RelayLayer {
+
+    /**
+    *=================================================================
+    * new()
+    *=================================================================
+    *
+    * Connector layer of the client, leaving the connections as they
+    * are: it only holds the relays, so they live as long as the
+    * client does.
+    *
+    *=================================================================
+    * @param relay Arc<ProxyRelay>
+    * @return Self
+    */
+    pub fn new(relay: Arc<ProxyRelay>) -> Self {
+        RelayLayer { relay }
+    }
+}
+
+impl<S> Layer<S> for RelayLayer {
+    type Service = RelayService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RelayService { inner, _relay: self.relay.clone() }
+    }
+}
+
+impl<S: Service<R>, R> Service<R> for RelayService<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        self.inner.call(request)
+    }
+}
+
+/**
+ *=================================================================
+ * ino_parse_source()
+ *=================================================================
+ *
+ * Parses a spoofed source, an address or a CIDR range, ex.
+ * 203.0.113.7 or 10.1.0.0/16.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<SourceRange>
+ */
+pub fn ino_parse_source(value: &str) -> Result<SourceRange> {
+    let (address, prefix) = match value.trim().split_once('/') {
+        None => (value.trim(), None),
+        Some((address, prefix)) => (address, Some(prefix)),
+    };
+    let network = IpAddr::from_str(address).with_context(|| format!("Invalid source address {}", value))?;
+    let bits = if network.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        None => bits,
+        Some(prefix) => prefix.parse::<u8>().ok().filter(|prefix| *prefix <= bits).with_context(|| format!("Invalid source prefix {}", value))?,
+    };
+    Ok(SourceRange { network, prefix })
+}
+
+/**
+ *=================================================================
+ * ino_bind()
+ *=================================================================
+ *
+ * Binds the ports of a host on the next free loopback address of
+ * the relays, shared by every run of the process.
+ *
+ *=================================================================
+ */
+fn ino_bind(ports: &BTreeSet<u16>) -> Result<(IpAddr, Vec<TcpListener>)> {
+    let mut failure = io::Error::from(io::ErrorKind::AddrInUse);
+    for _ in 0..RELAY_ATTEMPTS {
+        let offset = NEXT_RELAY.fetch_add(1, Ordering::Relaxed) % RELAY_ADDRESSES;
+        let address = IpAddr::V4(Ipv4Addr::from(u32::from(RELAY_NETWORK) + 1 + offset));
+        let bound: io::Result<Vec<TcpListener>> = ports
+            .iter()
+            .map(|port| {
+                let listener = std::net::TcpListener::bind((address, *port))?;
+                listener.set_nonblocking(true)?;
+                TcpListener::from_std(listener)
+            })
+            .collect();
+        match bound {
+            Ok(bound) => return Ok((address, bound)),
+            Err(e) => failure = e,
+        }
+    }
+    Err(failure).with_context(|| format!("Can not open the PROXY protocol relay in {}/9", RELAY_NETWORK))
+}
+
+/**
+ *=================================================================
+ * ino_accept()
+ *=================================================================
+ *
+ * Accepts the connections of the client to a relay and pipes each
+ * one to the host once the PROXY header is sent. The header names
+ * the spoofed address of the client with the local port of the
+ * connection, or the real local address without a spoofed one.
+ *
+ *=================================================================
+ */
+async fn ino_accept(listener: TcpListener, upstream: String, proxy: ProxyProtocol, source: Option<IpAddr>) {
+    while let Ok((mut inbound, _)) = listener.accept().await {
+        let upstream = upstream.clone();
+        let proxy = proxy.clone();
+        tokio::spawn(async move {
+            let Ok(Some(address)) = lookup_host(&upstream).await.map(|mut addresses| addresses.next()) else { return };
+            let Ok(mut outbound) = TcpStream::connect(address).await else { return };
+            let (Ok(local), Ok(peer)) = (outbound.local_addr(), outbound.peer_addr()) else { return };
+            let source = source.map_or(local, |ip| SocketAddr::new(ip, local.port()));
+            inbound.set_nodelay(true).unwrap_or(());
+            outbound.set_nodelay(true).unwrap_or(());
+            if outbound.write_all(&proxy.ino_header(source, peer)).await.is_ok() {
+                copy_bidirectional(&mut inbound, &mut outbound).await.unwrap_or_default();
+            }
+        });
+    }
+}
+
+fn ino_v6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_build_the_proxy_headers_of_the_clients() -> Result<()> {
+        let mut proxy = ProxyProtocol { version: ProxyVersion::V1, sources: vec![ino_parse_source("10.1.0.0/30")?, ino_parse_source("203.0.113.7")?] };
+        let sources: Vec<Option<IpAddr>> = (0..4).map(|n| proxy.ino_source(n)).collect();
+        let ip = |value: &str| IpAddr::from_str(value).ok();
+        assert_eq!(sources, [ip("10.1.0.1"), ip("10.1.0.2"), ip("203.0.113.7"), ip("10.1.0.1")]);
+        let source = SocketAddr::from_str("10.1.0.1:50000")?;
+        let destination = SocketAddr::from_str("192.0.2.10:443")?;
+        assert_eq!(proxy.ino_header(source, destination), b"PROXY TCP4 10.1.0.1 192.0.2.10 50000 443\r\n");
+        proxy.version = ProxyVersion::V2;
+        let header = proxy.ino_header(source, destination);
+        assert_eq!(&header[..16], &[&V2_SIGNATURE[..], &[0x21, 0x11, 0, 12]].concat());
+        assert_eq!(&header[16..], &[10, 1, 0, 1, 192, 0, 2, 10, 0xC3, 0x50, 0x01, 0xBB]);
+        let header = proxy.ino_header(source, SocketAddr::from_str("[2001:db8::1]:443")?);
+        assert_eq!((header[13], header.len()), (0x21, 16 + 36));
+        assert!(ino_parse_source("10.0.0.0/33").is_err() && ino_parse_source("host").is_err());
+        assert_eq!(ProxyProtocol { version: ProxyVersion::V2, sources: vec![] }.ino_source(3), None);
+        Ok(())
+    }
+}
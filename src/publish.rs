@@ -0,0 +1,258 @@
+use anyhow::{bail, Context, Result};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Url};
+use sha2::{Digest, Sha256};
+use std::env;
+
+use crate::benchmark::Summary;
+use crate::history::ino_format_timestamp;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/**
+ *=================================================================
+ * ino_publish()
+ *=================================================================
+ *
+ * Uploads the JSON summary of the run to the given destination:
+ * - s3://bucket/key   signed with the AWS_* environment credentials
+ * - gs://bucket/key   authorized with GOOGLE_OAUTH_ACCESS_TOKEN
+ * - http(s)://...     plain HTTP PUT
+ *
+ * A destination ending with '/' gets a generated file name.
+ *
+ *=================================================================
+ * @param destination &str
+ * @param summary &Summary
+ * @return Result<()>
+ */
+pub async fn ino_publish(destination: &str, summary: &Summary) -> Result<()> {
+    let body = serde_json::to_vec_pretty(summary)?;
+    let destination = match destination.ends_with('/') {
+        true => format!("{}inoue-{}.json", destination, summary.timestamp),
+        false => destination.to_string(),
+    };
+    let client = Client::new();
+    let request = match destination.split_once("://") {
+        Some(("s3", path)) => ino_s3_request(&client, path, &body, summary.timestamp)?,
+        Some(("gs", path)) => ino_gs_request(&client, path)?,
+        Some(("http", _)) | Some(("https", _)) => client.put(&destination),
+        _ => bail!("Unsupported publish destination {}", destination),
+    };
+    let response = request
+        .header("Content-Type", "application/json")
+        .body(body)
+        .send()
+        .await
+        .with_context(|| format!("Failed to publish report to {}", destination))?;
+    if !response.status().is_success() {
+        bail!("Failed to publish report to {}: {}", destination, response.status());
+    }
+    Ok(())
+}
+
+/**
+ *=================================================================
+ * ino_split_bucket()
+ *=================================================================
+ *
+ * Splits "bucket/some/key" into its bucket and object key.
+ *
+ *=================================================================
+ * @param path &str
+ * @return Result<(&str, &str)>
+ */
+fn ino_split_bucket(path: &str) -> Result<(&str, &str)> {
+    match path.split_once('/') {
+        Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() => Ok((bucket, key)),
+        _ => bail!("Publish destination must be in the form bucket/key"),
+    }
+}
+
+/**
+ *=================================================================
+ * ino_gs_request()
+ *=================================================================
+ *
+ * Builds a PUT request against the Google Cloud Storage XML API.
+ *
+ *=================================================================
+ * @param client &Client
+ * @param path &str
+ * @return Result<RequestBuilder>
+ */
+fn ino_gs_request(client: &Client, path: &str) -> Result<reqwest::RequestBuilder> {
+    let (bucket, key) = ino_split_bucket(path)?;
+    let token = env::var("GOOGLE_OAUTH_ACCESS_TOKEN")
+        .with_context(|| "GOOGLE_OAUTH_ACCESS_TOKEN is required to publish to gs://".to_string())?;
+    let url = format!("https://storage.googleapis.com/{}/{}", bucket, ino_uri_encode(key));
+    Ok(client.put(url).bearer_auth(token))
+}
+
+/**
+ *=================================================================
+ * ino_s3_request()
+ *=================================================================
+ *
+ * Builds a PUT request against S3 signed with AWS Signature V4.
+ * Uses AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY, the optional
+ * AWS_SESSION_TOKEN, AWS_REGION (default us-east-1) and the
+ * optional AWS_ENDPOINT_URL for S3 compatible stores.
+ *
+ *=================================================================
+ * @param client &Client
+ * @param path &str
+ * @param body &[u8]
+ * @param timestamp u64
+ * @return Result<RequestBuilder>
+ */
+fn ino_s3_request(client: &Client, path: &str, body: &[u8], timestamp: u64) -> Result<reqwest::RequestBuilder> {
+    let (bucket, key) = ino_split_bucket(path)?;
+    let access_key = env::var("AWS_ACCESS_KEY_ID")
+        .with_context(|| "AWS_ACCESS_KEY_ID is required to publish to s3://".to_string())?;
+    let secret_key = env::var("AWS_SECRET_ACCESS_KEY")
+        .with_context(|| "AWS_SECRET_ACCESS_KEY is required to publish to s3://".to_string())?;
+    let session_token = env::var("AWS_SESSION_TOKEN").ok();
+    let region = env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+
+    let url = match env::var("AWS_ENDPOINT_URL") {
+        Ok(endpoint) => format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, ino_uri_encode(key)),
+        Err(_) => format!("https://{}.s3.{}.amazonaws.com/{}", bucket, region, ino_uri_encode(key)),
+    };
+    let url = Url::parse(&url)?;
+    let host = match url.port() {
+        None => url.host_str().unwrap_or_default().to_string(),
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+    };
+
+    let formatted = ino_format_timestamp(timestamp);
+    let date = formatted[..10].replace('-', "");
+    let amz_date = format!("{}T{}Z", date, formatted[11..].replace(':', ""));
+    let payload_hash = ino_hex(&Sha256::digest(body));
+
+    let mut headers = vec![
+        ("host", host),
+        ("x-amz-content-sha256", payload_hash.clone()),
+        ("x-amz-date", amz_date.clone()),
+    ];
+    if let Some(token) = &session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    let canonical_headers: String = headers.iter().map(|(k, v)| format!("{}:{}\n", k, v)).collect();
+    let signed_headers = headers.iter().map(|(k, _)| *k).collect::<Vec<_>>().join(";");
+    let canonical_request = format!(
+        "PUT\n{}\n\n{}\n{}\n{}",
+        url.path(),
+        canonical_headers,
+        signed_headers,
+        payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        ino_hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signing_key = [region.as_str(), "s3", "aws4_request"]
+        .iter()
+        .fold(ino_hmac(format!("AWS4{}", secret_key).as_bytes(), date.as_bytes()), |key, part| {
+            ino_hmac(&key, part.as_bytes())
+        });
+    let signature = ino_hex(&ino_hmac(&signing_key, string_to_sign.as_bytes()));
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        access_key, scope, signed_headers, signature
+    );
+
+    let mut request = client
+        .put(url)
+        .header("x-amz-content-sha256", payload_hash)
+        .header("x-amz-date", amz_date)
+        .header("Authorization", authorization);
+    if let Some(token) = session_token {
+        request = request.header("x-amz-security-token", token);
+    }
+    Ok(request)
+}
+
+/**
+ *=================================================================
+ * ino_hmac()
+ *=================================================================
+ *
+ * Computes HMAC-SHA256 of the message with the given key.
+ *
+ *=================================================================
+ * @param key &[u8]
+ * @param message &[u8]
+ * @return Vec<u8>
+ */
+pub fn ino_hmac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/**
+ *=================================================================
+ * ino_hex()
+ *=================================================================
+ *
+ * Lowercase hexadecimal encoding of a byte slice.
+ *
+ *=================================================================
+ * @param bytes &[u8]
+ * @return String
+ */
+pub fn ino_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/**
+ *=================================================================
+ * ino_uri_encode()
+ *=================================================================
+ *
+ * Percent-encodes an object key keeping the unreserved characters
+ * and the path separators, as expected by S3 and GCS.
+ *
+ *=================================================================
+ * @param key &str
+ * @return String
+ */
+fn ino_uri_encode(key: &str) -> String {
+    key.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_encode_object_key() {
+        assert_eq!("runs/a%20b%2Bc.json", ino_uri_encode("runs/a b+c.json"));
+    }
+
+    #[test]
+    fn should_compute_hmac_sha256() {
+        // RFC 4231 test case 2
+        let mac = ino_hmac(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ino_hex(&mac)
+        );
+    }
+
+    #[test]
+    fn should_require_bucket_and_key() {
+        assert!(ino_split_bucket("bucket").is_err());
+        assert!(ino_split_bucket("bucket/key").is_ok());
+    }
+}
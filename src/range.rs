@@ -0,0 +1,211 @@
+use anyhow::{bail, Context, Result};
+use rand::rngs::StdRng;
+use rand::Rng;
+use reqwest::header::{ACCEPT_RANGES, CONTENT_LENGTH};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use crate::benchmark::BenchmarkResult;
+use crate::execution::{ino_build_client, ino_build_request};
+use crate::support::{Operation, Settings};
+
+// clear of the streams of the per-client start jitter
+pub const RNG_STREAM: u64 = 1 << 32;
+
+#[derive(Debug)]
+pub struct RangeRequests {
+    object_size: u64,
+    range_size: u64,
+    rng: Mutex<StdRng>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RangeSummary {
+    pub range_size: u64,
+    pub partial: u64,
+    pub full: u64,
+    pub bytes: u64,
+    pub throughput: f64,
+    pub p50_rate: f64,
+    pub p5_rate: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct RangeStats {
+    partial: u64,
+    full: u64,
+    bytes: u64,
+    rates: Vec<f64>,
+}
+
+impl RangeRequests {
+
+    /**
+    *=================================================================
+    * ino_from_settings()
+    *=================================================================
+    *
+    * Reads the size of the target object with a HEAD request. Fails
+    * when the size is unknown or the target refuses ranges.
+    *
+    *=================================================================
+    * @param settings &Settings
+    * @param range_size u64
+    * @return Result<RangeRequests>
+    */
+    pub async fn ino_from_settings(settings: &Settings, range_size: u64) -> Result<Self> {
+        let target = settings.ino_target();
+        let client = ino_build_client(settings)?;
+        let response = ino_build_request(&client, settings, Operation::Head, target.clone())
+            .send()
+            .await
+            .with_context(|| format!("Can not fetch the size of {}", target))?;
+        let header = |name| response.headers().get(name).and_then(|v| v.to_str().ok());
+        if header(ACCEPT_RANGES) == Some("none") {
+            bail!("{} does not accept range requests", target);
+        }
+        let object_size: u64 = header(CONTENT_LENGTH)
+            .and_then(|v| v.parse().ok())
+            .with_context(|| format!("{} did not return its Content-Length, the ranges can not be chosen", target))?;
+        if object_size == 0 || range_size == 0 {
+            bail!("The object {} and the ranges must not be empty", target);
+        }
+        Ok(RangeRequests {
+            object_size,
+            range_size,
+            rng: Mutex::new(settings.ino_rng(RNG_STREAM)),
+        })
+    }
+
+    /**
+    *=================================================================
+    * ino_next()
+    *=================================================================
+    *
+    * Range header value of a random range within the object, the
+    * whole object when it is smaller than a range.
+    *
+    *=================================================================
+    * @param void
+    * @return String
+    */
+    pub fn ino_next(&self) -> String {
+        let length = self.range_size.min(self.object_size);
+        let start = self.rng.lock().unwrap().gen_range(0..=self.object_size - length);
+        format!("bytes={}-{}", start, start + length - 1)
+    }
+}
+
+impl RangeStats {
+
+    /**
+    *=================================================================
+    * ino_add()
+    *=================================================================
+    *
+    * Records a 206 Partial Content or a full 200 OK response, its
+    * bytes and its rate. Other statuses are left out.
+    *
+    *=================================================================
+    * @param result &BenchmarkResult
+    * @return void
+    */
+    pub fn ino_add(&mut self, result: &BenchmarkResult) {
+        match &result.status {
+            s if s.starts_with("206") => self.partial += 1,
+            s if s.starts_with("200") => self.full += 1,
+            _ => return,
+        }
+        let size = result.size.unwrap_or(0);
+        self.bytes += size;
+        self.rates.push(size as f64 * 1000.0 / result.duration.max(1) as f64);
+    }
+
+    /**
+    *=================================================================
+    * ino_summary()
+    *=================================================================
+    *
+    * Counts the 206 Partial Content responses and the full ones of
+    * targets ignoring the Range header, with the bytes received per
+    * second over the run and the rate of a single range: its median
+    * and its 5th percentile, the slow ranges.
+    *
+    *=================================================================
+    * @param range_size u64
+    * @param elapsed f64 seconds
+    * @return RangeSummary
+    */
+    pub fn ino_summary(&self, range_size: u64, elapsed: f64) -> RangeSummary {
+        let mut rates = self.rates.clone();
+        rates.sort_by(f64::total_cmp);
+        let rate = |quantile: f64| match rates.len() {
+            0 => 0.0,
+            len => rates[((len as f64 * quantile).ceil() as usize).clamp(1, len) - 1],
+        };
+        RangeSummary {
+            range_size,
+            partial: self.partial,
+            full: self.full,
+            bytes: self.bytes,
+            throughput: match elapsed > 0.0 {
+                true => self.bytes as f64 / elapsed,
+                false => 0.0,
+            },
+            p50_rate: rate(0.5),
+            p5_rate: rate(0.05),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::ino_result;
+    use rand::SeedableRng;
+
+    #[test]
+    fn should_pick_ranges_within_the_object() {
+        let ranges = RangeRequests {
+            object_size: 1000,
+            range_size: 100,
+            rng: Mutex::new(StdRng::seed_from_u64(1)),
+        };
+        for _ in 0..100 {
+            let range = ranges.ino_next();
+            let (start, end) = range.trim_start_matches("bytes=").split_once('-').unwrap();
+            let (start, end): (u64, u64) = (start.parse().unwrap(), end.parse().unwrap());
+            assert_eq!(end - start + 1, 100);
+            assert!(end < 1000);
+        }
+        let whole = RangeRequests {
+            object_size: 10,
+            range_size: 100,
+            rng: Mutex::new(StdRng::seed_from_u64(1)),
+        };
+        assert_eq!(whole.ino_next(), "bytes=0-9");
+    }
+
+    #[test]
+    fn should_summarize_range_rates() {
+        let result = |status: &str, size, duration| BenchmarkResult {
+            status: status.to_string(),
+            duration,
+            size: Some(size),
+            ..ino_result()
+        };
+        let results = [
+            result("206 Partial Content", 1000, 1),
+            result("206 Partial Content", 1000, 10),
+            result("200 OK", 5000, 5),
+            result("503 Service Unavailable", 0, 1),
+        ];
+        let mut stats = RangeStats::default();
+        results.iter().for_each(|result| stats.ino_add(result));
+        let summary = stats.ino_summary(1000, 2.0);
+        assert_eq!((summary.partial, summary.full, summary.bytes), (2, 1, 7000));
+        assert_eq!(summary.throughput, 3500.0);
+        assert_eq!((summary.p50_rate, summary.p5_rate), (1_000_000.0, 100_000.0));
+    }
+}
@@ -0,0 +1,257 @@
+use anyhow::{bail, Result};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+pub struct RateBudget {
+    interval: Duration,
+    state: Mutex<BudgetState>,
+    notifies: Vec<Notify>,
+}
+
+struct BudgetState {
+    next: Instant,
+    virtual_time: f64,
+    shares: Vec<Share>,
+}
+
+struct Share {
+    weight: f64,
+    waiting: usize,
+    virtual_time: f64,
+}
+
+#[derive(Clone)]
+pub struct RateShare {
+    budget: Arc<RateBudget>,
+    index: usize,
+}
+
+/**
+ *=================================================================
+ * Waiting
+ *=================================================================
+ *
+ * A request of a share waiting for its slot. A request given up
+ * while it waits, on an interrupt or at the end of the run, stops
+ * counting so its share does not keep the others waiting.
+ *
+ *=================================================================
+ */
+struct Waiting<'a> {
+    share: &'a RateShare,
+    served: bool,
+}
+
+impl RateBudget {
+
+    /**
+    *=================================================================
+    * new()
+    *=================================================================
+    *
+    * Creates a rate budget of `rate` requests per second shared by
+    * weighted shares, the client groups of a scenario or a single
+    * run.
+    *
+    *=================================================================
+    * @param rate f64 requests per second
+    * @param weights &[f64]
+    * @return Result<Arc<RateBudget>>
+    */
+    pub fn new(rate: f64, weights: &[f64]) -> Result<Arc<Self>> {
+        if !(rate > 0.0 && rate.is_finite()) {
+            bail!("The rate must be a positive number of requests per second");
+        }
+        if weights.iter().any(|weight| !(*weight > 0.0 && weight.is_finite())) {
+            bail!("The weights of the rate shares must be positive");
+        }
+        Ok(Arc::new(RateBudget {
+            interval: Duration::from_secs_f64(1.0 / rate),
+            state: Mutex::new(BudgetState {
+                next: Instant::now(),
+                virtual_time: 0.0,
+                shares: weights.iter().map(|&weight| Share { weight, waiting: 0, virtual_time: 0.0 }).collect(),
+            }),
+            notifies: weights.iter().map(|_| Notify::new()).collect(),
+        }))
+    }
+
+    /**
+    *=================================================================
+    * ino_share()
+    *=================================================================
+    *
+    * Handle of a share of the budget, cloned by every client of the
+    * share.
+    *
+    *=================================================================
+    * @param index usize
+    * @return RateShare
+    */
+    pub fn ino_share(self: &Arc<Self>, index: usize) -> RateShare {
+        RateShare { budget: self.clone(), index }
+    }
+
+    fn ino_wake_elected(&self, state: &BudgetState) {
+        if let Some(elected) = state.ino_elected() {
+            self.notifies[elected].notify_waiters();
+        }
+    }
+}
+
+impl BudgetState {
+
+    /**
+    *=================================================================
+    * ino_elected()
+    *=================================================================
+    *
+    * Share served by the next slot: among the shares with waiting
+    * requests, the one with the earliest start tag. A share gets
+    * slots in proportion to its weight while the others wait, and
+    * the slots an idle share leaves go to the busy ones, it does
+    * not bank them for later.
+    *
+    *=================================================================
+    */
+    fn ino_elected(&self) -> Option<usize> {
+        self.shares
+            .iter()
+            .enumerate()
+            .filter(|(_, share)| share.waiting > 0)
+            .min_by(|(_, a), (_, b)| self.ino_start_tag(a).total_cmp(&self.ino_start_tag(b)))
+            .map(|(index, _)| index)
+    }
+
+    fn ino_start_tag(&self, share: &Share) -> f64 {
+        share.virtual_time.max(self.virtual_time)
+    }
+
+    fn ino_serve(&mut self, index: usize) {
+        let start = self.ino_start_tag(&self.shares[index]);
+        let share = &mut self.shares[index];
+        share.virtual_time = start + 1.0 / share.weight;
+        share.waiting -= 1;
+        self.virtual_time = start;
+    }
+}
+
+impl RateShare {
+
+    /**
+    *=================================================================
+    * ino_acquire()
+    *=================================================================
+    *
+    * Waits for a slot of the budget: the slots are spaced by the
+    * inverse of the rate, and each one goes to the share elected by
+    * weighted fair sharing. An idle budget does not accumulate
+    * slots, at most one request is sent late. Returns the instant
+    * the slot was due.
+    *
+    *=================================================================
+    * @param void
+    * @return Instant
+    */
+    pub async fn ino_acquire(&self) -> Instant {
+        let budget = &self.budget;
+        let mut waiting = Waiting::new(self);
+        loop {
+            let notified = budget.notifies[self.index].notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            let until = {
+                let mut state = budget.state.lock().unwrap();
+                let now = Instant::now();
+                let elected = state.ino_elected() == Some(self.index);
+                if elected && now >= state.next {
+                    let due = now.checked_sub(budget.interval).map_or(state.next, |late| state.next.max(late));
+                    state.next = due + budget.interval;
+                    state.ino_serve(self.index);
+                    waiting.served = true;
+                    budget.ino_wake_elected(&state);
+                    return due;
+                }
+                elected.then_some(state.next)
+            };
+            match until {
+                None => notified.await,
+                Some(until) => tokio::select! {
+                    _ = tokio::time::sleep_until(until) => {}
+                    _ = notified => {}
+                },
+            }
+        }
+    }
+}
+
+impl<'a> Waiting<'a> {
+    fn new(share: &'a RateShare) -> Self {
+        let mut state = share.budget.state.lock().unwrap();
+        state.shares[share.index].waiting += 1;
+        share.budget.ino_wake_elected(&state);
+        Waiting { share, served: false }
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        if self.served {
+            return;
+        }
+        let budget = &self.share.budget;
+        let mut state = budget.state.lock().unwrap();
+        state.shares[self.share.index].waiting -= 1;
+        budget.ino_wake_elected(&state);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_share_the_slots_by_weight() {
+        let mut state = BudgetState {
+            next: Instant::now(),
+            virtual_time: 0.0,
+            shares: [3.0, 1.0].map(|weight| Share { weight, waiting: 100, virtual_time: 0.0 }).into(),
+        };
+        let mut served = [0, 0];
+        for _ in 0..40 {
+            let elected = state.ino_elected().unwrap_or_default();
+            state.ino_serve(elected);
+            served[elected] += 1;
+        }
+        assert_eq!(served, [30, 10]);
+        state.shares[1].waiting = 0;
+        for _ in 0..10 {
+            assert_eq!(state.ino_elected(), Some(0));
+            state.ino_serve(0);
+        }
+        state.shares[1].waiting = 1;
+        assert_eq!(state.ino_elected(), Some(1));
+        assert!(RateBudget::new(0.0, &[1.0]).is_err() && RateBudget::new(10.0, &[0.0]).is_err());
+    }
+
+    #[tokio::test]
+    async fn should_space_the_slots_by_the_rate() -> Result<()> {
+        let budget = RateBudget::new(200.0, &[1.0, 1.0])?;
+        let start = Instant::now();
+        let clients = (0..4).map(|client| {
+            let share = budget.ino_share(client % 2);
+            tokio::spawn(async move {
+                for _ in 0..5 {
+                    share.ino_acquire().await;
+                }
+            })
+        });
+        futures::future::join_all(clients).await;
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(90) && elapsed < Duration::from_millis(500), "{:?}", elapsed);
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::benchmark::BenchmarkResult;
+
+const RAW_BUFFER: usize = 4096;
+
+#[derive(Debug, Serialize)]
+struct RawRecord<'a> {
+    offset_ms: f64,
+    client: usize,
+    execution: usize,
+    status: &'a str,
+    success: bool,
+    duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reused: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lag_us: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    step: Option<&'a str>,
+}
+
+pub struct RawWriter {
+    tx: mpsc::Sender<String>,
+    handle: JoinHandle<Result<()>>,
+}
+
+impl RawWriter {
+
+    /**
+    *=================================================================
+    * ino_create()
+    *=================================================================
+    *
+    * Creates the NDJSON file and starts the task writing the lines
+    * to it. At most a few thousand lines wait in memory, the run
+    * slows down rather than buffering more when the disk lags.
+    *
+    *=================================================================
+    * @param path &str
+    * @return Result<RawWriter>
+    */
+    pub async fn ino_create(path: &str) -> Result<Self> {
+        let file = File::create(path)
+            .await
+            .with_context(|| format!("Can not create raw results file {}", path))?;
+        let (tx, mut rx) = mpsc::channel::<String>(RAW_BUFFER);
+        let path = path.to_string();
+        let handle = tokio::spawn(async move {
+            let mut writer = BufWriter::new(file);
+            while let Some(line) = rx.recv().await {
+                writer
+                    .write_all(line.as_bytes())
+                    .await
+                    .with_context(|| format!("Can not write raw results to {}", path))?;
+            }
+            writer
+                .flush()
+                .await
+                .with_context(|| format!("Can not write raw results to {}", path))
+        });
+        Ok(RawWriter { tx, handle })
+    }
+
+    /**
+    *=================================================================
+    * ino_write()
+    *=================================================================
+    *
+    * Queues one completed request as a JSON line.
+    *
+    *=================================================================
+    * @param result &BenchmarkResult
+    * @param offset_ms f64 completion time since the start of the run
+    * @return Result<()>
+    */
+    pub async fn ino_write(&self, result: &BenchmarkResult, offset_ms: f64) -> Result<()> {
+        let mut line = serde_json::to_string(&ino_record(result, offset_ms))?;
+        line.push('\n');
+        if self.tx.send(line).await.is_err() {
+            anyhow::bail!("The raw results writer stopped");
+        }
+        Ok(())
+    }
+
+    /**
+    *=================================================================
+    * ino_finish()
+    *=================================================================
+    *
+    * Waits until every queued line is written to the file.
+    *
+    *=================================================================
+    * @param void
+    * @return Result<()>
+    */
+    pub async fn ino_finish(self) -> Result<()> {
+        drop(self.tx);
+        self.handle.await?
+    }
+}
+
+fn ino_record(result: &BenchmarkResult, offset_ms: f64) -> RawRecord<'_> {
+    RawRecord {
+        offset_ms,
+        client: result.num_client,
+        execution: result.execution,
+        status: &result.status,
+        success: result.success,
+        duration_ms: result.duration,
+        size: result.size,
+        reused: result.reused,
+        lag_us: result.lag,
+        step: result.step.as_deref(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::ino_result;
+
+    #[tokio::test]
+    async fn should_write_one_json_object_per_result() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("inoue-raw-{}.ndjson", std::process::id()));
+        let path = path.to_str().unwrap();
+        let writer = RawWriter::ino_create(path).await?;
+        for execution in 0..3 {
+            let result = BenchmarkResult {
+                duration: 12,
+                num_client: 1,
+                execution,
+                size: Some(42),
+                ..ino_result()
+            };
+            writer.ino_write(&result, 1.5).await?;
+        }
+        writer.ino_finish().await?;
+        let content = std::fs::read_to_string(path)?;
+        std::fs::remove_file(path)?;
+        let lines: Vec<serde_json::Value> = content.lines().map(serde_json::from_str).collect::<Result<_, _>>()?;
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[2]["execution"], 2);
+        assert_eq!(lines[0]["size"], 42);
+        assert!(lines[0].get("reused").is_none());
+        Ok(())
+    }
+}
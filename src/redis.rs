@@ -0,0 +1,321 @@
+use anyhow::{bail, Context, Result};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufStream};
+use tokio::net::{lookup_host, TcpStream};
+use tokio::time::{timeout, Instant};
+
+use crate::benchmark::BenchmarkResult;
+use crate::support::Settings;
+
+const REDIS_TIMEOUT: Duration = Duration::from_secs(5);
+
+const REDIS_PORT: u16 = 6379;
+
+#[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct RedisCommands {
+    #[serde(default = "ino_default_commands")]
+    pub commands: Vec<String>,
+    #[serde(default = "ino_default_pipeline")]
+    pub pipeline: usize,
+}
+
+fn ino_default_commands() -> Vec<String> {
+    vec!["PING".to_string()]
+}
+
+fn ino_default_pipeline() -> usize {
+    1
+}
+
+impl Default for RedisCommands {
+    fn default() -> Self {
+        RedisCommands { commands: ino_default_commands(), pipeline: ino_default_pipeline() }
+    }
+}
+
+pub struct Redis {
+    addr: SocketAddr,
+    setup: Vec<Vec<String>>,
+    commands: Vec<Vec<String>>,
+    pipeline: usize,
+    connections: Mutex<HashMap<usize, Vec<BufStream<TcpStream>>>>,
+}
+
+impl Redis {
+
+    /**
+    *=================================================================
+    * ino_from_settings()
+    *=================================================================
+    *
+    * Parses the target "host:port" or
+    * "redis://[[user]:password@]host[:port][/db]" and resolves it
+    * once. The password and the database are sent with AUTH and
+    * SELECT on every new connection.
+    *
+    *=================================================================
+    * @param settings &Settings
+    * @return Result<Redis>
+    */
+    pub async fn ino_from_settings(settings: &Settings) -> Result<Self> {
+        let target = settings.ino_target();
+        let url = match target.contains("://") {
+            true => Url::parse(&target).with_context(|| format!("Invalid redis target {}", target))?,
+            false => Url::parse(&format!("redis://{}", target)).with_context(|| format!("Invalid redis target {}", target))?,
+        };
+        if url.scheme() != "redis" {
+            bail!("Unsupported scheme {} for redis, use redis://", url.scheme());
+        }
+        let host = url.host_str().with_context(|| format!("The redis target {} needs a host", target))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let port = url.port().unwrap_or(REDIS_PORT);
+        let addr = lookup_host((host, port))
+            .await
+            .with_context(|| format!("Can not resolve {}", host))?
+            .next()
+            .with_context(|| format!("No address found for {}", host))?;
+        let mut setup = vec![];
+        if let Some(password) = url.password() {
+            setup.push(match url.username() {
+                "" => vec!["AUTH".to_string(), password.to_string()],
+                user => vec!["AUTH".to_string(), user.to_string(), password.to_string()],
+            });
+        }
+        let db = url.path().trim_matches('/');
+        if !db.is_empty() {
+            db.parse::<u32>().with_context(|| format!("Invalid redis database {}", db))?;
+            setup.push(vec!["SELECT".to_string(), db.to_string()]);
+        }
+        let redis = settings.redis.clone().unwrap_or_default();
+        let commands: Vec<Vec<String>> = redis
+            .commands
+            .iter()
+            .map(|command| command.split_whitespace().map(str::to_string).collect::<Vec<String>>())
+            .filter(|args| !args.is_empty())
+            .collect();
+        if commands.is_empty() || redis.pipeline == 0 {
+            bail!("The redis mode needs at least one command and a pipeline of one command or more");
+        }
+        Ok(Redis { addr, setup, commands, pipeline: redis.pipeline, connections: Mutex::new(HashMap::new()) })
+    }
+
+    /**
+    *=================================================================
+    * ino_exec()
+    *=================================================================
+    *
+    * Sends the commands, repeated by the pipeline, in a single write
+    * and waits for all their replies. Every client keeps its own
+    * connections open between its iterations, a new one is opened
+    * when none is free and measured with the iteration. A connection
+    * that failed is dropped. An error reply fails the iteration and
+    * becomes its status.
+    *
+    *=================================================================
+    * @param num_client usize
+    * @param execution usize
+    * @return BenchmarkResult
+    */
+    pub async fn ino_exec(&self, num_client: usize, execution: usize) -> BenchmarkResult {
+        let pooled = self.connections.lock().unwrap().get_mut(&num_client).and_then(Vec::pop);
+        let reused = pooled.is_some();
+        let begin = Instant::now();
+        let exchange = timeout(REDIS_TIMEOUT, self.ino_exchange(pooled, num_client, execution)).await;
+        let duration = begin.elapsed().as_millis() as u64;
+        let (status, success, size) = match exchange {
+            Err(_) => ("Timeout".to_string(), false, None),
+            Ok(Err(e)) => (format!("Failed: {}", e), false, None),
+            Ok(Ok((stream, size, error))) => {
+                self.connections.lock().unwrap().entry(num_client).or_default().push(stream);
+                match error {
+                    None => ("OK".to_string(), true, Some(size)),
+                    Some(error) => (error, false, Some(size)),
+                }
+            }
+        };
+        BenchmarkResult {
+            size,
+            reused: Some(reused),
+            ..BenchmarkResult::new(status, success, duration, num_client, execution)
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_exchange()
+    *=================================================================
+    *
+    * Writes the pipeline on the connection, a new one without any,
+    * and reads the replies. Returns the connection, the size of the
+    * replies and the first error reply.
+    *
+    *=================================================================
+    */
+    async fn ino_exchange(&self, stream: Option<BufStream<TcpStream>>, num_client: usize, execution: usize) -> Result<(BufStream<TcpStream>, u64, Option<String>)> {
+        let mut stream = match stream {
+            Some(stream) => stream,
+            None => self.ino_connect().await?,
+        };
+        let mut message = vec![];
+        for _ in 0..self.pipeline {
+            for command in &self.commands {
+                let args: Vec<String> = command
+                    .iter()
+                    .map(|arg| arg.replace("{client}", &num_client.to_string()).replace("{iteration}", &execution.to_string()))
+                    .collect();
+                ino_encode_command(&args, &mut message);
+            }
+        }
+        stream.write_all(&message).await?;
+        stream.flush().await?;
+        let (size, error) = ino_read_replies(&mut stream, self.commands.len() * self.pipeline).await?;
+        Ok((stream, size as u64, error))
+    }
+
+    /**
+    *=================================================================
+    * ino_connect()
+    *=================================================================
+    *
+    * Opens a connection and authenticates it and selects the
+    * database when the target asks for it.
+    *
+    *=================================================================
+    */
+    async fn ino_connect(&self) -> Result<BufStream<TcpStream>> {
+        let stream = TcpStream::connect(self.addr).await?;
+        stream.set_nodelay(true)?;
+        let mut stream = BufStream::new(stream);
+        if !self.setup.is_empty() {
+            let mut message = vec![];
+            for command in &self.setup {
+                ino_encode_command(command, &mut message);
+            }
+            stream.write_all(&message).await?;
+            stream.flush().await?;
+            if let (_, Some(error)) = ino_read_replies(&mut stream, self.setup.len()).await? {
+                bail!("{}", error);
+            }
+        }
+        Ok(stream)
+    }
+}
+
+/**
+ *=================================================================
+ * ino_encode_command()
+ *=================================================================
+ *
+ * Appends a command as a RESP array of bulk strings.
+ *
+ *=================================================================
+ */
+fn ino_encode_command(args: &[String], message: &mut Vec<u8>) {
+    message.extend_from_slice(format!("*{}\r\n", args.len()).as_bytes());
+    for arg in args {
+        message.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        message.extend_from_slice(arg.as_bytes());
+        message.extend_from_slice(b"\r\n");
+    }
+}
+
+/**
+ *=================================================================
+ * ino_read_replies()
+ *=================================================================
+ *
+ * Reads from the connection until `count` replies are complete.
+ *
+ *=================================================================
+ */
+async fn ino_read_replies(stream: &mut BufStream<TcpStream>, count: usize) -> Result<(usize, Option<String>)> {
+    let mut buffer = vec![];
+    loop {
+        if let Some(replies) = ino_parse_replies(&buffer, count)? {
+            return Ok(replies);
+        }
+        let chunk = stream.fill_buf().await?;
+        if chunk.is_empty() {
+            bail!("Connection closed");
+        }
+        let read = chunk.len();
+        buffer.extend_from_slice(chunk);
+        stream.consume(read);
+    }
+}
+
+/**
+ *=================================================================
+ * ino_parse_replies()
+ *=================================================================
+ *
+ * Walks `count` RESP replies at the beginning of the buffer, the
+ * elements of the arrays included. Returns their size and the
+ * first error reply, None while they are not complete.
+ *
+ *=================================================================
+ * @param buffer &[u8]
+ * @param count usize
+ * @return Result<Option<(usize, Option<String>)>>
+ */
+fn ino_parse_replies(buffer: &[u8], count: usize) -> Result<Option<(usize, Option<String>)>> {
+    let mut at = 0;
+    let mut pending = count;
+    let mut error = None;
+    while pending > 0 {
+        let Some(end) = buffer[at..].windows(2).position(|w| w == b"\r\n").map(|p| at + p) else { return Ok(None) };
+        let line = &buffer[at..end];
+        at = end + 2;
+        pending -= 1;
+        let length = || -> Result<i64> {
+            let text = String::from_utf8_lossy(&line[1..]);
+            text.parse().with_context(|| format!("Invalid RESP length {}", text))
+        };
+        match line.first() {
+            Some(b'+') | Some(b':') => {}
+            Some(b'-') => {
+                error.get_or_insert_with(|| String::from_utf8_lossy(&line[1..]).into_owned());
+            }
+            Some(b'$') => {
+                if let Ok(length) = usize::try_from(length()?) {
+                    if buffer.len() < at + length + 2 {
+                        return Ok(None);
+                    }
+                    at += length + 2;
+                }
+            }
+            Some(b'*') => pending += usize::try_from(length()?).unwrap_or(0),
+            _ => bail!("Invalid RESP reply {}", String::from_utf8_lossy(line)),
+        }
+    }
+    Ok(Some((at, error)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_encode_commands() {
+        let mut message = vec![];
+        ino_encode_command(&["SET".to_string(), "key".to_string(), "hello".to_string()], &mut message);
+        assert_eq!(message, b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nhello\r\n");
+    }
+
+    #[test]
+    fn should_parse_complete_replies_only() -> Result<()> {
+        let replies = b"+OK\r\n$5\r\nhello\r\n$-1\r\n:42\r\n*2\r\n$1\r\na\r\n*-1\r\n-ERR wrong type\r\n";
+        assert_eq!(ino_parse_replies(replies, 6)?, Some((replies.len(), Some("ERR wrong type".to_string()))));
+        assert_eq!(ino_parse_replies(replies, 2)?, Some((16, None)));
+        assert_eq!(ino_parse_replies(&replies[..10], 2)?, None);
+        assert_eq!(ino_parse_replies(&replies[..45], 6)?, None);
+        assert!(ino_parse_replies(b"?\r\n", 1).is_err());
+        Ok(())
+    }
+}
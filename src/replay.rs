@@ -0,0 +1,480 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Seek};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncSeekExt};
+use tokio::time::Instant;
+
+use crate::support::{Operation, Settings};
+
+pub const RNG_STREAM: u64 = 0;
+
+const STREAM_THRESHOLD: u64 = 64 << 20;
+const STREAM_BUFFER: usize = 1 << 20;
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReplayOrder {
+    #[default]
+    Sequential,
+    Shuffled,
+    WeightedByFrequency,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ReplayEntry {
+    pub operation: Operation,
+    pub url: String,
+    pub offset: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct Replay {
+    entries: Vec<ReplayEntry>,
+    faithful: bool,
+    cursor: AtomicUsize,
+    weights: Option<Mutex<(StdRng, WeightedIndex<usize>)>>,
+    stream: Option<ReplayStream>,
+    start: Instant,
+}
+
+#[derive(Debug)]
+struct ReplayStream {
+    first: ReplayEntry,
+    reader: tokio::sync::Mutex<ListReader>,
+}
+
+#[derive(Debug)]
+struct ListReader {
+    lines: tokio::io::BufReader<tokio::fs::File>,
+    base: Option<String>,
+    number: usize,
+    first_timestamp: Option<f64>,
+    found: bool,
+    wrapped: bool,
+}
+
+impl Replay {
+
+    /**
+    *=================================================================
+    * ino_from_settings()
+    *=================================================================
+    *
+    * Loads the URL list of the settings, if any. Every line is
+    * "[timestamp] [METHOD] URL", blank lines and lines starting with
+    * '#' are ignored. URLs starting with '/' are resolved against
+    * the target. Timestamps (unix seconds, fractions allowed) are
+    * only used for the timestamp faithful replay. Lists larger than
+    * 64 MiB walked in sequence are streamed instead of loaded.
+    *
+    *=================================================================
+    * @param settings &Settings
+    * @return Result<Option<Replay>>
+    */
+    pub fn ino_from_settings(settings: &Settings) -> Result<Option<Self>> {
+        let file = match &settings.url_list {
+            None => return Ok(None),
+            Some(file) => file,
+        };
+        let base = match settings.target.is_empty() {
+            true => None,
+            false => Some(settings.ino_target()),
+        };
+        let size = fs::metadata(file)
+            .with_context(|| format!("Failed to read file from {}", file))?
+            .len();
+        if size > STREAM_THRESHOLD && (settings.order == ReplayOrder::Sequential || settings.replay_timestamps) {
+            return Replay::ino_stream(file, base, settings.replay_timestamps).map(Some);
+        }
+        let content = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read file from {}", file))?;
+        let entries = ino_parse(&content, base.as_deref())?;
+        if entries.is_empty() {
+            bail!("URL list {} is empty", file);
+        }
+        let rng = settings.ino_rng(RNG_STREAM);
+        Ok(Some(Replay::new(entries, settings.order, settings.replay_timestamps, rng)))
+    }
+
+    /**
+    *=================================================================
+    * new()
+    *=================================================================
+    *
+    * Prepares the entries for the requested order. The timestamp
+    * faithful replay always walks the list sequentially.
+    *
+    *=================================================================
+    * @param entries Vec<ReplayEntry>
+    * @param order ReplayOrder
+    * @param faithful bool
+    * @param rng StdRng shuffles or draws the entries
+    * @return Self
+    */
+    pub fn new(mut entries: Vec<ReplayEntry>, order: ReplayOrder, faithful: bool, mut rng: StdRng) -> Self {
+        let order = match faithful {
+            true => ReplayOrder::Sequential,
+            false => order,
+        };
+        let mut weights = None;
+        match order {
+            ReplayOrder::Sequential => {}
+            ReplayOrder::Shuffled => entries.shuffle(&mut rng),
+            ReplayOrder::WeightedByFrequency => {
+                let mut counts: HashMap<(String, String), usize> = HashMap::new();
+                let mut distinct = vec![];
+                for entry in entries {
+                    let key = (entry.operation.to_string(), entry.url.clone());
+                    let count = counts.entry(key).or_insert(0);
+                    if *count == 0 {
+                        distinct.push(entry);
+                    }
+                    *count += 1;
+                }
+                let frequencies: Vec<usize> = distinct
+                    .iter()
+                    .map(|e| counts[&(e.operation.to_string(), e.url.clone())])
+                    .collect();
+                let index = WeightedIndex::new(frequencies).expect("URL list is not empty");
+                weights = Some(Mutex::new((rng, index)));
+                entries = distinct;
+            }
+        }
+        Replay {
+            entries,
+            faithful,
+            cursor: AtomicUsize::new(0),
+            weights,
+            stream: None,
+            start: Instant::now(),
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_stream()
+    *=================================================================
+    *
+    * Walks a URL list in sequence straight from the file, through an
+    * asynchronous buffered reader shared by the clients, so the
+    * memory stays bounded whatever its size and the reads never
+    * block the runtime. Only the lines up to the first entry are
+    * checked up front: a malformed line met later is reported and
+    * skipped instead of failing the run.
+    *
+    *=================================================================
+    * @param file &str
+    * @param base Option<String>
+    * @param faithful bool
+    * @return Result<Replay>
+    */
+    pub fn ino_stream(file: &str, base: Option<String>, faithful: bool) -> Result<Self> {
+        let mut open = File::open(file).with_context(|| format!("Failed to read file from {}", file))?;
+        let mut first = None;
+        let mut first_timestamp = None;
+        for (number, line) in BufReader::new(&open).split(b'\n').enumerate() {
+            let line = line.with_context(|| format!("Failed to read file from {}", file))?;
+            first = ino_parse_line(&String::from_utf8_lossy(&line), number + 1, base.as_deref(), &mut first_timestamp)
+                .with_context(|| format!("Failed to read file from {}", file))?;
+            if first.is_some() {
+                break;
+            }
+        }
+        let first = first.with_context(|| format!("URL list {} is empty", file))?;
+        open.rewind()?;
+        let reader = ListReader {
+            lines: tokio::io::BufReader::with_capacity(STREAM_BUFFER, tokio::fs::File::from_std(open)),
+            base,
+            number: 0,
+            first_timestamp: None,
+            found: false,
+            wrapped: false,
+        };
+        Ok(Replay {
+            entries: vec![],
+            faithful,
+            cursor: AtomicUsize::new(0),
+            weights: None,
+            stream: Some(ReplayStream { first, reader: tokio::sync::Mutex::new(reader) }),
+            start: Instant::now(),
+        })
+    }
+
+    /**
+    *=================================================================
+    * ino_first()
+    *=================================================================
+    *
+    * First entry of the list, without moving the shared cursor.
+    *
+    *=================================================================
+    * @param void
+    * @return Option<&ReplayEntry>
+    */
+    pub fn ino_first(&self) -> Option<&ReplayEntry> {
+        match &self.stream {
+            None => self.entries.first(),
+            Some(stream) => Some(&stream.first),
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_next()
+    *=================================================================
+    *
+    * Returns the next entry to request, shared across all clients.
+    * The list wraps around, except in timestamp faithful replay
+    * where None is returned once every entry was dispatched. In
+    * that mode the call waits until the original offset of the
+    * entry is reached.
+    *
+    *=================================================================
+    * @param void
+    * @return Option<ReplayEntry>
+    */
+    pub async fn ino_next(&self) -> Option<ReplayEntry> {
+        if let Some(weights) = &self.weights {
+            let mut guard = weights.lock().unwrap_or_else(PoisonError::into_inner);
+            let (rng, index) = &mut *guard;
+            return self.entries.get(index.sample(rng)).cloned();
+        }
+        let entry = match &self.stream {
+            Some(stream) => {
+                let read = stream.reader.lock().await.ino_read(!self.faithful).await;
+                match read {
+                    Ok(entry) => entry?,
+                    Err(e) => {
+                        eprintln!("{} {:#}", "URL list read failed:".red().bold(), e);
+                        return None;
+                    }
+                }
+            }
+            None => {
+                let position = self.cursor.fetch_add(1, Ordering::Relaxed);
+                if !self.faithful {
+                    return self.entries.get(position % self.entries.len()).cloned();
+                }
+                self.entries.get(position)?.clone()
+            }
+        };
+        if !self.faithful {
+            return Some(entry);
+        }
+        if let Some(offset) = entry.offset {
+            tokio::time::sleep_until(self.start + offset).await;
+        }
+        Some(entry)
+    }
+}
+
+impl ListReader {
+
+    /**
+    *=================================================================
+    * ino_read()
+    *=================================================================
+    *
+    * Reads the next entry of a streamed list. At the end of the file
+    * the list starts over when it wraps, None is returned otherwise.
+    * Malformed lines are skipped, reported on the first pass only.
+    *
+    *=================================================================
+    * @param wrap bool
+    * @return Result<Option<ReplayEntry>>
+    */
+    async fn ino_read(&mut self, wrap: bool) -> Result<Option<ReplayEntry>> {
+        let mut line = vec![];
+        loop {
+            line.clear();
+            if self.lines.read_until(b'\n', &mut line).await? == 0 {
+                if !wrap || !self.found {
+                    return Ok(None);
+                }
+                self.ino_rewind().await?;
+                self.wrapped = true;
+                continue;
+            }
+            self.number += 1;
+            let parsed = ino_parse_line(&String::from_utf8_lossy(&line), self.number, self.base.as_deref(), &mut self.first_timestamp);
+            match parsed {
+                Ok(None) => {}
+                Ok(Some(entry)) => {
+                    self.found = true;
+                    return Ok(Some(entry));
+                }
+                Err(e) if !self.wrapped => eprintln!("{}", format!("{:#}, skipped", e).yellow()),
+                Err(_) => {}
+            }
+        }
+    }
+
+    async fn ino_rewind(&mut self) -> Result<()> {
+        self.lines.rewind().await?;
+        self.number = 0;
+        self.first_timestamp = None;
+        self.found = false;
+        Ok(())
+    }
+}
+
+/**
+ *=================================================================
+ * ino_parse()
+ *=================================================================
+ *
+ * Parses the content of a URL list. Offsets are computed relative
+ * to the first timestamp of the list.
+ *
+ *=================================================================
+ * @param content &str
+ * @param base Option<&str>
+ * @return Result<Vec<ReplayEntry>>
+ */
+pub fn ino_parse(content: &str, base: Option<&str>) -> Result<Vec<ReplayEntry>> {
+    let mut entries = vec![];
+    let mut first_timestamp: Option<f64> = None;
+    for (number, line) in content.lines().enumerate() {
+        if let Some(entry) = ino_parse_line(line, number + 1, base, &mut first_timestamp)? {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/**
+ *=================================================================
+ * ino_parse_line()
+ *=================================================================
+ *
+ * Parses a line of a URL list, None for blank lines and comments.
+ *
+ *=================================================================
+ */
+fn ino_parse_line(line: &str, number: usize, base: Option<&str>, first_timestamp: &mut Option<f64>) -> Result<Option<ReplayEntry>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let mut tokens: Vec<&str> = line.split_whitespace().collect();
+    let offset = match tokens.first().and_then(|t| t.parse::<f64>().ok()) {
+        None => None,
+        Some(timestamp) => {
+            tokens.remove(0);
+            let first = *first_timestamp.get_or_insert(timestamp);
+            Some(Duration::from_secs_f64((timestamp - first).max(0.0)))
+        }
+    };
+    let (operation, url) = match tokens.as_slice() {
+        [url] => (Operation::Get, *url),
+        [operation, url] => (
+            Operation::from_str(&operation.to_uppercase())
+                .with_context(|| format!("Unknown method {} on line {}", operation, number))?,
+            *url,
+        ),
+        _ => bail!("Line {} of the URL list is not well formatted", number),
+    };
+    let url = match (url.starts_with('/'), base) {
+        (true, Some(base)) => format!("{}{}", base.trim_end_matches('/'), url),
+        (true, None) => bail!("Line {} is a relative URL but no target was given", number),
+        (false, _) => url.to_string(),
+    };
+    Ok(Some(ReplayEntry { operation, url, offset }))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_url_list() -> Result<()> {
+        let content = "# comment\n\
+            100.0 GET /a\n\
+            \n\
+            100.5 post http://other/b\n";
+        let entries = ino_parse(content, Some("http://localhost:3000/"))?;
+        assert_eq!(
+            entries,
+            vec![
+                ReplayEntry {
+                    operation: Operation::Get,
+                    url: "http://localhost:3000/a".to_string(),
+                    offset: Some(Duration::ZERO),
+                },
+                ReplayEntry {
+                    operation: Operation::Post,
+                    url: "http://other/b".to_string(),
+                    offset: Some(Duration::from_millis(500)),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn should_return_error_on_relative_url_without_target() {
+        assert!(ino_parse("/a", None).is_err());
+    }
+
+    #[tokio::test]
+    async fn should_wrap_around_sequential_list() -> Result<()> {
+        let replay = Replay::new(ino_parse("http://a\nhttp://b", None)?, ReplayOrder::Sequential, false, StdRng::seed_from_u64(1));
+        let mut urls = vec![];
+        for _ in 0..3 {
+            urls.push(replay.ino_next().await.unwrap().url);
+        }
+        assert_eq!(urls, vec!["http://a", "http://b", "http://a"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_keep_only_distinct_entries_when_weighted() -> Result<()> {
+        let replay = Replay::new(
+            ino_parse("http://a\nhttp://a\nhttp://b", None)?,
+            ReplayOrder::WeightedByFrequency,
+            false,
+            StdRng::seed_from_u64(1),
+        );
+        assert_eq!(replay.entries.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn should_shuffle_identically_with_same_seed() -> Result<()> {
+        let content = (0..20).map(|i| format!("http://host/{}", i)).collect::<Vec<_>>().join("\n");
+        let first = Replay::new(ino_parse(&content, None)?, ReplayOrder::Shuffled, false, StdRng::seed_from_u64(7));
+        let second = Replay::new(ino_parse(&content, None)?, ReplayOrder::Shuffled, false, StdRng::seed_from_u64(7));
+        assert_eq!(first.entries, second.entries);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_stream_the_list_from_the_file() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("inoue-replay-{}.txt", std::process::id()));
+        fs::write(&path, "# access log\n\nGET /a\nGET /c extra\n/b\n")?;
+        let replay = Replay::ino_stream(path.to_str().unwrap(), Some("http://host".to_string()), false)?;
+        assert_eq!(replay.ino_first().map(|e| e.url.as_str()), Some("http://host/a"));
+        let mut urls = vec![];
+        for _ in 0..5 {
+            urls.push(replay.ino_next().await.unwrap().url);
+        }
+        assert_eq!(urls, ["http://host/a", "http://host/b", "http://host/a", "http://host/b", "http://host/a"]);
+        let faithful = Replay::ino_stream(path.to_str().unwrap(), Some("http://host".to_string()), true)?;
+        assert!(faithful.ino_next().await.is_some() && faithful.ino_next().await.is_some());
+        assert!(faithful.ino_next().await.is_none());
+        fs::write(&path, "GET /c extra\n/b\n")?;
+        assert!(Replay::ino_stream(path.to_str().unwrap(), None, false).is_err());
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+const SCRAPE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceSample {
+    pub offset: f64,
+    pub cpu_percent: Option<f64>,
+    pub memory_bytes: Option<u64>,
+    #[serde(default)]
+    pub requests: u64,
+    #[serde(default)]
+    pub mean_latency: Option<f64>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Scrape {
+    cpu_total: Option<f64>,
+    cpu_idle: Option<f64>,
+    process_cpu: Option<f64>,
+    memory_total: Option<f64>,
+    memory_available: Option<f64>,
+    process_memory: Option<f64>,
+}
+
+pub struct ResourceMonitor {
+    stop: oneshot::Sender<()>,
+    handle: JoinHandle<Vec<ResourceSample>>,
+}
+
+impl ResourceMonitor {
+
+    /**
+    *=================================================================
+    * ino_start()
+    *=================================================================
+    *
+    * Starts scraping the Prometheus endpoint of the target every
+    * second. Failed scrapes are skipped, the run goes on.
+    *
+    *=================================================================
+    * @param url String
+    * @param start Instant offsets of the samples are relative to it
+    * @return ResourceMonitor
+    */
+    pub fn ino_start(url: String, start: Instant) -> Self {
+        let (stop, mut stopped) = oneshot::channel();
+        let handle = tokio::spawn(async move {
+            let client = Client::new();
+            let mut samples = vec![];
+            let mut previous: Option<(Instant, Scrape)> = None;
+            let mut interval = tokio::time::interval(SCRAPE_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = &mut stopped => break,
+                }
+                let Ok(scrape) = ino_scrape(&client, &url).await else { continue };
+                let now = Instant::now();
+                if let Some((at, before)) = previous {
+                    samples.push(ino_sample(&before, &scrape, (now - at).as_secs_f64(), (now - start).as_secs_f64()));
+                }
+                previous = Some((now, scrape));
+            }
+            samples
+        });
+        ResourceMonitor { stop, handle }
+    }
+
+    /**
+    *=================================================================
+    * ino_finish()
+    *=================================================================
+    *
+    * Stops the scraping and returns the samples.
+    *
+    *=================================================================
+    * @param void
+    * @return Vec<ResourceSample>
+    */
+    pub async fn ino_finish(self) -> Vec<ResourceSample> {
+        self.stop.send(()).unwrap_or(());
+        self.handle.await.unwrap_or_default()
+    }
+}
+
+/**
+ *=================================================================
+ * ino_scrape()
+ *=================================================================
+ *
+ * Fetches and parses the metrics of the endpoint.
+ *
+ *=================================================================
+ */
+async fn ino_scrape(client: &Client, url: &str) -> Result<Scrape> {
+    let body = client
+        .get(url)
+        .timeout(SCRAPE_INTERVAL)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await
+        .with_context(|| format!("Can not read the metrics of {}", url))?;
+    Ok(ino_parse(&body))
+}
+
+/**
+ *=================================================================
+ * ino_parse()
+ *=================================================================
+ *
+ * Extracts the CPU and memory metrics from the Prometheus text
+ * format: node-exporter metrics of the host, or the standard
+ * process metrics exposed by most Prometheus clients.
+ *
+ *=================================================================
+ */
+fn ino_parse(body: &str) -> Scrape {
+    let mut scrape = Scrape::default();
+    let add = |slot: &mut Option<f64>, value: f64| *slot = Some(slot.unwrap_or(0.0) + value);
+    for line in body.lines().filter(|l| !l.starts_with('#')) {
+        let line = line.trim();
+        // "name{labels} value [timestamp]" or "name value [timestamp]"
+        let split = match line.find('}') {
+            Some(end) => Some(line.split_at(end + 1)),
+            None => line.split_once(' '),
+        };
+        let Some((series, rest)) = split else { continue };
+        let Some(Ok(value)) = rest.split_whitespace().next().map(str::parse::<f64>) else { continue };
+        let (name, labels) = series.split_once('{').unwrap_or((series, ""));
+        match name.trim() {
+            "node_cpu_seconds_total" => {
+                add(&mut scrape.cpu_total, value);
+                if labels.contains("mode=\"idle\"") {
+                    add(&mut scrape.cpu_idle, value);
+                }
+            }
+            "node_memory_MemTotal_bytes" => scrape.memory_total = Some(value),
+            "node_memory_MemAvailable_bytes" => scrape.memory_available = Some(value),
+            "process_cpu_seconds_total" => scrape.process_cpu = Some(value),
+            "process_resident_memory_bytes" => scrape.process_memory = Some(value),
+            _ => {}
+        }
+    }
+    scrape
+}
+
+/**
+ *=================================================================
+ * ino_sample()
+ *=================================================================
+ *
+ * Turns two consecutive scrapes into a sample: the host CPU usage
+ * over all its cores and its used memory, or the CPU usage (100%
+ * is one core) and resident memory of the process.
+ *
+ *=================================================================
+ */
+fn ino_sample(before: &Scrape, after: &Scrape, seconds: f64, offset: f64) -> ResourceSample {
+    let delta = |a: Option<f64>, b: Option<f64>| Some(b? - a?);
+    let cpu_percent = match (delta(before.cpu_total, after.cpu_total), delta(before.cpu_idle, after.cpu_idle)) {
+        (Some(total), Some(idle)) if total > 0.0 => Some((1.0 - idle / total) * 100.0),
+        _ => delta(before.process_cpu, after.process_cpu)
+            .filter(|_| seconds > 0.0)
+            .map(|cpu| cpu / seconds * 100.0),
+    };
+    let memory_bytes = match (after.memory_total, after.memory_available) {
+        (Some(total), Some(available)) => Some((total - available) as u64),
+        _ => after.process_memory.map(|m| m as u64),
+    };
+    ResourceSample {
+        offset,
+        cpu_percent,
+        memory_bytes,
+        requests: 0,
+        mean_latency: None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_compute_host_usage_from_node_exporter() {
+        let before = ino_parse(
+            "# HELP node_cpu_seconds_total Seconds the CPUs spent in each mode.\n\
+             node_cpu_seconds_total{cpu=\"0\",mode=\"idle\"} 100\n\
+             node_cpu_seconds_total{cpu=\"0\",mode=\"user\"} 50\n\
+             node_memory_MemTotal_bytes 1000\n\
+             node_memory_MemAvailable_bytes 800\n",
+        );
+        let after = ino_parse(
+            "node_cpu_seconds_total{cpu=\"0\",mode=\"idle\"} 100.25\n\
+             node_cpu_seconds_total{cpu=\"0\",mode=\"user\"} 51.0 1700000000000\n\
+             node_memory_MemTotal_bytes 1e3\n\
+             node_memory_MemAvailable_bytes 600\n",
+        );
+        let sample = ino_sample(&before, &after, 1.0, 2.0);
+        assert_eq!(sample.cpu_percent, Some(80.0));
+        assert_eq!(sample.memory_bytes, Some(400));
+    }
+
+    #[test]
+    fn should_fall_back_to_process_metrics() {
+        let before = ino_parse("process_cpu_seconds_total 10\nprocess_resident_memory_bytes 2048\n");
+        let after = ino_parse("process_cpu_seconds_total 10.5\nprocess_resident_memory_bytes 4096\n");
+        let sample = ino_sample(&before, &after, 2.0, 3.0);
+        assert_eq!(sample.cpu_percent, Some(25.0));
+        assert_eq!(sample.memory_bytes, Some(4096));
+    }
+}
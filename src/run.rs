@@ -0,0 +1,392 @@
+use anyhow::{bail, Context, Result};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::time::Duration;
+use tokio::runtime::{self, Handle, Runtime};
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+
+use inoue::schema::{ExitSummary, RunSummary};
+
+use crate::bands::ino_verbose_line;
+use crate::benchmark::{BenchmarkResult, Report, Summary};
+use crate::control::{self, Command as ControlCommand, RunControl};
+use crate::execution::ino_run;
+use crate::monitor::{self, GeneratorSummary};
+use crate::prometheus::PrometheusExporter;
+use crate::rate::RateShare;
+use crate::raw::RawWriter;
+use crate::stable::StabilityTracker;
+use crate::resources::ResourceMonitor;
+use crate::stages::ConnectionPool;
+use crate::support::Settings;
+use crate::{history, notify, publish, threshold};
+
+const RESULTS_BUFFER: usize = 1024;
+
+const PROGRESS_REFRESH: Duration = Duration::from_millis(250);
+
+const STABLE_SAMPLE: Duration = Duration::from_secs(1);
+
+const PROGRESS_TEMPLATE: &str = "{prefix:>8} [{elapsed_precise}] [{wide_bar}] {pos}/{len} {msg}";
+
+/**
+ *=================================================================
+ * ino_progress_bar()
+ *=================================================================
+ *
+ * Adds the progress bar of a run or of a stage to the display, the
+ * bar is named and shows the requests per second and the errors of
+ * its run.
+ *
+ *=================================================================
+ * @param multi &MultiProgress
+ * @param name &str
+ * @param settings &Settings
+ * @return ProgressBar
+ */
+pub fn ino_progress_bar(multi: &MultiProgress, name: &str, settings: &Settings) -> ProgressBar {
+    let pb = multi.add(ProgressBar::new(settings.requests as u64));
+    pb.set_style(
+        ProgressStyle::with_template(PROGRESS_TEMPLATE)
+            .expect("the progress template is valid")
+            .progress_chars("=> "),
+    );
+    pb.set_prefix(name.to_string());
+    pb
+}
+
+/**
+ *=================================================================
+ * ino_benchmark()
+ *=================================================================
+ *
+ * Runs the benchmark of the settings once and collects its results
+ * in a report, handling the progress bar, the verbose output, the
+ * per-second exports, the raw results file and the interactive
+ * commands. The clients are taken from the pool when there is one,
+ * and the requests from the rate share when there is one. A run
+ * by duration with a stop condition ends as soon as its metric is
+ * stable. With the accounting audit, the results received are
+ * checked against the iterations dispatched once the run is over.
+ *
+ *=================================================================
+ * @param settings &Settings
+ * @param rx_sigint watch::Receiver<Option<()>>
+ * @param pb ProgressBar
+ * @param pool Option<&ConnectionPool>
+ * @param rate Option<RateShare>
+ * @return Result<Report>
+ */
+pub async fn ino_benchmark(
+    settings: &Settings,
+    rx_sigint: watch::Receiver<Option<()>>,
+    pb: ProgressBar,
+    pool: Option<&ConnectionPool>,
+    rate: Option<RateShare>,
+) -> Result<Report> {
+    let mut report = Report::new(settings)?;
+    let (benchmark_tx, mut benchmark_rx) = mpsc::channel(settings.requests.max(RESULTS_BUFFER));
+    let cpu_start = monitor::ino_cpu_time();
+    let resources = settings
+        .monitor
+        .clone()
+        .map(|url| ResourceMonitor::ino_start(url, Instant::now() - report.ino_elapsed()));
+    let mut exporter = settings
+        .prometheus
+        .as_ref()
+        .map(|p| PrometheusExporter::new(p, settings.ino_name(), &settings.tags));
+    let raw = match &settings.raw_out {
+        None => None,
+        Some(path) => Some(RawWriter::ino_create(path).await?),
+    };
+    if settings.stop_on_stable.is_some() && settings.duration.is_none() {
+        bail!("--stop-on-stable ends a run by duration, set a duration");
+    }
+    let (tx_stop, rx_stop) = ino_stop_channel(rx_sigint);
+    let mut run_control = ino_run(settings.clone(), benchmark_tx, rx_stop, pool, rate).await?;
+    let mut commands = control::ino_commands(settings.interactive);
+    let (mut received, mut errors, mut refreshed) = (0u64, 0u64, Instant::now());
+    let mut tracker = settings.stop_on_stable.map(StabilityTracker::new);
+    let mut sampled = Instant::now();
+    loop {
+        tokio::select! {
+            value = benchmark_rx.recv() => {
+                let Some(value) = value else { break };
+                received += 1;
+                errors += u64::from(value.ino_is_error());
+                if !settings.verbose {
+                    pb.inc(1);
+                }
+                if refreshed.elapsed() >= PROGRESS_REFRESH {
+                    refreshed = Instant::now();
+                    ino_progress_message(&pb, &report, errors);
+                }
+                if let Some(exporter) = &mut exporter {
+                    exporter.ino_add_result(&value);
+                }
+                ino_record(&mut report, raw.as_ref(), value, settings).await?;
+                if let (Some(tracker), Some(stop)) = (&mut tracker, settings.stop_on_stable) {
+                    if report.stable.is_none() && sampled.elapsed() >= STABLE_SAMPLE {
+                        sampled = Instant::now();
+                        let elapsed = report.ino_elapsed();
+                        if report.ino_metric(stop.metric).is_some_and(|metric| tracker.ino_sample(elapsed, metric)) {
+                            report.stable = Some((stop, elapsed));
+                            tx_stop.send(Some(())).unwrap_or(());
+                        }
+                    }
+                }
+            }
+            Some(command) = commands.recv() => {
+                match command {
+                    ControlCommand::AddClient => run_control.ino_add_client()?,
+                    ControlCommand::RemoveClient => run_control.ino_remove_client(),
+                    ControlCommand::TogglePause => run_control.ino_toggle_pause(),
+                    ControlCommand::Summary => {}
+                }
+                let state = run_control.ino_state();
+                let summary = report.ino_summary(settings);
+                pb.suspend(|| {
+                    eprintln!(
+                        "[{} clients{}] {}",
+                        state.active_clients,
+                        if state.paused { ", paused" } else { "" },
+                        summary
+                    )
+                });
+            }
+        }
+    }
+    ino_progress_message(&pb, &report, errors);
+    if let Some(exporter) = &mut exporter {
+        exporter.ino_flush().await;
+    }
+    if let Some(raw) = raw {
+        raw.ino_finish().await?;
+    }
+    report.generator = ino_generator_summary(&report, &run_control, cpu_start);
+    if settings.audit_accounting {
+        report.accounting = Some(run_control.ino_accounting(received - errors, errors));
+    }
+    if let Some(resources) = resources {
+        report.ino_set_resources(resources.ino_finish().await);
+    }
+    Ok(report)
+}
+
+/**
+ *=================================================================
+ * ino_record()
+ *=================================================================
+ *
+ * Adds a result to the report, and when the result is kept in full
+ * by the sample rate, writes it to the raw results file and to the
+ * verbose output.
+ *
+ *=================================================================
+ * @param report &mut Report
+ * @param raw Option<&RawWriter>
+ * @param value BenchmarkResult
+ * @param settings &Settings
+ * @return Result<()>
+ */
+async fn ino_record(report: &mut Report, raw: Option<&RawWriter>, value: BenchmarkResult, settings: &Settings) -> Result<()> {
+    let offset_ms = report.ino_elapsed().as_micros() as f64 / 1000.0;
+    if let Some(value) = report.ino_add_result(value) {
+        if let Some(raw) = raw {
+            raw.ino_write(value, offset_ms).await?;
+        }
+        if settings.verbose {
+            println!("{}", ino_verbose_line(value, settings.latency_bands));
+        }
+    }
+    Ok(())
+}
+
+/**
+ *=================================================================
+ * ino_stop_channel()
+ *=================================================================
+ *
+ * Stop signal of a single run: set on an interrupt, or by the run
+ * itself once its results are stable. Stopping one run leaves the
+ * interrupt of the others untouched.
+ *
+ *=================================================================
+ */
+fn ino_stop_channel(mut rx_sigint: watch::Receiver<Option<()>>) -> (watch::Sender<Option<()>>, watch::Receiver<Option<()>>) {
+    let (tx_stop, rx_stop) = watch::channel(rx_sigint.borrow().as_ref().copied());
+    let forward = tx_stop.clone();
+    tokio::spawn(async move {
+        tokio::select! {
+            Ok(()) = rx_sigint.changed() => forward.send(Some(())).unwrap_or(()),
+            _ = forward.closed() => {}
+        }
+    });
+    (tx_stop, rx_stop)
+}
+
+/**
+ *=================================================================
+ * ino_progress_message()
+ *=================================================================
+ */
+fn ino_progress_message(pb: &ProgressBar, report: &Report, errors: u64) {
+    let elapsed = report.ino_elapsed().as_secs_f64();
+    let rps = match elapsed > 0.0 {
+        true => pb.position() as f64 / elapsed,
+        false => 0.0,
+    };
+    pb.set_message(format!("{:.0} rps, {} errors", rps, errors));
+}
+
+/**
+ *=================================================================
+ * ino_generator_summary()
+ *=================================================================
+ *
+ * Resources used by inoue itself during the run.
+ *
+ *=================================================================
+ */
+fn ino_generator_summary(report: &Report, run_control: &RunControl, cpu_start: Option<Duration>) -> Option<GeneratorSummary> {
+    let cpu = monitor::ino_cpu_time()? - cpu_start?;
+    let elapsed = report.ino_elapsed().as_secs_f64();
+    let (blocked_sends, dropped_sends) = run_control.ino_send_stats();
+    Some(GeneratorSummary {
+        workers: Handle::current().metrics().num_workers(),
+        cpu_seconds: cpu.as_secs_f64(),
+        cpu_percent: match elapsed > 0.0 {
+            true => cpu.as_secs_f64() / elapsed * 100.0,
+            false => 0.0,
+        },
+        max_rss: monitor::ino_max_rss().unwrap_or(0),
+        blocked_sends,
+        dropped_sends,
+    })
+}
+
+/**
+ *=================================================================
+ * ino_runtime()
+ *=================================================================
+ *
+ * Builds the Tokio runtime, with the configured number of worker
+ * threads or one per core.
+ *
+ *=================================================================
+ * @param workers Option<usize>
+ * @return Result<Runtime>
+ */
+pub fn ino_runtime(workers: Option<usize>) -> Result<Runtime> {
+    let mut builder = runtime::Builder::new_multi_thread();
+    if let Some(workers) = workers {
+        builder.worker_threads(workers.max(1));
+    }
+    builder
+        .enable_all()
+        .build()
+        .with_context(|| "Can not start the Tokio runtime".to_string())
+}
+
+/**
+ *=================================================================
+ * ino_verdict()
+ *=================================================================
+ *
+ * Evaluates the thresholds of the settings against the summary.
+ *
+ *=================================================================
+ * @param settings &Settings
+ * @param summary &Summary
+ * @param show bool prints the verdict
+ * @return Vec<String> failed thresholds
+ */
+pub fn ino_verdict(settings: &Settings, summary: &Summary, show: bool) -> Vec<String> {
+    match &settings.thresholds {
+        None => vec![],
+        Some(thresholds) => {
+            let failures = thresholds.ino_evaluate(summary);
+            if show {
+                threshold::ino_show_verdict(&failures, summary.score.as_ref());
+            }
+            failures
+        }
+    }
+}
+
+/**
+ *=================================================================
+ * ino_print_exit_summary()
+ *=================================================================
+ *
+ * Prints the JSON exit summary of the runs on stdout.
+ *
+ *=================================================================
+ * @param runs Vec<RunSummary>
+ * @return Result<()>
+ */
+pub fn ino_print_exit_summary(runs: Vec<RunSummary>) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&ExitSummary::new(runs))?);
+    Ok(())
+}
+
+/**
+ *=================================================================
+ * ino_export()
+ *=================================================================
+ *
+ * Sends the summary of a run to every configured destination:
+ * history, publish, pushgateway and notifications.
+ *
+ *=================================================================
+ * @param settings &Settings
+ * @param summary &Summary
+ * @param failures &[String]
+ * @return Result<()>
+ */
+pub async fn ino_export(settings: &Settings, summary: &Summary, failures: &[String]) -> Result<()> {
+    if settings.save_history {
+        history::ino_save(summary)?;
+    }
+    if let Some(destination) = &settings.publish {
+        publish::ino_publish(destination, summary).await?;
+    }
+    if let Some(prometheus) = &settings.prometheus {
+        PrometheusExporter::new(prometheus, settings.ino_name(), &settings.tags)
+            .ino_push_summary(summary)
+            .await?;
+    }
+    if let Some(notifications) = &settings.notify {
+        notify::ino_notify(notifications, summary, failures).await;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    use crate::benchmark::ino_result;
+    use crate::support::Args;
+
+    #[tokio::test]
+    async fn should_write_only_the_sampled_results_to_the_raw_file() -> Result<()> {
+        let args = Args::parse_from(["inoue", "-t", "http://localhost/", "--sample-rate", "0.1"]);
+        let settings = Settings::ino_from_args(args)?;
+        let mut report = Report::new(&settings)?;
+        let path = std::env::temp_dir().join(format!("inoue-sampled-{}.ndjson", std::process::id()));
+        let path = path.to_str().unwrap();
+        let raw = RawWriter::ino_create(path).await?;
+        for _ in 0..100 {
+            ino_record(&mut report, Some(&raw), ino_result(), &settings).await?;
+        }
+        raw.ino_finish().await?;
+        let content = std::fs::read_to_string(path)?;
+        std::fs::remove_file(path)?;
+        assert_eq!(content.lines().count(), 10);
+        assert_eq!(report.ino_summary(&settings).total_requests, 100);
+        Ok(())
+    }
+}
@@ -0,0 +1,319 @@
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hdrhistogram::serialization::{Deserializer, Serializer, V2DeflateSerializer};
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/**
+ *=================================================================
+ * Exit summary schema
+ *=================================================================
+ *
+ * Types of the JSON summary printed on stdout with --format json,
+ * for the tools reading it. Within a schema version fields are only
+ * added, as optional ones, and readers ignore the fields they do
+ * not know. Renaming, removing or changing the meaning of a field
+ * bumps the schema version.
+ *
+ *=================================================================
+ */
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExitSummary {
+    pub schema_version: u32,
+    pub passed: bool,
+    pub runs: Vec<RunSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub target: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+    pub timestamp: u64,
+    pub passed: bool,
+    #[serde(default)]
+    pub failures: Vec<String>,
+    pub clients: usize,
+    pub requests: RequestCounts,
+    pub elapsed_seconds: f64,
+    pub throughput: f64,
+    pub latency_ms: Latency,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_byte_ms: Option<Latency>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connections: Option<ConnectionCounts>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub histogram: Option<EncodedHistogram>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<Score>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accounting: Option<AccountingCounts>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EncodedHistogram(String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RequestCounts {
+    pub total: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Latency {
+    pub mean: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<u64>,
+    pub p95: u64,
+    pub p999: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionCounts {
+    pub new: u64,
+    pub reused: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    pub score: f64,
+    pub min_score: f64,
+    pub steps: BTreeMap<String, Verdict>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Verdict {
+    pub weight: f64,
+    pub passed: bool,
+    #[serde(default)]
+    pub failures: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountingCounts {
+    pub dispatched: u64,
+    pub settled: u64,
+    pub cancelled: u64,
+    pub sent: u64,
+    pub dropped: u64,
+    pub completed: u64,
+    pub failed: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub discrepancies: Vec<String>,
+}
+
+impl ExitSummary {
+
+    /**
+    *=================================================================
+    * new()
+    *=================================================================
+    *
+    * Summary of the current schema version over the runs of the
+    * invocation, a single one or one per client group or stage. It
+    * passed when every run passed.
+    *
+    *=================================================================
+    * @param runs Vec<RunSummary>
+    * @return ExitSummary
+    */
+    pub fn new(runs: Vec<RunSummary>) -> Self {
+        ExitSummary {
+            schema_version: SCHEMA_VERSION,
+            passed: runs.iter().all(|run| run.passed),
+            runs,
+        }
+    }
+}
+
+impl RunSummary {
+
+    /**
+    *=================================================================
+    * merge()
+    *=================================================================
+    *
+    * Adds the run of another inoue process against the same target,
+    * usually on another machine, to this one. The latency
+    * histograms are merged and the latencies computed again from
+    * them, the counts, clients and throughputs are summed, the
+    * elapsed time is the longest one and the run passed when both
+    * passed. The time to first byte and the step score can not be
+    * merged and are dropped, the accounting counts are summed when
+    * both runs were audited and dropped otherwise. Both runs must
+    * carry their histogram.
+    *
+    *=================================================================
+    * @param other RunSummary
+    * @return Result<()>
+    */
+    pub fn merge(&mut self, other: RunSummary) -> Result<()> {
+        if self.target != other.target {
+            bail!("Can not merge the runs of {} and {}", self.target, other.target);
+        }
+        let mut histogram = self.ino_histogram()?;
+        histogram
+            .add(other.ino_histogram()?)
+            .with_context(|| "Can not merge the latency histograms".to_string())?;
+        if self.label != other.label {
+            self.label = None;
+        }
+        self.tags.retain(|key, value| other.tags.get(key) == Some(value));
+        self.timestamp = self.timestamp.min(other.timestamp);
+        self.passed &= other.passed;
+        self.failures.extend(other.failures);
+        self.clients += other.clients;
+        self.requests.total += other.requests.total;
+        self.requests.errors += other.requests.errors;
+        self.requests.error_rate = self.requests.errors as f64 / self.requests.total.max(1) as f64;
+        self.elapsed_seconds = self.elapsed_seconds.max(other.elapsed_seconds);
+        self.throughput += other.throughput;
+        self.latency_ms = Latency {
+            mean: histogram.mean(),
+            min: Some(histogram.min()),
+            max: Some(histogram.max()),
+            p95: histogram.value_at_quantile(0.95),
+            p999: histogram.value_at_quantile(0.999),
+        };
+        self.first_byte_ms = None;
+        self.connections = match (self.connections, other.connections) {
+            (Some(a), Some(b)) => Some(ConnectionCounts { new: a.new + b.new, reused: a.reused + b.reused }),
+            (a, b) => a.or(b),
+        };
+        self.histogram = Some(EncodedHistogram::encode(&histogram)?);
+        self.score = None;
+        self.accounting = match (self.accounting.take(), other.accounting) {
+            (Some(a), Some(b)) => Some(AccountingCounts {
+                dispatched: a.dispatched + b.dispatched,
+                settled: a.settled + b.settled,
+                cancelled: a.cancelled + b.cancelled,
+                sent: a.sent + b.sent,
+                dropped: a.dropped + b.dropped,
+                completed: a.completed + b.completed,
+                failed: a.failed + b.failed,
+                discrepancies: a.discrepancies.into_iter().chain(b.discrepancies).collect(),
+            }),
+            _ => None,
+        };
+        Ok(())
+    }
+
+    fn ino_histogram(&self) -> Result<Histogram<u64>> {
+        match &self.histogram {
+            None => bail!("The run of {} has no latency histogram to merge", self.target),
+            Some(histogram) => histogram.decode(),
+        }
+    }
+}
+
+impl EncodedHistogram {
+
+    /**
+    *=================================================================
+    * encode()
+    *=================================================================
+    *
+    * Serializes a latency histogram in the HdrHistogram V2 format,
+    * compressed and in base64, the format other HdrHistogram
+    * implementations read as well.
+    *
+    *=================================================================
+    * @param histogram &Histogram<u64>
+    * @return Result<EncodedHistogram>
+    */
+    pub fn encode(histogram: &Histogram<u64>) -> Result<Self> {
+        let mut bytes = vec![];
+        V2DeflateSerializer::new()
+            .serialize(histogram, &mut bytes)
+            .map_err(|e| anyhow::anyhow!("Can not serialize the histogram: {:?}", e))?;
+        Ok(EncodedHistogram(STANDARD.encode(bytes)))
+    }
+
+    /**
+    *=================================================================
+    * decode()
+    *=================================================================
+    *
+    * Reads back an encoded latency histogram.
+    *
+    *=================================================================
+    * @param void
+    * @return Result<Histogram<u64>>
+    */
+    pub fn decode(&self) -> Result<Histogram<u64>> {
+        let bytes = STANDARD.decode(&self.0).with_context(|| "The histogram is not valid base64".to_string())?;
+        Deserializer::new()
+            .deserialize(&mut bytes.as_slice())
+            .map_err(|e| anyhow::anyhow!("Can not read the histogram: {:?}", e))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_read_summaries_with_unknown_fields() -> serde_json::Result<()> {
+        let json = r#"{"schema_version":1,"passed":false,"extra":true,"runs":[{"target":"http://host/","timestamp":1,
+            "passed":false,"failures":["p95 120 > 100"],"clients":2,"requests":{"total":10,"errors":1,"error_rate":0.1},
+            "elapsed_seconds":1.5,"throughput":6.6,"latency_ms":{"mean":50.0,"p95":120,"p999":130},"future":1}]}"#;
+        let summary: ExitSummary = serde_json::from_str(json)?;
+        assert_eq!(summary.runs[0].label, None);
+        assert_eq!(summary.runs[0].latency_ms.max, None);
+        assert_eq!(ExitSummary::new(summary.runs.clone()), summary);
+        Ok(())
+    }
+
+    #[test]
+    fn should_merge_runs_of_two_machines() -> Result<()> {
+        let run = |values: &[u64], throughput, machine: &str| -> Result<RunSummary> {
+            let mut histogram = Histogram::<u64>::new(3)?;
+            for value in values {
+                histogram.record(*value)?;
+            }
+            Ok(RunSummary {
+                target: "http://host/".to_string(),
+                label: None,
+                tags: BTreeMap::from([("machine".to_string(), machine.to_string()), ("env".to_string(), "staging".to_string())]),
+                timestamp: 10,
+                passed: true,
+                failures: vec![],
+                clients: 2,
+                requests: RequestCounts { total: values.len() as u64, errors: 1, error_rate: 0.0 },
+                elapsed_seconds: 1.0,
+                throughput,
+                latency_ms: Latency { mean: 0.0, min: None, max: None, p95: 0, p999: 0 },
+                first_byte_ms: None,
+                connections: None,
+                histogram: Some(EncodedHistogram::encode(&histogram)?),
+                score: None,
+                accounting: Some(AccountingCounts { dispatched: values.len() as u64, ..Default::default() }),
+            })
+        };
+        let mut merged = run(&[10, 20, 30], 3.0, "a")?;
+        merged.merge(run(&[40, 50, 60, 70, 80], 5.0, "b")?)?;
+        assert_eq!((merged.clients, merged.requests.total, merged.requests.errors, merged.throughput), (4, 8, 2, 8.0));
+        assert_eq!(merged.requests.error_rate, 0.25);
+        assert_eq!((merged.latency_ms.mean, merged.latency_ms.min, merged.latency_ms.max), (45.0, Some(10), Some(80)));
+        assert_eq!(merged.histogram.as_ref().map(|h| h.decode().map(|h| h.len()).ok()), Some(Some(8)));
+        assert_eq!(merged.tags, BTreeMap::from([("env".to_string(), "staging".to_string())]));
+        assert_eq!(merged.accounting.as_ref().map(|accounting| accounting.dispatched), Some(8));
+        merged.merge(RunSummary { accounting: None, ..run(&[90], 1.0, "c")? })?;
+        assert_eq!(merged.accounting, None);
+        let mut other = run(&[1], 1.0, "c")?;
+        other.target = "http://other/".to_string();
+        assert!(merged.merge(other).is_err());
+        Ok(())
+    }
+}
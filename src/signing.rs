@@ -0,0 +1,149 @@
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Method, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::flow::{ino_render, Variables};
+use crate::publish::{ino_hex, ino_hmac};
+
+const DEFAULT_VALUE: &str = "{{signature}}";
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignatureEncoding {
+    #[default]
+    Hex,
+    Base64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Signing {
+    pub header: String,
+    #[serde(default)]
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub secret_env: Option<String>,
+    pub template: String,
+    #[serde(default)]
+    pub value: Option<String>,
+    #[serde(default)]
+    pub timestamp_header: Option<String>,
+    #[serde(default)]
+    pub encoding: SignatureEncoding,
+}
+
+impl Signing {
+
+    /**
+    *=================================================================
+    * ino_resolve()
+    *=================================================================
+    *
+    * Reads the secret from its environment variable when the
+    * scenario names one, so the secret stays out of the scenario
+    * file, and checks the header names once before the run.
+    *
+    *=================================================================
+    * @param void
+    * @return Result<()>
+    */
+    pub fn ino_resolve(&mut self) -> Result<()> {
+        match (&self.secret, &self.secret_env) {
+            (Some(_), Some(_)) => bail!("The signing takes a secret or a secret_env, not both"),
+            (None, None) => bail!("The signing needs a secret or a secret_env"),
+            (Some(_), None) => {}
+            (None, Some(name)) => {
+                let secret = env::var(name).with_context(|| format!("The signing secret variable {} is not set", name))?;
+                self.secret = Some(secret);
+            }
+        }
+        HeaderName::try_from(&self.header).with_context(|| format!("Invalid signature header {}", self.header))?;
+        if let Some(header) = &self.timestamp_header {
+            HeaderName::try_from(header).with_context(|| format!("Invalid timestamp header {}", header))?;
+        }
+        Ok(())
+    }
+
+    /**
+    *=================================================================
+    * ino_headers()
+    *=================================================================
+    *
+    * Signs a request with HMAC-SHA256. The string to sign is the
+    * template with the {{method}}, {{host}}, {{path}}, {{query}},
+    * {{body}}, {{body_sha256}} and {{timestamp}} placeholders
+    * substituted, the timestamp being the current Unix time in
+    * seconds. Returns the signature header, its value rendered from
+    * the value template with {{signature}} and {{timestamp}}, and
+    * the timestamp header when one is configured.
+    *
+    *=================================================================
+    * @param method &Method
+    * @param url &Url
+    * @param body &[u8]
+    * @return Result<HeaderMap>
+    */
+    pub fn ino_headers(&self, method: &Method, url: &Url, body: &[u8]) -> Result<HeaderMap> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.ino_headers_at(method, url, body, timestamp)
+    }
+
+    fn ino_headers_at(&self, method: &Method, url: &Url, body: &[u8], timestamp: u64) -> Result<HeaderMap> {
+        let mut variables = Variables::from([
+            ("method".to_string(), method.to_string()),
+            ("host".to_string(), url.host_str().unwrap_or_default().to_string()),
+            ("path".to_string(), url.path().to_string()),
+            ("query".to_string(), url.query().unwrap_or_default().to_string()),
+            ("body".to_string(), String::from_utf8_lossy(body).into_owned()),
+            ("body_sha256".to_string(), ino_hex(&Sha256::digest(body))),
+            ("timestamp".to_string(), timestamp.to_string()),
+        ]);
+        let string_to_sign = ino_render(&self.template, &variables);
+        let secret = self.secret.as_deref().unwrap_or_default();
+        let mac = ino_hmac(secret.as_bytes(), string_to_sign.as_bytes());
+        let signature = match self.encoding {
+            SignatureEncoding::Hex => ino_hex(&mac),
+            SignatureEncoding::Base64 => STANDARD.encode(mac),
+        };
+        variables.insert("signature".to_string(), signature);
+        let value = ino_render(self.value.as_deref().unwrap_or(DEFAULT_VALUE), &variables);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::try_from(&self.header)?,
+            HeaderValue::from_str(&value).with_context(|| "Invalid signature header value".to_string())?,
+        );
+        if let Some(header) = &self.timestamp_header {
+            headers.insert(HeaderName::try_from(header)?, HeaderValue::from(timestamp));
+        }
+        Ok(headers)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_sign_the_rendered_template() -> Result<()> {
+        let mut signing: Signing = serde_yaml::from_str(
+            "header: Authorization\nsecret: key\ntemplate: \"{{method}}\\n{{path}}?{{query}}\\n{{timestamp}}\\n{{body}}\"\n\
+             value: \"HMAC {{signature}}\"\ntimestamp_header: X-Timestamp",
+        )?;
+        signing.ino_resolve()?;
+        let url = Url::parse("http://host/orders?page=2")?;
+        let headers = signing.ino_headers_at(&Method::POST, &url, b"{}", 1700000000)?;
+        let expected = ino_hex(&ino_hmac(b"key", b"POST\n/orders?page=2\n1700000000\n{}"));
+        assert_eq!(headers["authorization"], format!("HMAC {}", expected).as_str());
+        assert_eq!(headers["x-timestamp"], "1700000000");
+        signing.secret_env = Some("INOUE_SIGNING_SECRET".to_string());
+        assert!(signing.ino_resolve().is_err());
+        Ok(())
+    }
+}
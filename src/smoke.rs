@@ -0,0 +1,113 @@
+use anyhow::Result;
+use colored::Colorize;
+use indicatif::ProgressBar;
+use tokio::sync::watch;
+
+use crate::benchmark::BenchmarkResult;
+use crate::run::ino_benchmark;
+use crate::support::Settings;
+
+/**
+ *=================================================================
+ * ino_smoke()
+ *=================================================================
+ *
+ * Runs the smoke check: the requests of the settings are sent and
+ * echoed one after the other, then the failed ones are listed.
+ *
+ *=================================================================
+ * @param settings Settings
+ * @return Result<bool> passed
+ */
+pub async fn ino_smoke(settings: Settings) -> Result<bool> {
+    println!(
+        "{} {} {}",
+        "Smoke check of".yellow().bold(),
+        settings.ino_name().purple(),
+        format!("with {} iterations", settings.requests).purple()
+    );
+    println!();
+    let (tx_sigint, rx_sigint) = watch::channel(None);
+    ctrlc::set_handler(move || {
+        tx_sigint.send(Some(())).unwrap_or(());
+    })?;
+    let report = ino_benchmark(&settings, rx_sigint, ProgressBar::hidden(), None, None).await?;
+    Ok(ino_check(&report.results))
+}
+
+/**
+ *=================================================================
+ * ino_check()
+ *=================================================================
+ *
+ * Lists every result that failed its status or header assertions,
+ * or ended a poll that timed out. Passes when at least one request
+ * was sent and none failed.
+ *
+ *=================================================================
+ * @param results &[BenchmarkResult]
+ * @return bool passed
+ */
+fn ino_check(results: &[BenchmarkResult]) -> bool {
+    let failed = ino_failed(results);
+    match (results.len(), failed.len()) {
+        (0, _) => println!("{} no request was sent", "Smoke check failed:".red().bold()),
+        (sent, 0) => println!("{} {} requests", "Smoke check passed:".green().bold(), sent),
+        (sent, count) => {
+            println!("{} {} of {} requests failed", "Smoke check failed:".red().bold(), count, sent);
+            for result in failed.iter() {
+                match &result.poll {
+                    Some(poll) if !poll.completed => println!("  {} (poll {} timed out)", result, poll.name),
+                    _ => println!("  {}", result),
+                }
+            }
+        }
+    }
+    !results.is_empty() && failed.is_empty()
+}
+
+/**
+ *=================================================================
+ * ino_failed()
+ *=================================================================
+ *
+ * Results failing the smoke check.
+ *
+ *=================================================================
+ * @param results &[BenchmarkResult]
+ * @return Vec<&BenchmarkResult>
+ */
+fn ino_failed(results: &[BenchmarkResult]) -> Vec<&BenchmarkResult> {
+    results
+        .iter()
+        .filter(|r| !r.success || r.poll.as_ref().is_some_and(|p| !p.completed))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::ino_result;
+    use crate::flow::PollOutcome;
+
+    #[test]
+    fn should_list_the_failed_results() {
+        let poll = |completed| PollOutcome { name: "order".to_string(), elapsed: 5, completed };
+        let results = [
+            ino_result(),
+            BenchmarkResult { success: false, status: "500 Internal Server Error".to_string(), execution: 1, ..ino_result() },
+            BenchmarkResult { poll: Some(poll(false)), execution: 2, ..ino_result() },
+            BenchmarkResult { poll: Some(poll(true)), execution: 3, ..ino_result() },
+        ];
+        let failed: Vec<_> = ino_failed(&results).iter().map(|r| r.execution).collect();
+        assert_eq!(failed, [1, 2]);
+        assert!(!ino_check(&results));
+        assert!(ino_check(&results[3..]));
+    }
+
+    #[test]
+    fn should_fail_without_results() {
+        assert!(!ino_check(&[]));
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::any::{AnyPoolOptions, AnyRow};
+use sqlx::query::Query;
+use sqlx::{Any, AnyPool, AssertSqlSafe};
+use sqlx::any::AnyArguments;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::benchmark::BenchmarkResult;
+use crate::feeder::DataFeeder;
+use crate::support::Settings;
+
+const SQL_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct SqlQuery {
+    pub query: String,
+    #[serde(default)]
+    pub data: Option<String>,
+}
+
+pub struct Sql {
+    pool: AnyPool,
+    query: String,
+    feeder: Option<DataFeeder>,
+}
+
+impl Sql {
+
+    /**
+    *=================================================================
+    * ino_from_settings()
+    *=================================================================
+    *
+    * Opens a pool on the connection string of the target,
+    * "postgres://..." or "mysql://...", with a connection per
+    * in-flight query of every client. One connection is opened
+    * first so wrong credentials stop the run before it starts.
+    *
+    *=================================================================
+    * @param settings &Settings
+    * @return Result<Sql>
+    */
+    pub async fn ino_from_settings(settings: &Settings) -> Result<Self> {
+        let sql = settings
+            .sql
+            .as_ref()
+            .with_context(|| "The sql mode needs a query".to_string())?;
+        let feeder = sql.data.as_deref().map(DataFeeder::ino_from_file).transpose()?;
+        sqlx::any::install_default_drivers();
+        let target = settings.ino_target();
+        let pool = AnyPoolOptions::new()
+            .max_connections((settings.clients * settings.in_flight.max(1)) as u32)
+            .acquire_timeout(SQL_CONNECT_TIMEOUT)
+            .connect(&target)
+            .await
+            .with_context(|| format!("Can not connect to the database {}", settings.ino_name()))?;
+        Ok(Sql { pool, query: sql.query.clone(), feeder })
+    }
+
+    /**
+    *=================================================================
+    * ino_exec()
+    *=================================================================
+    *
+    * Runs the query once, bound with the next row of the data file
+    * if any, and fetches all its rows. A database error becomes the
+    * status with its code.
+    *
+    *=================================================================
+    * @param num_client usize
+    * @param execution usize
+    * @return BenchmarkResult
+    */
+    pub async fn ino_exec(&self, num_client: usize, execution: usize) -> BenchmarkResult {
+        let mut query = sqlx::query(AssertSqlSafe(self.query.as_str()));
+        if let Some(feeder) = &self.feeder {
+            for value in feeder.ino_next() {
+                query = ino_bind(query, value);
+            }
+        }
+        let begin = Instant::now();
+        let rows: Result<Vec<AnyRow>, sqlx::Error> = query.fetch_all(&self.pool).await;
+        let duration = begin.elapsed().as_millis() as u64;
+        let status = match &rows {
+            Ok(_) => "OK".to_string(),
+            Err(sqlx::Error::Database(e)) => match e.code() {
+                Some(code) => format!("Error {}: {}", code, e.message()),
+                None => format!("Error: {}", e.message()),
+            },
+            Err(e) => format!("Failed: {}", e),
+        };
+        BenchmarkResult::new(status, rows.is_ok(), duration, num_client, execution)
+    }
+}
+
+/**
+ *=================================================================
+ * ino_bind()
+ *=================================================================
+ *
+ * Binds a value of the data file as the next parameter: an empty
+ * value is NULL, a number is bound as an integer or a float, any
+ * other value as text.
+ *
+ *=================================================================
+ */
+fn ino_bind<'q>(query: Query<'q, Any, AnyArguments>, value: &'q str) -> Query<'q, Any, AnyArguments> {
+    if value.is_empty() {
+        return query.bind(None::<String>);
+    }
+    match (value.parse::<i64>(), value.parse::<f64>()) {
+        (Ok(integer), _) => query.bind(integer),
+        (_, Ok(float)) => query.bind(float),
+        _ => query.bind(value),
+    }
+}
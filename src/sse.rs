@@ -0,0 +1,336 @@
+use anyhow::{bail, Result};
+use reqwest::header::{ACCEPT, CACHE_CONTROL};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::bandwidth::ino_throttle_transfer;
+use crate::benchmark::{ino_quantile, BenchmarkResult};
+use crate::execution::{ino_failure, Worker};
+use crate::exhaustion::ino_connect_status;
+use crate::support::Settings;
+
+pub struct Sse {
+    events: Option<u32>,
+    duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamEnd {
+    Completed,
+    Terminated,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamOutcome {
+    pub events: u32,
+    pub first_event: Option<u64>,
+    pub gaps: Vec<u64>,
+    pub end: StreamEnd,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SseSummary {
+    pub streams: u64,
+    pub completed: u64,
+    pub terminated: u64,
+    pub failed: u64,
+    pub termination_rate: f64,
+    pub error_rate: f64,
+    pub events: u64,
+    pub first_event_mean: f64,
+    pub first_event_p95: u64,
+    pub gap_mean: f64,
+    pub gap_p95: u64,
+    pub gap_max: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct SseStats {
+    streams: u64,
+    completed: u64,
+    terminated: u64,
+    failed: u64,
+    events: u64,
+    first_events: Vec<u64>,
+    gaps: Vec<u64>,
+}
+
+#[derive(Default)]
+struct EventParser {
+    line: Vec<u8>,
+    data: bool,
+}
+
+impl Sse {
+
+    /**
+    *=================================================================
+    * ino_from_settings()
+    *=================================================================
+    *
+    * Reads how long every stream is followed: a number of events,
+    * a duration, or whichever comes first.
+    *
+    *=================================================================
+    * @param settings &Settings
+    * @return Result<Sse>
+    */
+    pub fn ino_from_settings(settings: &Settings) -> Result<Self> {
+        if settings.sse_events.is_none() && settings.sse_duration.is_none() {
+            bail!("The sse mode needs --sse-events or --sse-duration to know when a stream is complete");
+        }
+        if settings.sse_events == Some(0) || settings.sse_duration == Some(0) {
+            bail!("The sse mode needs at least one event or one second per stream");
+        }
+        Ok(Sse { events: settings.sse_events, duration: settings.sse_duration.map(Duration::from_secs) })
+    }
+
+    /**
+    *=================================================================
+    * ino_exec()
+    *=================================================================
+    *
+    * Opens an event stream on the target and follows it until the
+    * configured events came or its duration is over, then closes
+    * it. The time to the first event and between the events are
+    * recorded from the arrival of the chunks carrying them. A
+    * stream the server ends early is terminated, one broken by an
+    * error failed, neither counts as a success.
+    *
+    *=================================================================
+    * @param worker &Worker
+    * @param execution usize
+    * @return BenchmarkResult
+    */
+    pub async fn ino_exec(&self, worker: &Worker, execution: usize) -> BenchmarkResult {
+        let num_client = worker.num_client;
+        let Some(url) = worker.request.url.clone() else { return ino_failure(num_client, execution, "Invalid URL".to_string(), 0) };
+        let mut request = worker
+            .client
+            .request(worker.request.method.clone(), url)
+            .headers(worker.request.headers.clone())
+            .header(ACCEPT, "text/event-stream")
+            .header(CACHE_CONTROL, "no-cache");
+        if let Some(body) = &worker.request.body {
+            request = request.body(body.clone());
+        }
+        let begin = Instant::now();
+        if let Some(latency) = &worker.latency {
+            latency.ino_delay().await;
+        }
+        let deadline = self.duration.map(|duration| begin + duration);
+        let mut outcome = StreamOutcome { events: 0, first_event: None, gaps: vec![], end: StreamEnd::Failed };
+        let (status, first_byte) = match ino_before(deadline, request.send()).await {
+            None => ("No response within the stream duration".to_string(), None),
+            Some(Err(e)) => (e.status().map_or_else(|| ino_connect_status(&e), |s| s.to_string()), None),
+            Some(Ok(r)) if !r.status().is_success() => (r.status().to_string(), Some(begin.elapsed().as_millis() as u64)),
+            Some(Ok(mut r)) => {
+                let first_byte = begin.elapsed().as_millis() as u64;
+                let mut parser = EventParser::default();
+                let mut last = None;
+                outcome.end = loop {
+                    if self.events.is_some_and(|events| outcome.events >= events) {
+                        break StreamEnd::Completed;
+                    }
+                    match ino_before(deadline, r.chunk()).await {
+                        None => break StreamEnd::Completed,
+                        Some(Ok(None)) => break StreamEnd::Terminated,
+                        Some(Err(_)) => break StreamEnd::Failed,
+                        Some(Ok(Some(chunk))) => {
+                            ino_throttle_transfer(&worker.bandwidth, chunk.len()).await;
+                            let at = begin.elapsed().as_millis() as u64;
+                            for _ in 0..parser.ino_feed(&chunk) {
+                                match last {
+                                    None => outcome.first_event = Some(at),
+                                    Some(last) => outcome.gaps.push(at - last),
+                                }
+                                last = Some(at);
+                                outcome.events += 1;
+                            }
+                        }
+                    }
+                };
+                let status = match outcome.end {
+                    StreamEnd::Completed => r.status().to_string(),
+                    StreamEnd::Terminated => format!("{} (stream terminated after {} events)", r.status(), outcome.events),
+                    StreamEnd::Failed => format!("{} (stream error after {} events)", r.status(), outcome.events),
+                };
+                (status, Some(first_byte))
+            }
+        };
+        let success = outcome.end == StreamEnd::Completed;
+        BenchmarkResult {
+            first_byte,
+            stream: Some(outcome),
+            ..BenchmarkResult::new(status, success, begin.elapsed().as_millis() as u64, num_client, execution)
+        }
+    }
+}
+
+impl EventParser {
+
+    /**
+    *=================================================================
+    * ino_feed()
+    *=================================================================
+    *
+    * Reads a chunk of the stream and counts the events it
+    * completes: a blank line ends an event when data lines came
+    * before it. Comments, used as heartbeats, are not events.
+    *
+    *=================================================================
+    * @param chunk &[u8]
+    * @return u32 the count of completed events
+    */
+    fn ino_feed(&mut self, chunk: &[u8]) -> u32 {
+        let mut events = 0;
+        for &byte in chunk {
+            if byte != b'\n' {
+                self.line.push(byte);
+                continue;
+            }
+            let line = self.line.strip_suffix(b"\r").unwrap_or(&self.line);
+            if line.is_empty() {
+                if self.data {
+                    events += 1;
+                }
+                self.data = false;
+            } else if line == b"data" || line.starts_with(b"data:") {
+                self.data = true;
+            }
+            self.line.clear();
+        }
+        events
+    }
+}
+
+/**
+ *=================================================================
+ * ino_before()
+ *=================================================================
+ *
+ * Awaits a future until the deadline if any, None once it is over.
+ *
+ *=================================================================
+ */
+async fn ino_before<F: Future>(deadline: Option<Instant>, future: F) -> Option<F::Output> {
+    match deadline {
+        None => Some(future.await),
+        Some(deadline) => tokio::time::timeout_at(deadline, future).await.ok(),
+    }
+}
+
+impl SseStats {
+
+    /**
+    *=================================================================
+    * ino_add()
+    *=================================================================
+    *
+    * Records the outcome, the first event and the gaps between the
+    * events of a stream.
+    *
+    *=================================================================
+    * @param result &BenchmarkResult
+    * @return void
+    */
+    pub fn ino_add(&mut self, result: &BenchmarkResult) {
+        let Some(stream) = &result.stream else { return };
+        self.streams += 1;
+        match stream.end {
+            StreamEnd::Completed => self.completed += 1,
+            StreamEnd::Terminated => self.terminated += 1,
+            StreamEnd::Failed => self.failed += 1,
+        }
+        self.events += stream.events as u64;
+        self.first_events.extend(stream.first_event);
+        self.gaps.extend_from_slice(&stream.gaps);
+    }
+
+    /**
+    *=================================================================
+    * ino_summary()
+    *=================================================================
+    *
+    * Outcome of the event streams over the results: how many were
+    * completed, terminated by the server or broken by an error, with
+    * their rates, the time to the first event and between the events
+    * in milliseconds. None without any stream.
+    *
+    *=================================================================
+    * @param void
+    * @return Option<SseSummary>
+    */
+    pub fn ino_summary(&self) -> Option<SseSummary> {
+        if self.streams == 0 {
+            return None;
+        }
+        let (mut first_events, mut gaps) = (self.first_events.clone(), self.gaps.clone());
+        first_events.sort_unstable();
+        gaps.sort_unstable();
+        let mean = |values: &[u64]| values.iter().sum::<u64>() as f64 / values.len().max(1) as f64;
+        let p95 = |values: &[u64]| match values.is_empty() {
+            true => 0,
+            false => ino_quantile(values, 0.95),
+        };
+        Some(SseSummary {
+            streams: self.streams,
+            completed: self.completed,
+            terminated: self.terminated,
+            failed: self.failed,
+            termination_rate: self.terminated as f64 / self.streams as f64,
+            error_rate: self.failed as f64 / self.streams as f64,
+            events: self.events,
+            first_event_mean: mean(&first_events),
+            first_event_p95: p95(&first_events),
+            gap_mean: mean(&gaps),
+            gap_p95: p95(&gaps),
+            gap_max: gaps.last().cloned().unwrap_or(0),
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::ino_result;
+
+    #[test]
+    fn should_count_complete_events_only() {
+        let mut parser = EventParser::default();
+        assert_eq!(parser.ino_feed(b": heartbeat\n\nevent: tick\ndata: 1\n\ndata: 2\r\n"), 1);
+        assert_eq!(parser.ino_feed(b"\r\nda"), 1);
+        assert_eq!(parser.ino_feed(b"ta: 3\nid: 3\n\nretry: 10\n\n"), 1);
+    }
+
+    #[test]
+    fn should_summarize_streams() {
+        let result = |first_event: Option<u64>, gaps: Vec<u64>, end| BenchmarkResult {
+            success: end == StreamEnd::Completed,
+            duration: 1000,
+            first_byte: Some(5),
+            stream: Some(StreamOutcome { events: first_event.map_or(0, |_| gaps.len() as u32 + 1), first_event, gaps, end }),
+            ..ino_result()
+        };
+        let mut stats = SseStats::default();
+        assert_eq!(stats.ino_summary(), None);
+        [
+            result(Some(10), vec![100, 100, 100], StreamEnd::Completed),
+            result(Some(30), vec![200], StreamEnd::Terminated),
+            result(None, vec![], StreamEnd::Failed),
+            result(Some(20), vec![], StreamEnd::Completed),
+        ]
+        .iter()
+        .for_each(|result| stats.ino_add(result));
+        let summary = stats.ino_summary().unwrap();
+        assert_eq!((summary.streams, summary.completed, summary.terminated, summary.failed), (4, 2, 1, 1));
+        assert_eq!((summary.termination_rate, summary.error_rate), (0.25, 0.25));
+        assert_eq!(summary.events, 7);
+        assert_eq!((summary.first_event_mean, summary.first_event_p95), (20.0, 30));
+        assert_eq!((summary.gap_mean, summary.gap_p95, summary.gap_max), (125.0, 200, 200));
+    }
+}
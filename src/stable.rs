@@ -0,0 +1,155 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+use strum::{Display as StrumDisplay, EnumString};
+
+use crate::support::ino_parse_duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, StrumDisplay, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum StableMetric {
+    Mean,
+    P50,
+    P90,
+    P95,
+    P99,
+    Rps,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct StopOnStable {
+    pub metric: StableMetric,
+    pub tolerance: f64,
+    pub window: Duration,
+}
+
+pub struct StabilityTracker {
+    stop: StopOnStable,
+    samples: VecDeque<(Duration, f64)>,
+}
+
+impl TryFrom<String> for StopOnStable {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        ino_parse_stop_on_stable(&value)
+    }
+}
+
+impl From<StopOnStable> for String {
+    fn from(stop: StopOnStable) -> Self {
+        format!("{}:{}%:{}ms", stop.metric, stop.tolerance * 100.0, stop.window.as_millis())
+    }
+}
+
+impl Display for StopOnStable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} stable within {}% for {} s", self.metric, self.tolerance * 100.0, self.window.as_secs_f64())
+    }
+}
+
+impl StabilityTracker {
+
+    /**
+    *=================================================================
+    * new()
+    *=================================================================
+    *
+    * Creates the tracker of a run, fed every second with the value
+    * of the metric over the results received so far.
+    *
+    *=================================================================
+    * @param stop StopOnStable
+    * @return Self
+    */
+    pub fn new(stop: StopOnStable) -> Self {
+        StabilityTracker { stop, samples: VecDeque::new() }
+    }
+
+    /**
+    *=================================================================
+    * ino_sample()
+    *=================================================================
+    *
+    * Records the value of the metric at an offset of the run and
+    * tells whether it has been stable: the samples of the last
+    * window, which must be covered entirely, all lie within the
+    * tolerance of the latest one. The metric is taken over every
+    * result so far, the figure the report would print, so a stable
+    * run is one whose report would not change by stopping now.
+    *
+    *=================================================================
+    * @param elapsed Duration
+    * @param value f64
+    * @return bool
+    */
+    pub fn ino_sample(&mut self, elapsed: Duration, value: f64) -> bool {
+        self.samples.push_back((elapsed, value));
+        while self.samples.len() > 1 && elapsed.saturating_sub(self.samples[1].0) >= self.stop.window {
+            self.samples.pop_front();
+        }
+        let Some(&(first, _)) = self.samples.front() else { return false };
+        if elapsed.saturating_sub(first) < self.stop.window {
+            return false;
+        }
+        let margin = value.abs() * self.stop.tolerance;
+        self.samples.iter().all(|(_, sample)| (sample - value).abs() <= margin)
+    }
+}
+
+/**
+ *=================================================================
+ * ino_parse_stop_on_stable()
+ *=================================================================
+ *
+ * Parses "METRIC:TOLERANCE%:WINDOW", ex. p95:2%:30s, the metric
+ * being mean, p50, p90, p95, p99 or rps.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<StopOnStable>
+ */
+pub fn ino_parse_stop_on_stable(value: &str) -> Result<StopOnStable> {
+    let parts: Vec<&str> = value.split(':').map(str::trim).collect();
+    let [metric, tolerance, window] = parts.as_slice() else {
+        bail!("Invalid stop condition {}, use METRIC:TOLERANCE%:WINDOW, ex. p95:2%:30s", value);
+    };
+    let metric = StableMetric::from_str(&metric.to_ascii_lowercase())
+        .with_context(|| format!("Unknown metric {}, use mean, p50, p90, p95, p99 or rps", metric))?;
+    let tolerance: f64 = tolerance
+        .strip_suffix('%')
+        .with_context(|| format!("The tolerance {} must be a percentage, ex. 2%", tolerance))?
+        .parse()
+        .with_context(|| format!("Invalid tolerance {}", tolerance))?;
+    if !(0.0..100.0).contains(&tolerance) {
+        bail!("The tolerance must be between 0% and 100%");
+    }
+    let window = ino_parse_duration(window)?;
+    if window.is_zero() {
+        bail!("The stability window must not be empty");
+    }
+    Ok(StopOnStable { metric, tolerance: tolerance / 100.0, window })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_stop_once_the_metric_held_for_the_window() -> Result<()> {
+        let stop = ino_parse_stop_on_stable("p95:2%:3s")?;
+        assert_eq!(stop, StopOnStable { metric: StableMetric::P95, tolerance: 0.02, window: Duration::from_secs(3) });
+        let mut tracker = StabilityTracker::new(stop);
+        let values = [80.0, 120.0, 101.0, 100.0, 101.5, 100.0, 100.5];
+        let stable: Vec<bool> = values.iter().enumerate().map(|(second, value)| tracker.ino_sample(Duration::from_secs(second as u64 + 1), *value)).collect();
+        assert_eq!(stable, [false, false, false, false, false, true, true]);
+        assert!(ino_parse_stop_on_stable("p95:2:30s").is_err() && ino_parse_stop_on_stable("p42:2%:30s").is_err());
+        assert_eq!(String::from(ino_parse_stop_on_stable("RPS:0.5%:1m")?), "rps:0.5%:60000ms");
+        Ok(())
+    }
+}
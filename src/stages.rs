@@ -0,0 +1,232 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+use indicatif::MultiProgress;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+use crate::benchmark::ConnectionSummary;
+use crate::execution::ino_build_worker_client;
+use crate::groups::ino_group_path;
+use crate::pacing::Pacing;
+use crate::run::{ino_benchmark, ino_export, ino_print_exit_summary, ino_progress_bar, ino_verdict};
+use crate::support::{human_duration, OutputFormat, Settings};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Stage {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub clients: Option<usize>,
+    #[serde(default)]
+    pub requests: Option<usize>,
+    #[serde(default, deserialize_with = "human_duration::seconds")]
+    pub duration: Option<u64>,
+    #[serde(default)]
+    pub pacing: Option<Pacing>,
+    #[serde(default)]
+    pub flush: bool,
+}
+
+pub type Connections = Arc<Mutex<HashSet<SocketAddr>>>;
+
+#[derive(Default)]
+pub struct ConnectionPool {
+    clients: Mutex<Vec<(Client, Connections)>>,
+}
+
+impl ConnectionPool {
+
+    /**
+    *=================================================================
+    * ino_client()
+    *=================================================================
+    *
+    * HTTP client of a worker with the connections it already opened,
+    * kept from a previous stage or created for this one and kept for
+    * the next ones.
+    *
+    *=================================================================
+    * @param settings &Settings
+    * @param num_client usize
+    * @return Result<(Client, Connections)>
+    */
+    pub fn ino_client(&self, settings: &Settings, num_client: usize) -> Result<(Client, Connections)> {
+        let mut clients = self.clients.lock().unwrap();
+        while clients.len() <= num_client {
+            let client = ino_build_worker_client(settings, clients.len())?;
+            clients.push((client, Connections::default()));
+        }
+        Ok(clients[num_client].clone())
+    }
+
+    /**
+    *=================================================================
+    * ino_flush()
+    *=================================================================
+    *
+    * Drops the clients and their idle connections, the next stage
+    * connects again.
+    *
+    *=================================================================
+    * @param void
+    * @return void
+    */
+    pub fn ino_flush(&self) {
+        self.clients.lock().unwrap().clear();
+    }
+}
+
+impl Stage {
+
+    /**
+    *=================================================================
+    * ino_name()
+    *=================================================================
+    *
+    * Name of the stage, its position without one.
+    *
+    *=================================================================
+    * @param index usize
+    * @return String
+    */
+    pub fn ino_name(&self, index: usize) -> String {
+        self.name.clone().unwrap_or_else(|| format!("stage {}", index + 1))
+    }
+
+    /**
+    *=================================================================
+    * ino_settings()
+    *=================================================================
+    *
+    * Settings of the stage: the scenario with the clients, requests,
+    * duration and pacing of the stage on top. A stage with requests
+    * and no duration runs its iterations. The stage name is appended
+    * to the label and to the raw results and heatmap files.
+    *
+    *=================================================================
+    * @param settings &Settings
+    * @param name &str
+    * @return Settings
+    */
+    pub fn ino_settings(&self, settings: &Settings, name: &str) -> Settings {
+        let mut stage = settings.clone();
+        stage.stages = None;
+        stage.clients = self.clients.unwrap_or(settings.clients);
+        stage.requests = self.requests.unwrap_or(settings.requests);
+        stage.duration = match self.requests {
+            None => self.duration.or(settings.duration),
+            Some(_) => self.duration,
+        };
+        if self.pacing.is_some() {
+            stage.pacing = self.pacing;
+        }
+        stage.label = Some(match &settings.label {
+            None => name.to_string(),
+            Some(label) => format!("{}/{}", label, name),
+        });
+        stage.raw_out = settings.raw_out.as_deref().map(|path| ino_group_path(path, name));
+        stage.heatmap = settings.heatmap.as_deref().map(|path| ino_group_path(path, name));
+        stage.interactive = false;
+        stage
+    }
+}
+
+/**
+ *=================================================================
+ * ino_run_stages()
+ *=================================================================
+ *
+ * Runs the stages of the scenario one after the other, each with
+ * its own progress bar, report section, verdict and exports. With
+ * warm connections the clients keep their connection pools from a
+ * stage to the next, unless a stage flushes them, so a stage does
+ * not start with a reconnect storm. The new connections of every
+ * stage are recapped at the end.
+ *
+ *=================================================================
+ * @param settings &Settings
+ * @param stages &[Stage]
+ * @param rx_sigint watch::Receiver<Option<()>>
+ * @return Result<bool> true when every stage passed
+ */
+pub async fn ino_run_stages(settings: &Settings, stages: &[Stage], rx_sigint: watch::Receiver<Option<()>>) -> Result<bool> {
+    if stages.is_empty() {
+        bail!("The scenario declares no stage");
+    }
+    let text = settings.format == OutputFormat::Text;
+    let multi = MultiProgress::new();
+    let pool = settings.warm_connections.then(ConnectionPool::default);
+    let mut passed = true;
+    let mut recap: Vec<(String, Option<ConnectionSummary>)> = vec![];
+    let mut exit_runs = vec![];
+    for (index, stage) in stages.iter().enumerate() {
+        if rx_sigint.borrow().is_some() {
+            break;
+        }
+        let name = stage.ino_name(index);
+        let stage_settings = stage.ino_settings(settings, &name);
+        if let (Some(pool), true) = (&pool, stage.flush) {
+            pool.ino_flush();
+        }
+        if text {
+            stage_settings.ino_print_banner();
+        }
+        let pb = ino_progress_bar(&multi, &name, &stage_settings);
+        let report = ino_benchmark(&stage_settings, rx_sigint.clone(), pb, pool.as_ref(), None).await?;
+        if text {
+            println!();
+            println!("{} {}", "Stage".yellow().bold(), name.purple().bold());
+            report.ino_show_result();
+        }
+        if let Some(path) = &stage_settings.heatmap {
+            report.ino_heatmap().ino_write(path, &stage_settings.ino_name())?;
+        }
+        let summary = report.ino_summary(&stage_settings);
+        let failures = ino_verdict(&stage_settings, &summary, text);
+        ino_export(&stage_settings, &summary, &failures).await?;
+        passed &= failures.is_empty();
+        recap.push((name, summary.connections));
+        exit_runs.push(summary.ino_run_summary(&failures, report.ino_encoded_histogram()));
+    }
+    if !text {
+        ino_print_exit_summary(exit_runs)?;
+        return Ok(passed);
+    }
+    println!();
+    for (name, connections) in recap {
+        let connections = match connections {
+            None => "-".to_string(),
+            Some(c) => format!("{} new connections, {} reused", c.new, c.reused),
+        };
+        println!("{} {} {}", "Stage".yellow().bold(), name.yellow().bold(), connections.purple());
+    }
+    Ok(passed)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_put_the_stage_on_top_of_the_scenario() -> Result<()> {
+        let settings: Settings = serde_yaml::from_str(
+            "target: http://host/\nclients: 10\nrequests: 100\nduration: 60\nraw_out: raw.jsonl\nwarm_connections: true\n\
+             stages:\n  - name: warmup\n    clients: 2\n    duration: 10\n  - requests: 500\n    flush: true",
+        )?;
+        let stages = settings.stages.clone().unwrap_or_default();
+        let warmup = stages[0].ino_settings(&settings, &stages[0].ino_name(0));
+        assert_eq!((warmup.clients, warmup.duration), (2, Some(10)));
+        assert_eq!(warmup.raw_out.as_deref(), Some("raw-warmup.jsonl"));
+        assert_eq!(stages[1].ino_name(1), "stage 2");
+        let second = stages[1].ino_settings(&settings, "stage 2");
+        assert_eq!((second.clients, second.requests, second.duration), (10, 500, None));
+        assert!(settings.warm_connections && stages[1].flush && second.stages.is_none());
+        Ok(())
+    }
+}
@@ -1,18 +1,57 @@
-use anyhow::{Context, Result};
-use clap::Parser;
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
+use std::net::IpAddr;
 use std::str::FromStr;
 use std::time::Duration;
-use strum::EnumString;
-use crate::support::Operation::Get;
+use strum::{Display, EnumString};
+
+use crate::bands::{ino_parse_bands, LatencyBands};
+use crate::bandwidth::{ino_parse_bandwidth, Bandwidth};
+use crate::benchmark::{ino_check_percentile, ino_check_sample_rate};
+use crate::burst::{ino_parse_burst, Burst};
+use crate::checksum::{ino_parse_checksum, Checksum};
+use crate::config::Defaults;
+use crate::credentials::{ino_read_tokens, Credential};
+use crate::dns::DnsQuery;
+use crate::flow::{ino_first_target, ino_targets, Step};
+use crate::groups::ClientGroup;
+use crate::headers::{ino_parse_expectation, HeaderExpectation};
+use crate::kafka::KafkaSettings;
+use crate::latency::{ino_parse_latency, AddedLatency};
+use crate::notify::Notification;
+use crate::mqtt::MqttSettings;
+use crate::pacing::Pacing;
+use crate::profile::LoadProfile;
+use crate::proxy_protocol::{ino_parse_source, ProxyProtocol, ProxyVersion, SourceRange};
+use crate::signing::Signing;
+use crate::stable::{ino_parse_stop_on_stable, StopOnStable};
+use crate::stages::Stage;
+use crate::sweep::Sweep;
+use crate::tags::{ino_check_tag_key, ino_format_tags, ino_parse_tag, Tags};
+use crate::prometheus::Prometheus;
+use crate::redis::RedisCommands;
+use crate::replay::ReplayOrder;
+use crate::sql::SqlQuery;
+use crate::threshold::Thresholds;
+use crate::xml::{ino_parse_xpath_expectation, XPathExpectation, XML_CONTENT_TYPE};
 
 #[derive(Parser, Debug, Default)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
     #[arg(short, long)]
     verbose: bool,
-    #[arg(short, long, conflicts_with = "scenario", required_unless_present = "scenario")]
+    #[arg(long, value_name = "GOOD:SLOW", value_parser = ino_parse_bands, requires = "verbose")]
+    latency_bands: Option<LatencyBands>,
+    #[arg(short, long, conflicts_with = "scenario", required_unless_present_any = ["scenario", "url_list", "query"])]
     target: Option<String>,
     #[arg(short, long, conflicts_with = "scenario")]
     request_body: Option<String>,
@@ -20,37 +59,563 @@ pub struct Args {
     clients: usize,
     #[arg(short, long, default_value_t = 1, conflicts_with_all = ["duration", "scenario"])]
     iterations: usize,
-    #[arg(short, long, conflicts_with_all = ["iterations", "scenario"])]
+    #[arg(short, long, value_parser = ino_parse_seconds, conflicts_with_all = ["iterations", "scenario"])]
     duration: Option<u64>,
+    #[arg(long, value_name = "METRIC:TOLERANCE%:WINDOW", value_parser = ino_parse_stop_on_stable)]
+    stop_on_stable: Option<StopOnStable>,
     #[arg(long, conflicts_with = "scenario")]
     headers: Option<Vec<String>>,
     #[arg(long, conflicts_with = "target")]
     scenario: Option<String>,
+    #[arg(long)]
+    save_history: bool,
+    #[arg(long)]
+    label: Option<String>,
+    #[arg(long, value_name = "KEY=VALUE", value_parser = ino_parse_tag)]
+    tag: Option<Vec<(String, String)>>,
+    #[arg(long)]
+    publish: Option<String>,
+    #[arg(long)]
+    pushgateway: Option<String>,
+    #[arg(long, conflicts_with = "scenario")]
+    url_list: Option<String>,
+    #[arg(long, value_enum, default_value_t = ReplayOrder::Sequential, conflicts_with = "scenario")]
+    order: ReplayOrder,
+    #[arg(long, requires = "url_list")]
+    replay_timestamps: bool,
+    #[arg(long, conflicts_with = "scenario")]
+    pool_max_idle_per_host: Option<usize>,
+    #[arg(long, conflicts_with = "scenario")]
+    max_connections_per_host: Option<usize>,
+    #[arg(long, value_enum, default_value_t = Mode::Http, conflicts_with = "scenario")]
+    mode: Mode,
+    #[arg(long, num_args = 1..=3, value_names = ["NAME", "TYPE", "@SERVER"], conflicts_with = "scenario")]
+    query: Option<Vec<String>>,
+    #[arg(long, requires = "query")]
+    dns_tcp: bool,
+    #[arg(long, value_name = "COMMAND", conflicts_with = "scenario")]
+    redis_command: Option<Vec<String>>,
+    #[arg(long, value_name = "N", requires = "redis_command")]
+    pipeline: Option<usize>,
+    #[arg(long, value_name = "QUERY", conflicts_with = "scenario")]
+    sql: Option<String>,
+    #[arg(long, value_name = "FILE", requires = "sql")]
+    data: Option<String>,
+    #[arg(long, conflicts_with = "scenario")]
+    topic: Option<String>,
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=1), requires = "topic")]
+    qos: Option<u8>,
+    #[arg(long, requires = "topic")]
+    subscribe: bool,
+    #[arg(long, value_name = "BYTES", requires = "topic")]
+    payload_size: Option<usize>,
+    #[arg(long, value_name = "TOPIC", conflicts_with = "scenario")]
+    kafka_topic: Option<String>,
+    #[arg(long, value_name = "BYTES", requires = "kafka_topic")]
+    message_size: Option<usize>,
+    #[arg(long, value_name = "N", requires = "kafka_topic")]
+    batch: Option<usize>,
+    #[arg(long, value_name = "N", conflicts_with = "scenario")]
+    sse_events: Option<u32>,
+    #[arg(long, value_name = "SECONDS", value_parser = ino_parse_seconds, conflicts_with = "scenario")]
+    sse_duration: Option<u64>,
+    #[arg(long)]
+    no_precheck: bool,
+    #[arg(long)]
+    audit_accounting: bool,
+    #[arg(long)]
+    interactive: bool,
+    #[arg(long, value_parser = ino_parse_duration)]
+    watch: Option<Duration>,
+    #[arg(long, requires = "watch")]
+    watch_tolerance: Option<f64>,
+    #[arg(long, value_enum, default_value_t = HttpVersion::Http1, conflicts_with = "scenario")]
+    http_version: HttpVersion,
+    #[arg(long, value_parser = ino_parse_duration)]
+    timeout: Option<Duration>,
+    #[arg(long, value_name = "URL")]
+    proxy: Option<String>,
+    #[arg(long, value_enum, value_name = "VERSION", conflicts_with = "scenario")]
+    proxy_protocol: Option<ProxyVersion>,
+    #[arg(long, value_name = "ADDR|CIDR", value_parser = ino_parse_source, requires = "proxy_protocol")]
+    proxy_source: Option<Vec<SourceRange>>,
+    #[arg(long, value_name = "MS", value_parser = ino_parse_millis, conflicts_with = "scenario")]
+    start_jitter: Option<u64>,
+    #[arg(long)]
+    seed: Option<u64>,
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=5))]
+    histogram_sigfig: Option<u8>,
+    #[arg(long, value_name = "MS", value_parser = ino_parse_millis)]
+    histogram_max: Option<u64>,
+    #[arg(long, value_name = "P", value_delimiter = ',', value_parser = ino_parse_percentile)]
+    percentiles: Option<Vec<f64>>,
+    #[arg(long, value_name = "K", default_value_t = 1, conflicts_with = "scenario")]
+    in_flight: usize,
+    #[arg(long, value_name = "SIZE:INTERVAL", value_parser = ino_parse_burst, conflicts_with = "scenario")]
+    burst: Option<Burst>,
+    #[arg(long, value_name = "RPS")]
+    rate: Option<f64>,
+    #[arg(long, value_name = "SECONDS", value_parser = ino_parse_seconds, requires = "duration", conflicts_with = "scenario")]
+    ramp_down: Option<u64>,
+    #[arg(long, value_name = "SECONDS", value_parser = ino_parse_seconds, requires = "duration", conflicts_with = "scenario")]
+    cooldown: Option<u64>,
+    #[arg(long, conflicts_with = "scenario")]
+    respect_retry_after: bool,
+    #[arg(long, value_name = "FILE", conflicts_with = "scenario")]
+    tokens: Option<String>,
+    #[arg(long, value_name = "NAME=VALUE", value_parser = ino_parse_expectation, conflicts_with = "scenario")]
+    expect_header: Option<Vec<HeaderExpectation>>,
+    #[arg(long, value_name = "NAME", conflicts_with = "scenario")]
+    extract_header: Option<Vec<String>>,
+    #[arg(long, value_name = "XPATH=VALUE", value_parser = ino_parse_xpath_expectation, conflicts_with_all = ["scenario", "no_read_body"])]
+    expect_xpath: Option<Vec<XPathExpectation>>,
+    #[arg(long, value_name = "HASH", value_parser = ino_parse_checksum, conflicts_with_all = ["scenario", "no_read_body"])]
+    verify_sha256: Option<Checksum>,
+    #[arg(long, value_name = "PARAM", conflicts_with = "scenario")]
+    cache_bust: Option<String>,
+    #[arg(long, conflicts_with_all = ["scenario", "url_list"])]
+    conditional: bool,
+    #[arg(long, value_name = "BYTES", conflicts_with_all = ["scenario", "url_list"])]
+    range_size: Option<u64>,
+    #[arg(long, conflicts_with = "scenario")]
+    download: bool,
+    #[arg(long, conflicts_with_all = ["scenario", "download", "range_size", "conditional"])]
+    no_read_body: bool,
+    #[arg(long, value_name = "BYTES", conflicts_with = "scenario")]
+    upload: Option<u64>,
+    #[arg(long, value_name = "BYTES_PER_SEC", requires = "upload")]
+    upload_rate: Option<u64>,
+    #[arg(long, value_name = "RATE", value_parser = ino_parse_bandwidth, conflicts_with = "scenario")]
+    max_bandwidth: Option<Bandwidth>,
+    #[arg(long, value_name = "RATE", value_parser = ino_parse_bandwidth, conflicts_with = "scenario")]
+    client_bandwidth: Option<Bandwidth>,
+    #[arg(long, value_name = "DELAY", value_parser = ino_parse_latency, conflicts_with = "scenario")]
+    added_latency: Option<AddedLatency>,
+    #[arg(long)]
+    workers: Option<usize>,
+    #[arg(long, value_name = "URL")]
+    monitor: Option<String>,
+    #[arg(long, value_name = "FILE")]
+    raw_out: Option<String>,
+    #[arg(long, value_name = "FILE")]
+    heatmap: Option<String>,
+    #[arg(long, value_name = "RATE", value_parser = ino_parse_sample_rate)]
+    sample_rate: Option<f64>,
+    #[arg(long, value_enum)]
+    latency_boundary: Option<LatencyBoundary>,
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+    #[arg(long)]
+    no_config: bool,
+}
+
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Shows the p95/throughput trend of the saved runs for a target
+    History {
+        target: String,
+        #[arg(long)]
+        label: Option<String>,
+    },
+    /// Runs the same workload over HTTP/1.1 and HTTP/2 and compares the results
+    CompareProtocols(Workload),
+    /// Sends a few requests with a single client, echoes them and fails on the first unmet assertion
+    Smoke(Smoke),
+    /// Merges the JSON exit summaries of several inoue processes, the runs of a target together
+    Merge {
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// Explores a raw results file, filtering the requests and recomputing their percentiles
+    Inspect {
+        file: String,
+    },
+}
+
+
+#[derive(clap::Args, Debug)]
+pub struct Workload {
+    #[arg(short, long, conflicts_with = "scenario", required_unless_present = "scenario")]
+    target: Option<String>,
+    #[arg(short, long, conflicts_with = "scenario")]
+    request_body: Option<String>,
+    #[arg(short, long, default_value_t = 1, conflicts_with = "scenario")]
+    clients: usize,
+    #[arg(short, long, default_value_t = 1, conflicts_with_all = ["duration", "scenario"])]
+    iterations: usize,
+    #[arg(short, long, value_parser = ino_parse_seconds, conflicts_with_all = ["iterations", "scenario"])]
+    duration: Option<u64>,
+    #[arg(long, conflicts_with = "scenario")]
+    headers: Option<Vec<String>>,
+    #[arg(long)]
+    scenario: Option<String>,
+}
+
+
+impl Workload {
+
+    /**
+    *=================================================================
+    * ino_to_settings()
+    *=================================================================
+    *
+    * Builds the settings of the workload like the main command
+    * would, from the options or from the scenario file.
+    *
+    *=================================================================
+    * @param void
+    * @return Result<Settings>
+    */
+    pub fn ino_to_settings(self) -> Result<Settings> {
+        Args {
+            target: self.target,
+            request_body: self.request_body,
+            clients: self.clients,
+            iterations: self.iterations,
+            duration: self.duration,
+            headers: self.headers,
+            scenario: self.scenario,
+            ..Default::default()
+        }
+        .ino_to_string()
+    }
+}
+
+
+#[derive(clap::Args, Debug)]
+pub struct Smoke {
+    #[arg(short, long, conflicts_with = "scenario", required_unless_present = "scenario")]
+    target: Option<String>,
+    #[arg(short, long, conflicts_with = "scenario")]
+    request_body: Option<String>,
+    #[arg(short, long, default_value_t = 3)]
+    iterations: usize,
+    #[arg(long, conflicts_with = "scenario")]
+    headers: Option<Vec<String>>,
+    #[arg(long, value_name = "NAME=VALUE", value_parser = ino_parse_expectation, conflicts_with = "scenario")]
+    expect_header: Option<Vec<HeaderExpectation>>,
+    #[arg(long)]
+    scenario: Option<String>,
 }
 
 
-#[derive(Eq, PartialEq, Debug, EnumString)]
+impl Smoke {
+
+    /**
+    *=================================================================
+    * ino_to_settings()
+    *=================================================================
+    *
+    * Builds the settings of the smoke check from the options or from
+    * the scenario file of the load test, then narrows them to a
+    * single client sending the iterations once, one at a time,
+    * echoing every request, without any groups, stages, sweep,
+    * burst, rate, pacing, monitoring or export.
+    *
+    *=================================================================
+    * @param void
+    * @return Result<Settings>
+    */
+    pub fn ino_to_settings(self) -> Result<Settings> {
+        let mut settings = Args {
+            target: self.target,
+            request_body: self.request_body,
+            headers: self.headers,
+            expect_header: self.expect_header,
+            scenario: self.scenario,
+            ..Default::default()
+        }
+        .ino_to_string()?;
+        if self.iterations == 0 {
+            anyhow::bail!("The smoke check needs at least one iteration");
+        }
+        settings.clients = 1;
+        settings.in_flight = 1;
+        settings.requests = self.iterations;
+        settings.duration = None;
+        settings.burst = None;
+        settings.rate = None;
+        settings.groups = None;
+        settings.stages = None;
+        settings.sweep = None;
+        settings.ramp_down = None;
+        settings.cooldown = None;
+        settings.pacing = None;
+        settings.start_jitter = None;
+        settings.verbose = false;
+        settings.interactive = false;
+        settings.watch = None;
+        settings.save_history = false;
+        settings.publish = None;
+        settings.prometheus = None;
+        settings.notify = None;
+        settings.monitor = None;
+        settings.raw_out = None;
+        settings.heatmap = None;
+        settings.sample_rate = None;
+        settings.echo = true;
+        Ok(settings)
+    }
+}
+
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    #[default]
+    Http,
+    TcpConnect,
+    Dns,
+    Sse,
+    Redis,
+    Sql,
+    Mqtt,
+    Kafka,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum HttpVersion {
+    #[default]
+    Http1,
+    Http2,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum LatencyBoundary {
+    Headers,
+    #[default]
+    Body,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, EnumString, Display)]
 pub enum Operation {
     #[strum(serialize = "GET")]
     Get,
     #[strum(serialize = "POST")]
     Post,
+    #[strum(serialize = "HEAD")]
     Head,
+    #[strum(serialize = "PATCH")]
     Patch,
+    #[strum(serialize = "PUT")]
     Put,
+    #[strum(serialize = "DELETE")]
     Delete,
 }
 
+impl Operation {
+
+    /**
+    *=================================================================
+    * ino_method()
+    *=================================================================
+    *
+    * HTTP method of the operation.
+    *
+    *=================================================================
+    * @param void
+    * @return Method
+    */
+    pub fn ino_method(self) -> Method {
+        match self {
+            Operation::Get => Method::GET,
+            Operation::Post => Method::POST,
+            Operation::Head => Method::HEAD,
+            Operation::Patch => Method::PATCH,
+            Operation::Put => Method::PUT,
+            Operation::Delete => Method::DELETE,
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct Settings {
+    #[serde(default)]
     pub clients: usize,
     pub requests: usize,
+    #[serde(default)]
     pub target: String,
+    #[serde(default, with = "human_duration::option")]
     pub keep_alive: Option<Duration>,
     pub body: Option<String>,
     pub headers: Option<Vec<Header>>,
+    #[serde(default, deserialize_with = "human_duration::seconds")]
     pub duration: Option<u64>,
+    #[serde(default)]
+    pub stop_on_stable: Option<StopOnStable>,
+    #[serde(default)]
     pub verbose: bool,
+    #[serde(default)]
+    pub latency_bands: Option<LatencyBands>,
+    #[serde(default)]
+    pub save_history: bool,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub tags: Tags,
+    #[serde(default)]
+    pub publish: Option<String>,
+    #[serde(default)]
+    pub notify: Option<Vec<Notification>>,
+    #[serde(default)]
+    pub thresholds: Option<Thresholds>,
+    #[serde(default)]
+    pub prometheus: Option<Prometheus>,
+    #[serde(default)]
+    pub url_list: Option<String>,
+    #[serde(default)]
+    pub order: ReplayOrder,
+    #[serde(default)]
+    pub replay_timestamps: bool,
+    #[serde(default)]
+    pub pool_max_idle_per_host: Option<usize>,
+    #[serde(default)]
+    pub max_connections_per_host: Option<usize>,
+    #[serde(default)]
+    pub mode: Mode,
+    #[serde(default)]
+    pub dns: Option<DnsQuery>,
+    #[serde(default)]
+    pub redis: Option<RedisCommands>,
+    #[serde(default)]
+    pub sql: Option<SqlQuery>,
+    #[serde(default)]
+    pub mqtt: Option<MqttSettings>,
+    #[serde(default)]
+    pub kafka: Option<KafkaSettings>,
+    #[serde(default)]
+    pub sse_events: Option<u32>,
+    #[serde(default, deserialize_with = "human_duration::seconds")]
+    pub sse_duration: Option<u64>,
+    #[serde(default)]
+    pub no_precheck: bool,
+    #[serde(default)]
+    pub audit_accounting: bool,
+    #[serde(default)]
+    pub interactive: bool,
+    #[serde(default, with = "human_duration::option")]
+    pub watch: Option<Duration>,
+    #[serde(default = "ino_default_watch_tolerance")]
+    pub watch_tolerance: f64,
+    #[serde(default)]
+    pub http_version: HttpVersion,
+    #[serde(default, deserialize_with = "human_duration::millis")]
+    pub start_jitter: Option<u64>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default = "ino_default_histogram_sigfig")]
+    pub histogram_sigfig: u8,
+    #[serde(default, deserialize_with = "human_duration::millis")]
+    pub histogram_max: Option<u64>,
+    #[serde(default)]
+    pub percentiles: Vec<f64>,
+    #[serde(default = "ino_default_in_flight")]
+    pub in_flight: usize,
+    #[serde(default)]
+    pub burst: Option<Burst>,
+    #[serde(default)]
+    pub rate: Option<f64>,
+    #[serde(default)]
+    pub pacing: Option<Pacing>,
+    #[serde(default)]
+    pub profile: Option<LoadProfile>,
+    #[serde(default, deserialize_with = "human_duration::seconds")]
+    pub ramp_down: Option<u64>,
+    #[serde(default, deserialize_with = "human_duration::seconds")]
+    pub cooldown: Option<u64>,
+    #[serde(default)]
+    pub respect_retry_after: bool,
+    #[serde(default)]
+    pub credentials: Option<Vec<Credential>>,
+    #[serde(default)]
+    pub steps: Option<Vec<Step>>,
+    #[serde(default)]
+    pub groups: Option<Vec<ClientGroup>>,
+    #[serde(default)]
+    pub stages: Option<Vec<Stage>>,
+    #[serde(default)]
+    pub warm_connections: bool,
+    #[serde(default)]
+    pub hosts: BTreeMap<String, IpAddr>,
+    #[serde(default)]
+    pub signing: Option<Signing>,
+    #[serde(default, with = "human_duration::option")]
+    pub timeout: Option<Duration>,
+    #[serde(default)]
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub proxy_protocol: Option<ProxyProtocol>,
+    #[serde(default)]
+    pub expect_header: Vec<HeaderExpectation>,
+    #[serde(default)]
+    pub extract_header: Vec<String>,
+    #[serde(default)]
+    pub expect_xpath: Vec<XPathExpectation>,
+    #[serde(default)]
+    pub verify_sha256: Option<Checksum>,
+    #[serde(default)]
+    pub xml: Option<String>,
+    #[serde(default)]
+    pub cache_bust: Option<String>,
+    #[serde(default)]
+    pub sweep: Option<Sweep>,
+    #[serde(default)]
+    pub conditional: bool,
+    #[serde(default)]
+    pub range_size: Option<u64>,
+    #[serde(default)]
+    pub download: bool,
+    #[serde(default)]
+    pub no_read_body: bool,
+    #[serde(default)]
+    pub upload: Option<u64>,
+    #[serde(default)]
+    pub upload_rate: Option<u64>,
+    #[serde(default)]
+    pub max_bandwidth: Option<Bandwidth>,
+    #[serde(default)]
+    pub client_bandwidth: Option<Bandwidth>,
+    #[serde(default)]
+    pub added_latency: Option<AddedLatency>,
+    #[serde(default)]
+    pub workers: Option<usize>,
+    #[serde(default)]
+    pub monitor: Option<String>,
+    #[serde(default)]
+    pub raw_out: Option<String>,
+    #[serde(default)]
+    pub heatmap: Option<String>,
+    #[serde(default)]
+    pub sample_rate: Option<f64>,
+    #[serde(default)]
+    pub latency_boundary: LatencyBoundary,
+    #[serde(default)]
+    pub format: OutputFormat,
+    #[serde(skip)]
+    pub echo: bool,
+}
+
+fn ino_default_watch_tolerance() -> f64 {
+    20.0
+}
+
+fn ino_default_histogram_sigfig() -> u8 {
+    5
+}
+
+fn ino_default_in_flight() -> usize {
+    1
 }
 
 #[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
@@ -60,6 +625,24 @@ pub struct Header {
 }
 
 
+/**
+ *=================================================================
+ * ino_default_content_type()
+ *=================================================================
+ *
+ * Adds a Content-Type header unless the headers already have one.
+ *
+ *=================================================================
+ * @param headers &mut Vec<Header>
+ * @param content_type &str
+ * @return void
+ */
+pub fn ino_default_content_type(headers: &mut Vec<Header>, content_type: &str) {
+    if !headers.iter().any(|h| h.key.eq_ignore_ascii_case("content-type")) {
+        headers.push(Header { key: "Content-Type".to_string(), value: content_type.to_string() });
+    }
+}
+
 
 /**
  *=================================================================
@@ -74,12 +657,62 @@ pub struct Header {
  * @return Result<Settings>
  */
 impl Args {
-    pub fn ino_to_string(self) -> Result<Settings> {
-        match self.scenario {
-            None => Settings::ino_from_args(self),
-            Some(file) => Settings::ino_from_file(file),
+    pub fn ino_to_string(mut self) -> Result<Settings> {
+        let defaults = match self.no_config {
+            true => Defaults::default(),
+            false => Defaults::ino_load()?,
+        };
+        match &self.scenario {
+            None => {
+                self.ino_apply_defaults(&defaults);
+                let mut settings = Settings::ino_from_args(self)?;
+                settings.keep_alive = defaults.keep_alive;
+                settings.hosts = defaults.hosts;
+                if settings.prometheus.is_none() {
+                    settings.prometheus = defaults.prometheus;
+                }
+                Ok(settings)
+            }
+            Some(file) => {
+                let mut settings = Settings::ino_from_file(file.clone(), &defaults)?;
+                settings.ino_apply_args(&self);
+                Ok(settings)
+            }
         }
     }
+
+    /**
+    *=================================================================
+    * ino_apply_defaults()
+    *=================================================================
+    *
+    * Fills the options left out of the command line with the
+    * defaults of the user. The tags of the command line win over the
+    * default tags of the same key.
+    *
+    *=================================================================
+    * @param defaults &Defaults
+    * @return void
+    */
+    pub fn ino_apply_defaults(&mut self, defaults: &Defaults) {
+        self.format = self.format.or(defaults.format);
+        self.latency_bands = self.latency_bands.or(defaults.latency_bands);
+        self.histogram_sigfig = self.histogram_sigfig.or(defaults.histogram_sigfig);
+        self.histogram_max = self.histogram_max.or(defaults.histogram_max);
+        self.percentiles = self.percentiles.take().or_else(|| defaults.percentiles.clone());
+        self.timeout = self.timeout.or(defaults.timeout);
+        self.proxy = self.proxy.take().or_else(|| defaults.proxy.clone());
+        self.latency_boundary = self.latency_boundary.or(defaults.latency_boundary);
+        self.sample_rate = self.sample_rate.or(defaults.sample_rate);
+        self.workers = self.workers.or(defaults.workers);
+        self.pool_max_idle_per_host = self.pool_max_idle_per_host.or(defaults.pool_max_idle_per_host);
+        self.max_connections_per_host = self.max_connections_per_host.or(defaults.max_connections_per_host);
+        self.publish = self.publish.take().or_else(|| defaults.publish.clone());
+        self.save_history |= defaults.save_history.unwrap_or_default();
+        self.no_precheck |= defaults.no_precheck.unwrap_or_default();
+        let tags = self.tag.take().unwrap_or_default();
+        self.tag = Some(defaults.tags.clone().into_iter().chain(tags).collect());
+    }
 }
 
 
@@ -100,17 +733,26 @@ impl Settings {
     * @return void
     */
     pub fn ino_print_banner(&self) {
+        let target = match (&self.url_list, &self.dns) {
+            (Some(file), _) => format!("URL list {}", file),
+            (None, Some(_)) => self.ino_name(),
+            (None, None) if self.target.is_empty() => self.ino_name(),
+            (None, None) => self.target.clone(),
+        };
         let banner = match &self.duration {
             None => format!(
                 "kamehameha to {} with {} concurrent clients and {} total iterations",
-                &self.target, &self.clients, &self.requests
+                &target, &self.clients, &self.requests
             ),
             Some(d) => format!(
                 "kamehameha to {} with {} concurrent clients for {} seconds",
-                &self.target, &self.clients, d
+                &target, &self.clients, d
             ),
         };
         println!("{}", banner);
+        if !self.tags.is_empty() {
+            println!("Tags {}", ino_format_tags(&self.tags));
+        }
     }
 
 
@@ -119,17 +761,68 @@ impl Settings {
     * ino_request_by_client()
     *=================================================================
     *
-    * Calculates the number of requests per client.
-    *
-    * Divides total requests by the number of clients.
+    * Calculates the number of requests of a client.
     *
+    * Divides total requests by the number of clients, the first
+    * clients send one more request each until the remainder is
+    * spread, so the clients send the requested total together.
+    * Clients added while running get the even share.
     *
     *=================================================================
-    * @param void
+    * @param num_client usize
     * @return usize
     */
-    pub fn ino_requests_by_client(&self) -> usize {
-        self.requests / self.clients
+    pub fn ino_requests_by_client(&self, num_client: usize) -> usize {
+        let clients = self.clients.max(1);
+        self.requests / clients + usize::from(num_client < self.requests % clients)
+    }
+
+
+    /**
+    *=================================================================
+    * ino_client_end()
+    *=================================================================
+    *
+    * Time since the start at which a client of a timed run stops.
+    *
+    * Without a ramp-down every client runs for the whole duration.
+    * With one the clients stop one after the other during its last
+    * seconds, the highest ids first, so the concurrency winds down
+    * to zero at the end of the duration. Clients added while
+    * running stop first.
+    *
+    *=================================================================
+    * @param num_client usize
+    * @return Option<Duration> None for a run by iterations
+    */
+    pub fn ino_client_end(&self, num_client: usize) -> Option<Duration> {
+        let duration = Duration::from_secs(self.duration?);
+        let Some(ramp_down) = self.ramp_down.filter(|r| *r > 0) else { return Some(duration) };
+        let ramp_down = Duration::from_secs(ramp_down).min(duration);
+        let clients = self.clients.max(1) as u32;
+        let rank = clients.saturating_sub(num_client as u32 + 1);
+        Some(duration - ramp_down + ramp_down * (rank + 1) / clients)
+    }
+
+
+    /**
+    *=================================================================
+    * ino_rng()
+    *=================================================================
+    *
+    * Random generator of one consumer of the run. With a seed every
+    * stream is reproducible and independent from the others,
+    * without one it is seeded from the OS entropy.
+    *
+    *=================================================================
+    * @param stream u64 identifies the consumer
+    * @return StdRng
+    */
+    pub fn ino_rng(&self, stream: u64) -> StdRng {
+        match self.seed {
+            None => StdRng::from_entropy(),
+            Some(seed) => StdRng::seed_from_u64(seed ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15)),
+        }
     }
 
 
@@ -141,29 +834,145 @@ impl Settings {
     * Loads settings from a YAML file.
     *
     * Parses the file and returns a Settings instance.
-    * Handles errors with appropriate messages.
+    * Handles errors with appropriate messages, invalid headers
+    * are reported before the run starts. The keys the scenario
+    * leaves out are taken from the defaults of the user.
     *
     *
     *=================================================================
     * @param file String
+    * @param defaults &Defaults merged under the scenario
     * @return Result<Self>
     */
-    pub fn ino_from_file(file: String) -> Result<Self> {
+    pub fn ino_from_file(file: String, defaults: &Defaults) -> Result<Self> {
         let content = fs::read_to_string(&file)
             .with_context(|| format!("Failed to read file from {}", &file))?;
-        let settings: Settings = serde_yaml::from_str(&content)
+        let mut settings: Settings = serde_yaml::from_str(&content)
             .with_context(|| "Invalid YAML format".to_string())?;
+        if *defaults != Defaults::default() {
+            let mut scenario: serde_yaml::Value = serde_yaml::from_str(&content)?;
+            defaults.ino_merge_under(&mut scenario)?;
+            settings = serde_yaml::from_value(scenario).with_context(|| "Invalid defaults for the scenario".to_string())?;
+        }
+        if let Some(xml) = settings.xml.take() {
+            settings.body = Some(xml);
+            ino_default_content_type(settings.headers.get_or_insert_with(Vec::new), XML_CONTENT_TYPE);
+        }
+        if settings.profile.is_some() && (settings.pacing.is_none() || settings.duration.is_none()) {
+            bail!("The load profile of {} needs a pacing and a duration", file);
+        }
+        if settings.clients == 0 && settings.groups.is_none() && settings.stages.is_none() {
+            bail!("The scenario {} needs clients, client groups or stages", file);
+        }
+        if let Some(signing) = settings.signing.as_mut() {
+            signing.ino_resolve()?;
+            if settings.upload.is_some() {
+                bail!("The generated upload bodies can not be signed");
+            }
+        }
+        for key in settings.tags.keys() {
+            ino_check_tag_key(key)?;
+        }
+        settings.ino_check_headers()?;
         Ok(settings)
     }
 
 
+    /**
+    *=================================================================
+    * ino_apply_args()
+    *=================================================================
+    *
+    * Applies the command line options that are allowed alongside
+    * a scenario file on top of the loaded settings.
+    *
+    *=================================================================
+    * @param args &Args
+    * @return void
+    */
+    pub fn ino_apply_args(&mut self, args: &Args) {
+        self.save_history |= args.save_history;
+        self.no_precheck |= args.no_precheck;
+        self.audit_accounting |= args.audit_accounting;
+        self.interactive |= args.interactive;
+        if args.latency_bands.is_some() {
+            self.latency_bands = args.latency_bands;
+        }
+        if args.stop_on_stable.is_some() {
+            self.stop_on_stable = args.stop_on_stable;
+        }
+        if args.watch.is_some() {
+            self.watch = args.watch;
+        }
+        if args.seed.is_some() {
+            self.seed = args.seed;
+        }
+        if args.workers.is_some() {
+            self.workers = args.workers;
+        }
+        if args.monitor.is_some() {
+            self.monitor = args.monitor.clone();
+        }
+        if args.raw_out.is_some() {
+            self.raw_out = args.raw_out.clone();
+        }
+        if args.heatmap.is_some() {
+            self.heatmap = args.heatmap.clone();
+        }
+        if args.sample_rate.is_some() {
+            self.sample_rate = args.sample_rate;
+        }
+        if let Some(boundary) = args.latency_boundary {
+            self.latency_boundary = boundary;
+        }
+        if let Some(format) = args.format {
+            self.format = format;
+        }
+        if let Some(sigfig) = args.histogram_sigfig {
+            self.histogram_sigfig = sigfig;
+        }
+        if args.histogram_max.is_some() {
+            self.histogram_max = args.histogram_max;
+        }
+        if let Some(percentiles) = &args.percentiles {
+            self.percentiles = percentiles.clone();
+        }
+        if args.timeout.is_some() {
+            self.timeout = args.timeout;
+        }
+        if args.proxy.is_some() {
+            self.proxy = args.proxy.clone();
+        }
+        if let Some(tolerance) = args.watch_tolerance {
+            self.watch_tolerance = tolerance;
+        }
+        if args.label.is_some() {
+            self.label = args.label.clone();
+        }
+        if args.rate.is_some() {
+            self.rate = args.rate;
+        }
+        self.tags.extend(args.tag.iter().flatten().cloned());
+        if args.publish.is_some() {
+            self.publish = args.publish.clone();
+        }
+        if let Some(pushgateway) = &args.pushgateway {
+            match &mut self.prometheus {
+                None => self.prometheus = Some(Prometheus::new(pushgateway.clone())),
+                Some(prometheus) => prometheus.pushgateway = pushgateway.clone(),
+            }
+        }
+    }
+
+
     /**
     *=================================================================
     * ino_from_args()
     *=================================================================
     * Creates a Settings instance from Args.
     *
-    * Handles headers and request body.
+    * Handles headers and request body, headers that are not
+    * valid HTTP headers are reported before the run starts.
     * Populates Settings with provided arguments.
     *
     *
@@ -174,23 +983,45 @@ impl Settings {
     *
     */
     pub fn ino_from_args(args: Args) -> Result<Self> {
-        let headers = args.headers.map(|headers_string| {
-            headers_string
-                .iter()
-                .filter_map(|header| {
-                    let split: Vec<&str> = header.split(':').collect();
-                    if split.len() == 2 {
-                        Some(Header {
-                            key: split[0].trim().to_string(),
-                            value: split[1].trim().to_string(),
+        let headers = match args.headers {
+            None => None,
+            Some(headers_string) => Some(
+                headers_string
+                    .iter()
+                    .map(|header| {
+                        let (key, value) = header
+                            .split_once(':')
+                            .with_context(|| format!("Invalid header {}, use headerName:HeaderValue", header))?;
+                        Ok(Header {
+                            key: key.trim().to_string(),
+                            value: value.trim().to_string(),
                         })
-                    } else {
-                        None
-                    }
-                })
-                .collect()
+                    })
+                    .collect::<Result<Vec<Header>>>()?,
+            ),
+        };
+
+        let dns = match &args.query {
+            None => None,
+            Some(values) => Some(DnsQuery::ino_from_args(values, args.target.as_deref(), args.dns_tcp)?),
+        };
+
+        let redis = args.redis_command.map(|commands| RedisCommands {
+            commands,
+            pipeline: args.pipeline.unwrap_or(1),
         });
 
+        let sql = args.sql.map(|query| SqlQuery { query, data: args.data });
+
+        let mqtt = args.topic.map(|topic| MqttSettings {
+            topic,
+            qos: args.qos.unwrap_or(0),
+            subscribe: args.subscribe,
+            payload_size: args.payload_size.unwrap_or(MqttSettings::default().payload_size),
+        });
+
+        let kafka = args.kafka_topic.map(|topic| KafkaSettings::ino_from_args(topic, args.message_size, args.batch));
+
         let body = match args.request_body {
             None => None,
             Some(file) => {
@@ -200,16 +1031,169 @@ impl Settings {
             }
         };
 
-        Ok(Settings {
+        let settings = Settings {
             clients: args.clients,
             requests: args.iterations,
-            target: args.target.expect("Target URL is required"),
+            target: match (args.target, &args.url_list, &dns) {
+                (Some(target), _, _) => target,
+                (None, Some(_), _) | (None, _, Some(_)) => String::new(),
+                (None, None, None) => panic!("Target URL is required"),
+            },
             keep_alive: None,
             body,
             headers,
             duration: args.duration,
+            stop_on_stable: args.stop_on_stable,
             verbose: args.verbose,
-        })
+            latency_bands: args.latency_bands,
+            save_history: args.save_history,
+            label: args.label,
+            tags: args.tag.unwrap_or_default().into_iter().collect(),
+            publish: args.publish,
+            notify: None,
+            thresholds: None,
+            prometheus: args.pushgateway.map(Prometheus::new),
+            url_list: args.url_list,
+            order: args.order,
+            replay_timestamps: args.replay_timestamps,
+            pool_max_idle_per_host: args.pool_max_idle_per_host,
+            max_connections_per_host: args.max_connections_per_host,
+            mode: match (&dns, &redis, &sql, &mqtt, &kafka) {
+                (Some(_), _, _, _, _) => Mode::Dns,
+                (None, Some(_), _, _, _) => Mode::Redis,
+                (None, None, Some(_), _, _) => Mode::Sql,
+                (None, None, None, Some(_), _) => Mode::Mqtt,
+                (None, None, None, None, Some(_)) => Mode::Kafka,
+                (None, None, None, None, None) => args.mode,
+            },
+            dns,
+            redis,
+            sql,
+            mqtt,
+            kafka,
+            sse_events: args.sse_events,
+            sse_duration: args.sse_duration,
+            no_precheck: args.no_precheck,
+            audit_accounting: args.audit_accounting,
+            interactive: args.interactive,
+            watch: args.watch,
+            watch_tolerance: args.watch_tolerance.unwrap_or_else(ino_default_watch_tolerance),
+            http_version: args.http_version,
+            start_jitter: args.start_jitter,
+            seed: args.seed,
+            histogram_sigfig: args.histogram_sigfig.unwrap_or_else(ino_default_histogram_sigfig),
+            histogram_max: args.histogram_max,
+            percentiles: args.percentiles.unwrap_or_default(),
+            in_flight: args.in_flight.max(1),
+            burst: args.burst,
+            rate: args.rate,
+            pacing: None,
+            profile: None,
+            ramp_down: args.ramp_down,
+            cooldown: args.cooldown,
+            respect_retry_after: args.respect_retry_after,
+            steps: None,
+            groups: None,
+            stages: None,
+            warm_connections: false,
+            hosts: BTreeMap::new(),
+            signing: None,
+            timeout: args.timeout,
+            proxy: args.proxy,
+            proxy_protocol: args.proxy_protocol.map(|version| ProxyProtocol {
+                version,
+                sources: args.proxy_source.unwrap_or_default(),
+            }),
+            credentials: args.tokens.as_deref().map(ino_read_tokens).transpose()?,
+            expect_header: args.expect_header.unwrap_or_default(),
+            extract_header: args.extract_header.unwrap_or_default(),
+            expect_xpath: args.expect_xpath.unwrap_or_default(),
+            verify_sha256: args.verify_sha256,
+            xml: None,
+            cache_bust: args.cache_bust,
+            sweep: None,
+            conditional: args.conditional,
+            range_size: args.range_size,
+            download: args.download,
+            no_read_body: args.no_read_body,
+            upload: args.upload,
+            upload_rate: args.upload_rate,
+            max_bandwidth: args.max_bandwidth,
+            client_bandwidth: args.client_bandwidth,
+            added_latency: args.added_latency,
+            workers: args.workers,
+            monitor: args.monitor,
+            raw_out: args.raw_out,
+            heatmap: args.heatmap,
+            sample_rate: args.sample_rate,
+            latency_boundary: args.latency_boundary.unwrap_or_default(),
+            format: args.format.unwrap_or_default(),
+            echo: false,
+        };
+        settings.ino_check_headers()?;
+        Ok(settings)
+    }
+
+
+    /**
+    *=================================================================
+    * ino_header_map()
+    *=================================================================
+    *
+    * Headers of the settings as a header map, an error names the
+    * first header with an invalid name or value.
+    *
+    *=================================================================
+    * @param void
+    * @return Result<HeaderMap>
+    */
+    pub fn ino_header_map(&self) -> Result<HeaderMap> {
+        ino_to_header_map(self.headers.iter().flatten())
+    }
+
+
+    /**
+    *=================================================================
+    * ino_check_headers()
+    *=================================================================
+    *
+    * Fails on the first invalid header of the settings or of the
+    * credentials, before the run starts.
+    *
+    *=================================================================
+    * @param void
+    * @return Result<()>
+    */
+    pub fn ino_check_headers(&self) -> Result<()> {
+        self.ino_header_map()?;
+        if let Some(credentials) = &self.credentials {
+            if credentials.is_empty() {
+                anyhow::bail!("The credentials must not be empty");
+            }
+            for credential in credentials {
+                credential.ino_header_map()?;
+            }
+        }
+        Ok(())
+    }
+
+
+    /**
+    *=================================================================
+    * ino_credential()
+    *=================================================================
+    *
+    * Credential of a client, the credentials are assigned one per
+    * client in order and reused from the first one when there are
+    * more clients than credentials.
+    *
+    *=================================================================
+    * @param num_client usize
+    * @return Option<&Credential>
+    */
+    pub fn ino_credential(&self, num_client: usize) -> Option<&Credential> {
+        let credentials = self.credentials.as_ref().filter(|c| !c.is_empty())?;
+        credentials.get(num_client % credentials.len())
     }
 
 
@@ -240,6 +1224,32 @@ impl Settings {
     }
 
 
+    /**
+    *=================================================================
+    * ino_name()
+    *=================================================================
+    *
+    * Name of what is benchmarked, used to key the exports: the URL
+    * of the target, the DNS query, or, when replaying without a
+    * target, the path of the URL list.
+    *
+    *=================================================================
+    * @param void
+    * @return String
+    *
+    */
+    pub fn ino_name(&self) -> String {
+        if let Some(dns) = &self.dns {
+            return format!("{} {} @{}", dns.name, dns.record_type, dns.server);
+        }
+        match (&self.url_list, self.target.is_empty()) {
+            (Some(file), true) => file.clone(),
+            (None, true) => self.steps.as_deref().and_then(ino_first_target).unwrap_or_default(),
+            _ => self.ino_target(),
+        }
+    }
+
+
     /**
     *=================================================================
     * ino_target()
@@ -269,9 +1279,262 @@ impl Settings {
                 .to_string()
         }
     }
+
+
+    /**
+    *=================================================================
+    * ino_targets()
+    *=================================================================
+    *
+    * URLs the run requests: the target and the URLs of the steps.
+    *
+    *=================================================================
+    * @param void
+    * @return Vec<String>
+    */
+    pub fn ino_targets(&self) -> Vec<String> {
+        let target = (!self.target.trim().is_empty()).then(|| self.ino_target());
+        target.into_iter().chain(self.steps.as_deref().map(ino_targets).unwrap_or_default()).collect()
+    }
+}
+
+
+
+/**
+ *=================================================================
+ * ino_to_header_map()
+ *=================================================================
+ *
+ * Builds a header map, an error names the first header with an
+ * invalid name or value.
+ *
+ *=================================================================
+ * @param headers I
+ * @return Result<HeaderMap>
+ */
+pub fn ino_to_header_map<'a, I>(headers: I) -> Result<HeaderMap>
+where
+    I: IntoIterator<Item = &'a Header>,
+{
+    let mut headers_map = HeaderMap::new();
+    for header in headers {
+        let name = HeaderName::from_str(&header.key)
+            .with_context(|| format!("Invalid header name {:?}", header.key))?;
+        let value = HeaderValue::from_str(&header.value)
+            .with_context(|| format!("Invalid value {:?} of header {}", header.value, header.key))?;
+        headers_map.insert(name, value);
+    }
+    Ok(headers_map)
+}
+
+
+/**
+ *=================================================================
+ * ino_parse_duration()
+ *=================================================================
+ *
+ * Parses a human duration such as "500ms", "30s", "2m30s" or
+ * "1h", a bare number is a count of seconds.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<Duration>
+ */
+pub fn ino_parse_duration(value: &str) -> Result<Duration> {
+    ino_parse_duration_as(value, Duration::from_secs(1))
+}
+
+/**
+ *=================================================================
+ * ino_parse_seconds()
+ *=================================================================
+ *
+ * Parses a human duration into whole seconds, for the options
+ * counted in seconds. A bare number is a count of seconds.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<u64>
+ */
+pub fn ino_parse_seconds(value: &str) -> Result<u64> {
+    let duration = ino_parse_duration_as(value, Duration::from_secs(1))?;
+    if duration.subsec_nanos() != 0 {
+        bail!("{} is not a whole number of seconds", value.trim());
+    }
+    Ok(duration.as_secs())
+}
+
+/**
+ *=================================================================
+ * ino_parse_millis()
+ *=================================================================
+ *
+ * Parses a human duration into whole milliseconds, for the options
+ * counted in milliseconds. A bare number is a count of
+ * milliseconds.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<u64>
+ */
+pub fn ino_parse_millis(value: &str) -> Result<u64> {
+    let duration = ino_parse_duration_as(value, Duration::from_millis(1))?;
+    if duration.subsec_nanos() % 1_000_000 != 0 {
+        bail!("{} is not a whole number of milliseconds", value.trim());
+    }
+    Ok(duration.as_millis() as u64)
+}
+
+/**
+ *=================================================================
+ * ino_parse_duration_as()
+ *=================================================================
+ *
+ * Parses a sum of numbers followed by their unit, ms, s, m or h,
+ * from the largest unit to the smallest one, or a bare number
+ * counted in the given unit.
+ *
+ *=================================================================
+ */
+fn ino_parse_duration_as(value: &str, bare: Duration) -> Result<Duration> {
+    let value = value.trim();
+    if value.is_empty() {
+        bail!("Invalid empty duration");
+    }
+    if let Ok(number) = value.parse::<f64>() {
+        if !(number >= 0.0 && number.is_finite()) || value.starts_with(['+', '-']) {
+            bail!("Invalid duration {}", value);
+        }
+        return Ok(bare.mul_f64(number));
+    }
+    let mut rest = value;
+    let mut total = Duration::ZERO;
+    let mut previous = f64::INFINITY;
+    while !rest.is_empty() {
+        let split = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(split);
+        let number: f64 = number.parse().with_context(|| format!("Invalid duration {}", value))?;
+        let split = tail.find(|c: char| c.is_ascii_digit()).unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(split);
+        let seconds = match unit {
+            "ms" => 0.001,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3_600.0,
+            _ => bail!("Invalid duration unit {} in {}, use ms, s, m or h", unit, value),
+        };
+        if seconds >= previous {
+            bail!("Invalid duration {}, give the units from the largest to the smallest", value);
+        }
+        previous = seconds;
+        total += Duration::from_secs_f64(number * seconds);
+        rest = tail;
+    }
+    Ok(total)
 }
 
+/**
+ *=================================================================
+ * human_duration
+ *=================================================================
+ *
+ * Serde formats of the durations of a scenario: a human duration
+ * such as "90s" or "2m30s", or a bare number keeping the unit the
+ * key always had, seconds or milliseconds.
+ *
+ *=================================================================
+ */
+pub mod human_duration {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    use super::{ino_parse_duration, ino_parse_millis, ino_parse_seconds};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum HumanDuration {
+        Number(f64),
+        Text(String),
+    }
+
+    fn ino_text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+        Ok(Option::<HumanDuration>::deserialize(deserializer)?.map(|value| match value {
+            HumanDuration::Number(number) => number.to_string(),
+            HumanDuration::Text(text) => text,
+        }))
+    }
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{}ms", duration.as_millis()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let value = ino_text(deserializer)?.ok_or_else(|| serde::de::Error::custom("missing duration"))?;
+        ino_parse_duration(&value).map_err(serde::de::Error::custom)
+    }
+
+    pub fn seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+        ino_text(deserializer)?.map(|value| ino_parse_seconds(&value)).transpose().map_err(serde::de::Error::custom)
+    }
+
+    pub fn millis<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+        ino_text(deserializer)?.map(|value| ino_parse_millis(&value)).transpose().map_err(serde::de::Error::custom)
+    }
 
+    pub mod option {
+        use serde::{Deserializer, Serializer};
+        use std::time::Duration;
+
+        use crate::support::ino_parse_duration;
+
+        pub fn serialize<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+            match duration {
+                None => serializer.serialize_none(),
+                Some(duration) => super::serialize(duration, serializer),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+            super::ino_text(deserializer)?.map(|value| ino_parse_duration(&value)).transpose().map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+
+/**
+ *=================================================================
+ * ino_parse_sample_rate()
+ *=================================================================
+ *
+ * Parses the fraction of the results kept in full, 0.1 for one in
+ * ten.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<f64>
+ */
+pub fn ino_parse_sample_rate(value: &str) -> Result<f64> {
+    let rate = value.trim().parse().with_context(|| format!("Invalid sample rate {}", value))?;
+    ino_check_sample_rate(rate)
+}
+
+
+/**
+ *=================================================================
+ * ino_parse_percentile()
+ *=================================================================
+ *
+ * Parses a percentile of the latency to report, 99.9 for the
+ * 99.9th.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<f64>
+ */
+pub fn ino_parse_percentile(value: &str) -> Result<f64> {
+    let percentile = value.trim().parse().with_context(|| format!("Invalid percentile {}", value))?;
+    ino_check_percentile(percentile)
+}
 
 
 #[cfg(test)]
@@ -279,6 +1542,15 @@ mod tests {
     use super::*;
     use crate::support::Operation::{Get, Post};
 
+    #[test]
+    fn should_spread_the_remainder_of_the_requests() -> Result<()> {
+        let settings: Settings = serde_yaml::from_str("clients: 3\nrequests: 10")?;
+        let requests: Vec<usize> = (0..3).map(|n| settings.ino_requests_by_client(n)).collect();
+        assert_eq!(requests, vec![4, 3, 3]);
+        assert_eq!(settings.ino_requests_by_client(3), 3);
+        Ok(())
+    }
+
     #[test]
     fn should_set_get_as_default_operation() -> Result<()> {
         let args = Args {
@@ -393,4 +1665,56 @@ mod tests {
         );
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn should_reject_invalid_headers() {
+        let args = |header: &str| Args {
+            target: Some("https://localhost:3000".to_string()),
+            headers: Some(vec![header.to_string()]),
+            ..Default::default()
+        };
+        let error = Settings::ino_from_args(args("bar foo")).err().unwrap();
+        assert_eq!(error.to_string(), "Invalid header bar foo, use headerName:HeaderValue");
+        let error = Settings::ino_from_args(args("b@r:foo")).err().unwrap();
+        assert_eq!(error.to_string(), "Invalid header name \"b@r\"");
+        let settings = Settings::ino_from_args(args("Referer:http://localhost/")).unwrap();
+        assert_eq!(settings.headers.unwrap()[0].value, "http://localhost/");
+    }
+
+    #[test]
+    fn should_wind_clients_down_during_ramp_down() -> Result<()> {
+        let mut settings: Settings = serde_yaml::from_str("clients: 4\nrequests: 1\nduration: 60")?;
+        assert_eq!(settings.ino_client_end(3), Some(Duration::from_secs(60)));
+        settings.ramp_down = Some(20);
+        let ends: Vec<_> = (0..5).filter_map(|c| settings.ino_client_end(c)).collect();
+        assert_eq!(ends, [60, 55, 50, 45, 45].map(Duration::from_secs));
+        settings.duration = None;
+        assert_eq!(settings.ino_client_end(0), None);
+        Ok(())
+    }
+
+    #[test]
+    fn should_map_hosts_to_static_addresses() -> Result<()> {
+        let settings: Settings = serde_yaml::from_str("requests: 1\nhosts:\n  api.shop.test: 10.0.0.7\n  cdn.shop.test: \"::1\"")?;
+        assert_eq!(settings.hosts["api.shop.test"], IpAddr::from([10, 0, 0, 7]));
+        assert!(settings.hosts["cdn.shop.test"].is_ipv6());
+        assert!(serde_yaml::from_str::<Settings>("requests: 1\nhosts:\n  api.shop.test: staging").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn should_parse_human_durations() -> Result<()> {
+        assert_eq!(ino_parse_duration("5m")?, Duration::from_secs(300));
+        assert_eq!(ino_parse_duration("500ms")?, Duration::from_millis(500));
+        assert_eq!(ino_parse_duration("90")?, Duration::from_secs(90));
+        assert!(ino_parse_duration("5 days").is_err());
+        assert_eq!(ino_parse_duration("1h30m")?, Duration::from_secs(5400));
+        assert!(ino_parse_duration("30s2m").is_err() && ino_parse_duration("2m30").is_err() && ino_parse_duration("-5").is_err());
+        assert_eq!((ino_parse_seconds("2m30s")?, ino_parse_millis("250")?, ino_parse_millis("1.5s")?), (150, 250, 1500));
+        assert!(ino_parse_seconds("1500ms").is_err());
+        let settings: Settings = serde_yaml::from_str("requests: 1\nduration: 2m30s\nkeep_alive: 90s\nstart_jitter: 2s\nramp_down: 30")?;
+        assert_eq!((settings.duration, settings.ramp_down, settings.start_jitter), (Some(150), Some(30), Some(2000)));
+        assert_eq!(settings.keep_alive, Some(Duration::from_secs(90)));
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+use anyhow::{bail, Result};
+use rand::rngs::StdRng;
+use rand::seq::index;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+pub const RNG_STREAM: u64 = 3 << 32;
+
+const MAX_COMBINATIONS: usize = 100_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SweepValue {
+    Text(String),
+    Number(serde_yaml::Number),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Combination {
+    pub label: String,
+    pub params: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sweep {
+    pub params: BTreeMap<String, Vec<SweepValue>>,
+    #[serde(default)]
+    pub sample: Option<usize>,
+    #[serde(skip)]
+    combinations: Vec<Combination>,
+}
+
+impl Display for SweepValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SweepValue::Text(text) => write!(f, "{}", text),
+            SweepValue::Number(number) => write!(f, "{}", number),
+            SweepValue::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl Sweep {
+
+    /**
+    *=================================================================
+    * ino_expand()
+    *=================================================================
+    *
+    * Expands the parameters into their cartesian product, once per
+    * run, or into a sample of it drawn with the random generator
+    * given, seeded with --seed, keeping the order of the product.
+    *
+    *=================================================================
+    * @param rng StdRng
+    * @return Result<()>
+    */
+    pub fn ino_expand(&mut self, mut rng: StdRng) -> Result<()> {
+        if self.params.is_empty() || self.params.values().any(Vec::is_empty) {
+            bail!("Every parameter of the sweep needs at least one value");
+        }
+        let total = self.params.values().try_fold(1usize, |total, values| total.checked_mul(values.len()));
+        let total = match (total, self.sample) {
+            (_, Some(0)) => bail!("The sample of the sweep must take at least one combination"),
+            (Some(total), _) if total <= MAX_COMBINATIONS || self.sample.is_some_and(|sample| sample <= MAX_COMBINATIONS) => total,
+            _ => bail!("The sweep has more than {} combinations, sample them", MAX_COMBINATIONS),
+        };
+        let mut picked: Vec<usize> = match self.sample.filter(|sample| *sample < total) {
+            None => (0..total).collect(),
+            Some(sample) => index::sample(&mut rng, total, sample).into_vec(),
+        };
+        picked.sort_unstable();
+        self.combinations = picked.into_iter().map(|picked| self.ino_combination_at(picked)).collect();
+        Ok(())
+    }
+
+    /**
+    *=================================================================
+    * ino_pick()
+    *=================================================================
+    *
+    * Combination of a request: the clients go through the
+    * combinations in turn, one iteration after the other, so every
+    * combination gets its share of the load.
+    *
+    *=================================================================
+    * @param num_client usize
+    * @param execution usize
+    * @param clients usize
+    * @return Option<&Combination> None before the expansion
+    */
+    pub fn ino_pick(&self, num_client: usize, execution: usize, clients: usize) -> Option<&Combination> {
+        if self.combinations.is_empty() {
+            return None;
+        }
+        let turn = execution.wrapping_mul(clients.max(1)).wrapping_add(num_client);
+        self.combinations.get(turn % self.combinations.len())
+    }
+
+    /**
+    *=================================================================
+    * ino_combination_at()
+    *=================================================================
+    *
+    * Combination at a position of the product, the last parameter
+    * varying the fastest.
+    *
+    *=================================================================
+    */
+    fn ino_combination_at(&self, mut position: usize) -> Combination {
+        let mut params: Vec<(String, String)> = self
+            .params
+            .iter()
+            .rev()
+            .map(|(name, values)| {
+                let value = &values[position % values.len()];
+                position /= values.len();
+                (name.clone(), value.to_string())
+            })
+            .collect();
+        params.reverse();
+        let label = params.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>().join("&");
+        Combination { label, params }
+    }
+}
+
+impl Combination {
+
+    /**
+    *=================================================================
+    * ino_apply()
+    *=================================================================
+    *
+    * Appends the parameters of the combination to the query of a
+    * URL, after the ones it already has.
+    *
+    *=================================================================
+    * @param url &mut Url
+    * @return void
+    */
+    pub fn ino_apply(&self, url: &mut Url) {
+        url.query_pairs_mut().extend_pairs(&self.params);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn should_expand_the_parameters_into_their_product() -> Result<()> {
+        let mut sweep: Sweep = serde_yaml::from_str("params:\n  page_size: [10, 100, 1000]\n  filter: [active, all]")?;
+        sweep.ino_expand(StdRng::seed_from_u64(1))?;
+        let labels: Vec<&str> = sweep.combinations.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels[..3], ["filter=active&page_size=10", "filter=active&page_size=100", "filter=active&page_size=1000"]);
+        assert_eq!(labels.len(), 6);
+        assert_eq!(sweep.ino_pick(1, 2, 4).map(|c| c.label.as_str()), Some("filter=all&page_size=10"));
+        let mut url = Url::parse("http://host/items?sort=id")?;
+        sweep.combinations[5].ino_apply(&mut url);
+        assert_eq!(url.as_str(), "http://host/items?sort=id&filter=all&page_size=1000");
+        sweep.sample = Some(4);
+        sweep.ino_expand(StdRng::seed_from_u64(1))?;
+        assert_eq!(sweep.combinations.len(), 4);
+        sweep.params.insert("empty".to_string(), vec![]);
+        assert!(sweep.ino_expand(StdRng::seed_from_u64(1)).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+
+pub type Tags = BTreeMap<String, String>;
+
+/**
+ *=================================================================
+ * ino_parse_tag()
+ *=================================================================
+ *
+ * Parses a run tag, "key=value". The key is a metric label name,
+ * letters, digits and underscores not starting with a digit, so
+ * the tag reaches every export unchanged.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<(String, String)>
+ */
+pub fn ino_parse_tag(value: &str) -> Result<(String, String)> {
+    let Some((key, tag)) = value.split_once('=') else {
+        bail!("Invalid tag {}, use key=value", value);
+    };
+    let key = key.trim();
+    ino_check_tag_key(key)?;
+    Ok((key.to_string(), tag.trim().to_string()))
+}
+
+/**
+ *=================================================================
+ * ino_check_tag_key()
+ *=================================================================
+ *
+ * Checks that a tag key is a valid metric label name. The names
+ * starting with two underscores are reserved by Prometheus.
+ *
+ *=================================================================
+ * @param key &str
+ * @return Result<()>
+ */
+pub fn ino_check_tag_key(key: &str) -> Result<()> {
+    let mut chars = key.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !key.starts_with("__");
+    if !valid {
+        bail!("Invalid tag key {}, use letters, digits and underscores", key);
+    }
+    Ok(())
+}
+
+/**
+ *=================================================================
+ * ino_format_tags()
+ *=================================================================
+ *
+ * Tags as "key=value" pairs separated by commas, for the report
+ * and the notifications.
+ *
+ *=================================================================
+ * @param tags &Tags
+ * @return String
+ */
+pub fn ino_format_tags(tags: &Tags) -> String {
+    tags.iter().map(|(key, value)| format!("{}={}", key, value)).collect::<Vec<_>>().join(", ")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_tags_as_label_names() -> Result<()> {
+        assert_eq!(ino_parse_tag("branch = feature/login")?, ("branch".to_string(), "feature/login".to_string()));
+        assert_eq!(ino_parse_tag("env=")?, ("env".to_string(), String::new()));
+        assert!(ino_parse_tag("env").is_err());
+        assert!(ino_parse_tag("1env=staging").is_err());
+        assert!(ino_parse_tag("build-id=42").is_err());
+        assert!(ino_parse_tag("__name__=x").is_err());
+        let tags = Tags::from([ino_parse_tag("env=staging")?, ino_parse_tag("branch=main")?]);
+        assert_eq!(ino_format_tags(&tags), "branch=main, env=staging");
+        Ok(())
+    }
+}
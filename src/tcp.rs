@@ -0,0 +1,147 @@
+use anyhow::{bail, Context, Result};
+use rustls::pki_types::ServerName;
+use std::net::SocketAddr;
+use tokio::net::{lookup_host, TcpStream};
+use tokio::time::Instant;
+use tokio_rustls::TlsConnector;
+
+use crate::benchmark::BenchmarkResult;
+use crate::exhaustion::{ino_local_limit, ino_local_status};
+use crate::support::Settings;
+use crate::tls::ino_insecure_connector;
+
+pub struct TcpConnect {
+    addr: SocketAddr,
+    tls: Option<(TlsConnector, ServerName<'static>)>,
+}
+
+impl TcpConnect {
+
+    /**
+    *=================================================================
+    * ino_from_settings()
+    *=================================================================
+    *
+    * Parses the target "host:port", "tcp://host:port" or
+    * "tls://host:port" and resolves it once, so DNS resolution is
+    * not part of the measured handshakes.
+    *
+    *=================================================================
+    * @param settings &Settings
+    * @return Result<TcpConnect>
+    */
+    pub async fn ino_from_settings(settings: &Settings) -> Result<Self> {
+        let target = settings.ino_target();
+        let (tls, authority) = match target.split_once("://") {
+            None => (false, target.as_str()),
+            Some(("tcp", authority)) => (false, authority),
+            Some(("tls", authority)) => (true, authority),
+            Some((scheme, _)) => bail!("Unsupported scheme {} for tcp-connect, use tcp:// or tls://", scheme),
+        };
+        let authority = authority.trim_end_matches('/');
+        let addr = lookup_host(authority)
+            .await
+            .with_context(|| format!("Can not resolve {}, expected host:port", authority))?
+            .next()
+            .with_context(|| format!("No address found for {}", authority))?;
+        let tls = match tls {
+            false => None,
+            true => {
+                let host = authority.rsplit_once(':').map(|(h, _)| h).unwrap_or(authority);
+                let host = host.trim_start_matches('[').trim_end_matches(']');
+                let name = ServerName::try_from(host.to_string())
+                    .with_context(|| format!("Invalid server name {}", host))?;
+                Some((ino_insecure_connector(), name))
+            }
+        };
+        Ok(TcpConnect { addr, tls })
+    }
+
+    /**
+    *=================================================================
+    * ino_exec()
+    *=================================================================
+    *
+    * Opens a connection, performs the TLS handshake when required,
+    * and closes it. Only the connection establishment is measured.
+    *
+    *=================================================================
+    * @param num_client usize
+    * @param execution usize
+    * @return BenchmarkResult
+    */
+    pub async fn ino_exec(&self, num_client: usize, execution: usize) -> BenchmarkResult {
+        let begin = Instant::now();
+        let status = match TcpStream::connect(self.addr).await {
+            Err(e) => Err(match ino_local_limit(&e) {
+                Some(limit) => ino_local_status(limit),
+                None => format!("Connect failed: {}", e.kind()),
+            }),
+            Ok(stream) => match &self.tls {
+                None => Ok("Connected"),
+                Some((connector, name)) => match connector.connect(name.clone(), stream).await {
+                    Ok(_) => Ok("TLS established"),
+                    Err(e) => Err(format!("TLS failed: {}", e)),
+                },
+            },
+        };
+        let duration = begin.elapsed().as_millis() as u64;
+        let success = status.is_ok();
+        BenchmarkResult::new(status.map(str::to_string).unwrap_or_else(|e| e), success, duration, num_client, execution)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    async fn ino_connect(target: &str) -> Result<TcpConnect> {
+        let settings: Settings = serde_yaml::from_str(&format!("target: \"{}\"\nclients: 1\nrequests: 1", target))?;
+        TcpConnect::ino_from_settings(&settings).await
+    }
+
+    #[tokio::test]
+    async fn should_parse_the_target_schemes() -> Result<()> {
+        let plain = ino_connect("127.0.0.1:8080").await?;
+        assert_eq!(plain.addr, "127.0.0.1:8080".parse()?);
+        assert!(plain.tls.is_none());
+        let tcp = ino_connect("tcp://127.0.0.1:8080/").await?;
+        assert_eq!(tcp.addr, "127.0.0.1:8080".parse()?);
+        assert!(tcp.tls.is_none());
+        let tls = ino_connect("tls://127.0.0.1:8443").await?;
+        assert_eq!(tls.addr, "127.0.0.1:8443".parse()?);
+        assert_eq!(tls.tls.map(|(_, name)| name.to_str().to_string()), Some("127.0.0.1".to_string()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_strip_the_brackets_of_an_ipv6_server_name() -> Result<()> {
+        let tls = ino_connect("tls://[::1]:8443").await?;
+        assert_eq!(tls.addr, "[::1]:8443".parse()?);
+        assert_eq!(tls.tls.map(|(_, name)| name.to_str().to_string()), Some("::1".to_string()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_reject_an_unsupported_scheme() {
+        let error = ino_connect("http://127.0.0.1:8080").await.err().unwrap();
+        assert_eq!(error.to_string(), "Unsupported scheme http for tcp-connect, use tcp:// or tls://");
+    }
+
+    #[tokio::test]
+    async fn should_measure_a_connection_to_a_listener() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let connect = ino_connect(&listener.local_addr()?.to_string()).await?;
+        let result = connect.ino_exec(1, 2).await;
+        assert!(result.success);
+        assert_eq!(result.status, "Connected");
+        assert_eq!((result.num_client, result.execution), (1, 2));
+        drop(listener);
+        let result = connect.ino_exec(1, 3).await;
+        assert!(!result.success);
+        assert!(result.status.starts_with("Connect failed"));
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::benchmark::Summary;
+use crate::flow::StepSummary;
+
+#[derive(PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Thresholds {
+    pub mean: Option<f64>,
+    pub max: Option<u64>,
+    pub p95: Option<u64>,
+    pub p999: Option<u64>,
+    pub error_rate: Option<f64>,
+    pub min_throughput: Option<f64>,
+    pub min_score: Option<f64>,
+    #[serde(default)]
+    pub steps: BTreeMap<String, StepThresholds>,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StepThresholds {
+    #[serde(default = "ino_default_weight")]
+    pub weight: f64,
+    pub mean: Option<f64>,
+    pub max: Option<u64>,
+    pub p95: Option<u64>,
+    pub p999: Option<u64>,
+    pub error_rate: Option<f64>,
+}
+
+fn ino_default_weight() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepScore {
+    pub score: f64,
+    pub min_score: f64,
+    pub steps: BTreeMap<String, StepVerdict>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepVerdict {
+    pub weight: f64,
+    pub passed: bool,
+    pub failures: Vec<String>,
+}
+
+struct Measured {
+    requests: u64,
+    errors: u64,
+    mean: f64,
+    max: u64,
+    p95: u64,
+    p999: u64,
+}
+
+impl Thresholds {
+
+    /**
+    *=================================================================
+    * ino_evaluate()
+    *=================================================================
+    *
+    * Checks the summary of the run against every declared criterion.
+    * Latencies are in milliseconds, error_rate is the fraction of
+    * non 2xx/3xx results and min_throughput is in requests/second.
+    *
+    *=================================================================
+    * @param summary &Summary
+    * @return Vec<String> one message per failed criterion
+    */
+    pub fn ino_evaluate(&self, summary: &Summary) -> Vec<String> {
+        let limits = StepThresholds {
+            weight: ino_default_weight(),
+            mean: self.mean,
+            max: self.max,
+            p95: self.p95,
+            p999: self.p999,
+            error_rate: self.error_rate,
+        };
+        let mut failures = limits.ino_check(&Measured {
+            requests: summary.total_requests,
+            errors: summary.errors,
+            mean: summary.mean,
+            max: summary.max,
+            p95: summary.p95,
+            p999: summary.p999,
+        });
+        if let Some(limit) = self.min_throughput {
+            if summary.throughput < limit {
+                failures.push(format!("throughput {:.2} rps < {} rps", summary.throughput, limit));
+            }
+        }
+        if let Some(score) = summary.score.as_ref().filter(|score| score.score < score.min_score) {
+            failures.push(format!("step score {:.2} < {}", score.score, score.min_score));
+            for (name, step) in score.steps.iter().filter(|(_, step)| !step.passed) {
+                for failure in &step.failures {
+                    failures.push(format!("step {} (weight {}): {}", name, step.weight, failure));
+                }
+            }
+        }
+        failures
+    }
+
+    /**
+    *=================================================================
+    * ino_score()
+    *=================================================================
+    *
+    * Checks every step declared under `steps` against its own
+    * criteria and weighs the outcome: the score is the weight of the
+    * passing steps over the total weight, a step without any result
+    * fails. The steps then pass as a whole when the score reaches
+    * `min_score`, all of them by default. None without any step.
+    *
+    *=================================================================
+    * @param steps Option<&BTreeMap<String, StepSummary>>
+    * @return Option<StepScore>
+    */
+    pub fn ino_score(&self, steps: Option<&BTreeMap<String, StepSummary>>) -> Option<StepScore> {
+        if self.steps.is_empty() {
+            return None;
+        }
+        let verdicts: BTreeMap<String, StepVerdict> = self
+            .steps
+            .iter()
+            .map(|(name, limits)| {
+                let failures = match steps.and_then(|steps| steps.get(name)) {
+                    None => vec!["no request".to_string()],
+                    Some(step) => limits.ino_check(&Measured {
+                        requests: step.requests,
+                        errors: step.errors,
+                        mean: step.mean,
+                        max: step.max,
+                        p95: step.p95,
+                        p999: step.p999,
+                    }),
+                };
+                let verdict = StepVerdict { weight: limits.weight.max(0.0), passed: failures.is_empty(), failures };
+                (name.clone(), verdict)
+            })
+            .collect();
+        let total: f64 = verdicts.values().map(|step| step.weight).sum();
+        let passed: f64 = verdicts.values().filter(|step| step.passed).map(|step| step.weight).sum();
+        Some(StepScore {
+            score: match total > 0.0 {
+                true => passed / total,
+                false => 1.0,
+            },
+            min_score: self.min_score.unwrap_or(1.0),
+            steps: verdicts,
+        })
+    }
+}
+
+impl StepThresholds {
+
+    /**
+    *=================================================================
+    * ino_check()
+    *=================================================================
+    *
+    * Compares measured latencies and errors with the limits.
+    *
+    *=================================================================
+    */
+    fn ino_check(&self, measured: &Measured) -> Vec<String> {
+        let mut failures = vec![];
+        if let Some(limit) = self.mean {
+            if measured.mean > limit {
+                failures.push(format!("mean {:.2} ms > {} ms", measured.mean, limit));
+            }
+        }
+        if let Some(limit) = self.max {
+            if measured.max > limit {
+                failures.push(format!("max {} ms > {} ms", measured.max, limit));
+            }
+        }
+        if let Some(limit) = self.p95 {
+            if measured.p95 > limit {
+                failures.push(format!("p95 {} ms > {} ms", measured.p95, limit));
+            }
+        }
+        if let Some(limit) = self.p999 {
+            if measured.p999 > limit {
+                failures.push(format!("p99.9 {} ms > {} ms", measured.p999, limit));
+            }
+        }
+        if let Some(limit) = self.error_rate {
+            let rate = match measured.requests {
+                0 => 0.0,
+                total => measured.errors as f64 / total as f64,
+            };
+            if rate > limit {
+                failures.push(format!("error rate {:.4} > {}", rate, limit));
+            }
+        }
+        failures
+    }
+}
+
+/**
+ *=================================================================
+ * ino_show_verdict()
+ *=================================================================
+ *
+ * Prints the pass/fail verdict of the thresholds, after the
+ * weighted verdict of the steps if any.
+ *
+ *=================================================================
+ * @param failures &[String]
+ * @param score Option<&StepScore>
+ * @return void
+ */
+pub fn ino_show_verdict(failures: &[String], score: Option<&StepScore>) {
+    println!();
+    if let Some(score) = score {
+        println!(
+            "{} {}",
+            "Step score".yellow().bold(),
+            format!("{:.2} (min {})", score.score, score.min_score).purple()
+        );
+        for (name, step) in &score.steps {
+            match step.passed {
+                true => println!("  {} {} (weight {})", "✓".green(), name, step.weight),
+                false => println!("  {} {} (weight {}): {}", "✗".red(), name, step.weight, step.failures.join(", ")),
+            }
+        }
+    }
+    if failures.is_empty() {
+        println!("{}", "Thresholds passed".green().bold());
+        return;
+    }
+    println!("{}", "Thresholds failed".red().bold());
+    for failure in failures {
+        println!("  {} {}", "✗".red(), failure);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::Tags;
+
+    fn summary() -> Summary {
+        Summary {
+            target: "http://localhost:3000".to_string(),
+            label: None,
+            tags: Tags::new(),
+            timestamp: 0,
+            clients: 1,
+            total_requests: 100,
+            errors: 5,
+            elapsed: 1.0,
+            throughput: 100.0,
+            mean: 20.0,
+            max: 90,
+            min: 1,
+            p95: 50,
+            p999: 80,
+            percentiles: BTreeMap::new(),
+            first_byte: None,
+            response_size: None,
+            connections: None,
+            generator: None,
+            accounting: None,
+            resources: None,
+            cache: None,
+            conditional: None,
+            ranges: None,
+            download: None,
+            upload: None,
+            kafka: None,
+            corrupted: None,
+            local_errors: None,
+            schedule: None,
+            throttled: None,
+            polls: None,
+            sse: None,
+            steps: None,
+            score: None,
+            response_headers: None,
+            status_timeline: None,
+        }
+    }
+
+    #[test]
+    fn should_pass_when_no_threshold_is_declared() {
+        assert!(Thresholds::default().ino_evaluate(&summary()).is_empty());
+    }
+
+    #[test]
+    fn should_report_every_failed_threshold() {
+        let thresholds = Thresholds {
+            p95: Some(40),
+            error_rate: Some(0.01),
+            min_throughput: Some(50.0),
+            ..Default::default()
+        };
+        let failures = thresholds.ino_evaluate(&summary());
+        assert_eq!(failures, vec!["p95 50 ms > 40 ms", "error rate 0.0500 > 0.01"]);
+    }
+
+    #[test]
+    fn should_weigh_the_steps() {
+        let step = |p95, errors| StepSummary { requests: 100, errors, mean: 10.0, p95, p999: p95, max: p95 };
+        let measured = BTreeMap::from([("checkout".to_string(), step(400, 0)), ("healthz".to_string(), step(80, 0))]);
+        let limits = |weight, p95| StepThresholds { weight, mean: None, max: None, p95: Some(p95), p999: None, error_rate: Some(0.01) };
+        let mut thresholds = Thresholds {
+            min_score: Some(0.9),
+            steps: BTreeMap::from([("checkout".to_string(), limits(9.0, 500)), ("healthz".to_string(), limits(1.0, 50))]),
+            ..Default::default()
+        };
+        let mut summary = summary();
+        summary.errors = 0;
+        summary.score = thresholds.ino_score(Some(&measured));
+        assert_eq!(summary.score.as_ref().map(|score| score.score), Some(0.9));
+        assert!(thresholds.ino_evaluate(&summary).is_empty());
+        thresholds.steps.insert("login".to_string(), limits(10.0, 500));
+        summary.score = thresholds.ino_score(Some(&measured));
+        assert_eq!(summary.score.as_ref().map(|score| score.score), Some(0.45));
+        assert_eq!(
+            thresholds.ino_evaluate(&summary),
+            vec!["step score 0.45 < 0.9", "step healthz (weight 1): p95 80 ms > 50 ms", "step login (weight 10): no request"]
+        );
+        assert_eq!(Thresholds::default().ino_score(Some(&measured)), None);
+    }
+}
@@ -0,0 +1,132 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+use crate::benchmark::BenchmarkResult;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ThrottleSummary {
+    pub throttled: u64,
+    pub pause_ms: u64,
+    pub goodput: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct ThrottleStats {
+    throttled: u64,
+    pause_ms: u64,
+    successes: u64,
+}
+
+/**
+ *=================================================================
+ * ino_retry_after()
+ *=================================================================
+ *
+ * Delay asked by a 429 Too Many Requests or 503 Service
+ * Unavailable response with its Retry-After header, in seconds or
+ * as an HTTP date. None for the other responses.
+ *
+ *=================================================================
+ * @param status StatusCode
+ * @param headers &HeaderMap
+ * @return Option<Duration>
+ */
+pub fn ino_retry_after(status: StatusCode, headers: &HeaderMap) -> Option<Duration> {
+    if status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::SERVICE_UNAVAILABLE {
+        return None;
+    }
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => {
+            let date = httpdate::parse_http_date(value).ok()?;
+            Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+        }
+    }
+}
+
+impl ThrottleStats {
+
+    /**
+    *=================================================================
+    * ino_add()
+    *=================================================================
+    *
+    * Counts a throttled request with its pause, or a successful one.
+    *
+    *=================================================================
+    * @param result &BenchmarkResult
+    * @return void
+    */
+    pub fn ino_add(&mut self, result: &BenchmarkResult) {
+        if let Some(pause) = result.throttled {
+            self.throttled += 1;
+            self.pause_ms += pause;
+        }
+        self.successes += u64::from(result.success);
+    }
+
+    /**
+    *=================================================================
+    * ino_summary()
+    *=================================================================
+    *
+    * Count of the throttled requests, the time the clients paused
+    * for them and the successful requests per second over the run,
+    * the throughput the target really allows.
+    *
+    *=================================================================
+    * @param elapsed f64 seconds
+    * @return ThrottleSummary
+    */
+    pub fn ino_summary(&self, elapsed: f64) -> ThrottleSummary {
+        ThrottleSummary {
+            throttled: self.throttled,
+            pause_ms: self.pause_ms,
+            goodput: match elapsed > 0.0 {
+                true => self.successes as f64 / elapsed,
+                false => 0.0,
+            },
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::ino_result;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn should_read_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(ino_retry_after(StatusCode::TOO_MANY_REQUESTS, &headers), None);
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+        assert_eq!(ino_retry_after(StatusCode::TOO_MANY_REQUESTS, &headers), Some(Duration::from_secs(3)));
+        assert_eq!(ino_retry_after(StatusCode::OK, &headers), None);
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60));
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(&date).unwrap());
+        let delay = ino_retry_after(StatusCode::SERVICE_UNAVAILABLE, &headers).unwrap();
+        assert!(delay > Duration::from_secs(55) && delay <= Duration::from_secs(60));
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(ino_retry_after(StatusCode::SERVICE_UNAVAILABLE, &headers), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn should_summarize_throttling() {
+        let result = |success, throttled| BenchmarkResult {
+            status: "429 Too Many Requests".to_string(),
+            success,
+            throttled,
+            ..ino_result()
+        };
+        let results = [result(true, None), result(true, None), result(false, Some(1000)), result(false, Some(500)), result(false, None)];
+        let mut stats = ThrottleStats::default();
+        results.iter().for_each(|result| stats.ino_add(result));
+        let summary = stats.ino_summary(2.0);
+        assert_eq!(summary, ThrottleSummary { throttled: 2, pause_ms: 1500, goodput: 1.0 });
+    }
+}
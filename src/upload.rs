@@ -0,0 +1,175 @@
+use bytes::Bytes;
+use futures::Stream;
+use reqwest::Body;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::bandwidth::{ino_throttle_stream, BandwidthLimiter};
+use crate::benchmark::{ino_quantile, BenchmarkResult};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+static CHUNK: [u8; CHUNK_SIZE] = [b'x'; CHUNK_SIZE];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct UploadSummary {
+    pub bytes: u64,
+    pub throughput: f64,
+    pub ack_mean: f64,
+    pub ack_p95: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct UploadStats {
+    successes: u64,
+    acks: Vec<u64>,
+}
+
+/**
+ *=================================================================
+ * ino_upload_body()
+ *=================================================================
+ *
+ * Streaming body of `size` generated bytes, sent with chunked
+ * transfer encoding since its length is not announced. With a
+ * rate the chunks are paced to that many bytes per second, they
+ * also go through the bandwidth limiters of the client. The
+ * instant the last chunk is handed over is stored in `sent`.
+ *
+ *=================================================================
+ * @param size u64
+ * @param rate Option<u64> bytes per second
+ * @param sent Arc<Mutex<Option<Instant>>>
+ * @param limiters Vec<Arc<BandwidthLimiter>>
+ * @return Body
+ */
+pub fn ino_upload_body(size: u64, rate: Option<u64>, sent: Arc<Mutex<Option<Instant>>>, limiters: Vec<Arc<BandwidthLimiter>>) -> Body {
+    Body::wrap_stream(ino_throttle_stream(ino_upload_stream(size, rate, sent), limiters))
+}
+
+/**
+ *=================================================================
+ * ino_upload_stream()
+ *=================================================================
+ *
+ * Chunks of the upload body, slices of a static buffer so nothing
+ * is allocated per chunk.
+ *
+ *=================================================================
+ */
+fn ino_upload_stream(size: u64, rate: Option<u64>, sent: Arc<Mutex<Option<Instant>>>) -> impl Stream<Item = io::Result<Bytes>> {
+    let start = Instant::now();
+    futures::stream::unfold(0, move |uploaded: u64| {
+        let sent = sent.clone();
+        async move {
+            if uploaded >= size {
+                *sent.lock().unwrap() = Some(Instant::now());
+                return None;
+            }
+            if let Some(rate) = rate.filter(|r| *r > 0) {
+                tokio::time::sleep_until(start + Duration::from_secs_f64(uploaded as f64 / rate as f64)).await;
+            }
+            let length = (size - uploaded).min(CHUNK_SIZE as u64);
+            let chunk = Bytes::from_static(&CHUNK).slice(..length as usize);
+            Some((Ok(chunk), uploaded + length))
+        }
+    })
+}
+
+impl UploadStats {
+
+    /**
+    *=================================================================
+    * ino_add()
+    *=================================================================
+    *
+    * Counts a successful upload and records its acknowledgement.
+    *
+    *=================================================================
+    * @param result &BenchmarkResult
+    * @return void
+    */
+    pub fn ino_add(&mut self, result: &BenchmarkResult) {
+        self.successes += u64::from(result.success);
+        if let Some(ack) = result.ack {
+            self.acks.push(ack);
+        }
+    }
+
+    /**
+    *=================================================================
+    * ino_summary()
+    *=================================================================
+    *
+    * Bytes of the successful uploads per second over the run, and
+    * the acknowledgement latency: the time the server took to answer
+    * once the whole body was sent.
+    *
+    *=================================================================
+    * @param size u64 bytes of one upload
+    * @param elapsed f64 seconds
+    * @return UploadSummary
+    */
+    pub fn ino_summary(&self, size: u64, elapsed: f64) -> UploadSummary {
+        let mut acks = self.acks.clone();
+        acks.sort_unstable();
+        let bytes = self.successes * size;
+        UploadSummary {
+            bytes,
+            throughput: match elapsed > 0.0 {
+                true => bytes as f64 / elapsed,
+                false => 0.0,
+            },
+            ack_mean: match acks.len() {
+                0 => 0.0,
+                len => acks.iter().sum::<u64>() as f64 / len as f64,
+            },
+            ack_p95: match acks.is_empty() {
+                true => 0,
+                false => ino_quantile(&acks, 0.95),
+            },
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::ino_result;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn should_stream_the_whole_size_at_the_rate() {
+        let sent = Arc::new(Mutex::new(None));
+        let begin = Instant::now();
+        let mut stream = Box::pin(ino_upload_stream(200_000, Some(1_000_000), sent.clone()));
+        let mut size = 0;
+        while let Some(chunk) = stream.next().await {
+            size += chunk.unwrap().len();
+        }
+        assert_eq!(size, 200_000);
+        assert!(begin.elapsed() >= Duration::from_millis(190));
+        assert!(sent.lock().unwrap().is_some());
+    }
+
+    #[test]
+    fn should_summarize_uploads() {
+        let result = |success, ack| BenchmarkResult {
+            status: "201 Created".to_string(),
+            success,
+            duration: 100,
+            ack,
+            ..ino_result()
+        };
+        let results = [result(true, Some(10)), result(true, Some(30)), result(false, None)];
+        let mut stats = UploadStats::default();
+        results.iter().for_each(|result| stats.ino_add(result));
+        let summary = stats.ino_summary(1000, 2.0);
+        assert_eq!((summary.bytes, summary.throughput), (2000, 1000.0));
+        assert_eq!((summary.ack_mean, summary.ack_p95), (20.0, 30));
+    }
+}
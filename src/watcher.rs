@@ -0,0 +1,232 @@
+use anyhow::Result;
+use colored::Colorize;
+use indicatif::ProgressBar;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use crate::benchmark::Summary;
+use crate::history::ino_format_timestamp;
+use crate::run::{ino_benchmark, ino_export, ino_verdict};
+use crate::support::Settings;
+
+const BASELINE_RUNS: usize = 5;
+
+#[derive(Debug, Default)]
+pub struct Baseline {
+    runs: VecDeque<Summary>,
+}
+
+impl Baseline {
+
+    /**
+    *=================================================================
+    * ino_deviations()
+    *=================================================================
+    *
+    * Compares a run with the average of the previous runs. The p95
+    * must not grow and the throughput must not drop by more than
+    * the tolerance, in percent.
+    *
+    *=================================================================
+    * @param summary &Summary
+    * @param tolerance f64
+    * @return Vec<String> description of each deviation
+    */
+    pub fn ino_deviations(&self, summary: &Summary, tolerance: f64) -> Vec<String> {
+        if self.runs.is_empty() {
+            return vec![];
+        }
+        let count = self.runs.len() as f64;
+        let p95 = self.runs.iter().map(|s| s.p95 as f64).sum::<f64>() / count;
+        let throughput = self.runs.iter().map(|s| s.throughput).sum::<f64>() / count;
+        let mut deviations = vec![];
+        let p95_change = ino_change(summary.p95 as f64, p95);
+        if p95_change > tolerance {
+            deviations.push(format!("p95 {} ms is {:+.0}% over the baseline {:.1} ms", summary.p95, p95_change, p95));
+        }
+        let throughput_change = ino_change(summary.throughput, throughput);
+        if -throughput_change > tolerance {
+            deviations.push(format!(
+                "throughput {:.1} rps is {:+.0}% under the baseline {:.1} rps",
+                summary.throughput, throughput_change, throughput
+            ));
+        }
+        deviations
+    }
+
+    /**
+    *=================================================================
+    * ino_push()
+    *=================================================================
+    *
+    * Adds a run to the baseline, only the last runs are kept.
+    *
+    *=================================================================
+    * @param summary Summary
+    * @return void
+    */
+    pub fn ino_push(&mut self, summary: Summary) {
+        if self.runs.len() == BASELINE_RUNS {
+            self.runs.pop_front();
+        }
+        self.runs.push_back(summary);
+    }
+}
+
+/**
+ *=================================================================
+ * ino_change()
+ *=================================================================
+ *
+ * Relative change of a value against its baseline, in percent.
+ *
+ *=================================================================
+ */
+pub fn ino_change(value: f64, baseline: f64) -> f64 {
+    match baseline > 0.0 {
+        true => (value - baseline) / baseline * 100.0,
+        false => 0.0,
+    }
+}
+
+/**
+ *=================================================================
+ * ino_watch()
+ *=================================================================
+ *
+ * Repeats the benchmark every interval until interrupted, printing
+ * a one line summary per run. Every run goes through the exports
+ * of the settings, deviations from the rolling baseline are
+ * reported as failures alongside the failed thresholds. A failing
+ * run is reported and the next one still starts on schedule.
+ *
+ *=================================================================
+ * @param settings &Settings
+ * @param interval Duration
+ * @param rx_sigint watch::Receiver<Option<()>>
+ * @return Result<bool> true when every run passed
+ */
+pub async fn ino_watch(settings: &Settings, interval: Duration, mut rx_sigint: watch::Receiver<Option<()>>) -> Result<bool> {
+    let mut baseline = Baseline::default();
+    let mut passed = true;
+    loop {
+        let begin = Instant::now();
+        let report = ino_benchmark(settings, rx_sigint.clone(), ProgressBar::hidden(), None, None).await;
+        if rx_sigint.borrow().is_some() {
+            break;
+        }
+        match report {
+            Err(e) => {
+                passed = false;
+                println!("{} {} {:#}", ino_now(), "run failed:".red(), e);
+            }
+            Ok(report) => {
+                let summary = report.ino_summary(settings);
+                let mut failures = ino_verdict(settings, &summary, false);
+                failures.extend(baseline.ino_deviations(&summary, settings.watch_tolerance));
+                println!("{} {}", ino_format_timestamp(summary.timestamp), summary);
+                for failure in &failures {
+                    println!("    {} {}", "✗".red(), failure);
+                }
+                passed &= failures.is_empty();
+                if let Err(e) = ino_export(settings, &summary, &failures).await {
+                    println!("    {} {:#}", "export failed:".yellow(), e);
+                }
+                baseline.ino_push(summary);
+            }
+        }
+        tokio::select! {
+            _ = tokio::time::sleep_until(begin + interval) => {}
+            _ = rx_sigint.changed() => break,
+        }
+    }
+    Ok(passed)
+}
+
+/**
+ *=================================================================
+ * ino_now()
+ *=================================================================
+ *
+ * Current time formatted like the run timestamps.
+ *
+ *=================================================================
+ */
+fn ino_now() -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    ino_format_timestamp(now)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tags::Tags;
+
+    fn ino_summary(p95: u64, throughput: f64) -> Summary {
+        Summary {
+            target: "http://localhost".to_string(),
+            label: None,
+            tags: Tags::new(),
+            timestamp: 0,
+            clients: 1,
+            total_requests: 100,
+            errors: 0,
+            elapsed: 1.0,
+            throughput,
+            mean: 10.0,
+            max: 100,
+            min: 1,
+            p95,
+            p999: 50,
+            percentiles: Default::default(),
+            first_byte: None,
+            response_size: None,
+            connections: None,
+            generator: None,
+            accounting: None,
+            resources: None,
+            cache: None,
+            conditional: None,
+            ranges: None,
+            download: None,
+            upload: None,
+            kafka: None,
+            corrupted: None,
+            local_errors: None,
+            schedule: None,
+            throttled: None,
+            polls: None,
+            sse: None,
+            steps: None,
+            score: None,
+            response_headers: None,
+            status_timeline: None,
+        }
+    }
+
+    #[test]
+    fn should_report_deviation_from_baseline() {
+        let mut baseline = Baseline::default();
+        assert!(baseline.ino_deviations(&ino_summary(100, 100.0), 20.0).is_empty());
+        baseline.ino_push(ino_summary(10, 100.0));
+        baseline.ino_push(ino_summary(14, 100.0));
+        assert!(baseline.ino_deviations(&ino_summary(14, 90.0), 20.0).is_empty());
+        assert_eq!(baseline.ino_deviations(&ino_summary(20, 50.0), 20.0).len(), 2);
+    }
+
+    #[test]
+    fn should_keep_only_last_runs() {
+        let mut baseline = Baseline::default();
+        for p95 in 0..(BASELINE_RUNS as u64 + 2) {
+            baseline.ino_push(ino_summary(p95, 1.0));
+        }
+        assert_eq!(baseline.runs.len(), BASELINE_RUNS);
+        assert_eq!(baseline.runs.front().map(|s| s.p95), Some(2));
+    }
+}
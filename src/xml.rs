@@ -0,0 +1,351 @@
+use anyhow::{bail, Context, Result};
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+pub const XML_CONTENT_TYPE: &str = "text/xml; charset=utf-8";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XPath {
+    steps: Vec<XPathStep>,
+    value: XPathValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct XPathStep {
+    descendant: bool,
+    name: Option<String>,
+    predicates: Vec<Predicate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Predicate {
+    Position(usize),
+    Attribute(String, Option<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum XPathValue {
+    Content,
+    Text,
+    Attribute(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct XPathExpectation {
+    pub path: String,
+    pub value: Option<String>,
+    xpath: XPath,
+}
+
+impl TryFrom<String> for XPathExpectation {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        ino_parse_xpath_expectation(&value)
+    }
+}
+
+impl From<XPathExpectation> for String {
+    fn from(expectation: XPathExpectation) -> Self {
+        expectation.to_string()
+    }
+}
+
+impl Display for XPathExpectation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            None => write!(f, "{}", self.path),
+            Some(value) => write!(f, "{}={}", self.path, value),
+        }
+    }
+}
+
+impl XPath {
+
+    /**
+    *=================================================================
+    * ino_eval()
+    *=================================================================
+    *
+    * Value of the first node selected by the path: the text content
+    * of an element, trimmed, or the value of an attribute. None when
+    * nothing matches.
+    *
+    *=================================================================
+    * @param document &Document
+    * @return Option<String>
+    */
+    pub fn ino_eval(&self, document: &Document) -> Option<String> {
+        let mut nodes = vec![document.root()];
+        for step in &self.steps {
+            nodes = nodes.into_iter().flat_map(|node| step.ino_select(node)).collect();
+        }
+        nodes.into_iter().find_map(|node| match &self.value {
+            XPathValue::Content => Some(ino_text_content(node)),
+            XPathValue::Text => node.children().find(|c| c.is_text()).and_then(|c| c.text()).map(|t| t.trim().to_string()),
+            XPathValue::Attribute(name) => ino_attribute(node, name).map(str::to_string),
+        })
+    }
+}
+
+impl XPathStep {
+
+    fn ino_select<'a, 'input>(&self, node: Node<'a, 'input>) -> Vec<Node<'a, 'input>> {
+        let candidates: Box<dyn Iterator<Item = Node<'a, 'input>>> = match self.descendant {
+            true => Box::new(node.descendants().skip(1)),
+            false => Box::new(node.children()),
+        };
+        let mut selected: Vec<Node> = candidates
+            .filter(|n| n.is_element() && self.name.as_ref().is_none_or(|name| n.tag_name().name() == name))
+            .collect();
+        for predicate in &self.predicates {
+            selected = match predicate {
+                Predicate::Position(position) => selected.get(position - 1).copied().into_iter().collect(),
+                Predicate::Attribute(name, value) => selected
+                    .into_iter()
+                    .filter(|n| match (ino_attribute(*n, name), value) {
+                        (None, _) => false,
+                        (Some(_), None) => true,
+                        (Some(actual), Some(expected)) => actual == expected,
+                    })
+                    .collect(),
+            };
+        }
+        selected
+    }
+}
+
+fn ino_attribute<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attributes().find(|a| a.name() == name).map(|a| a.value())
+}
+
+fn ino_text_content(node: Node) -> String {
+    node.descendants().filter(|n| n.is_text()).filter_map(|n| n.text()).collect::<String>().trim().to_string()
+}
+
+/**
+ *=================================================================
+ * ino_parse_xpath()
+ *=================================================================
+ *
+ * Parses the XPath subset used to chain and assert on XML
+ * responses: `/` and `//` steps, `*`, positions `[1]`, attribute
+ * filters `[@id='3']` and a final `@name` or `text()`. Namespace
+ * prefixes are ignored, `/soap:Envelope` matches any Envelope. A
+ * path not starting with `/` is searched anywhere.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<XPath>
+ */
+pub fn ino_parse_xpath(value: &str) -> Result<XPath> {
+    let path = value.trim();
+    let mut rest = path;
+    let mut descendant = !path.starts_with('/');
+    let mut steps = vec![];
+    let mut value_step = XPathValue::Content;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("//") {
+            descendant = true;
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('/') {
+            rest = after;
+        }
+        let end = ino_step_end(rest);
+        let (step, after) = rest.split_at(end);
+        rest = after;
+        if step.is_empty() {
+            bail!("Invalid XPath {}, a step is empty", value);
+        }
+        if let Some(name) = step.strip_prefix('@') {
+            value_step = XPathValue::Attribute(ino_local_name(name).to_string());
+        } else if step == "text()" {
+            value_step = XPathValue::Text;
+        } else {
+            steps.push(ino_parse_step(step, descendant).with_context(|| format!("Invalid XPath {}", value))?);
+            descendant = false;
+            continue;
+        }
+        if !rest.is_empty() {
+            bail!("Invalid XPath {}, an attribute or text() must be the last step", value);
+        }
+    }
+    if steps.is_empty() {
+        bail!("Invalid XPath {}, it selects no element", value);
+    }
+    Ok(XPath { steps, value: value_step })
+}
+
+fn ino_step_end(rest: &str) -> usize {
+    let mut depth = 0;
+    for (index, c) in rest.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            '/' if depth == 0 => return index,
+            _ => {}
+        }
+    }
+    rest.len()
+}
+
+fn ino_local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name).trim()
+}
+
+fn ino_parse_step(step: &str, descendant: bool) -> Result<XPathStep> {
+    let (name, mut predicates_text) = step.split_at(step.find('[').unwrap_or(step.len()));
+    let name = match ino_local_name(name) {
+        "*" => None,
+        "" => bail!("a step has no name"),
+        name => Some(name.to_string()),
+    };
+    let mut predicates = vec![];
+    while let Some(after) = predicates_text.strip_prefix('[') {
+        let end = after.find(']').with_context(|| "a predicate is not closed".to_string())?;
+        let predicate = after[..end].trim();
+        predicates.push(match predicate.strip_prefix('@') {
+            None => match predicate.parse::<usize>() {
+                Ok(position) if position > 0 => Predicate::Position(position),
+                _ => bail!("unsupported predicate [{}], use a position or an attribute", predicate),
+            },
+            Some(attribute) => match attribute.split_once('=') {
+                None => Predicate::Attribute(ino_local_name(attribute).to_string(), None),
+                Some((name, value)) => {
+                    let value = value.trim().trim_matches(|c| c == '\'' || c == '"');
+                    Predicate::Attribute(ino_local_name(name).to_string(), Some(value.to_string()))
+                }
+            },
+        });
+        predicates_text = &after[end + 1..];
+    }
+    if !predicates_text.is_empty() {
+        bail!("unexpected {} after a predicate", predicates_text);
+    }
+    Ok(XPathStep { descendant, name, predicates })
+}
+
+/**
+ *=================================================================
+ * ino_parse_xpath_expectation()
+ *=================================================================
+ *
+ * Parses an XPath assertion, "path=value" when the selected value
+ * must equal the given text or "path" when a node must only be
+ * found. The `=` of the predicates is not taken as the separator.
+ *
+ *=================================================================
+ * @param value &str
+ * @return Result<XPathExpectation>
+ */
+pub fn ino_parse_xpath_expectation(value: &str) -> Result<XPathExpectation> {
+    let mut depth = 0;
+    let separator = value.char_indices().find(|&(_, c)| {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
+        }
+        c == '=' && depth == 0
+    });
+    let (path, expected) = match separator {
+        None => (value, None),
+        Some((index, _)) => (&value[..index], Some(value[index + 1..].trim().to_string())),
+    };
+    let path = path.trim().to_string();
+    let xpath = ino_parse_xpath(&path)?;
+    Ok(XPathExpectation { path, value: expected, xpath })
+}
+
+/**
+ *=================================================================
+ * ino_xpath()
+ *=================================================================
+ *
+ * Evaluates an XPath on an XML body. None when the body is not XML
+ * or nothing matches.
+ *
+ *=================================================================
+ * @param path &str
+ * @param body &[u8]
+ * @return Option<String>
+ */
+pub fn ino_xpath(path: &str, body: &[u8]) -> Option<String> {
+    let xpath = ino_parse_xpath(path).ok()?;
+    let document = Document::parse(std::str::from_utf8(body).ok()?).ok()?;
+    xpath.ino_eval(&document)
+}
+
+/**
+ *=================================================================
+ * ino_check_xpath()
+ *=================================================================
+ *
+ * Checks an XML body against the XPath assertions, every one of
+ * them fails when the body is not XML.
+ *
+ *=================================================================
+ * @param expectations &[XPathExpectation]
+ * @param body &[u8]
+ * @return Option<&XPathExpectation> the first unmet assertion
+ */
+pub fn ino_check_xpath<'a>(expectations: &'a [XPathExpectation], body: &[u8]) -> Option<&'a XPathExpectation> {
+    let text = std::str::from_utf8(body).ok();
+    let document = text.and_then(|text| Document::parse(text).ok());
+    expectations.iter().find(|expectation| {
+        let actual = document.as_ref().and_then(|d| expectation.xpath.ino_eval(d));
+        match (actual, &expectation.value) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(actual), Some(value)) => actual != *value,
+        }
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESPONSE: &str = r#"<?xml version="1.0"?>
+<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/" xmlns:m="urn:prices">
+  <soap:Body>
+    <m:GetPriceResponse>
+      <m:Price currency="EUR">1.90</m:Price>
+      <m:Item id="1"><m:Name>tea</m:Name></m:Item>
+      <m:Item id="2"><m:Name>coffee</m:Name></m:Item>
+    </m:GetPriceResponse>
+  </soap:Body>
+</soap:Envelope>"#;
+
+    #[test]
+    fn should_evaluate_xpaths() {
+        let body = RESPONSE.as_bytes();
+        assert_eq!(ino_xpath("/soap:Envelope/soap:Body/m:GetPriceResponse/m:Price", body).as_deref(), Some("1.90"));
+        assert_eq!(ino_xpath("//Price/@currency", body).as_deref(), Some("EUR"));
+        assert_eq!(ino_xpath("//Item[2]/Name", body).as_deref(), Some("coffee"));
+        assert_eq!(ino_xpath("//Item[@id='1']/Name/text()", body).as_deref(), Some("tea"));
+        assert_eq!(ino_xpath("Body/*/Price", body).as_deref(), Some("1.90"));
+        assert_eq!(ino_xpath("//Missing", body), None);
+        assert_eq!(ino_xpath("//Price", b"{\"json\": true}"), None);
+        assert!(ino_parse_xpath("//Price/@currency/Name").is_err());
+        assert!(ino_parse_xpath("//Item[last()]").is_err());
+    }
+
+    #[test]
+    fn should_check_xpath_assertions() -> Result<()> {
+        let body = RESPONSE.as_bytes();
+        let price = ino_parse_xpath_expectation("//Price=1.90")?;
+        let item = ino_parse_xpath_expectation("//Item[@id='2']")?;
+        let name = ino_parse_xpath_expectation("//Item[@id='2']/Name=tea")?;
+        assert_eq!(name.path, "//Item[@id='2']/Name");
+        assert_eq!(name.value.as_deref(), Some("tea"));
+        assert_eq!(ino_check_xpath(&[price.clone(), item.clone()], body), None);
+        assert_eq!(ino_check_xpath(&[price.clone(), name.clone()], body), Some(&name));
+        assert_eq!(ino_check_xpath(std::slice::from_ref(&item), b"not xml"), Some(&item));
+        assert_eq!(String::from(price), "//Price=1.90");
+        Ok(())
+    }
+}